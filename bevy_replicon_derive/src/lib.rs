@@ -0,0 +1,169 @@
+//! Derive macros for [bevy_replicon](https://docs.rs/bevy_replicon).
+//!
+//! Re-exported by the main crate, don't depend on it directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Field, Fields, Index, LitInt};
+
+/// Implements `MapEntities` by mapping all fields marked with `#[entities]`.
+///
+/// See `bevy_replicon::entity_mapping::MapEventEntities` for details.
+#[proc_macro_derive(MapEventEntities, attributes(entities))]
+pub fn derive_map_event_entities(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    map_event_entities(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Implements `Replicate` with options from the `#[replicate(...)]` attribute.
+///
+/// See `bevy_replicon::replication_core::Replicate` for details.
+#[proc_macro_derive(Replicate, attributes(replicate))]
+pub fn derive_replicate(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    replicate(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn replicate(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut options = Vec::new();
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("replicate"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let Some(ident) = meta.path.get_ident() else {
+                return Err(meta.error("expected a replication option"));
+            };
+            match ident.to_string().as_str() {
+                "once" | "reliable" | "owner_only" | "mapped" | "notify" => {
+                    options.push(quote! { .#ident() });
+                }
+                "max_lod" => {
+                    let lod: LitInt = meta.value()?.parse()?;
+                    options.push(quote! { .max_lod(#lod) });
+                }
+                _ => return Err(meta.error(format!("unknown replication option `{ident}`"))),
+            }
+            Ok(())
+        })?;
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_replicon::replication_core::Replicate for #ident #type_generics #where_clause {
+            fn replicate(app: &mut ::bevy_replicon::__private::App) {
+                ::bevy_replicon::replication_core::AppReplicationExt::replicate::<Self>(app) #(#options)*;
+            }
+        }
+    })
+}
+
+fn map_event_entities(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let body = match &input.data {
+        Data::Struct(data) => map_struct_fields(&data.fields),
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let (pattern, mappings) = map_variant_fields(&variant.fields);
+                quote! { Self::#ident #pattern => { #(#mappings)* } }
+            });
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "`MapEventEntities` can't be derived for unions",
+            ))
+        }
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_replicon::__private::MapEntities for #ident #type_generics #where_clause {
+            fn map_entities(&mut self, entity_mapper: &mut ::bevy_replicon::__private::EntityMapper) {
+                #body
+            }
+        }
+    })
+}
+
+fn map_struct_fields(fields: &Fields) -> TokenStream2 {
+    let mappings = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| has_entities_attr(field))
+        .map(|(index, field)| {
+            let member = match &field.ident {
+                Some(ident) => quote! { #ident },
+                None => {
+                    let index = Index::from(index);
+                    quote! { #index }
+                }
+            };
+            map_nested_entities(quote! { &mut self.#member })
+        });
+
+    quote! { #(#mappings)* }
+}
+
+/// Returns a pattern that binds all fields marked with `#[entities]` and mappings for them.
+fn map_variant_fields(fields: &Fields) -> (TokenStream2, Vec<TokenStream2>) {
+    let mut mappings = Vec::new();
+    let pattern = match fields {
+        Fields::Named(fields) => {
+            let bindings: Vec<_> = fields
+                .named
+                .iter()
+                .filter(|field| has_entities_attr(field))
+                .map(|field| field.ident.as_ref().expect("named field should have ident"))
+                .collect();
+            mappings.extend(
+                bindings
+                    .iter()
+                    .map(|ident| map_nested_entities(quote! { #ident })),
+            );
+            quote! { { #(#bindings,)* .. } }
+        }
+        Fields::Unnamed(fields) => {
+            let bindings = fields.unnamed.iter().enumerate().map(|(index, field)| {
+                if has_entities_attr(field) {
+                    let ident = format_ident!("field_{index}");
+                    mappings.push(map_nested_entities(quote! { #ident }));
+                    quote! { #ident }
+                } else {
+                    quote! { _ }
+                }
+            });
+            let bindings: Vec<_> = bindings.collect();
+            quote! { ( #(#bindings),* ) }
+        }
+        Fields::Unit => TokenStream2::new(),
+    };
+
+    (pattern, mappings)
+}
+
+fn map_nested_entities(value: TokenStream2) -> TokenStream2 {
+    quote! {
+        ::bevy_replicon::entity_mapping::MapNestedEntities::map_nested_entities(#value, entity_mapper);
+    }
+}
+
+fn has_entities_attr(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("entities"))
+}
@@ -2,7 +2,7 @@
 //! Run it with `--hotseat` to play locally or with `--client` / `--server`
 
 use std::{
-    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
     time::SystemTime,
 };
 
@@ -10,13 +10,7 @@ use anyhow::Result;
 use bevy::prelude::*;
 use bevy_replicon::{
     prelude::*,
-    renet::{
-        transport::{
-            ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport,
-            ServerAuthentication, ServerConfig,
-        },
-        ConnectionConfig, ServerEvent,
-    },
+    renet::{transport::NetcodeClientTransport, ServerEvent},
     transport::client_connected,
 };
 use clap::{Parser, ValueEnum};
@@ -48,7 +42,7 @@ impl Plugin for TicTacToePlugin {
             .replicate::<Symbol>()
             .replicate::<CellIndex>()
             .replicate::<Player>()
-            .add_client_event::<CellPick>(SendPolicy::Ordered)
+            .add_client_event::<CellPick>(SendPolicy::reliable().ordered())
             .insert_resource(ClearColor(BACKGROUND_COLOR))
             .add_systems(
                 Startup,
@@ -242,6 +236,8 @@ impl TicTacToePlugin {
         mut game_state: ResMut<NextState<GameState>>,
         cli: Res<Cli>,
         network_channels: Res<NetworkChannels>,
+        netcode_settings: Res<NetcodeSettings>,
+        protocol_version: Res<ProtocolVersion>,
     ) -> Result<()> {
         match *cli {
             Cli::Hotseat => {
@@ -251,51 +247,26 @@ impl TicTacToePlugin {
                 game_state.set(GameState::InGame);
             }
             Cli::Server { port, symbol } => {
-                let server_channels_config = network_channels.server_channels();
-                let client_channels_config = network_channels.client_channels();
-
-                let server = RenetServer::new(ConnectionConfig {
-                    server_channels_config,
-                    client_channels_config,
-                    ..Default::default()
-                });
-
-                let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
                 let public_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
-                let socket = UdpSocket::bind(public_addr)?;
-                let server_config = ServerConfig {
-                    max_clients: 1,
-                    protocol_id: PROTOCOL_ID,
-                    public_addr,
-                    authentication: ServerAuthentication::Unsecure,
-                };
-                let transport = NetcodeServerTransport::new(current_time, server_config, socket)?;
+                let (server, transport) =
+                    new_netcode_server(&network_channels, public_addr, 1, PROTOCOL_ID, None)?;
 
                 commands.insert_resource(server);
                 commands.insert_resource(transport);
                 commands.spawn(PlayerBundle::server(symbol));
             }
             Cli::Client { port, ip } => {
-                let server_channels_config = network_channels.server_channels();
-                let client_channels_config = network_channels.client_channels();
-
-                let client = RenetClient::new(ConnectionConfig {
-                    server_channels_config,
-                    client_channels_config,
-                    ..Default::default()
-                });
-
                 let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
                 let client_id = current_time.as_millis() as u64;
                 let server_addr = SocketAddr::new(ip, port);
-                let socket = UdpSocket::bind((ip, 0))?;
-                let authentication = ClientAuthentication::Unsecure {
-                    client_id,
-                    protocol_id: PROTOCOL_ID,
+                let (client, transport) = new_netcode_client(
+                    &network_channels,
+                    &netcode_settings,
                     server_addr,
-                    user_data: None,
-                };
-                let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
+                    client_id,
+                    PROTOCOL_ID,
+                    protocol_version.current,
+                )?;
 
                 commands.insert_resource(client);
                 commands.insert_resource(transport);
@@ -408,7 +379,10 @@ impl TicTacToePlugin {
         current_turn: Res<CurrentTurn>,
         players: Query<(&Player, &Symbol)>,
     ) {
-        for FromClient { client_id, event } in pick_events.iter().copied() {
+        for FromClient {
+            client_id, event, ..
+        } in pick_events.iter().copied()
+        {
             // It's good to check the received data, client could be cheating.
             if event.0 > GRID_SIZE * GRID_SIZE {
                 error!("received invalid cell index {:?}", event.0);
@@ -512,6 +486,7 @@ impl TicTacToePlugin {
 }
 
 /// Returns `true` if the local player can select cells.
+#[allow(clippy::type_complexity)]
 fn local_player_turn(
 ) -> impl FnMut(Res<CurrentTurn>, Option<Res<NetcodeClientTransport>>, Query<(&Player, &Symbol)>) -> bool
 {
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_renet::renet::RenetClient;
+
+use crate::{client::ServerTickEstimate, replication_core::AppReplicationExt, server::ServerTick};
+
+/// Replicates animation state machines without streaming per-frame animation data.
+///
+/// The server inserts [`AnimationState`] when a clip starts and replicates only it.
+/// On clients, [`AnimationPlayback`] is inserted for entities with [`AnimationState`] and
+/// its playback position is reconstructed every frame in `Update` from [`ServerTickEstimate`],
+/// so it stays in sync with the server between updates and after packet loss.
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately
+/// on both server and clients after them.
+pub struct AnimationSyncPlugin;
+
+impl Plugin for AnimationSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<AnimationState>().add_systems(
+            Update,
+            (Self::cleanup_system, Self::playback_system).run_if(resource_exists::<RenetClient>()),
+        );
+    }
+}
+
+impl AnimationSyncPlugin {
+    fn playback_system(
+        mut commands: Commands,
+        estimate: Res<ServerTickEstimate>,
+        mut states: Query<(Entity, &AnimationState, Option<&mut AnimationPlayback>)>,
+    ) {
+        let Some(tick_duration) = estimate.tick_duration() else {
+            return;
+        };
+        let tick = estimate.tick();
+        let overstep = estimate.overstep();
+        for (entity, state, playback) in &mut states {
+            let elapsed = state.elapsed(tick, overstep, tick_duration);
+            match playback {
+                Some(mut playback) => playback.elapsed = elapsed,
+                None => {
+                    commands
+                        .entity(entity)
+                        .insert(AnimationPlayback { elapsed });
+                }
+            }
+        }
+    }
+
+    fn cleanup_system(
+        mut commands: Commands,
+        mut removed_states: RemovedComponents<AnimationState>,
+        playbacks: Query<(), With<AnimationPlayback>>,
+    ) {
+        for entity in &mut removed_states {
+            if playbacks.get(entity).is_ok() {
+                commands.entity(entity).remove::<AnimationPlayback>();
+            }
+        }
+    }
+}
+
+/// Currently playing animation clip of an entity.
+///
+/// Should be inserted or replaced on server only when the clip changes,
+/// playback position is reconstructed on clients in [`AnimationPlayback`].
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct AnimationState {
+    /// User-defined identifier of the playing clip.
+    pub clip_id: u32,
+    /// Value of [`ServerTick`] on which the clip started.
+    pub start_tick: u32,
+    /// Playback speed multiplier.
+    pub speed: f32,
+}
+
+impl AnimationState {
+    /// Creates a state for a clip that starts on `server_tick` with normal speed.
+    pub fn start(clip_id: u32, server_tick: ServerTick) -> Self {
+        Self {
+            clip_id,
+            start_tick: server_tick.get(),
+            speed: 1.0,
+        }
+    }
+
+    /// Sets playback speed multiplier.
+    #[must_use]
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Returns the playback position in seconds at `tick` with `overstep` fraction of the next tick.
+    ///
+    /// Returns zero if the clip starts after `tick`.
+    pub fn elapsed(&self, tick: u32, overstep: f64, tick_duration: Duration) -> f32 {
+        let ticks = tick.wrapping_sub(self.start_tick) as i32 as f64 + overstep;
+        (ticks.max(0.0) * tick_duration.as_secs_f64()) as f32 * self.speed
+    }
+}
+
+/// Reconstructed playback position of an entity with [`AnimationState`].
+///
+/// Inserted and updated automatically on clients.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct AnimationPlayback {
+    elapsed: f32,
+}
+
+impl AnimationPlayback {
+    /// Returns the playback position of the current clip in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed() {
+        let state = AnimationState {
+            clip_id: 0,
+            start_tick: 10,
+            speed: 2.0,
+        };
+        let tick_duration = Duration::from_millis(100);
+        assert_eq!(state.elapsed(10, 0.0, tick_duration), 0.0);
+        assert_eq!(state.elapsed(12, 0.5, tick_duration), 0.5);
+        assert_eq!(
+            state.elapsed(8, 0.0, tick_duration),
+            0.0,
+            "should hold before the start"
+        );
+
+        let state = AnimationState {
+            start_tick: u32::MAX,
+            ..state
+        };
+        assert_eq!(
+            state.elapsed(1, 0.0, tick_duration),
+            0.4,
+            "should handle tick wrapping"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::Debug;
+
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        system::SystemParam,
+    },
+    prelude::*,
+};
+use bevy_renet::{
+    renet::{transport::NetcodeClientTransport, RenetClient},
+    transport::client_connected,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    client::{ClientSet, NetworkEntityMap},
+    network_event::{
+        client_event::{ClientEventAppExt, FromClient},
+        server_event::{SendMode, ServerEventAppExt, ToClients},
+        SendPolicy,
+    },
+    relay::RelayServer,
+    server::{has_authority, ServerSet, SERVER_ID},
+    transport_switch::TransportSwitch,
+};
+
+/// Peer that simulates an entity.
+///
+/// Replicated automatically, so all peers know who currently owns the entity.
+/// Entities without this component are simulated by server.
+/// Use [`LocalAuthority`] or [`has_authority_over`] to run logic only on the owning peer
+/// in mixed-authority setups, for example to move client-authoritative characters on clients.
+#[derive(Component, Default, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum Authority {
+    /// Entity is simulated by server or in singleplayer.
+    #[default]
+    Server,
+    /// Entity is simulated by a client with the specified ID.
+    ///
+    /// [`SERVER_ID`] refers to the server when it's also a player.
+    Client(u64),
+}
+
+impl Authority {
+    /// Returns ID of the peer that simulates the entity.
+    pub fn peer_id(self) -> u64 {
+        match self {
+            Authority::Server => SERVER_ID,
+            Authority::Client(client_id) => client_id,
+        }
+    }
+}
+
+/// A system parameter to check which entities are simulated by this peer.
+#[derive(SystemParam)]
+pub struct LocalAuthority<'w, 's> {
+    client: Option<Res<'w, RenetClient>>,
+    transport: Option<Res<'w, NetcodeClientTransport>>,
+    switch: Option<Res<'w, TransportSwitch>>,
+    relay: Option<Res<'w, RelayServer>>,
+    authorities: Query<'w, 's, &'static Authority>,
+}
+
+impl LocalAuthority<'_, '_> {
+    /// Returns ID of this peer.
+    ///
+    /// Returns [`SERVER_ID`] for server or in singleplayer and ID from [`NetcodeClientTransport`] for client.
+    /// Returns [`None`] for client without netcode transport or while client resources are being
+    /// replaced with [`TransportSwitch`].
+    pub fn local_id(&self) -> Option<u64> {
+        if self
+            .switch
+            .as_ref()
+            .is_some_and(|switch| switch.is_client())
+        {
+            return None;
+        }
+        if self.client.is_none() {
+            return Some(SERVER_ID);
+        }
+        self.transport
+            .as_ref()
+            .map(|transport| transport.client_id())
+    }
+
+    /// Returns `true` if this peer simulates the entity according to its [`Authority`].
+    ///
+    /// Always returns `false` on [`RelayServer`].
+    pub fn has_authority_over(&self, entity: Entity) -> bool {
+        if self.relay.is_some() {
+            return false;
+        }
+        let authority = self.authorities.get(entity).copied().unwrap_or_default();
+        self.local_id() == Some(authority.peer_id())
+    }
+}
+
+/// Condition that returns `true` if this peer simulates the entity according to its [`Authority`].
+///
+/// See also [`LocalAuthority`] to check multiple entities inside a system.
+pub fn has_authority_over(entity: Entity) -> impl FnMut(LocalAuthority) -> bool + Clone {
+    move |authority| authority.has_authority_over(entity)
+}
+
+/// Registers systems that send changes of client-authoritative component `T` from its owner to server.
+///
+/// See [`ReplicationRuleBuilder::client_authoritative`](crate::replication_core::ReplicationRuleBuilder::client_authoritative).
+pub(crate) fn add_client_authoritative<T>(app: &mut App, validator: Option<ComponentValidator<T>>)
+where
+    T: Component + Clone + Debug + Serialize + DeserializeOwned,
+{
+    app.add_client_event::<ComponentChange<T>>(SendPolicy::reliable().ordered())
+        .add_mapped_server_event::<ComponentCorrection<T>>(SendPolicy::reliable().ordered())
+        .insert_resource(ClientAuthoritative { validator })
+        .add_systems(
+            PreUpdate,
+            (
+                change_receiving_system::<T>
+                    .after(ServerSet::Receive)
+                    .run_if(has_authority()),
+                correction_system::<T>
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected()),
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            change_sending_system::<T>
+                .before(ClientSet::Send)
+                .run_if(client_connected()),
+        );
+}
+
+/// Sends local changes of client-authoritative components on owned entities.
+///
+/// Insertions are made by server, so only changes are sent.
+fn change_sending_system<T: Component + Clone>(
+    mut change_events: EventWriter<ComponentChange<T>>,
+    authority: LocalAuthority,
+    entity_map: Res<NetworkEntityMap>,
+    components: Query<(Entity, Ref<T>), Changed<T>>,
+) {
+    for (entity, component) in &components {
+        if component.is_added() || !authority.has_authority_over(entity) {
+            continue;
+        }
+        let Some(server_entity) = entity_map.to_server().get(entity) else {
+            continue;
+        };
+        change_events.send(ComponentChange {
+            entity: server_entity,
+            component: component.clone(),
+        });
+    }
+}
+
+/// Applies changes received from owners of entities.
+///
+/// Server replicates applied changes to other clients.
+/// Rejected changes are corrected on the owner with [`ComponentCorrection`].
+fn change_receiving_system<T: Component + Clone>(
+    mut change_events: EventReader<FromClient<ComponentChange<T>>>,
+    mut correction_events: EventWriter<ToClients<ComponentCorrection<T>>>,
+    client_authoritative: Res<ClientAuthoritative<T>>,
+    mut components: Query<(&Authority, &mut T)>,
+) {
+    for FromClient {
+        client_id, event, ..
+    } in &mut change_events
+    {
+        let Ok((&authority, mut component)) = components.get_mut(event.entity) else {
+            debug!(
+                "ignoring change of {} for missing {:?} from client {client_id}",
+                std::any::type_name::<T>(),
+                event.entity
+            );
+            continue;
+        };
+        if authority != Authority::Client(*client_id) {
+            warn!(
+                "ignoring change of {} for {:?} from client {client_id} without authority",
+                std::any::type_name::<T>(),
+                event.entity
+            );
+            continue;
+        }
+        if let Some(validator) = client_authoritative.validator {
+            if !validator(*client_id, &component, &event.component) {
+                debug!(
+                    "rejected change of {} for {:?} from client {client_id}",
+                    std::any::type_name::<T>(),
+                    event.entity
+                );
+                correction_events.send(ToClients {
+                    mode: SendMode::Direct(*client_id),
+                    event: ComponentCorrection {
+                        entity: event.entity,
+                        component: component.clone(),
+                    },
+                });
+                continue;
+            }
+        }
+        *component = event.component.clone();
+    }
+}
+
+/// Applies values of rejected changes received from server.
+///
+/// Change detection is bypassed to avoid sending the corrected value back as a local change.
+fn correction_system<T: Component + Clone>(
+    mut correction_events: EventReader<ComponentCorrection<T>>,
+    mut components: Query<&mut T>,
+) {
+    for correction in &mut correction_events {
+        if let Ok(mut component) = components.get_mut(correction.entity) {
+            *component.bypass_change_detection() = correction.component.clone();
+        }
+    }
+}
+
+/// Validates a change of client-authoritative component.
+///
+/// Called on server with the ID of the sender, the current value and the received value.
+/// Should return `true` to accept the received value.
+pub type ComponentValidator<T> = fn(u64, &T, &T) -> bool;
+
+/// Options of a client-authoritative component.
+#[derive(Resource)]
+struct ClientAuthoritative<T> {
+    validator: Option<ComponentValidator<T>>,
+}
+
+/// Change of a client-authoritative component sent by the owner.
+#[derive(Debug, Deserialize, Event, Serialize)]
+struct ComponentChange<T> {
+    /// Server entity.
+    entity: Entity,
+    component: T,
+}
+
+/// Current server value of a client-authoritative component sent to the owner after rejecting its change.
+#[derive(Debug, Deserialize, Event, Serialize)]
+struct ComponentCorrection<T> {
+    entity: Entity,
+    component: T,
+}
+
+impl<T> MapEntities for ComponentCorrection<T> {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.entity = entity_mapper.get_or_reserve(self.entity);
+    }
+}
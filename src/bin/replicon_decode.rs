@@ -0,0 +1,42 @@
+//! Prints messages from a capture file written by `TrafficLog::start_capture`.
+//!
+//! Only messages of built-in components and events are fully decoded. To decode messages of
+//! app-specific types, use `TrafficDecoder` with the world of the app that captured the traffic.
+//!
+//! Usage: `replicon_decode <capture file>`
+
+use std::{env, fs::File, io::BufReader, process::ExitCode};
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+fn main() -> ExitCode {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("usage: replicon_decode <capture file>");
+        return ExitCode::FAILURE;
+    };
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("unable to open {path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let messages = match CapturedMessage::read_all(BufReader::new(file)) {
+        Ok(messages) => messages,
+        Err(e) => {
+            eprintln!("unable to read {path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut app = App::new();
+    app.add_plugins(ReplicationPlugins);
+    let decoder = TrafficDecoder::new(&app.world);
+    for message in &messages {
+        println!("[tick {}] {}", message.tick, decoder.decode(message));
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,140 @@
+use std::{collections::VecDeque, net::SocketAddr};
+
+use bevy::{ecs::event::Event, prelude::*};
+use bevy_renet::renet::transport::{NetcodeClientTransport, NetcodeError};
+
+use crate::{
+    client::NetworkEntityMap, netcode::NetcodeWorldExt, server::ServerPlugin, ReplicationPlugins,
+};
+
+/// A headless client that simulates a player in tests and soak runs.
+///
+/// Wraps an [`App`] with [`MinimalPlugins`] and [`ReplicationPlugins`] without [`ServerPlugin`],
+/// so replication is received into a bare world without rendering or game systems.
+/// Replicated components and client events should be registered in `setup` in the same order as in the game.
+/// Client events could be sent on the next update with [`Self::send_event`] or scripted ahead
+/// with [`Self::send_event_after`] and [`Self::run_after`].
+///
+/// ```no_run
+/// # use std::net::{Ipv4Addr, SocketAddr};
+/// # use bevy::prelude::*;
+/// # use bevy_replicon::{bot_client::BotClient, prelude::*};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Debug, Deserialize, Event, Serialize)]
+/// # struct Jump;
+/// let mut bot = BotClient::new(|app| {
+///     app.replicate::<Transform>()
+///         .add_client_event::<Jump>(SendPolicy::reliable().ordered());
+/// });
+/// let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 5000);
+/// bot.connect(server_addr, 1, 0)?;
+/// for updates in (0..600).step_by(60) {
+///     bot.send_event_after(updates, Jump);
+/// }
+/// while !bot.is_script_finished() {
+///     bot.update();
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct BotClient {
+    pub app: App,
+    /// Actions with the number of the update on which they should run, sorted by it.
+    script: VecDeque<(u32, ScriptedAction)>,
+    /// Number of updates since creation.
+    updates: u32,
+}
+
+type ScriptedAction = Box<dyn FnOnce(&mut World) + Send>;
+
+impl BotClient {
+    /// Creates a client app and calls `setup` for it.
+    pub fn new(setup: impl FnOnce(&mut App)) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.build().disable::<ServerPlugin>(),
+        ));
+        setup(&mut app);
+
+        Self {
+            app,
+            script: Default::default(),
+            updates: 0,
+        }
+    }
+
+    /// Starts connecting to the server with netcode transport.
+    ///
+    /// The connection will be established during the following updates.
+    pub fn connect(
+        &mut self,
+        server_addr: SocketAddr,
+        client_id: u64,
+        protocol_id: u64,
+    ) -> Result<(), NetcodeError> {
+        self.app
+            .world
+            .create_netcode_client(server_addr, client_id, protocol_id)
+    }
+
+    /// Returns `true` if the transport is connected.
+    pub fn is_connected(&self) -> bool {
+        self.app
+            .world
+            .get_resource::<NetcodeClientTransport>()
+            .is_some_and(|transport| transport.is_connected())
+    }
+
+    /// Disconnects from the server.
+    pub fn disconnect(&mut self) {
+        if let Some(mut transport) = self.app.world.get_resource_mut::<NetcodeClientTransport>() {
+            transport.disconnect();
+        }
+    }
+
+    /// Runs scripted actions that are due and updates the app.
+    pub fn update(&mut self) {
+        while let Some((update, _)) = self.script.front() {
+            if *update > self.updates {
+                break;
+            }
+            let (_, action) = self
+                .script
+                .pop_front()
+                .expect("script should contain the checked action");
+            action(&mut self.app.world);
+        }
+
+        self.app.update();
+        self.updates += 1;
+    }
+
+    /// Sends a client event on the next update.
+    pub fn send_event<T: Event>(&mut self, event: T) {
+        self.send_event_after(0, event);
+    }
+
+    /// Sends a client event after the specified number of updates.
+    pub fn send_event_after<T: Event>(&mut self, updates: u32, event: T) {
+        self.run_after(updates, move |world| world.send_event(event));
+    }
+
+    /// Runs an arbitrary action with the client world after the specified number of updates.
+    ///
+    /// Actions scheduled for the same update run in the order of scheduling.
+    pub fn run_after(&mut self, updates: u32, action: impl FnOnce(&mut World) + Send + 'static) {
+        let update = self.updates + updates;
+        let index = self.script.partition_point(|&(other, _)| other <= update);
+        self.script.insert(index, (update, Box::new(action)));
+    }
+
+    /// Returns `true` if all scripted actions have run.
+    pub fn is_script_finished(&self) -> bool {
+        self.script.is_empty()
+    }
+
+    /// Returns mappings between server and client entities of received replication.
+    pub fn entity_map(&self) -> &NetworkEntityMap {
+        self.app.world.resource::<NetworkEntityMap>()
+    }
+}
@@ -1,17 +1,47 @@
+use std::{
+    any::{self, TypeId},
+    cmp::Ordering,
+    collections::VecDeque,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    mem,
+    time::Duration,
+};
+
 use bevy::{
-    ecs::{component::Tick, entity::EntityMap, reflect::ReflectMapEntities, system::Command},
+    ecs::{
+        component::{Components, Tick},
+        entity::{Entities, EntityMap, MapEntities},
+        reflect::ReflectMapEntities,
+    },
     prelude::*,
     reflect::TypeRegistryInternal,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::transport::{client_connected, client_just_connected};
+use bevy_renet::{
+    renet::{
+        self,
+        transport::{NetcodeClientTransport, NetcodeDisconnectReason},
+        Bytes, RenetClient,
+    },
+    transport::NetcodeClientPlugin,
+    RenetClientPlugin,
 };
-use bevy_renet::transport::client_connected;
-use bevy_renet::{renet::RenetClient, transport::NetcodeClientPlugin, RenetClientPlugin};
-use bincode::{DefaultOptions, Options};
 use serde::{de::DeserializeSeed, Deserialize, Serialize};
 
 use crate::{
-    replication_core::REPLICATION_CHANNEL_ID,
-    world_diff::{ComponentDiff, WorldDiff, WorldDiffDeserializer},
+    netcode::NetcodeSettings,
+    replication_core::{
+        AuthRequest, AuthRequired, InitialSynced, Kicked, ReplicationRules, ReplicationSet,
+        ReplicationToggled, RuleSetChanged, SpawnGroup, ENTITY_MAPPING_CHANNEL_ID,
+        RELIABLE_REPLICATION_CHANNEL_ID, REPLICATION_CHANNEL_ID,
+    },
+    server::ServerTick,
+    server_config::configs_received,
+    transport_switch::TransportSwitch,
+    wire_format::{deserialize_message, message_deserializer, message_payload},
+    world_diff::{ComponentDiff, DespawnReason, WorldDiff, WorldDiffDeserializer},
     Replication,
 };
 
@@ -20,7 +50,19 @@ pub struct ClientPlugin;
 impl Plugin for ClientPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((RenetClientPlugin, NetcodeClientPlugin))
-            .init_resource::<LastTick>()
+            .init_resource::<LastReceivedServerTick>()
+            .init_resource::<UnconfirmedEntities>()
+            .init_resource::<ServerTick>()
+            .init_resource::<ServerTickEstimate>()
+            .init_resource::<DespawnPolicy>()
+            .init_resource::<DiffCoalescing>()
+            .init_resource::<EntityMappingPolicy>()
+            .init_resource::<NetcodeSettings>()
+            .init_resource::<InitialSync>()
+            .add_event::<ServerTickApplied>()
+            .add_event::<InitialSyncComplete>()
+            .add_event::<ReplicationDespawned>()
+            .add_event::<ClientDisconnected>()
             .init_resource::<NetworkEntityMap>()
             .configure_set(
                 PreUpdate,
@@ -32,167 +74,1383 @@ impl Plugin for ClientPlugin {
             )
             .add_systems(
                 PreUpdate,
-                Self::diff_receiving_system
-                    .in_set(ClientSet::Receive)
+                (
+                    (
+                        Self::diff_receiving_system
+                            .in_set(ReplicationSet)
+                            .run_if(configs_received()),
+                        Self::tick_estimation_system,
+                    )
+                        .chain()
+                        .in_set(ClientSet::Receive),
+                    Self::initial_sync_system.after(ClientSet::Receive),
+                    Self::rule_set_receiving_system.after(ClientSet::Receive),
+                    Self::toggle_receiving_system.after(ClientSet::Receive),
+                    Self::auth_required_system.after(ClientSet::Receive),
+                )
                     .run_if(client_connected()),
             )
+            .add_systems(
+                PreUpdate,
+                Self::disconnect_system
+                    .after(ClientSet::Receive)
+                    .run_if(resource_exists::<NetcodeClientTransport>())
+                    .run_if(resource_exists::<RenetClient>()),
+            )
             .add_systems(
                 PostUpdate,
                 (
-                    Self::ack_sending_system
+                    (
+                        Self::ack_sending_system,
+                        Self::entity_map_cleanup_system,
+                        Self::entity_mappings_sending_system,
+                    )
+                        .chain()
                         .in_set(ClientSet::Send)
                         .run_if(client_connected()),
+                    Self::auth_sending_system
+                        .before(ClientSet::Send)
+                        .run_if(client_just_connected()),
                     Self::reset_system.run_if(resource_removed::<RenetClient>()),
+                    Self::entity_pool_refill_system.run_if(resource_exists::<ClientEntityPool>()),
+                    Self::despawn_budget_system.run_if(resource_exists::<DespawnBudget>()),
                 ),
             );
     }
 }
 
 impl ClientPlugin {
-    fn diff_receiving_system(
-        mut commands: Commands,
-        mut last_tick: ResMut<LastTick>,
-        mut client: ResMut<RenetClient>,
-        registry: Res<AppTypeRegistry>,
+    /// Receives and applies world diffs.
+    ///
+    /// Exclusive to apply the whole tick in one step, see [`ReplicationSet`].
+    fn diff_receiving_system(world: &mut World) {
+        world.resource_scope(|world, mut client: Mut<RenetClient>| {
+            // Any tick is accepted until the first diff is applied.
+            let last_server_tick = (world.resource::<LastReceivedServerTick>().0 != 0)
+                .then(|| *world.resource::<ServerTick>());
+            let mut messages = Vec::new();
+            while let Some(message) = client.receive_message(REPLICATION_CHANNEL_ID) {
+                let server_tick: ServerTick = match deserialize_message(&message) {
+                    Ok(server_tick) => server_tick,
+                    Err(e) => {
+                        error!("unable to read world diff from server: {e}");
+                        continue;
+                    }
+                };
+                // Diffs could arrive out of order, so stale ones are discarded.
+                if last_server_tick.map_or(true, |last_tick| server_tick.is_newer_than(last_tick)) {
+                    messages.push((server_tick, message));
+                }
+            }
+            messages.sort_by(|(a, _), (b, _)| {
+                if a.is_newer_than(*b) {
+                    Ordering::Greater
+                } else if b.is_newer_than(*a) {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            });
+            let now = world.resource::<Time>().elapsed();
+            if let Some(mut jitter_buffer) = world.get_resource_mut::<JitterBuffer>() {
+                jitter_buffer.buffer(messages, now);
+                messages = jitter_buffer.release();
+            }
+            if let Some(mut playback) = world.get_resource_mut::<DiffPlayback>() {
+                playback.buffer(messages);
+                let messages = playback.take();
+                let last_index = messages.len().saturating_sub(1);
+                for (index, message) in messages.into_iter().enumerate() {
+                    // Notify only about the last diff while catching up.
+                    let notify = index == last_index || !playback_catching_up(world);
+                    apply_unreliable_diff(world, message, notify);
+                }
+            } else {
+                if *world.resource::<DiffCoalescing>() == DiffCoalescing::Coalesce {
+                    // Each diff contains all changes since the last acknowledged tick,
+                    // so the newest one includes changes from all others.
+                    let stale_count = messages.len().saturating_sub(1);
+                    messages.drain(..stale_count);
+                }
+
+                for (_, message) in messages {
+                    apply_unreliable_diff(world, message, true);
+                }
+            }
+
+            while let Some(message) = client.receive_message(RELIABLE_REPLICATION_CHANNEL_ID) {
+                if let Some(mut received_diffs) = world.get_resource_mut::<ReceivedWorldDiffs>() {
+                    received_diffs.push(message.clone());
+                }
+                let registry = world.resource::<AppTypeRegistry>().clone();
+                let registry = registry.read();
+                let (_, mut world_diff) = deserialize_world_diff(&registry, &message).expect(
+                    "server should send only world diffs over reliable replication channel",
+                );
+                world.resource_scope(|world, mut entity_map: Mut<NetworkEntityMap>| {
+                    // Despawns are sent unreliably and could arrive earlier.
+                    for archetype_diff in &mut world_diff.archetypes {
+                        archetype_diff.entities.retain(|entity_diff| {
+                            entity_map.get_by_server(entity_diff.entity) != MappedEntity::Despawned
+                        });
+                    }
+                    world_diff.apply(world, &mut entity_map);
+                });
+            }
+        });
+    }
+
+    /// Drops received world diffs while [`ReplicationSet`] is suspended.
+    ///
+    /// Server will resend all changes since the last acknowledged tick after resumption.
+    /// Reliable diffs are not resent, so they are kept in the channel until resumption.
+    pub(super) fn diff_discarding_system(mut client: ResMut<RenetClient>) {
+        while client.receive_message(REPLICATION_CHANNEL_ID).is_some() {}
+    }
+
+    fn toggle_receiving_system(
+        mut toggle_events: EventReader<ReplicationToggled>,
+        mut replication_rules: ResMut<ReplicationRules>,
+        components: &Components,
     ) {
-        let mut last_message = None;
-        while let Some(message) = client.receive_message(REPLICATION_CHANNEL_ID) {
-            last_message = Some(message);
+        for event in &mut toggle_events {
+            match components
+                .iter()
+                .find(|info| info.name() == event.component)
+            {
+                Some(info) => replication_rules.set_component_enabled(info.id(), event.enabled),
+                None => warn!(
+                    "received toggle for unknown component `{}`",
+                    event.component
+                ),
+            }
         }
+    }
 
-        if let Some(last_message) = last_message {
-            let registry = registry.read();
-            // Set options to match `bincode::serialize`.
-            // https://docs.rs/bincode/latest/bincode/config/index.html#options-struct-vs-bincode-functions
-            let options = DefaultOptions::new()
-                .with_fixint_encoding()
-                .allow_trailing_bytes();
-            let mut deserializer = bincode::Deserializer::from_slice(&last_message, options);
-            let world_diff = WorldDiffDeserializer::new(&registry)
-                .deserialize(&mut deserializer)
-                .expect("server should send only world diffs over replication channel");
-            *last_tick = world_diff.tick.into();
-            commands.apply_world_diff(world_diff);
+    fn rule_set_receiving_system(
+        mut rule_set_events: EventReader<RuleSetChanged>,
+        mut replication_rules: ResMut<ReplicationRules>,
+    ) {
+        if let Some(event) = rule_set_events.iter().last() {
+            replication_rules.set_active_rule_set(event.rule_set.as_deref());
         }
     }
 
-    fn ack_sending_system(last_tick: Res<LastTick>, mut client: ResMut<RenetClient>) {
-        let message = bincode::serialize(&*last_tick)
+    /// Disconnects on [`Kicked`] or timeout and emits [`ClientDisconnected`] once per connection.
+    ///
+    /// Timeout is checked here because Renet always uses its default timeout on client.
+    fn disconnect_system(
+        mut local_reason: Local<Option<ClientDisconnectReason>>,
+        mut reported: Local<bool>,
+        settings: Res<NetcodeSettings>,
+        mut kicked_events: EventReader<Kicked>,
+        mut disconnect_events: EventWriter<ClientDisconnected>,
+        mut transport: ResMut<NetcodeClientTransport>,
+        client: Res<RenetClient>,
+    ) {
+        if !transport.is_disconnected() {
+            if let Some(kicked) = kicked_events.iter().last() {
+                *local_reason = Some(match *kicked {
+                    Kicked::Code(code) => ClientDisconnectReason::Kicked(code),
+                    Kicked::ServerFull => ClientDisconnectReason::ServerFull,
+                    Kicked::AuthRejected(code) => ClientDisconnectReason::AuthRejected(code),
+                });
+                transport.disconnect();
+            } else if transport.is_connected()
+                && settings
+                    .timeout
+                    .is_some_and(|timeout| transport.time_since_last_received_packet() > timeout)
+            {
+                *local_reason = Some(ClientDisconnectReason::TimedOut);
+                transport.disconnect();
+            }
+        }
+
+        if !transport.is_disconnected() && !client.is_disconnected() {
+            // Reset for a new connection.
+            *local_reason = None;
+            *reported = false;
+            return;
+        }
+
+        if *reported {
+            return;
+        }
+
+        let reason = if let Some(reason) = *local_reason {
+            reason
+        } else {
+            match client.disconnect_reason() {
+                Some(renet::DisconnectReason::DisconnectedByServer) => {
+                    ClientDisconnectReason::DisconnectedByServer
+                }
+                Some(renet::DisconnectReason::DisconnectedByClient) => {
+                    ClientDisconnectReason::DisconnectedByClient
+                }
+                Some(renet::DisconnectReason::Transport) | None => {
+                    match transport.disconnect_reason() {
+                        Some(NetcodeDisconnectReason::ConnectTokenExpired) => {
+                            ClientDisconnectReason::TokenExpired
+                        }
+                        Some(
+                            NetcodeDisconnectReason::ConnectionTimedOut
+                            | NetcodeDisconnectReason::ConnectionResponseTimedOut
+                            | NetcodeDisconnectReason::ConnectionRequestTimedOut,
+                        ) => ClientDisconnectReason::TimedOut,
+                        Some(NetcodeDisconnectReason::ConnectionDenied) => {
+                            ClientDisconnectReason::Denied
+                        }
+                        Some(NetcodeDisconnectReason::DisconnectedByClient) => {
+                            ClientDisconnectReason::DisconnectedByClient
+                        }
+                        Some(NetcodeDisconnectReason::DisconnectedByServer) => {
+                            ClientDisconnectReason::DisconnectedByServer
+                        }
+                        // Renet client will be marked as disconnected on the next transport update.
+                        None => return,
+                    }
+                }
+                Some(reason) => ClientDisconnectReason::Protocol(reason),
+            }
+        };
+
+        disconnect_events.send(ClientDisconnected { reason });
+        *reported = true;
+    }
+
+    fn tick_estimation_system(
+        time: Res<Time>,
+        server_tick: Res<ServerTick>,
+        mut estimate: ResMut<ServerTickEstimate>,
+    ) {
+        estimate.update(server_tick.get(), time.elapsed());
+    }
+
+    fn ack_sending_system(
+        last_tick: Res<LastReceivedServerTick>,
+        server_tick: Res<ServerTick>,
+        unconfirmed: Res<UnconfirmedEntities>,
+        mut client: ResMut<RenetClient>,
+    ) {
+        let ack = ReplicationAck {
+            tick: *last_tick,
+            server_tick: (last_tick.0 != 0).then_some(*server_tick),
+            unconfirmed: unconfirmed
+                .iter()
+                .map(|(&entity, tick)| (entity, tick.get()))
+                .collect(),
+        };
+        let message = bincode::serialize(&ack)
             .unwrap_or_else(|e| panic!("client ack should be serialized: {e}"));
         client.send_message(REPLICATION_CHANNEL_ID, message);
     }
 
-    fn reset_system(mut last_tick: ResMut<LastTick>, mut entity_map: ResMut<NetworkEntityMap>) {
+    /// Removes mappings for client entities that were despawned locally.
+    fn entity_map_cleanup_system(
+        mut removed_replications: RemovedComponents<Replication>,
+        mut entity_map: ResMut<NetworkEntityMap>,
+        entities: &Entities,
+    ) {
+        for client_entity in &mut removed_replications {
+            if !entities.contains(client_entity) {
+                entity_map.remove_by_client(client_entity);
+            }
+        }
+    }
+
+    /// Warns if server requires authentication, but [`AuthCredentials`] are missing.
+    fn auth_required_system(
+        mut required_events: EventReader<AuthRequired>,
+        credentials: Option<Res<AuthCredentials>>,
+    ) {
+        if required_events.iter().count() > 0 && credentials.is_none() {
+            warn!("server requires authentication, but `AuthCredentials` resource is missing");
+        }
+    }
+
+    fn auth_sending_system(
+        credentials: Option<Res<AuthCredentials>>,
+        mut auth_events: EventWriter<AuthRequest>,
+    ) {
+        if let Some(credentials) = credentials {
+            auth_events.send(AuthRequest {
+                credentials: credentials.0.clone(),
+            });
+        }
+    }
+
+    fn entity_mappings_sending_system(
+        mut entity_map: ResMut<NetworkEntityMap>,
+        mut client: ResMut<RenetClient>,
+    ) {
+        if entity_map.unsent.is_empty() {
+            return;
+        }
+
+        let message = bincode::serialize(&entity_map.unsent)
+            .unwrap_or_else(|e| panic!("entity mappings should be serialized: {e}"));
+        client.send_message(ENTITY_MAPPING_CHANNEL_ID, message);
+        entity_map.unsent.clear();
+        entity_map.despawned.clear();
+    }
+
+    /// Spawns entities for [`ClientEntityPool`] to keep it at its size.
+    fn entity_pool_refill_system(world: &mut World) {
+        let missing = world.resource::<ClientEntityPool>().missing();
+        if missing == 0 {
+            return;
+        }
+
+        let entities: Vec<_> = world.spawn_batch(vec![(); missing]).collect();
+        world
+            .resource_mut::<ClientEntityPool>()
+            .entities
+            .extend(entities);
+    }
+
+    /// Despawns queued entities within [`DespawnBudget`].
+    fn despawn_budget_system(world: &mut World) {
+        let mut budget = world.resource_mut::<DespawnBudget>();
+        let count = budget.per_frame.min(budget.pending.len());
+        let despawns: Vec<_> = budget.pending.drain(..count).collect();
+        for (client_entity, reason) in despawns {
+            despawn_replicated(world, client_entity, reason);
+        }
+    }
+
+    /// Emits [`InitialSyncComplete`] once server confirms the initial world state and no entities are unconfirmed.
+    fn initial_sync_system(
+        mut synced_events: EventReader<InitialSynced>,
+        mut sync_events: EventWriter<InitialSyncComplete>,
+        mut initial_sync: ResMut<InitialSync>,
+        unconfirmed: Res<UnconfirmedEntities>,
+    ) {
+        if synced_events.iter().count() > 0 && *initial_sync == InitialSync::Waiting {
+            *initial_sync = InitialSync::Confirmed;
+        }
+        if *initial_sync == InitialSync::Confirmed && unconfirmed.is_empty() {
+            *initial_sync = InitialSync::Complete;
+            sync_events.send(InitialSyncComplete);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn reset_system(
+        mut last_tick: ResMut<LastReceivedServerTick>,
+        mut initial_sync: ResMut<InitialSync>,
+        mut unconfirmed: ResMut<UnconfirmedEntities>,
+        mut server_tick: ResMut<ServerTick>,
+        mut estimate: ResMut<ServerTickEstimate>,
+        mut entity_map: ResMut<NetworkEntityMap>,
+        mut replication_rules: ResMut<ReplicationRules>,
+        playback: Option<ResMut<DiffPlayback>>,
+        jitter_buffer: Option<ResMut<JitterBuffer>>,
+        switch: Option<Res<TransportSwitch>>,
+    ) {
         last_tick.0 = 0;
-        // TODO 0.12: Possibly use built-in method.
-        entity_map.client_to_server = Default::default();
-        entity_map.server_to_client = Default::default();
+        *initial_sync = Default::default();
+        unconfirmed.clear();
+        if let Some(mut playback) = playback {
+            playback.clear();
+        }
+        if let Some(mut jitter_buffer) = jitter_buffer {
+            jitter_buffer.clear();
+        }
+        replication_rules.set_active_rule_set(None);
+        *server_tick = Default::default();
+        *estimate = Default::default();
+        entity_map.unsent.clear();
+        if switch.is_none() {
+            // TODO 0.12: Possibly use built-in method.
+            entity_map.client_to_server = Default::default();
+            entity_map.server_to_client = Default::default();
+            entity_map.deferred.clear();
+        }
     }
 }
 
+/// Deserializes a world diff message received from server and applies it to `world`.
+///
+/// The world could be any, not only the one with [`ClientPlugin`], for example a shadow world for
+/// verification or a headless observer. It should contain [`AppTypeRegistry`] with all replicated components.
+/// Entities are mapped using `entity_map`, so each world needs its own map.
+/// Messages applied by [`ClientPlugin`] can be obtained with [`ReceivedWorldDiffs`].
+///
+/// Returns the server tick and the tick of the applied diff. Resources of the world are not updated.
+pub fn apply_world_diff_message(
+    world: &mut World,
+    entity_map: &mut NetworkEntityMap,
+    message: &[u8],
+) -> bincode::Result<(ServerTick, Tick)> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let (server_tick, world_diff) = deserialize_world_diff(&registry, message)?;
+    let tick = world_diff.tick;
+    world_diff.apply(world, entity_map);
+
+    Ok((server_tick, tick))
+}
+
+/// Applies a world diff received over the unreliable replication channel and updates tick resources.
+///
+/// With [`SpawnBudget`] the diff could be applied partially, in this case skipped entities are
+/// acknowledged as unconfirmed and [`ServerTickApplied`] is not emitted.
+fn apply_unreliable_diff(world: &mut World, message: Bytes, notify: bool) {
+    if let Some(mut received_diffs) = world.get_resource_mut::<ReceivedWorldDiffs>() {
+        received_diffs.push(message.clone());
+    }
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let (server_tick, mut world_diff) = deserialize_world_diff(&registry, &message)
+        .expect("server should send only world diffs over replication channel");
+    let tick = world_diff.tick;
+    let (updated_entities, skipped_entities) =
+        world.resource_scope(|world, mut entity_map: Mut<NetworkEntityMap>| {
+            discard_outdated(world, &mut world_diff, &entity_map, server_tick);
+            let mut skipped_entities = Vec::new();
+            if let Some(budget) = world.get_resource::<SpawnBudget>().copied() {
+                let received_entities = diff_entities(&world_diff);
+                if !budget.limit(world, &mut world_diff, &entity_map) {
+                    let updated_entities: HashSet<_> =
+                        diff_entities(&world_diff).into_iter().collect();
+                    skipped_entities = received_entities
+                        .into_iter()
+                        .filter(|entity| !updated_entities.contains(entity))
+                        .collect();
+                }
+            }
+            let updated_entities = diff_entities(&world_diff);
+            world_diff.apply(world, &mut entity_map);
+            for &server_entity in &updated_entities {
+                if let MappedEntity::Mapped(client_entity) = entity_map.get_by_server(server_entity)
+                {
+                    if let Some(mut entity) = world.get_entity_mut(client_entity) {
+                        entity.insert(ReplicationTick(server_tick));
+                    }
+                }
+            }
+            (updated_entities, skipped_entities)
+        });
+    *world.resource_mut::<ServerTick>() = server_tick;
+
+    // The client has all changes up to the previous acknowledged tick, so entities
+    // over the budget will be resent by the server since it.
+    let last_tick = world.resource::<LastReceivedServerTick>().get();
+    let complete = skipped_entities.is_empty();
+    let mut unconfirmed = world.resource_mut::<UnconfirmedEntities>();
+    for entity in &updated_entities {
+        unconfirmed.remove(entity);
+    }
+    for entity in skipped_entities {
+        unconfirmed.entry(entity).or_insert(last_tick);
+    }
+    *world.resource_mut::<LastReceivedServerTick>() = tick.into();
+    if complete && notify {
+        world.send_event(ServerTickApplied(tick));
+    }
+}
+
+/// Returns server entities with changes in the diff.
+fn diff_entities(world_diff: &WorldDiff) -> Vec<Entity> {
+    world_diff
+        .archetypes
+        .iter()
+        .flat_map(|archetype_diff| &archetype_diff.entities)
+        .map(|entity_diff| entity_diff.entity)
+        .collect()
+}
+
+/// Removes changes of entities that already received an update from a newer server tick.
+///
+/// Unreliable diffs could arrive out of order and applying an older one would roll entities back.
+fn discard_outdated(
+    world: &World,
+    world_diff: &mut WorldDiff,
+    entity_map: &NetworkEntityMap,
+    server_tick: ServerTick,
+) {
+    for archetype_diff in &mut world_diff.archetypes {
+        archetype_diff.entities.retain(|entity_diff| {
+            let MappedEntity::Mapped(client_entity) = entity_map.get_by_server(entity_diff.entity)
+            else {
+                return true;
+            };
+            world
+                .get::<ReplicationTick>(client_entity)
+                .map_or(true, |last_tick| !last_tick.0.is_newer_than(server_tick))
+        });
+    }
+}
+
+fn playback_catching_up(world: &World) -> bool {
+    world
+        .get_resource::<DiffPlayback>()
+        .is_some_and(|playback| playback.catching_up)
+}
+
+/// Despawns a replicated client entity according to [`DespawnPolicy`] and emits [`ReplicationDespawned`].
+///
+/// Does nothing if the entity was already despawned locally.
+pub(super) fn despawn_replicated(world: &mut World, client_entity: Entity, reason: DespawnReason) {
+    let default_policy = world
+        .get_resource::<DespawnPolicy>()
+        .copied()
+        .unwrap_or_default();
+    let Some(mut entity) = world.get_entity_mut(client_entity) else {
+        return;
+    };
+    match entity
+        .get::<DespawnPolicy>()
+        .copied()
+        .unwrap_or(default_policy)
+    {
+        DespawnPolicy::Recursive => entity.despawn_recursive(),
+        DespawnPolicy::Single => {
+            if let Some(children) = entity.get::<Children>() {
+                let children = children.to_vec();
+                entity.remove_children(&children);
+            }
+            entity.remove_parent();
+            entity.despawn();
+        }
+    }
+
+    if let Some(mut despawned_events) = world.get_resource_mut::<Events<ReplicationDespawned>>() {
+        despawned_events.send(ReplicationDespawned {
+            entity: client_entity,
+            reason,
+        });
+    }
+}
+
+pub(super) fn deserialize_world_diff<'a>(
+    registry: &'a TypeRegistryInternal,
+    message: &[u8],
+) -> bincode::Result<(ServerTick, WorldDiff<'a>)> {
+    let keepalive = message_payload(message)?.len() == KEEPALIVE_PAYLOAD_SIZE;
+    let mut deserializer = message_deserializer(message)?;
+    let server_tick = ServerTick::deserialize(&mut deserializer)?;
+    let world_diff = if keepalive {
+        WorldDiff::new(Tick::new(u32::deserialize(&mut deserializer)?))
+    } else {
+        WorldDiffDeserializer::new(registry).deserialize(&mut deserializer)?
+    };
+
+    Ok((server_tick, world_diff))
+}
+
+/// Size of a diff without changes: server tick and change tick as `u32`.
+///
+/// Any other diff is larger because it also contains lengths of its sequences.
+const KEEPALIVE_PAYLOAD_SIZE: usize = 8;
+
+/// Copies of world diff messages applied on client.
+///
+/// Filled only if inserted as a resource and should be drained manually.
+/// Each message could be applied to another world with [`apply_world_diff_message`].
+#[derive(Default, Deref, DerefMut, Resource)]
+pub struct ReceivedWorldDiffs(pub Vec<Bytes>);
+
 /// Last received tick from server.
 ///
 /// Exists only on clients, sent to the server.
-#[derive(Default, Deserialize, Resource, Serialize)]
-pub(super) struct LastTick(u32);
+#[derive(Clone, Copy, Debug, Default, Deserialize, Resource, Serialize)]
+pub struct LastReceivedServerTick(u32);
 
-impl From<Tick> for LastTick {
+impl LastReceivedServerTick {
+    /// Returns the tick of the last applied world diff.
+    pub fn get(&self) -> Tick {
+        Tick::new(self.0)
+    }
+}
+
+impl From<Tick> for LastReceivedServerTick {
     fn from(value: Tick) -> Self {
         Self(value.get())
     }
 }
 
-impl From<LastTick> for Tick {
-    fn from(value: LastTick) -> Self {
+impl From<LastReceivedServerTick> for Tick {
+    fn from(value: LastReceivedServerTick) -> Self {
         Self::new(value.0)
     }
 }
 
-trait ApplyWorldDiffExt {
-    fn apply_world_diff(&mut self, world_diff: WorldDiff);
+/// Acknowledgment of received world diffs that client sends to server every frame.
+#[derive(Deserialize, Serialize)]
+pub(super) struct ReplicationAck {
+    /// Tick of the last applied world diff.
+    pub(super) tick: LastReceivedServerTick,
+
+    /// Server tick of the last applied world diff, [`None`] if nothing was received yet.
+    ///
+    /// Used by server to detect clients that lag behind, see [`CatchUpPolicy`](crate::server::CatchUpPolicy).
+    pub(super) server_tick: Option<ServerTick>,
+
+    /// Server entities whose changes from applied diffs were skipped with the last acknowledged ticks
+    /// before the skip.
+    ///
+    /// Server resends changes of these entities since their ticks, so only unconfirmed entities are resent.
+    pub(super) unconfirmed: Vec<(Entity, u32)>,
 }
 
-impl ApplyWorldDiffExt for Commands<'_, '_> {
-    fn apply_world_diff(&mut self, world_diff: WorldDiff) {
-        self.add(ApplyWorldDiff(world_diff));
+/// Server entities that were skipped by [`SpawnBudget`] and not applied since.
+///
+/// Stores the last acknowledged tick before the skip for each entity.
+#[derive(Default, Deref, DerefMut, Resource)]
+pub(crate) struct UnconfirmedEntities(HashMap<Entity, Tick>);
+
+impl UnconfirmedEntities {
+    /// Returns the estimated memory in bytes used by unconfirmed entities.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.len() * mem::size_of::<(Entity, Tick)>()
     }
 }
 
-struct ApplyWorldDiff(WorldDiff);
+/// An event that emitted on client after applying a world diff from server.
+///
+/// Contains the server tick of the applied diff, the same as in [`LastReceivedServerTick`].
+/// Emitted in [`ClientSet::Receive`] right after the world is updated, so systems that
+/// run after it observe the state of this tick.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ServerTickApplied(pub Tick);
 
-impl Command for ApplyWorldDiff {
-    fn apply(self, world: &mut World) {
-        let registry = world.resource::<AppTypeRegistry>().clone();
-        let registry = registry.read();
-        world.resource_scope(|world, mut entity_map: Mut<NetworkEntityMap>| {
-            // Map entities non-lazily in order to correctly map components that reference server entities.
-            for (entity, components) in map_entities(world, &mut entity_map, self.0.entities) {
-                for component_diff in components {
-                    apply_component_diff(
-                        world,
-                        &mut entity_map,
-                        &registry,
-                        entity,
-                        &component_diff,
-                    );
+/// An event that emitted on client once after connection when the initial world state is applied.
+///
+/// Emitted after server confirms that the client acknowledged all entities of the initial world,
+/// even if they were split over multiple ticks, so all entities visible to the client are spawned
+/// with their components and games could hide the loading screen and enable input.
+/// Server emits [`ClientSynced`](crate::server::ClientSynced) at the same time.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct InitialSyncComplete;
+
+/// Progress of [`InitialSyncComplete`] since connection.
+#[derive(Clone, Copy, Default, PartialEq, Resource)]
+enum InitialSync {
+    #[default]
+    Waiting,
+    /// Server confirmed the initial world state, but some entities are still unconfirmed.
+    Confirmed,
+    /// [`InitialSyncComplete`] was emitted.
+    Complete,
+}
+
+/// An event that emitted on client for each replicated entity despawned by server.
+///
+/// The entity is already despawned when the event is read.
+/// Use [`DespawnReason`] to distinguish entities that were destroyed on server
+/// from entities that just left client visibility, for example to play death effects only for real deaths.
+/// Server could also attach its own code to pick the right effect, see [`DespawnReason::Custom`].
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ReplicationDespawned {
+    /// Despawned client entity.
+    pub entity: Entity,
+    /// Why the entity was despawned.
+    pub reason: DespawnReason,
+}
+
+/// An event that emitted on client when replication inserts, updates or removes component `T`.
+///
+/// Emitted only for components registered with [`ReplicationRuleBuilder::notify`](crate::replication_core::ReplicationRuleBuilder::notify).
+/// Unlike change detection, it doesn't fire for local edits, so it's useful for systems that only
+/// care about changes from server. Components of despawned entities are not reported as removed,
+/// see [`ReplicationDespawned`] instead.
+#[derive(Event)]
+pub struct ComponentReplicated<T> {
+    /// Client entity.
+    pub entity: Entity,
+    pub change: ReplicatedChange,
+    marker: PhantomData<T>,
+}
+
+impl<T> Clone for ComponentReplicated<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ComponentReplicated<T> {}
+
+impl<T> Debug for ComponentReplicated<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct(&format!("ComponentReplicated<{}>", any::type_name::<T>()))
+            .field("entity", &self.entity)
+            .field("change", &self.change)
+            .finish()
+    }
+}
+
+/// How replication changed a component, see [`ComponentReplicated`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReplicatedChange {
+    Inserted,
+    Updated,
+    Removed,
+}
+
+/// Emitters of [`ComponentReplicated`] events for components registered with
+/// [`ReplicationRuleBuilder::notify`](crate::replication_core::ReplicationRuleBuilder::notify).
+#[derive(Default, Resource)]
+pub(crate) struct ReplicationNotifiers(HashMap<TypeId, ReplicationNotifier>);
+
+/// Sends [`ComponentReplicated`] event for a component.
+pub(crate) type ReplicationNotifier = fn(&mut World, Entity, ReplicatedChange);
+
+impl ReplicationNotifiers {
+    pub(crate) fn insert<T: Component>(&mut self) {
+        self.0.insert(TypeId::of::<T>(), notify::<T>);
+    }
+
+    /// Returns notifier for the component from the world if it was registered.
+    pub(crate) fn get(world: &World, type_id: TypeId) -> Option<ReplicationNotifier> {
+        world
+            .get_resource::<Self>()
+            .and_then(|notifiers| notifiers.0.get(&type_id))
+            .copied()
+    }
+}
+
+fn notify<T: Component>(world: &mut World, entity: Entity, change: ReplicatedChange) {
+    world.send_event(ComponentReplicated::<T> {
+        entity,
+        change,
+        marker: PhantomData,
+    });
+}
+
+/// An event that emitted on client once when it's disconnected from server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ClientDisconnected {
+    pub reason: ClientDisconnectReason,
+}
+
+/// The reason of [`ClientDisconnected`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientDisconnectReason {
+    /// Server kicked the client with the code from [`KickClient`](crate::server::KickClient).
+    Kicked(u32),
+    /// Server disconnected the client without a code or stopped.
+    DisconnectedByServer,
+    /// Client disconnected itself.
+    DisconnectedByClient,
+    /// No packets were received from server for too long.
+    TimedOut,
+    /// Server rejected the connection because all its [`ServerSlots`](crate::server::ServerSlots) are occupied.
+    ServerFull,
+    /// Server rejected [`AuthCredentials`] with the code from [`ServerAuth`](crate::server::ServerAuth) validator.
+    AuthRejected(u32),
+    /// Transport refused the connection, for example because it reached its maximum number of clients.
+    Denied,
+    /// The connect token expired before the connection was established.
+    TokenExpired,
+    /// Connection was terminated due to a protocol error in Renet.
+    Protocol(renet::DisconnectReason),
+}
+
+/// Credentials that client sends to server once after connection.
+///
+/// Server checks them if [`ServerAuth`](crate::server::ServerAuth) is present and starts
+/// replication to the client only after accepting them. The payload is defined by the game,
+/// like a session token received from a login service.
+///
+/// Insert as a resource on client before connecting.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct AuthCredentials(pub Vec<u8>);
+
+/// Extrapolated [`ServerTick`] between received updates.
+///
+/// Since the server sends updates according to its [`TickPolicy`](crate::server::TickPolicy),
+/// the duration of a single server tick is measured from the intervals between received ticks
+/// and used to advance the estimate every frame until the next update arrives.
+///
+/// Exists only on clients.
+#[derive(Default, Resource)]
+pub struct ServerTickEstimate {
+    /// Last received server tick.
+    last_tick: Option<u32>,
+
+    /// Time when [`Self::last_tick`] was received.
+    received_at: Duration,
+
+    /// Smoothed duration of a single server tick.
+    tick_duration: Option<Duration>,
+
+    /// Number of ticks passed since [`Self::last_tick`].
+    elapsed_ticks: f64,
+}
+
+impl ServerTickEstimate {
+    /// Weight of the latest measured interval in [`Self::tick_duration`].
+    const SMOOTHING_FACTOR: f64 = 0.1;
+
+    /// Returns the estimated current server tick.
+    pub fn tick(&self) -> u32 {
+        self.last_tick
+            .unwrap_or_default()
+            .wrapping_add(self.elapsed_ticks as u32)
+    }
+
+    /// Returns the fraction of the estimated tick that has passed, in range `[0, 1)`.
+    ///
+    /// Useful to interpolate animations between ticks.
+    pub fn overstep(&self) -> f64 {
+        self.elapsed_ticks.fract()
+    }
+
+    /// Returns the measured duration of a single server tick.
+    ///
+    /// Returns [`None`] if less than two ticks were received.
+    pub fn tick_duration(&self) -> Option<Duration> {
+        self.tick_duration
+    }
+
+    fn update(&mut self, server_tick: u32, now: Duration) {
+        match self.last_tick {
+            Some(last_tick) if last_tick == server_tick => (),
+            Some(last_tick) => {
+                let ticks = server_tick.wrapping_sub(last_tick);
+                let interval = now.saturating_sub(self.received_at) / ticks;
+                self.tick_duration = Some(match self.tick_duration {
+                    Some(tick_duration) => {
+                        tick_duration.mul_f64(1.0 - Self::SMOOTHING_FACTOR)
+                            + interval.mul_f64(Self::SMOOTHING_FACTOR)
+                    }
+                    None => interval,
+                });
+                self.last_tick = Some(server_tick);
+                self.received_at = now;
+            }
+            None => {
+                self.last_tick = Some(server_tick);
+                self.received_at = now;
+            }
+        }
+
+        self.elapsed_ticks = match self.tick_duration {
+            Some(tick_duration) if !tick_duration.is_zero() => {
+                now.saturating_sub(self.received_at).as_secs_f64() / tick_duration.as_secs_f64()
+            }
+            _ => 0.0,
+        };
+    }
+}
+
+/// Marks entities that are no longer visible for this client, but kept according to
+/// [`LeavePolicy::Hide`](crate::server::LeavePolicy::Hide).
+///
+/// Removed automatically when the entity becomes visible again.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct ReplicationHidden;
+
+/// Server tick of the last unreliable update applied to a replicated entity.
+///
+/// Inserted automatically on client. Updates from older ticks that arrive out of order
+/// are discarded for the entity instead of rolling it back.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ReplicationTick(ServerTick);
+
+impl ReplicationTick {
+    /// Returns the server tick of the last applied update.
+    pub fn get(&self) -> ServerTick {
+        self.0
+    }
+}
+
+/// Controls how client applies multiple world diffs received in the same frame, for example after a hitch.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Resource)]
+pub enum DiffCoalescing {
+    /// Apply only the newest diff.
+    ///
+    /// Each diff contains all changes since the last acknowledged tick, so the newest diff
+    /// already includes changes from the others and intermediate states are skipped.
+    #[default]
+    Coalesce,
+    /// Apply all diffs in order of their ticks.
+    ///
+    /// Useful if every intermediate state should be observed, [`ServerTickApplied`] is emitted for each diff.
+    Sequential,
+}
+
+/// Buffers received world diffs on client to apply one per frame and catches up when falling behind.
+///
+/// When inserted, diffs are applied sequentially regardless of [`DiffCoalescing`].
+/// If more than [`Self::target_delay`] diffs are buffered, for example after a hitch,
+/// the client applies up to [`Self::catch_up_rate`] diffs per frame until it's back within the delay.
+/// While catching up, [`ServerTickApplied`] is emitted only for the last diff applied in a frame.
+/// Use [`Self::is_catching_up`] and [`Self::buffered_count`] to display the progress.
+#[derive(Resource)]
+pub struct DiffPlayback {
+    /// Number of buffered diffs the client is allowed to fall behind.
+    pub target_delay: usize,
+    /// Maximum number of diffs applied per frame while catching up.
+    pub catch_up_rate: usize,
+    buffered: VecDeque<(ServerTick, Bytes)>,
+    catching_up: bool,
+}
+
+impl DiffPlayback {
+    pub fn new(target_delay: usize, catch_up_rate: usize) -> Self {
+        Self {
+            target_delay,
+            catch_up_rate,
+            buffered: Default::default(),
+            catching_up: false,
+        }
+    }
+
+    /// Returns `true` if the client applied more than one diff in the last frame to catch up.
+    pub fn is_catching_up(&self) -> bool {
+        self.catching_up
+    }
+
+    /// Returns the number of diffs waiting to be applied.
+    pub fn buffered_count(&self) -> usize {
+        self.buffered.len()
+    }
+
+    /// Adds sorted received diffs, skipping the ones that aren't newer than already buffered.
+    fn buffer(&mut self, messages: Vec<(ServerTick, Bytes)>) {
+        for (server_tick, message) in messages {
+            if self
+                .buffered
+                .back()
+                .map_or(true, |&(last_tick, _)| server_tick.is_newer_than(last_tick))
+            {
+                self.buffered.push_back((server_tick, message));
+            }
+        }
+    }
+
+    /// Takes diffs that should be applied in this frame.
+    fn take(&mut self) -> Vec<Bytes> {
+        self.catching_up = self.buffered.len() > self.target_delay;
+        let count = if self.catching_up {
+            self.catch_up_rate.max(1)
+        } else {
+            1
+        };
+        let count = count.min(self.buffered.len());
+        self.buffered
+            .drain(..count)
+            .map(|(_, message)| message)
+            .collect()
+    }
+
+    /// Returns the estimated memory in bytes used by buffered diffs.
+    pub(crate) fn memory_usage(&self) -> usize {
+        buffered_memory_usage(&self.buffered)
+    }
+
+    fn clear(&mut self) {
+        self.buffered.clear();
+        self.catching_up = false;
+    }
+}
+
+fn buffered_memory_usage(buffered: &VecDeque<(ServerTick, Bytes)>) -> usize {
+    buffered
+        .iter()
+        .map(|(_, message)| mem::size_of::<(ServerTick, Bytes)>() + message.len())
+        .sum()
+}
+
+/// Holds received world diffs on client and releases them at a steady cadence to absorb network jitter.
+///
+/// Each diff is released once the server tick, extrapolated from the newest received diff like
+/// [`ServerTickEstimate`], is [`Self::delay`] ticks ahead of it. Diffs that arrive with jitter smaller
+/// than the delay are still applied on time, at the cost of the delay added to the latency.
+/// Released diffs are then applied according to [`DiffCoalescing`] or [`DiffPlayback`].
+///
+/// Unlike [`Interpolated`](crate::interpolation::Interpolated), which smooths values between applied diffs,
+/// it controls when diffs are applied.
+#[derive(Resource)]
+pub struct JitterBuffer {
+    /// Number of ticks diffs are held for.
+    pub delay: u32,
+    buffered: VecDeque<(ServerTick, Bytes)>,
+    estimate: ServerTickEstimate,
+}
+
+impl JitterBuffer {
+    pub fn new(delay: u32) -> Self {
+        Self {
+            delay,
+            buffered: Default::default(),
+            estimate: Default::default(),
+        }
+    }
+
+    /// Returns the number of diffs waiting to be released.
+    pub fn buffered_count(&self) -> usize {
+        self.buffered.len()
+    }
+
+    /// Adds sorted received diffs, skipping the ones that aren't newer than already buffered.
+    fn buffer(&mut self, messages: Vec<(ServerTick, Bytes)>, now: Duration) {
+        for (server_tick, message) in messages {
+            if self
+                .buffered
+                .back()
+                .map_or(true, |&(last_tick, _)| server_tick.is_newer_than(last_tick))
+            {
+                self.buffered.push_back((server_tick, message));
+            }
+        }
+        let newest_tick = match (self.buffered.back(), self.estimate.last_tick) {
+            (Some(&(newest_tick, _)), Some(last_tick)) => {
+                let newest_tick = newest_tick.get();
+                (newest_tick.wrapping_sub(last_tick) as i32 > 0).then_some(newest_tick)
+            }
+            (Some(&(newest_tick, _)), None) => Some(newest_tick.get()),
+            (None, _) => None,
+        };
+        // Advance the estimate even without new diffs to keep releasing at a steady cadence.
+        if let Some(tick) = newest_tick.or(self.estimate.last_tick) {
+            self.estimate.update(tick, now);
+        }
+    }
+
+    /// Takes diffs whose release tick has come.
+    fn release(&mut self) -> Vec<(ServerTick, Bytes)> {
+        let release_tick = self.estimate.tick().wrapping_sub(self.delay);
+        let count = self
+            .buffered
+            .iter()
+            .take_while(|(server_tick, _)| release_tick.wrapping_sub(server_tick.get()) as i32 >= 0)
+            .count();
+        self.buffered.drain(..count).collect()
+    }
+
+    /// Returns the estimated memory in bytes used by buffered diffs.
+    pub(crate) fn memory_usage(&self) -> usize {
+        buffered_memory_usage(&self.buffered)
+    }
+
+    fn clear(&mut self) {
+        self.buffered.clear();
+        self.estimate = Default::default();
+    }
+}
+
+/// Controls how entities are despawned on client when the server despawns them.
+///
+/// Insert as a resource to set the policy for all entities or as a component
+/// to override it for a specific client entity.
+#[derive(Clone, Component, Copy, Debug, Default, Eq, PartialEq, Resource)]
+pub enum DespawnPolicy {
+    /// Despawn the entity with all its children.
+    #[default]
+    Recursive,
+    /// Despawn only the entity and detach its children, so client-only children like
+    /// particles or audio emitters can outlive their replicated parent.
+    Single,
+}
+
+/// Controls how client applies replicated components that reference server entities unknown to the client.
+///
+/// Only affects components registered with [`ReflectMapEntities`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Resource)]
+pub enum EntityMappingPolicy {
+    /// Apply components immediately and map unknown entities to reserved entities
+    /// with [`EntityMapper::get_or_reserve`](bevy::ecs::entity::EntityMapper::get_or_reserve).
+    ///
+    /// Reserved entities are never spawned, so if the referenced entity is never replicated,
+    /// the component will point to an entity that doesn't exist.
+    #[default]
+    Reserve,
+    /// Defer such components until all referenced entities are replicated.
+    ///
+    /// Until then the entity keeps the previous value of the component or doesn't have it at all.
+    /// A newer value or removal of the component from server replaces the deferred value.
+    /// Deferred values are dropped when the entity is despawned.
+    Defer,
+}
+
+/// Entities spawned in advance to be used for replicated spawns.
+///
+/// Insert as a resource on client to avoid allocation spikes when many entities arrive at once,
+/// for example when joining a large world. Pooled entities are empty until they are used.
+/// The pool is refilled to its size in `PostUpdate`, when the spawn spike is over.
+/// If the pool is exhausted, entities are spawned as usual.
+#[derive(Resource)]
+pub struct ClientEntityPool {
+    size: usize,
+    entities: Vec<Entity>,
+}
+
+impl ClientEntityPool {
+    /// Creates a pool that keeps `size` entities available.
+    pub fn new(size: usize) -> Self {
+        Self {
+            size,
+            entities: Vec::with_capacity(size),
+        }
+    }
+
+    /// Returns the number of entities the pool is refilled to.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns currently available entities.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    fn missing(&self) -> usize {
+        self.size.saturating_sub(self.entities.len())
+    }
+}
+
+/// Limits the number of replicated entities spawned per frame on client.
+///
+/// Entities over the limit are dropped from the received world diff with all their components
+/// and acknowledged as unconfirmed, so the server keeps resending only them until all are spawned.
+/// Changes of already spawned entities and despawns are not limited.
+/// Useful to spread spawns over multiple frames when joining a large world.
+///
+/// Entities are spawned only after new entities they reference, like parents with [`ParentSync`](crate::parent_sync::ParentSync).
+/// Entities with the same [`SpawnGroup`] are always spawned in the same frame, even if the group exceeds the budget.
+/// Only unreliable world diffs are limited, reliable ones are always applied completely.
+#[derive(Clone, Copy, Debug, Deref, DerefMut, Resource)]
+pub struct SpawnBudget(pub usize);
+
+impl SpawnBudget {
+    /// Removes entities over the budget from the diff.
+    ///
+    /// Entities are spawned only after new entities they reference from the same diff,
+    /// so references from [`ReflectMapEntities`] components, like [`ParentSync`](crate::parent_sync::ParentSync),
+    /// never point to entities that were left for the next frames.
+    ///
+    /// Returns `false` if any entities were removed.
+    fn limit(
+        &self,
+        world: &mut World,
+        world_diff: &mut WorldDiff,
+        entity_map: &NetworkEntityMap,
+    ) -> bool {
+        // Keep the diff order to spawn higher-priority entities first.
+        let mut new_entities = Vec::new();
+        let mut new_set = HashSet::new();
+        for archetype_diff in &world_diff.archetypes {
+            for entity_diff in &archetype_diff.entities {
+                if !matches!(
+                    entity_map.get_by_server(entity_diff.entity),
+                    MappedEntity::Mapped(_)
+                ) && new_set.insert(entity_diff.entity)
+                {
+                    new_entities.push(entity_diff.entity);
                 }
             }
+        }
+        if new_entities.len() <= self.0 {
+            return true;
+        }
 
-            for server_entity in self.0.despawns {
-                // The entity might have already been deleted with the last diff,
-                // but the server might not yet have received confirmation from the
-                // client and could include the deletion in the latest diff.
-                if let Some(client_entity) = entity_map.remove_by_server(server_entity) {
-                    world.entity_mut(client_entity).despawn_recursive();
+        // Entities from the same spawn group are spawned together as a single unit.
+        let entity_groups = spawn_groups(world_diff, &new_set);
+        let mut group_members = HashMap::<_, Vec<_>>::new();
+        for &entity in &new_entities {
+            if let Some(group) = entity_groups.get(&entity) {
+                group_members.entry(*group).or_default().push(entity);
+            }
+        }
+        let mut units = Vec::new();
+        for &entity in &new_entities {
+            match entity_groups.get(&entity) {
+                Some(group) => {
+                    if let Some(members) = group_members.remove(group) {
+                        units.push(members);
+                    }
                 }
+                None => units.push(vec![entity]),
             }
-        });
+        }
+
+        let dependencies = new_entity_dependencies(world, world_diff, entity_map, &new_set);
+        let unit_dependencies: Vec<HashSet<_>> = units
+            .iter()
+            .map(|unit| {
+                unit.iter()
+                    .filter_map(|entity| dependencies.get(entity))
+                    .flatten()
+                    .filter(|entity| !unit.contains(entity))
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        let mut spawned = HashSet::new();
+        let mut pending: Vec<_> = (0..units.len()).collect();
+        let fits = |spawned: &HashSet<Entity>, unit: &[Entity]| {
+            // Allow a group larger than the budget to not block it forever.
+            spawned.is_empty() || spawned.len() + unit.len() <= self.0
+        };
+        while !pending.is_empty() && spawned.len() < self.0 {
+            let previous_len = pending.len();
+            let mut full = false;
+            pending.retain(|&index| {
+                if full || !unit_dependencies[index].is_subset(&spawned) {
+                    return true;
+                }
+                if !fits(&spawned, &units[index]) {
+                    full = true;
+                    return true;
+                }
+                spawned.extend(&units[index]);
+                false
+            });
+
+            if full {
+                break;
+            }
+            if pending.len() == previous_len {
+                // Entities reference each other, fallback to the diff order.
+                let index = pending[0];
+                if !fits(&spawned, &units[index]) {
+                    break;
+                }
+                spawned.extend(&units[index]);
+                pending.remove(0);
+            }
+        }
+
+        for archetype_diff in &mut world_diff.archetypes {
+            archetype_diff.entities.retain(|entity_diff| {
+                !new_set.contains(&entity_diff.entity) || spawned.contains(&entity_diff.entity)
+            });
+        }
+
+        false
     }
 }
 
-/// Maps entities received from server into client entities.
-fn map_entities(
-    world: &mut World,
-    entity_map: &mut NetworkEntityMap,
-    entities: HashMap<Entity, Vec<ComponentDiff>>,
-) -> Vec<(Entity, Vec<ComponentDiff>)> {
-    let mut mapped_entities = Vec::with_capacity(entities.len());
-    for (server_entity, components) in entities {
-        let client_entity = entity_map.get_by_server_or_spawn(world, server_entity);
-        mapped_entities.push((client_entity, components));
+/// Returns [`SpawnGroup`]s of new entities from the diff.
+fn spawn_groups(world_diff: &WorldDiff, new_entities: &HashSet<Entity>) -> HashMap<Entity, u64> {
+    let mut groups = HashMap::new();
+    for archetype_diff in &world_diff.archetypes {
+        let Some(index) = archetype_diff
+            .components
+            .iter()
+            .filter_map(|component_diff| match component_diff {
+                ComponentDiff::Changed(registration) => Some(registration),
+                ComponentDiff::Removed(_) => None,
+            })
+            .position(|registration| registration.type_id() == TypeId::of::<SpawnGroup>())
+        else {
+            continue;
+        };
+        for entity_diff in archetype_diff
+            .entities
+            .iter()
+            .filter(|entity_diff| new_entities.contains(&entity_diff.entity))
+        {
+            if let Some(group) = SpawnGroup::from_reflect(&*entity_diff.components[index]) {
+                groups.insert(entity_diff.entity, group.0);
+            }
+        }
     }
-    mapped_entities
+
+    groups
 }
 
-fn apply_component_diff(
+/// Returns other new entities from the diff that are referenced by components of each new entity.
+fn new_entity_dependencies(
     world: &mut World,
-    entity_map: &mut NetworkEntityMap,
-    registry: &TypeRegistryInternal,
-    client_entity: Entity,
-    component_diff: &ComponentDiff,
-) {
-    let type_name = component_diff.type_name();
-    let registration = registry
-        .get_with_name(type_name)
-        .unwrap_or_else(|| panic!("{type_name} should be registered"));
-
-    let reflect_component = registration
-        .data::<ReflectComponent>()
-        .unwrap_or_else(|| panic!("{type_name} should have reflect(Component)"));
-
-    match component_diff {
-        ComponentDiff::Changed(component) => {
-            reflect_component.apply_or_insert(&mut world.entity_mut(client_entity), &**component);
-            if let Some(reflect_map_entities) = registration.data::<ReflectMapEntities>() {
-                // TODO 0.12: Remove mutable access.
-                reflect_map_entities.map_entities(
+    world_diff: &WorldDiff,
+    entity_map: &NetworkEntityMap,
+    new_entities: &HashSet<Entity>,
+) -> HashMap<Entity, HashSet<Entity>> {
+    let mut dependencies = HashMap::<_, HashSet<_>>::new();
+    let scratch_entity = world.spawn_empty().id();
+    for archetype_diff in &world_diff.archetypes {
+        let changed_registrations = archetype_diff
+            .components
+            .iter()
+            .filter_map(|component_diff| match component_diff {
+                ComponentDiff::Changed(registration) => Some(registration),
+                ComponentDiff::Removed(_) => None,
+            });
+        for entity_diff in archetype_diff
+            .entities
+            .iter()
+            .filter(|entity_diff| new_entities.contains(&entity_diff.entity))
+        {
+            for (registration, component) in
+                changed_registrations.clone().zip(&entity_diff.components)
+            {
+                let (Some(reflect_component), Some(reflect_map_entities)) = (
+                    registration.data::<ReflectComponent>(),
+                    registration.data::<ReflectMapEntities>(),
+                ) else {
+                    continue;
+                };
+                let unmapped = entity_map.unmapped_entities(
                     world,
-                    &mut entity_map.server_to_client,
-                    &[client_entity],
+                    scratch_entity,
+                    reflect_component,
+                    reflect_map_entities,
+                    &**component,
+                );
+                dependencies.entry(entity_diff.entity).or_default().extend(
+                    unmapped.into_iter().filter(|&entity| {
+                        entity != entity_diff.entity && new_entities.contains(&entity)
+                    }),
                 );
             }
         }
-        ComponentDiff::Removed(_) => reflect_component.remove(&mut world.entity_mut(client_entity)),
+    }
+    world.despawn(scratch_entity);
+
+    dependencies
+}
+
+/// Spreads despawns of replicated entities over multiple frames on client.
+///
+/// When inserted, entities despawned by server are unmapped immediately, but queued instead of despawned.
+/// Up to [`Self::per_frame`] queued entities are despawned each frame in `PostUpdate`
+/// according to [`DespawnPolicy`], emitting [`ReplicationDespawned`] for each of them.
+/// Queued entities no longer receive updates from server.
+///
+/// Use zero per frame to despawn only with [`Self::flush`], for example in a cleanup phase
+/// after the end of a round.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct DespawnBudget {
+    /// Maximum number of queued entities despawned each frame.
+    pub per_frame: usize,
+    pending: VecDeque<(Entity, DespawnReason)>,
+}
+
+impl DespawnBudget {
+    pub fn new(per_frame: usize) -> Self {
+        Self {
+            per_frame,
+            pending: Default::default(),
+        }
+    }
+
+    /// Returns the number of entities waiting for despawn.
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Immediately despawns all queued entities.
+    ///
+    /// Could be used as an exclusive system.
+    pub fn flush(world: &mut World) {
+        let pending = mem::take(&mut world.resource_mut::<DespawnBudget>().pending);
+        for (client_entity, reason) in pending {
+            despawn_replicated(world, client_entity, reason);
+        }
+    }
+
+    /// Returns the estimated memory in bytes used by queued despawns.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.pending.len() * mem::size_of::<(Entity, DespawnReason)>()
+    }
+
+    pub(super) fn push(&mut self, client_entity: Entity, reason: DespawnReason) {
+        self.pending.push_back((client_entity, reason));
     }
 }
 
@@ -211,38 +1469,259 @@ pub enum ClientSet {
 
 /// Maps server entities to client entities and vice versa.
 ///
+/// Mappings are removed automatically when entities are despawned by server or locally on client.
+///
 /// Used only on client.
 #[derive(Default, Resource)]
 pub struct NetworkEntityMap {
     server_to_client: EntityMap,
     client_to_server: EntityMap,
+
+    /// Server and client entity pairs that wasn't sent to the server yet.
+    unsent: Vec<(Entity, Entity)>,
+
+    /// Server and client entity pairs of the most recent despawns, oldest first.
+    despawned: VecDeque<(Entity, Entity)>,
+
+    /// Components waiting for their referenced entities according to [`EntityMappingPolicy::Defer`].
+    deferred: Vec<DeferredComponent>,
 }
 
 impl NetworkEntityMap {
+    /// Number of the most recent despawns remembered to distinguish them in lookups.
+    const DESPAWN_HISTORY_LEN: usize = 1024;
+
+    /// Returns the estimated memory in bytes used by mappings, remembered despawns and deferred components.
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.server_to_client.len()
+            + self.client_to_server.len()
+            + self.unsent.len()
+            + self.despawned.len())
+            * mem::size_of::<(Entity, Entity)>()
+            + self.deferred.len() * mem::size_of::<DeferredComponent>()
+    }
+
+    /// Maps a server entity to a client entity.
+    ///
+    /// The mapping will be sent to the server to map entities inside client events.
     pub fn insert(&mut self, server_entity: Entity, client_entity: Entity) {
         self.server_to_client.insert(server_entity, client_entity);
         self.client_to_server.insert(client_entity, server_entity);
+        self.unsent.push((server_entity, client_entity));
     }
 
-    fn get_by_server_or_spawn(&mut self, world: &mut World, server_entity: Entity) -> Entity {
+    pub(super) fn get_by_server_or_spawn(
+        &mut self,
+        world: &mut World,
+        server_entity: Entity,
+    ) -> Entity {
         *self
             .server_to_client
             .entry(server_entity)
             .or_insert_with(|| {
-                let client_entity = world.spawn(Replication).id();
+                let pooled_entity = world
+                    .get_resource_mut::<ClientEntityPool>()
+                    .and_then(|mut pool| pool.entities.pop());
+                let client_entity = match pooled_entity {
+                    Some(entity) => {
+                        world.entity_mut(entity).insert(Replication);
+                        entity
+                    }
+                    None => world.spawn(Replication).id(),
+                };
                 self.client_to_server.insert(client_entity, server_entity);
+                self.unsent.push((server_entity, client_entity));
                 client_entity
             })
     }
 
-    fn remove_by_server(&mut self, server_entity: Entity) -> Option<Entity> {
+    pub(super) fn remove_by_server(&mut self, server_entity: Entity) -> Option<Entity> {
         let client_entity = self.server_to_client.remove(server_entity);
         if let Some(client_entity) = client_entity {
             self.client_to_server.remove(client_entity);
+            self.remember_despawn(server_entity, client_entity);
         }
         client_entity
     }
 
+    fn remove_by_client(&mut self, client_entity: Entity) -> Option<Entity> {
+        let server_entity = self.client_to_server.remove(client_entity);
+        if let Some(server_entity) = server_entity {
+            self.server_to_client.remove(server_entity);
+            self.remember_despawn(server_entity, client_entity);
+        }
+        server_entity
+    }
+
+    fn remember_despawn(&mut self, server_entity: Entity, client_entity: Entity) {
+        self.unsent.retain(|&(entity, _)| entity != server_entity);
+        self.deferred
+            .retain(|component| component.client_entity != client_entity);
+        if self.despawned.len() == Self::DESPAWN_HISTORY_LEN {
+            self.despawned.pop_front();
+        }
+        self.despawned.push_back((server_entity, client_entity));
+    }
+
+    /// Returns the client entity for a server entity, distinguishing recently despawned entities.
+    pub fn get_by_server(&self, server_entity: Entity) -> MappedEntity {
+        if let Some(client_entity) = self.server_to_client.get(server_entity) {
+            MappedEntity::Mapped(client_entity)
+        } else if self
+            .despawned
+            .iter()
+            .any(|&(entity, _)| entity == server_entity)
+        {
+            MappedEntity::Despawned
+        } else {
+            MappedEntity::Unknown
+        }
+    }
+
+    /// Same as [`Self::get_by_server`], but returns the server entity for a client entity.
+    pub fn get_by_client(&self, client_entity: Entity) -> MappedEntity {
+        if let Some(server_entity) = self.client_to_server.get(client_entity) {
+            MappedEntity::Mapped(server_entity)
+        } else if self
+            .despawned
+            .iter()
+            .any(|&(_, entity)| entity == client_entity)
+        {
+            MappedEntity::Despawned
+        } else {
+            MappedEntity::Unknown
+        }
+    }
+
+    /// Maps entities inside components of `entities` using [`ReflectMapEntities`].
+    pub(super) fn map_entities(
+        &mut self,
+        world: &mut World,
+        reflect_map_entities: &ReflectMapEntities,
+        entities: &[Entity],
+    ) {
+        // TODO 0.12: Remove mutable access.
+        reflect_map_entities.map_entities(world, &mut self.server_to_client, entities);
+    }
+
+    /// Returns server entities referenced by `component` that have no client mapping.
+    ///
+    /// The component is temporarily inserted into `scratch_entity` to access it with `reflect_map_entities`.
+    pub(super) fn unmapped_entities(
+        &self,
+        world: &mut World,
+        scratch_entity: Entity,
+        reflect_component: &ReflectComponent,
+        reflect_map_entities: &ReflectMapEntities,
+        component: &dyn Reflect,
+    ) -> Vec<Entity> {
+        reflect_component.insert(&mut world.entity_mut(scratch_entity), component);
+        let mut reserved = EntityMap::default();
+        reflect_map_entities.map_entities(world, &mut reserved, &[scratch_entity]);
+        reflect_component.remove(&mut world.entity_mut(scratch_entity));
+
+        reserved
+            .keys()
+            .filter(|&entity| self.server_to_client.get(entity).is_none())
+            .collect()
+    }
+
+    /// Stores `component` until all `dependencies` are mapped.
+    pub(super) fn defer(
+        &mut self,
+        client_entity: Entity,
+        type_id: TypeId,
+        component: Box<dyn Reflect>,
+        dependencies: Vec<Entity>,
+    ) {
+        self.discard_deferred(client_entity, type_id);
+        self.deferred.push(DeferredComponent {
+            client_entity,
+            type_id,
+            component,
+            dependencies,
+        });
+    }
+
+    /// Drops deferred value of a component if any.
+    pub(super) fn discard_deferred(&mut self, client_entity: Entity, type_id: TypeId) {
+        self.deferred.retain(|component| {
+            component.client_entity != client_entity || component.type_id != type_id
+        });
+    }
+
+    /// Applies deferred components whose referenced entities are now mapped.
+    pub(super) fn apply_deferred(&mut self, world: &mut World) {
+        let (ready, deferred): (Vec<_>, _) =
+            mem::take(&mut self.deferred)
+                .into_iter()
+                .partition(|component| {
+                    component
+                        .dependencies
+                        .iter()
+                        .all(|&entity| self.server_to_client.get(entity).is_some())
+                });
+        self.deferred = deferred;
+        if ready.is_empty() {
+            return;
+        }
+
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        for deferred in ready {
+            let registration = registry
+                .get(deferred.type_id)
+                .expect("deferred component should be registered");
+            let reflect_component = registration.data::<ReflectComponent>().unwrap_or_else(|| {
+                panic!(
+                    "{} should have reflect(Component)",
+                    registration.type_name()
+                )
+            });
+            let Some(mut entity) = world.get_entity_mut(deferred.client_entity) else {
+                continue;
+            };
+            let change = if entity.contains_type_id(deferred.type_id) {
+                ReplicatedChange::Updated
+            } else {
+                ReplicatedChange::Inserted
+            };
+            reflect_component.apply_or_insert(&mut entity, &*deferred.component);
+            if let Some(notifier) = ReplicationNotifiers::get(world, deferred.type_id) {
+                notifier(world, deferred.client_entity, change);
+            }
+            if let Some(reflect_map_entities) = registration.data::<ReflectMapEntities>() {
+                self.map_entities(world, reflect_map_entities, &[deferred.client_entity]);
+            }
+        }
+    }
+
+    /// Returns the number of components deferred according to [`EntityMappingPolicy::Defer`].
+    pub fn deferred_count(&self) -> usize {
+        self.deferred.len()
+    }
+
+    /// Maps server entities inside `value` to client entities using [`MapEntities`].
+    ///
+    /// Returns the first server entity that has no client mapping as an error.
+    /// Unmapped entities are replaced with [`Entity::PLACEHOLDER`] and no new mappings are added.
+    pub fn map_to_client<T: MapEntities>(
+        &self,
+        world: &mut World,
+        value: &mut T,
+    ) -> Result<(), Entity> {
+        try_map_entities(world, &self.server_to_client, value)
+    }
+
+    /// Same as [`Self::map_to_client`], but maps client entities to server entities.
+    pub fn map_to_server<T: MapEntities>(
+        &self,
+        world: &mut World,
+        value: &mut T,
+    ) -> Result<(), Entity> {
+        try_map_entities(world, &self.client_to_server, value)
+    }
+
     pub fn to_client(&self) -> &EntityMap {
         &self.server_to_client
     }
@@ -251,3 +1730,53 @@ impl NetworkEntityMap {
         &self.client_to_server
     }
 }
+
+/// Component value with unmapped server entities.
+struct DeferredComponent {
+    client_entity: Entity,
+    type_id: TypeId,
+    component: Box<dyn Reflect>,
+    /// Referenced server entities that should be mapped before applying.
+    dependencies: Vec<Entity>,
+}
+
+/// Result of an entity lookup in [`NetworkEntityMap`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MappedEntity {
+    /// The entity is mapped to the contained entity.
+    Mapped(Entity),
+    /// The entity was mapped, but was recently despawned on server or client.
+    Despawned,
+    /// The entity was never mapped or was despawned long ago.
+    Unknown,
+}
+
+/// Maps entities inside `value` using `entity_map` without inserting anything into it.
+///
+/// Unlike [`EntityMap::world_scope`], unmapped entities are reported as an error
+/// and replaced with [`Entity::PLACEHOLDER`] instead of reserving new entities.
+pub(super) fn try_map_entities<T: MapEntities>(
+    world: &mut World,
+    entity_map: &EntityMap,
+    value: &mut T,
+) -> Result<(), Entity> {
+    // Replace all entities with reserved ones first to collect entities from the value.
+    let mut reserved = EntityMap::default();
+    reserved.world_scope(world, |_, entity_mapper| value.map_entities(entity_mapper));
+
+    let mut unmapped = None;
+    let mut mapped = EntityMap::default();
+    for (entity, reserved_entity) in reserved.iter() {
+        let mapped_entity = entity_map.get(entity).unwrap_or_else(|| {
+            unmapped.get_or_insert(entity);
+            Entity::PLACEHOLDER
+        });
+        mapped.insert(reserved_entity, mapped_entity);
+    }
+    mapped.world_scope(world, |_, entity_mapper| value.map_entities(entity_mapper));
+
+    match unmapped {
+        Some(entity) => Err(entity),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use bevy::{
+    app::{AppExit, PluginGroupBuilder, ScheduleRunnerPlugin},
+    core::{FrameCountPlugin, TaskPoolPlugin, TypeRegistrationPlugin},
+    prelude::*,
+    time::TimePlugin,
+};
+use bevy_renet::renet::RenetServer;
+
+use crate::{
+    client::ClientPlugin,
+    replication_core::ReplicationCorePlugin,
+    server::{ServerPlugin, ServerSet, TickPolicy},
+    ReplicationPlugins,
+};
+
+/// Plugins for a headless dedicated server.
+///
+/// Contains [`ReplicationPlugins`] without [`ClientPlugin`], the minimal Bevy plugins
+/// and [`DedicatedServerPlugin`]. The app runs in a loop that sleeps to keep `tick_rate`
+/// updates per second and sends world diffs on each update.
+///
+/// # Panics
+///
+/// Panics on building if `tick_rate` is zero.
+pub struct DedicatedServerPlugins {
+    /// Number of updates per second.
+    pub tick_rate: u16,
+}
+
+impl PluginGroup for DedicatedServerPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        assert!(self.tick_rate > 0, "tick rate should be greater than zero");
+        let update_duration = Duration::from_secs_f64(1.0 / self.tick_rate as f64);
+        ReplicationPlugins
+            .build()
+            .disable::<ClientPlugin>()
+            .set(ServerPlugin::new(TickPolicy::Manual))
+            .add_before::<ReplicationCorePlugin, _>(TaskPoolPlugin::default())
+            .add_before::<ReplicationCorePlugin, _>(TypeRegistrationPlugin)
+            .add_before::<ReplicationCorePlugin, _>(FrameCountPlugin)
+            .add_before::<ReplicationCorePlugin, _>(TimePlugin)
+            .add_before::<ReplicationCorePlugin, _>(ScheduleRunnerPlugin::run_loop(update_duration))
+            .add(DedicatedServerPlugin::default())
+    }
+}
+
+/// Handles shutdown and admin commands of a dedicated server.
+///
+/// On SIGINT, [`ShutdownHandle::request`] or the `stop` admin command, emits [`ShutdownRequested`]
+/// for user hooks, like saving the world, disconnects all clients and exits the app on the next update
+/// to let the transport send disconnect packets.
+///
+/// Lines from stdin are emitted as [`AdminCommand`] events.
+pub struct DedicatedServerPlugin {
+    /// Request shutdown on SIGINT.
+    ///
+    /// The handler could be set only once per process.
+    pub handle_sigint: bool,
+    /// Read admin commands from stdin.
+    pub read_stdin: bool,
+}
+
+impl Default for DedicatedServerPlugin {
+    fn default() -> Self {
+        Self {
+            handle_sigint: true,
+            read_stdin: true,
+        }
+    }
+}
+
+impl Plugin for DedicatedServerPlugin {
+    fn build(&self, app: &mut App) {
+        let shutdown = ShutdownHandle::default();
+        if self.handle_sigint {
+            let handle = shutdown.clone();
+            if let Err(e) = ctrlc::set_handler(move || handle.request()) {
+                error!("unable to set SIGINT handler: {e}");
+            }
+        }
+
+        if self.read_stdin {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for line in io::stdin().lock().lines() {
+                    let Ok(line) = line else {
+                        break;
+                    };
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            app.insert_resource(StdinCommands(Mutex::new(receiver)));
+        }
+
+        app.insert_resource(shutdown)
+            .add_event::<AdminCommand>()
+            .add_event::<ShutdownRequested>()
+            .add_systems(
+                First,
+                (
+                    Self::stdin_system.run_if(resource_exists::<StdinCommands>()),
+                    Self::signal_system,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                Self::disconnect_system
+                    .after(ServerSet::Send)
+                    .run_if(resource_exists::<RenetServer>()),
+            )
+            .add_systems(Last, Self::exit_system);
+    }
+}
+
+impl DedicatedServerPlugin {
+    fn stdin_system(
+        stdin_commands: Res<StdinCommands>,
+        mut admin_commands: EventWriter<AdminCommand>,
+    ) {
+        let receiver = stdin_commands
+            .0
+            .lock()
+            .expect("stdin receiver should not be poisoned");
+        for line in receiver.try_iter() {
+            if let Some(command) = AdminCommand::parse(&line) {
+                admin_commands.send(command);
+            }
+        }
+    }
+
+    /// Emits [`ShutdownRequested`] once after a shutdown request.
+    fn signal_system(
+        mut admin_commands: EventReader<AdminCommand>,
+        mut shutdown_events: EventWriter<ShutdownRequested>,
+        shutdown: Res<ShutdownHandle>,
+        mut requested: Local<bool>,
+    ) {
+        if admin_commands.iter().any(|command| command.name == "stop") {
+            shutdown.request();
+        }
+
+        if !*requested && shutdown.is_requested() {
+            info!("shutting down");
+            *requested = true;
+            shutdown_events.send(ShutdownRequested);
+        }
+    }
+
+    fn disconnect_system(
+        mut shutdown_events: EventReader<ShutdownRequested>,
+        mut server: ResMut<RenetServer>,
+    ) {
+        if shutdown_events.iter().count() > 0 {
+            server.disconnect_all();
+        }
+    }
+
+    /// Exits on the update after [`ShutdownRequested`].
+    fn exit_system(
+        mut shutdown_events: EventReader<ShutdownRequested>,
+        mut exit_events: EventWriter<AppExit>,
+        mut pending: Local<bool>,
+    ) {
+        if *pending {
+            exit_events.send(AppExit);
+        }
+        if shutdown_events.iter().count() > 0 {
+            *pending = true;
+        }
+    }
+}
+
+/// Requests shutdown of a dedicated server.
+///
+/// Could be cloned and used from other threads.
+#[derive(Clone, Default, Resource)]
+pub struct ShutdownHandle(Arc<AtomicBool>);
+
+impl ShutdownHandle {
+    pub fn request(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// An event that emitted once in [`First`] when shutdown of a dedicated server is requested.
+///
+/// Clients are disconnected in [`PostUpdate`] of the same update, so systems in [`Update`] could still
+/// send them final messages.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ShutdownRequested;
+
+/// A command for a dedicated server, read from stdin or sent manually.
+///
+/// The first word is the name, the rest are arguments. `stop` requests shutdown,
+/// other commands should be handled by user systems.
+#[derive(Clone, Debug, Event, PartialEq, Eq)]
+pub struct AdminCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl AdminCommand {
+    /// Splits a line by whitespaces, returns [`None`] for empty lines.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace().map(ToString::to_string);
+        let name = words.next()?;
+        Some(Self {
+            name,
+            args: words.collect(),
+        })
+    }
+}
+
+/// Lines read from stdin by a background thread.
+#[derive(Resource)]
+struct StdinCommands(Mutex<Receiver<String>>);
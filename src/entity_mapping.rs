@@ -0,0 +1,235 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+};
+
+use bevy::{
+    ecs::entity::EntityMapper,
+    prelude::*,
+    utils::hashbrown::{HashMap as HashbrownMap, HashSet as HashbrownSet},
+};
+/// Derives [`MapEntities`](bevy::ecs::entity::MapEntities) by mapping fields marked with `#[entities]`.
+///
+/// Fields are mapped with [`MapNestedEntities`], so they could be an [`Entity`] or any supported
+/// container of entities, like `Option<Entity>` or `Vec<Entity>`. Works for structs and enums,
+/// which makes it convenient for events registered with
+/// [`ServerEventAppExt::add_mapped_server_event()`](crate::network_event::server_event::ServerEventAppExt::add_mapped_server_event)
+/// or [`ClientEventAppExt::add_mapped_client_event()`](crate::network_event::client_event::ClientEventAppExt::add_mapped_client_event):
+///
+/// ```
+/// use bevy::prelude::*;
+/// use bevy_replicon::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, Event, MapEventEntities, Serialize)]
+/// enum CombatEvent {
+///     Attack {
+///         #[entities]
+///         target: Entity,
+///         damage: u32,
+///     },
+///     Heal(#[entities] Vec<Entity>, u32),
+///     Retreat,
+/// }
+///
+/// # let mut app = App::new();
+/// # app.add_plugins(ReplicationPlugins);
+/// app.add_mapped_server_event::<CombatEvent>(SendPolicy::reliable().ordered());
+/// ```
+pub use bevy_replicon_derive::MapEventEntities;
+
+/// Maps all entities stored inside a value.
+///
+/// Implemented for [`Entity`] and common containers of such values, including map keys,
+/// so [`MapEntities`](bevy::ecs::entity::MapEntities) for types with collections of entities
+/// can be implemented by calling it for each field:
+///
+/// ```
+/// use bevy::{
+///     ecs::{
+///         entity::{EntityMapper, MapEntities},
+///         reflect::ReflectMapEntities,
+///     },
+///     prelude::*,
+///     utils::HashMap,
+/// };
+/// use bevy_replicon::prelude::*;
+///
+/// #[derive(Component, Reflect)]
+/// #[reflect(Component, MapEntities)]
+/// struct Squad {
+///     leader: Option<Entity>,
+///     members: Vec<Entity>,
+///     targets: HashMap<Entity, u32>,
+/// }
+///
+/// impl MapEntities for Squad {
+///     fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+///         self.leader.map_nested_entities(entity_mapper);
+///         self.members.map_nested_entities(entity_mapper);
+///         self.targets.map_nested_entities(entity_mapper);
+///     }
+/// }
+/// # impl FromWorld for Squad {
+/// #     fn from_world(_world: &mut World) -> Self {
+/// #         Self {
+/// #             leader: None,
+/// #             members: Default::default(),
+/// #             targets: Default::default(),
+/// #         }
+/// #     }
+/// # }
+/// ```
+///
+/// Don't forget to register field types with [`App::register_type`] for reflection,
+/// e.g. `Vec<Entity>` and `HashMap<Entity, u32>` for the example above.
+pub trait MapNestedEntities {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper);
+}
+
+impl MapNestedEntities for Entity {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        *self = entity_mapper.get_or_reserve(*self);
+    }
+}
+
+impl<T: MapNestedEntities> MapNestedEntities for Option<T> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        if let Some(value) = self {
+            value.map_nested_entities(entity_mapper);
+        }
+    }
+}
+
+impl<T: MapNestedEntities> MapNestedEntities for Box<T> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        (**self).map_nested_entities(entity_mapper);
+    }
+}
+
+impl<T: MapNestedEntities> MapNestedEntities for [T] {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        for value in self {
+            value.map_nested_entities(entity_mapper);
+        }
+    }
+}
+
+impl<T: MapNestedEntities, const N: usize> MapNestedEntities for [T; N] {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.as_mut_slice().map_nested_entities(entity_mapper);
+    }
+}
+
+impl<T: MapNestedEntities> MapNestedEntities for Vec<T> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.as_mut_slice().map_nested_entities(entity_mapper);
+    }
+}
+
+impl<T: MapNestedEntities> MapNestedEntities for VecDeque<T> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        for value in self {
+            value.map_nested_entities(entity_mapper);
+        }
+    }
+}
+
+impl<A: MapNestedEntities, B: MapNestedEntities> MapNestedEntities for (A, B) {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.0.map_nested_entities(entity_mapper);
+        self.1.map_nested_entities(entity_mapper);
+    }
+}
+
+/// Implements [`MapNestedEntities`] for a map type.
+///
+/// Maps are rebuilt because mapped keys have different hashes.
+macro_rules! impl_for_map {
+    ($map:ident, $($bounds:tt)*) => {
+        impl<K, V, S> MapNestedEntities for $map<K, V, S>
+        where
+            K: MapNestedEntities + $($bounds)*,
+            V: MapNestedEntities,
+            S: BuildHasher + Default,
+        {
+            fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+                *self = self
+                    .drain()
+                    .map(|(mut key, mut value)| {
+                        key.map_nested_entities(entity_mapper);
+                        value.map_nested_entities(entity_mapper);
+                        (key, value)
+                    })
+                    .collect();
+            }
+        }
+    };
+}
+
+impl_for_map!(HashMap, Eq + Hash);
+impl_for_map!(HashbrownMap, Eq + Hash);
+
+/// Implements [`MapNestedEntities`] for a set type.
+macro_rules! impl_for_set {
+    ($set:ident, $($bounds:tt)*) => {
+        impl<T, S> MapNestedEntities for $set<T, S>
+        where
+            T: MapNestedEntities + $($bounds)*,
+            S: BuildHasher + Default,
+        {
+            fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+                *self = self
+                    .drain()
+                    .map(|mut value| {
+                        value.map_nested_entities(entity_mapper);
+                        value
+                    })
+                    .collect();
+            }
+        }
+    };
+}
+
+impl_for_set!(HashSet, Eq + Hash);
+impl_for_set!(HashbrownSet, Eq + Hash);
+
+impl<K: MapNestedEntities + Ord, V: MapNestedEntities> MapNestedEntities for BTreeMap<K, V> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        *self = std::mem::take(self)
+            .into_iter()
+            .map(|(mut key, mut value)| {
+                key.map_nested_entities(entity_mapper);
+                value.map_nested_entities(entity_mapper);
+                (key, value)
+            })
+            .collect();
+    }
+}
+
+impl<T: MapNestedEntities + Ord> MapNestedEntities for BTreeSet<T> {
+    fn map_nested_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        *self = std::mem::take(self)
+            .into_iter()
+            .map(|mut value| {
+                value.map_nested_entities(entity_mapper);
+                value
+            })
+            .collect();
+    }
+}
+
+/// Implements [`MapNestedEntities`] as no-op for types that can't contain entities.
+macro_rules! impl_noop {
+    ($($ty:ty),*) => {
+        $(
+            impl MapNestedEntities for $ty {
+                fn map_nested_entities(&mut self, _entity_mapper: &mut EntityMapper) {}
+            }
+        )*
+    };
+}
+
+impl_noop!(
+    bool, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, char, String
+);
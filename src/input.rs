@@ -0,0 +1,469 @@
+use std::{collections::VecDeque, fmt::Debug};
+
+use bevy::{ecs::event::Event, prelude::*, utils::HashMap};
+use bevy_renet::renet::{RenetClient, SendType, ServerEvent};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    client::{ClientSet, ServerTickEstimate},
+    network_event::{
+        client_event::{ClientEventAppExt, FromClient},
+        EventChannel,
+    },
+    server::{has_authority, ServerSet, ServerTick},
+};
+
+/// An extension trait for [`App`] for creating client inputs.
+pub trait ClientInputAppExt {
+    /// Registers input `T` that is stamped with a tick and sent to server.
+    ///
+    /// Send `T` as a regular event on client once per tick. It will be stored in [`InputBuffer<T>`]
+    /// under the predicted tick from [`InputConfig`] and sent as [`TickedInput<T>`] client event,
+    /// so the server will receive [`FromClient<TickedInput<T>>`].
+    ///
+    /// Received inputs are also buffered on server in [`InputJitterBuffer<T>`] and emitted
+    /// as [`FromClient<T>`] exactly once per [`ServerTick`] for each client.
+    fn add_client_input<T>(&mut self, policy: impl Into<SendType>) -> &mut Self
+    where
+        T: Event + Serialize + DeserializeOwned + Debug + Clone;
+}
+
+impl ClientInputAppExt for App {
+    fn add_client_input<T>(&mut self, policy: impl Into<SendType>) -> &mut Self
+    where
+        T: Event + Serialize + DeserializeOwned + Debug + Clone,
+    {
+        self.add_event::<T>()
+            .add_client_event::<TickedInput<T>>(policy)
+            .init_resource::<InputConfig>()
+            .init_resource::<InputBuffer<T>>()
+            .add_event::<FromClient<T>>()
+            .init_resource::<InputJitterConfig>()
+            .init_resource::<InputJitterBuffer<T>>()
+            .add_systems(
+                PreUpdate,
+                (
+                    buffering_system::<T>,
+                    consumption_system::<T>.run_if(resource_changed::<ServerTick>()),
+                )
+                    .chain()
+                    .after(ServerSet::Receive)
+                    .run_if(has_authority()),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    stamping_system::<T>.before(ClientSet::Send),
+                    reset_system::<T>.run_if(resource_removed::<RenetClient>()),
+                ),
+            )
+    }
+}
+
+/// Stamps inputs with predicted ticks, stores them and sends to server.
+///
+/// Uses [`ServerTickEstimate`] on client and [`ServerTick`] on server or in singleplayer.
+fn stamping_system<T: Event + Clone>(
+    mut inputs: EventReader<T>,
+    mut ticked_inputs: EventWriter<TickedInput<T>>,
+    mut buffer: ResMut<InputBuffer<T>>,
+    config: Res<InputConfig>,
+    estimate: Option<Res<ServerTickEstimate>>,
+    server_tick: Res<ServerTick>,
+    client: Option<Res<RenetClient>>,
+) {
+    let current_tick = match estimate.filter(|_| client.is_some()) {
+        Some(estimate) => estimate.tick(),
+        None => server_tick.get(),
+    };
+    let tick = current_tick.wrapping_add(config.delay);
+    for input in &mut inputs {
+        buffer.push(tick, input.clone(), config.buffer_len);
+        ticked_inputs.send(TickedInput {
+            tick,
+            input: input.clone(),
+        });
+    }
+}
+
+fn reset_system<T: Send + Sync + 'static>(mut buffer: ResMut<InputBuffer<T>>) {
+    buffer.clear();
+}
+
+/// Stores received inputs in [`InputJitterBuffer<T>`] and removes disconnected clients.
+fn buffering_system<T: Clone + Send + Sync + 'static>(
+    mut server_events: EventReader<ServerEvent>,
+    mut ticked_inputs: EventReader<FromClient<TickedInput<T>>>,
+    mut jitter_buffer: ResMut<InputJitterBuffer<T>>,
+    config: Res<InputJitterConfig>,
+) {
+    for event in &mut server_events {
+        if let ServerEvent::ClientDisconnected { client_id, .. } = event {
+            jitter_buffer.clients.remove(client_id);
+        }
+    }
+
+    for FromClient {
+        client_id, event, ..
+    } in &mut ticked_inputs
+    {
+        jitter_buffer
+            .clients
+            .entry(*client_id)
+            .or_insert_with(|| ClientInputs::new(config.min_delay))
+            .push(event.tick, event.input.clone());
+    }
+}
+
+/// Emits a single input for each client per server tick.
+///
+/// Inputs are stamped with the current [`ServerTick`] as the receive tick.
+fn consumption_system<T: Clone + Send + Sync + 'static>(
+    mut inputs: EventWriter<FromClient<T>>,
+    mut jitter_buffer: ResMut<InputJitterBuffer<T>>,
+    config: Res<InputJitterConfig>,
+    channel: Res<EventChannel<TickedInput<T>>>,
+    server_tick: Res<ServerTick>,
+) {
+    for (&client_id, client_inputs) in &mut jitter_buffer.clients {
+        if let Some(event) = client_inputs.consume(&config) {
+            inputs.send(FromClient {
+                client_id,
+                event,
+                tick: None,
+                received_tick: server_tick.get(),
+                channel_id: channel.id,
+            });
+        }
+    }
+}
+
+/// Input configuration on client.
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct InputConfig {
+    /// Number of ticks between the current tick and the tick for which inputs are predicted.
+    ///
+    /// Gives inputs more time to reach the server at the cost of responsiveness.
+    pub delay: u32,
+    /// Maximum number of inputs stored in [`InputBuffer<T>`] for each input type.
+    pub buffer_len: usize,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            delay: 0,
+            buffer_len: 128,
+        }
+    }
+}
+
+/// An input with the tick for which it was predicted.
+///
+/// Sent from client as a client event by [`ClientInputAppExt::add_client_input`].
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct TickedInput<T> {
+    /// Server tick for which the input was predicted, including [`InputConfig::delay`].
+    pub tick: u32,
+    pub input: T,
+}
+
+/// Ring buffer of sent inputs keyed by predicted ticks.
+///
+/// Can be used to replay inputs after the state was corrected by server.
+#[derive(Resource)]
+pub struct InputBuffer<T> {
+    /// Inputs ordered from the oldest to the newest tick.
+    inputs: VecDeque<(u32, T)>,
+}
+
+impl<T> InputBuffer<T> {
+    /// Returns input for the specified tick.
+    pub fn get(&self, tick: u32) -> Option<&T> {
+        self.inputs
+            .iter()
+            .find(|&&(input_tick, _)| input_tick == tick)
+            .map(|(_, input)| input)
+    }
+
+    /// Returns inputs with ticks after the specified tick from the oldest to the newest.
+    ///
+    /// Useful to replay inputs on top of a confirmed state from server.
+    pub fn iter_after(&self, tick: u32) -> impl Iterator<Item = &(u32, T)> {
+        self.inputs
+            .iter()
+            .filter(move |&&(input_tick, _)| (input_tick.wrapping_sub(tick) as i32) > 0)
+    }
+
+    /// Returns the latest input with its tick.
+    pub fn last(&self) -> Option<&(u32, T)> {
+        self.inputs.back()
+    }
+
+    /// Returns the number of stored inputs.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Returns `true` if there are no stored inputs.
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    /// Removes all stored inputs.
+    pub fn clear(&mut self) {
+        self.inputs.clear();
+    }
+
+    /// Stores input, replacing the input with the same tick.
+    ///
+    /// Removes the oldest inputs to keep the buffer under `max_len`.
+    fn push(&mut self, tick: u32, input: T, max_len: usize) {
+        if let Some((last_tick, last_input)) = self.inputs.back_mut() {
+            if *last_tick == tick {
+                *last_input = input;
+                return;
+            }
+        }
+        self.inputs.push_back((tick, input));
+        while self.inputs.len() > max_len {
+            self.inputs.pop_front();
+        }
+    }
+}
+
+impl<T> Default for InputBuffer<T> {
+    fn default() -> Self {
+        Self {
+            inputs: Default::default(),
+        }
+    }
+}
+
+/// Adaptive delay configuration for [`InputJitterBuffer<T>`] on server.
+///
+/// The delay is measured in buffered inputs. It increases on every underrun
+/// and decreases after [`Self::decrease_after`] ticks without underruns.
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct InputJitterConfig {
+    /// Minimal number of buffered inputs before consumption.
+    pub min_delay: usize,
+    /// Maximum number of buffered inputs before consumption.
+    pub max_delay: usize,
+    /// Number of ticks without underruns after which the delay decreases by one.
+    pub decrease_after: u32,
+}
+
+impl Default for InputJitterConfig {
+    fn default() -> Self {
+        Self {
+            min_delay: 1,
+            max_delay: 8,
+            decrease_after: 120,
+        }
+    }
+}
+
+/// Per-client buffers of received inputs on server.
+///
+/// Smooths out network jitter by keeping a few inputs buffered, so each client
+/// has exactly one input consumed per [`ServerTick`]. If the buffer runs empty,
+/// the last consumed input is repeated.
+#[derive(Resource)]
+pub struct InputJitterBuffer<T> {
+    clients: HashMap<u64, ClientInputs<T>>,
+}
+
+impl<T> InputJitterBuffer<T> {
+    /// Returns buffer health of a client for tuning [`InputJitterConfig`].
+    ///
+    /// Returns [`None`] if no inputs were received from the client.
+    pub fn health(&self, client_id: u64) -> Option<InputBufferHealth> {
+        self.clients
+            .get(&client_id)
+            .map(|client_inputs| client_inputs.health)
+    }
+}
+
+impl<T> Default for InputJitterBuffer<T> {
+    fn default() -> Self {
+        Self {
+            clients: Default::default(),
+        }
+    }
+}
+
+/// Statistics of a client input buffer on server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InputBufferHealth {
+    /// Number of currently buffered inputs.
+    pub buffered: usize,
+    /// Current adaptive delay in inputs.
+    pub delay: usize,
+    /// Number of ticks on which the buffer was empty and the last input was repeated.
+    pub underruns: u32,
+    /// Number of inputs dropped because the buffer grew too large.
+    pub overruns: u32,
+    /// Number of inputs that arrived after their tick was already consumed.
+    pub late: u32,
+}
+
+struct ClientInputs<T> {
+    /// Inputs ordered by ticks.
+    inputs: VecDeque<(u32, T)>,
+    /// Last consumed input with its tick.
+    last: Option<(u32, T)>,
+    /// Whether the buffer was filled up to the delay for the first time.
+    started: bool,
+    /// Number of ticks since the last underrun.
+    stable_ticks: u32,
+    health: InputBufferHealth,
+}
+
+impl<T: Clone> ClientInputs<T> {
+    fn new(delay: usize) -> Self {
+        Self {
+            inputs: Default::default(),
+            last: None,
+            started: false,
+            stable_ticks: 0,
+            health: InputBufferHealth {
+                delay,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Inserts input according to its tick.
+    fn push(&mut self, tick: u32, input: T) {
+        let is_newer = |a: u32, b: u32| (a.wrapping_sub(b) as i32) > 0;
+        if self
+            .last
+            .as_ref()
+            .is_some_and(|&(last_tick, _)| !is_newer(tick, last_tick))
+        {
+            self.health.late += 1;
+            return;
+        }
+
+        let index = self
+            .inputs
+            .iter()
+            .position(|&(buffered_tick, _)| !is_newer(tick, buffered_tick))
+            .unwrap_or(self.inputs.len());
+        match self.inputs.get_mut(index) {
+            Some((buffered_tick, buffered_input)) if *buffered_tick == tick => {
+                *buffered_input = input
+            }
+            _ => self.inputs.insert(index, (tick, input)),
+        }
+        self.health.buffered = self.inputs.len();
+    }
+
+    /// Returns the next input or repeats the last one if the buffer is empty.
+    fn consume(&mut self, config: &InputJitterConfig) -> Option<T> {
+        if !self.started {
+            if self.inputs.len() < self.health.delay {
+                return None;
+            }
+            self.started = true;
+        }
+
+        // Drop inputs that exceed twice the delay to avoid accumulating latency.
+        let max_buffered = self.health.delay * 2;
+        while self.inputs.len() > max_buffered.max(1) {
+            self.last = self.inputs.pop_front();
+            self.health.overruns += 1;
+        }
+
+        match self.inputs.pop_front() {
+            Some(input) => {
+                self.last = Some(input);
+                self.stable_ticks += 1;
+                if self.stable_ticks >= config.decrease_after {
+                    self.stable_ticks = 0;
+                    self.health.delay = self.health.delay.saturating_sub(1).max(config.min_delay);
+                }
+            }
+            None => {
+                self.health.underruns += 1;
+                self.stable_ticks = 0;
+                self.health.delay = (self.health.delay + 1).min(config.max_delay);
+            }
+        }
+        self.health.buffered = self.inputs.len();
+
+        self.last.as_ref().map(|(_, input)| input.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer() {
+        let mut buffer = InputBuffer::default();
+        for tick in 0..4 {
+            buffer.push(tick, tick, 3);
+        }
+        buffer.push(3, 4, 3);
+
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.get(0), None, "oldest input should be removed");
+        assert_eq!(
+            buffer.get(3),
+            Some(&4),
+            "input with the same tick should be replaced"
+        );
+
+        let replayed: Vec<_> = buffer.iter_after(1).map(|&(tick, _)| tick).collect();
+        assert_eq!(replayed, [2, 3]);
+    }
+
+    #[test]
+    fn jitter_buffer() {
+        let config = InputJitterConfig {
+            min_delay: 2,
+            max_delay: 3,
+            decrease_after: 2,
+        };
+        let mut client_inputs = ClientInputs::new(config.min_delay);
+
+        client_inputs.push(2, 2);
+        assert_eq!(
+            client_inputs.consume(&config),
+            None,
+            "consumption should wait for the delay"
+        );
+
+        client_inputs.push(1, 1);
+        assert_eq!(client_inputs.consume(&config), Some(1));
+        assert_eq!(client_inputs.consume(&config), Some(2));
+        assert_eq!(
+            client_inputs.consume(&config),
+            Some(2),
+            "last input should be repeated on underrun"
+        );
+        assert_eq!(client_inputs.health.underruns, 1);
+        assert_eq!(client_inputs.health.delay, 3);
+
+        client_inputs.push(2, 2);
+        assert_eq!(client_inputs.health.late, 1);
+
+        for tick in 3..=10 {
+            client_inputs.push(tick, tick);
+        }
+        assert_eq!(
+            client_inputs.consume(&config),
+            Some(5),
+            "inputs above twice the delay should be dropped"
+        );
+        assert_eq!(client_inputs.health.overruns, 2);
+        assert_eq!(client_inputs.consume(&config), Some(6));
+        assert_eq!(
+            client_inputs.health.delay, 2,
+            "delay should decrease without underruns"
+        );
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    ecs::component::{Components, Tick},
+    prelude::*,
+};
+use bevy_renet::renet::RenetClient;
+use derive_more::Constructor;
+
+use crate::{
+    client::{ClientSet, ServerTickEstimate},
+    replication_core::{AppReplicationExt, ReplicationRules},
+    server::ServerTick,
+    time_scale::ServerTimeScale,
+};
+
+/// Smooths replicated [`Transform`] changes on clients.
+///
+/// The server simulates entities with a fixed step and sends their transforms with [`ServerTick`].
+/// On clients, received transforms of entities with [`Interpolated`] are stored together with
+/// their server ticks and the rendered [`Transform`] is interpolated between them in `Update`
+/// using [`ServerTickEstimate`] delayed by [`InterpolationDelay`].
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately
+/// on both server and clients after them.
+#[derive(Constructor)]
+pub struct TransformInterpolationPlugin {
+    /// Initial value for [`InterpolationDelay`].
+    delay: f64,
+}
+
+impl Default for TransformInterpolationPlugin {
+    fn default() -> Self {
+        Self { delay: 2.0 }
+    }
+}
+
+impl Plugin for TransformInterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Interpolated>()
+            .insert_resource(InterpolationDelay(self.delay))
+            .add_systems(
+                PreUpdate,
+                Self::recording_system
+                    .after(ClientSet::Receive)
+                    .run_if(resource_exists::<RenetClient>()),
+            )
+            .add_systems(
+                Update,
+                Self::interpolation_system.run_if(resource_exists::<RenetClient>()),
+            );
+    }
+}
+
+impl TransformInterpolationPlugin {
+    /// Records replicated transforms into [`TransformSnapshots`].
+    ///
+    /// Changes made by [`Self::interpolation_system`] are ignored.
+    #[allow(clippy::type_complexity)]
+    fn recording_system(
+        mut commands: Commands,
+        server_tick: Res<ServerTick>,
+        mut transforms: Query<
+            (Entity, Ref<Transform>, Option<&mut TransformSnapshots>),
+            With<Interpolated>,
+        >,
+    ) {
+        for (entity, transform, snapshots) in &mut transforms {
+            match snapshots {
+                Some(mut snapshots) => {
+                    if transform.is_changed()
+                        && snapshots.written_at != Some(transform.last_changed())
+                    {
+                        snapshots.push(server_tick.get(), *transform);
+                    }
+                }
+                None => {
+                    let mut snapshots = TransformSnapshots::default();
+                    snapshots.push(server_tick.get(), *transform);
+                    commands.entity(entity).insert(snapshots);
+                }
+            }
+        }
+    }
+
+    /// Writes interpolated transforms.
+    ///
+    /// Transforms are not written while their replication is disabled, so they could be changed locally.
+    /// Local changes are recorded as snapshots, so interpolation continues from them once enabled again.
+    fn interpolation_system(
+        time: Res<Time>,
+        delay: Res<InterpolationDelay>,
+        estimate: Res<ServerTickEstimate>,
+        time_scale: Res<ServerTimeScale>,
+        replication_rules: Res<ReplicationRules>,
+        components: &Components,
+        mut transforms: Query<
+            (
+                &mut Transform,
+                &mut TransformSnapshots,
+                Option<&mut Extrapolation>,
+            ),
+            With<Interpolated>,
+        >,
+    ) {
+        let tick = estimate.tick();
+        let overstep = estimate.overstep();
+        let enabled = components
+            .component_id::<Transform>()
+            .map_or(true, |component_id| {
+                replication_rules.is_component_enabled(component_id)
+            });
+        for (mut transform, mut snapshots, extrapolation) in &mut transforms {
+            snapshots.cleanup(tick, overstep, delay.0);
+            if !enabled {
+                continue;
+            }
+            let Some(mut target) = snapshots.sample(tick, overstep, delay.0) else {
+                continue;
+            };
+
+            if let Some(mut extrapolation) = extrapolation {
+                // Entities don't move during pause, so transforms stop arriving.
+                let extrapolated = if time_scale.paused {
+                    None
+                } else {
+                    snapshots.extrapolate(tick, overstep, delay.0, extrapolation.max_ticks)
+                };
+                if let Some(extrapolated) = extrapolated {
+                    target = extrapolated;
+                } else if extrapolation.extrapolating {
+                    // A fresh transform arrived, blend from the extrapolated position.
+                    extrapolation.error = transform.translation - target.translation;
+                }
+                extrapolation.extrapolating = extrapolated.is_some();
+
+                let elapsed_ticks = estimate.tick_duration().map_or(0.0, |tick_duration| {
+                    time_scale.delta(&time).as_secs_f64() / tick_duration.as_secs_f64()
+                });
+                let decay = if extrapolation.blend_ticks > 0.0 {
+                    (1.0 - elapsed_ticks / extrapolation.blend_ticks).max(0.0)
+                } else {
+                    0.0
+                };
+                extrapolation.error *= decay as f32;
+                target.translation += extrapolation.error;
+            }
+
+            *transform = target;
+            snapshots.written_at = Some(transform.last_changed());
+        }
+    }
+}
+
+/// Marks entity for transform interpolation on clients.
+///
+/// Replicated to clients, so it's enough to insert it on server.
+#[derive(Component, Default, Reflect, Clone, Copy)]
+#[reflect(Component)]
+pub struct Interpolated;
+
+/// Enables dead reckoning for an [`Interpolated`] entity on client.
+///
+/// When the next transform is late, translation is projected from the latest received
+/// transform using the velocity between the two latest transforms, for up to [`Self::max_ticks`].
+/// Disabled while simulation is paused by [`ServerTimeScale`].
+/// When a fresh transform arrives, the rendered translation blends back over [`Self::blend_ticks`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Extrapolation {
+    /// Maximum number of ticks to extrapolate beyond the latest received transform.
+    pub max_ticks: f64,
+    /// Number of ticks to blend from the extrapolated translation back to the interpolated one.
+    pub blend_ticks: f64,
+    /// Offset between the rendered and the interpolated translations that decays over [`Self::blend_ticks`].
+    error: Vec3,
+    /// Whether the last rendered translation was extrapolated.
+    extrapolating: bool,
+}
+
+impl Extrapolation {
+    pub fn new(max_ticks: f64, blend_ticks: f64) -> Self {
+        Self {
+            max_ticks,
+            blend_ticks,
+            error: Vec3::ZERO,
+            extrapolating: false,
+        }
+    }
+}
+
+impl Default for Extrapolation {
+    fn default() -> Self {
+        Self::new(5.0, 2.0)
+    }
+}
+
+/// How far in the past, in server ticks, interpolated transforms are rendered.
+///
+/// Larger values tolerate more jitter and packet loss at the cost of latency.
+/// Should be at least 1 to have two transforms to interpolate between.
+#[derive(Clone, Copy, Debug, Deref, DerefMut, Resource)]
+pub struct InterpolationDelay(pub f64);
+
+/// Received transforms of an [`Interpolated`] entity with server ticks on which they were sent.
+///
+/// Inserted automatically on clients.
+#[derive(Component, Default)]
+pub struct TransformSnapshots {
+    /// Snapshots ordered from the oldest to the newest.
+    snapshots: VecDeque<(u32, Transform)>,
+
+    /// Change tick of the last interpolated write to distinguish it from replicated changes.
+    written_at: Option<Tick>,
+}
+
+impl TransformSnapshots {
+    /// Returns stored transforms with their server ticks from the oldest to the newest.
+    pub fn iter(&self) -> impl Iterator<Item = &(u32, Transform)> {
+        self.snapshots.iter()
+    }
+
+    /// Returns the latest received transform with its server tick.
+    pub fn last(&self) -> Option<&(u32, Transform)> {
+        self.snapshots.back()
+    }
+
+    fn push(&mut self, tick: u32, transform: Transform) {
+        if let Some((last_tick, last_transform)) = self.snapshots.back_mut() {
+            if *last_tick == tick {
+                // Multiple changes within a single tick.
+                *last_transform = transform;
+                return;
+            }
+        }
+        self.snapshots.push_back((tick, transform));
+    }
+
+    /// Removes snapshots that are too old to be used for interpolation.
+    ///
+    /// Keeps the newest snapshot that is older than the rendered point
+    /// and at least two snapshots to calculate velocity for extrapolation.
+    fn cleanup(&mut self, current_tick: u32, overstep: f64, delay: f64) {
+        while self.snapshots.len() > 2
+            && self
+                .snapshots
+                .get(1)
+                .is_some_and(|&(tick, _)| age(current_tick, overstep, tick) >= delay)
+        {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Returns the transform at `delay` ticks before the current estimated tick.
+    ///
+    /// Returns the closest snapshot if the rendered point is outside of the stored range.
+    fn sample(&self, current_tick: u32, overstep: f64, delay: f64) -> Option<Transform> {
+        let mut snapshots = self.snapshots.iter();
+        let &(mut from_tick, mut from) = snapshots.next()?;
+        for &(to_tick, to) in snapshots {
+            let from_age = age(current_tick, overstep, from_tick);
+            let to_age = age(current_tick, overstep, to_tick);
+            if to_age <= delay {
+                if from_age <= delay {
+                    return Some(from);
+                }
+                let t = (from_age - delay) / (from_age - to_age);
+                return Some(lerp_transform(&from, &to, t as f32));
+            }
+            (from_tick, from) = (to_tick, to);
+        }
+
+        Some(from)
+    }
+
+    /// Returns the transform projected beyond the latest snapshot if the rendered point is newer than it.
+    ///
+    /// Only translation is projected and no further than `max_ticks`.
+    fn extrapolate(
+        &self,
+        current_tick: u32,
+        overstep: f64,
+        delay: f64,
+        max_ticks: f64,
+    ) -> Option<Transform> {
+        let mut snapshots = self.snapshots.iter().rev();
+        let &(last_tick, last) = snapshots.next()?;
+        let &(previous_tick, previous) = snapshots.next()?;
+
+        let ahead = age(current_tick, overstep, last_tick) - delay;
+        if ahead <= 0.0 {
+            return None;
+        }
+
+        let interval = last_tick.wrapping_sub(previous_tick) as f32;
+        let velocity = (last.translation - previous.translation) / interval;
+        let mut extrapolated = last;
+        extrapolated.translation += velocity * ahead.min(max_ticks) as f32;
+
+        Some(extrapolated)
+    }
+}
+
+/// Returns the number of ticks passed since `tick`.
+fn age(current_tick: u32, overstep: f64, tick: u32) -> f64 {
+    current_tick.wrapping_sub(tick) as i32 as f64 + overstep
+}
+
+fn lerp_transform(from: &Transform, to: &Transform, t: f32) -> Transform {
+    Transform {
+        translation: from.translation.lerp(to.translation, t),
+        rotation: from.rotation.slerp(to.rotation, t),
+        scale: from.scale.lerp(to.scale, t),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampling() {
+        let mut snapshots = TransformSnapshots::default();
+        snapshots.push(1, Transform::from_xyz(0.0, 0.0, 0.0));
+        snapshots.push(2, Transform::from_xyz(2.0, 0.0, 0.0));
+        snapshots.push(3, Transform::from_xyz(4.0, 0.0, 0.0));
+
+        let sample = |current_tick, overstep| {
+            snapshots
+                .sample(current_tick, overstep, 1.0)
+                .unwrap()
+                .translation
+                .x
+        };
+        assert_eq!(sample(2, 0.0), 0.0, "should hold the oldest snapshot");
+        assert_eq!(sample(2, 0.5), 1.0);
+        assert_eq!(sample(3, 0.25), 2.5);
+        assert_eq!(sample(5, 0.0), 4.0, "should hold the newest snapshot");
+    }
+
+    #[test]
+    fn cleanup() {
+        let mut snapshots = TransformSnapshots::default();
+        for tick in u32::MAX - 1..=u32::MAX {
+            snapshots.push(tick, Transform::default());
+        }
+        snapshots.push(0, Transform::default());
+
+        snapshots.cleanup(1, 0.0, 1.0);
+        let ticks: Vec<_> = snapshots.iter().map(|&(tick, _)| tick).collect();
+        assert_eq!(ticks, [u32::MAX, 0], "should handle tick wrapping");
+    }
+
+    #[test]
+    fn extrapolation() {
+        let mut snapshots = TransformSnapshots::default();
+        snapshots.push(1, Transform::from_xyz(0.0, 0.0, 0.0));
+        snapshots.push(3, Transform::from_xyz(2.0, 0.0, 0.0));
+
+        assert!(snapshots.extrapolate(4, 0.0, 1.0, 5.0).is_none());
+
+        let extrapolate = |current_tick, max_ticks| {
+            snapshots
+                .extrapolate(current_tick, 0.0, 1.0, max_ticks)
+                .unwrap()
+                .translation
+                .x
+        };
+        assert_eq!(extrapolate(6, 5.0), 4.0);
+        assert_eq!(extrapolate(10, 1.0), 3.0, "should be limited by max ticks");
+    }
+}
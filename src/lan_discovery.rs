@@ -0,0 +1,333 @@
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::{transport::NetcodeServerTransport, RenetServer};
+use serde::{Deserialize, Serialize};
+
+/// Finds servers in the local network without external infrastructure.
+///
+/// Servers with [`LanServerInfo`] resource respond to discovery requests on [`LanDiscoveryConfig::port`].
+/// Clients send requests while [`DiscoveredServers`] resource exists and receive [`DiscoveredServer`] events.
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately
+/// on both server and clients.
+pub struct LanDiscoveryPlugin {
+    /// Initial value for [`LanDiscoveryConfig`].
+    pub config: LanDiscoveryConfig,
+}
+
+impl LanDiscoveryPlugin {
+    /// Creates plugin with default config for the specified protocol ID.
+    pub fn new(protocol_id: u64) -> Self {
+        Self {
+            config: LanDiscoveryConfig::new(protocol_id),
+        }
+    }
+}
+
+impl Plugin for LanDiscoveryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config)
+            .add_event::<DiscoveredServer>()
+            .add_systems(
+                PreUpdate,
+                (
+                    (Self::responder_creation_system, Self::responding_system)
+                        .chain()
+                        .run_if(resource_exists::<LanServerInfo>())
+                        .run_if(resource_exists::<NetcodeServerTransport>()),
+                    Self::responder_removal_system.run_if(
+                        not(resource_exists::<LanServerInfo>())
+                            .or_else(not(resource_exists::<NetcodeServerTransport>())),
+                    ),
+                    (
+                        Self::searcher_creation_system,
+                        Self::receiving_system,
+                        Self::requesting_system,
+                    )
+                        .chain()
+                        .run_if(resource_exists::<DiscoveredServers>()),
+                    Self::searcher_removal_system
+                        .run_if(not(resource_exists::<DiscoveredServers>())),
+                ),
+            );
+    }
+}
+
+impl LanDiscoveryPlugin {
+    /// Retries failed binds only after [`LanDiscoveryConfig::interval`] to avoid logging errors every frame.
+    fn responder_creation_system(
+        mut commands: Commands,
+        mut last_failure: Local<Option<Duration>>,
+        time: Res<Time>,
+        config: Res<LanDiscoveryConfig>,
+        responder: Option<Res<DiscoveryResponder>>,
+    ) {
+        if responder.is_some() {
+            return;
+        }
+        if last_failure.is_some_and(|last_failure| {
+            time.elapsed().saturating_sub(last_failure) < config.interval
+        }) {
+            return;
+        }
+
+        let addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), config.port);
+        match UdpSocket::bind(addr).and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            Ok(socket)
+        }) {
+            Ok(socket) => {
+                *last_failure = None;
+                commands.insert_resource(DiscoveryResponder(socket));
+            }
+            Err(e) => {
+                error!(
+                    "unable to bind discovery socket to {addr}, retrying in {:?}: {e}",
+                    config.interval
+                );
+                *last_failure = Some(time.elapsed());
+            }
+        }
+    }
+
+    fn responding_system(
+        config: Res<LanDiscoveryConfig>,
+        info: Res<LanServerInfo>,
+        responder: Option<Res<DiscoveryResponder>>,
+        server: Option<Res<RenetServer>>,
+        transport: Res<NetcodeServerTransport>,
+    ) {
+        let Some(responder) = responder else {
+            return;
+        };
+
+        let mut buffer = [0; MAX_MESSAGE_BYTES];
+        loop {
+            let (len, client_addr) = match responder.0.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    error!("unable to receive discovery request: {e}");
+                    break;
+                }
+            };
+
+            match bincode::deserialize::<DiscoveryRequest>(&buffer[..len]) {
+                Ok(request) if request.protocol_id == config.protocol_id => {
+                    let response = DiscoveryResponse {
+                        name: info.name.clone(),
+                        players: server
+                            .as_ref()
+                            .map_or(0, |server| server.connected_clients()),
+                        max_players: info.max_players,
+                        port: transport.addr().port(),
+                    };
+                    let message = bincode::serialize(&response)
+                        .expect("discovery response should be serializable");
+                    if let Err(e) = responder.0.send_to(&message, client_addr) {
+                        error!("unable to respond to {client_addr}: {e}");
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => debug!("ignoring invalid discovery request from {client_addr}: {e}"),
+            }
+        }
+    }
+
+    fn responder_removal_system(mut commands: Commands) {
+        commands.remove_resource::<DiscoveryResponder>();
+    }
+
+    fn searcher_creation_system(mut commands: Commands, searcher: Option<Res<DiscoverySearcher>>) {
+        if searcher.is_some() {
+            return;
+        }
+
+        match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            socket.set_broadcast(true)?;
+            Ok(socket)
+        }) {
+            Ok(socket) => commands.insert_resource(DiscoverySearcher {
+                socket,
+                last_request: None,
+            }),
+            Err(e) => error!("unable to bind discovery socket: {e}"),
+        }
+    }
+
+    fn receiving_system(
+        time: Res<Time>,
+        config: Res<LanDiscoveryConfig>,
+        searcher: Option<Res<DiscoverySearcher>>,
+        mut discovered_servers: ResMut<DiscoveredServers>,
+        mut discovered_events: EventWriter<DiscoveredServer>,
+    ) {
+        if let Some(searcher) = searcher {
+            let mut buffer = [0; MAX_MESSAGE_BYTES];
+            loop {
+                let (len, server_addr) = match searcher.socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        error!("unable to receive discovery response: {e}");
+                        break;
+                    }
+                };
+
+                match bincode::deserialize::<DiscoveryResponse>(&buffer[..len]) {
+                    Ok(response) => {
+                        let server = DiscoveredServer {
+                            addr: SocketAddr::new(server_addr.ip(), response.port),
+                            name: response.name,
+                            players: response.players,
+                            max_players: response.max_players,
+                        };
+                        discovered_servers
+                            .servers
+                            .insert(server.addr, (server.clone(), time.elapsed()));
+                        discovered_events.send(server);
+                    }
+                    Err(e) => {
+                        debug!("ignoring invalid discovery response from {server_addr}: {e}")
+                    }
+                }
+            }
+        }
+
+        let timeout = config.interval * 3;
+        discovered_servers
+            .servers
+            .retain(|_, &mut (_, last_seen)| time.elapsed().saturating_sub(last_seen) <= timeout);
+    }
+
+    fn requesting_system(
+        time: Res<Time>,
+        config: Res<LanDiscoveryConfig>,
+        searcher: Option<ResMut<DiscoverySearcher>>,
+    ) {
+        let Some(mut searcher) = searcher else {
+            return;
+        };
+        if searcher.last_request.is_some_and(|last_request| {
+            time.elapsed().saturating_sub(last_request) < config.interval
+        }) {
+            return;
+        }
+
+        let request = DiscoveryRequest {
+            protocol_id: config.protocol_id,
+        };
+        let message =
+            bincode::serialize(&request).expect("discovery request should be serializable");
+        let addr = SocketAddr::new(config.target_ip, config.port);
+        if let Err(e) = searcher.socket.send_to(&message, addr) {
+            error!("unable to send discovery request to {addr}: {e}");
+        }
+        searcher.last_request = Some(time.elapsed());
+    }
+
+    fn searcher_removal_system(mut commands: Commands) {
+        commands.remove_resource::<DiscoverySearcher>();
+    }
+}
+
+/// Maximum size of discovery messages.
+const MAX_MESSAGE_BYTES: usize = 1200;
+
+/// Settings of [`LanDiscoveryPlugin`].
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct LanDiscoveryConfig {
+    /// Servers ignore requests from clients with a different protocol ID.
+    pub protocol_id: u64,
+    /// Port on which servers listen for discovery requests.
+    pub port: u16,
+    /// Address to which clients send requests, broadcast by default.
+    pub target_ip: IpAddr,
+    /// How often clients send requests.
+    ///
+    /// Servers that didn't respond for 3 intervals are removed from [`DiscoveredServers`].
+    /// Also used by servers as a delay before retrying to bind the discovery socket.
+    pub interval: Duration,
+}
+
+impl LanDiscoveryConfig {
+    pub fn new(protocol_id: u64) -> Self {
+        Self {
+            protocol_id,
+            port: 5001,
+            target_ip: Ipv4Addr::BROADCAST.into(),
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Information about a server that is sent to discovering clients.
+///
+/// Insert it on server to respond to discovery requests.
+#[derive(Clone, Debug, Resource)]
+pub struct LanServerInfo {
+    pub name: String,
+    pub max_players: usize,
+}
+
+/// Servers found in the local network.
+///
+/// Insert it on client to start discovery and remove to stop.
+#[derive(Default, Resource)]
+pub struct DiscoveredServers {
+    /// Servers with times when they responded last time.
+    servers: HashMap<SocketAddr, (DiscoveredServer, Duration)>,
+}
+
+impl DiscoveredServers {
+    /// Returns servers that responded recently.
+    pub fn iter(&self) -> impl Iterator<Item = &DiscoveredServer> {
+        self.servers.values().map(|(server, _)| server)
+    }
+
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+}
+
+/// An event that emitted on client for each received server response.
+#[derive(Clone, Debug, Event)]
+pub struct DiscoveredServer {
+    /// Address to connect to.
+    pub addr: SocketAddr,
+    pub name: String,
+    pub players: usize,
+    pub max_players: usize,
+}
+
+#[derive(Resource)]
+struct DiscoveryResponder(UdpSocket);
+
+#[derive(Resource)]
+struct DiscoverySearcher {
+    socket: UdpSocket,
+    last_request: Option<Duration>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DiscoveryRequest {
+    protocol_id: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+struct DiscoveryResponse {
+    name: String,
+    players: usize,
+    max_players: usize,
+    port: u16,
+}
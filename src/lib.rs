@@ -51,8 +51,8 @@ By default, no components are replicated. To start replication, you need two
 things:
 
 1. Mark component type for replication. Component should implement [`Reflect`],
-have `#[reflect(Component)]` and all its fields should be registered. You can
-use [`AppReplicationExt::replicate()`] to mark the component for replication:
+   have `#[reflect(Component)]` and all its fields should be registered. You can
+   use [`AppReplicationExt::replicate()`] to mark the component for replication:
 
 ```rust
 # use bevy::prelude::*;
@@ -69,6 +69,9 @@ struct DummyComponent;
 This also automatically registers the specified type, so you don't need to call
 [`App::register_type()`] if you replicating the type.
 
+Both table and `#[component(storage = "SparseSet")]` storages are supported,
+including insertions, changes and removals.
+
 If your component contains [`Entity`] then it cannot be deserialized as is
 because entity IDs are different on server and client. The client should do the
 mapping. Therefore, to replicate such components properly, they need implement
@@ -94,10 +97,10 @@ impl MapEntities for MappedComponent {
 }
 
 // We need to impl either `FromWorld` or `Default` so `MappedComponent` can
-// be registered as `Reflect`. This is because `Reflect` deserialize by
-// creating an instance and apply a patch on top. However `MappedComponent`
-// should only ever be created with a real user-defined entity, so it's better
-// to implement `FromWorld`.
+// be registered as `Reflect`. This is because replicated components are applied
+// by creating an instance with `FromWorld` and applying a patch on top.
+// However `MappedComponent` should only ever be created with a real user-defined
+// entity, so it's better to implement `FromWorld` and not `Default`.
 // Bevy uses the same pattern to reflect components with `Entity`.
 impl FromWorld for MappedComponent {
     fn from_world(_world: &mut World) -> Self {
@@ -106,10 +109,18 @@ impl FromWorld for MappedComponent {
 }
 ```
 
+For entities inside collections like `Vec<Entity>` or `HashMap<Entity, T>` use [`MapNestedEntities`].
+
+By default entities that are not replicated yet are mapped to reserved entities that are never spawned.
+Insert [`EntityMappingPolicy::Defer`] on client to apply such components only after all referenced
+entities are replicated.
+
 2. You need to choose entities you want to replicate using [`Replication`]
-component. Just insert it to the entity you want to replicate. Only components
-marked for replication through [`AppReplicationExt::replicate()`]
-will be replicated.
+   component. Just insert it to the entity you want to replicate. Only components
+   marked for replication through [`AppReplicationExt::replicate()`]
+   will be replicated.
+   To replicate a whole hierarchy, insert [`ReplicationRecursive`] to its root
+   instead.
 
 If you need more control, you add special rules. For example, if you don't want
 to replicate [`Transform`] on entities marked for replication if your special
@@ -131,6 +142,57 @@ app.replicate::<Visibility>()
 
 Could be called any number times.
 
+[`AppReplicationExt::replicate()`] returns [`ReplicationRuleBuilder`] to configure
+the component replication. For example, components that never change after
+spawn could be sent only on insertion:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate::<Name>().once();
+```
+
+Changes are sent over an unreliable channel and resent until the client acknowledges
+them. Acknowledgments are per tick, but entities left out of a diff, for example by
+[`SpawnBudget`] or [`MessageEntityLimit`], are tracked individually, so only entities
+the client hasn't confirmed are resent. Gameplay-critical components could be sent over a reliable channel instead
+with [`ReplicationRuleBuilder::reliable()`], each change will be sent only once.
+
+To react only to changes that came from server, register the component with
+[`ReplicationRuleBuilder::notify()`] and read [`ComponentReplicated`] events on client.
+
+Options could also be declared next to the component with [`Replicate`] derive.
+This way the whole replication rule is defined in one place and registered with
+[`AppReplicationExt::replicate_derived()`]:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate_derived::<Score>();
+
+#[derive(Component, Default, Reflect, Replicate)]
+#[reflect(Component)]
+#[replicate(reliable, max_lod = 1)]
+struct Score(u32);
+```
+
+In debug builds server warns once per type about reflected components on replicated entities
+that aren't marked for replication. Components that are intentionally kept on server could be
+excluded with [`UnreplicatedDiagnostics::ignore()`].
+
+To investigate desync of a single player, watch its client with [`TrafficLog::watch()`].
+All messages sent to and received from the client will be logged with decoded summaries,
+like entity counts, component names and event values.
+For offline analysis, record traffic of all clients into a file with [`TrafficLog::start_capture()`]
+and read it back with [`CapturedMessage::read_all()`]. [`TrafficDecoder`] created from the world
+of your app describes captured world diffs with values of changed components and events with their values.
+The `replicon_decode` binary behind `traffic_decoder` feature prints capture files, but without
+access to your types it fully decodes only built-in messages.
+
 ### "Blueprints" pattern
 
 The idea was borrowed from [iyes_scene_tools](https://github.com/IyesGames/iyes_scene_tools#blueprints-pattern).
@@ -179,6 +241,10 @@ If your game have save states you probably want to re-use the same logic to
 keep you saves clean. Also, although things like `Handle<T>` can technically be
 serialized, they won't be valid after deserialization.
 
+If you use [bevy_save](https://github.com/hankjordan/bevy_save), enable the `bevy_save` feature.
+All replicated components and [`Replication`] will be registered as saveable, so saves contain
+exactly the replicated state without a separate registry.
+
 ### Component relations
 
 Sometimes components depend on each other. For example, [`Parent`] and
@@ -187,9 +253,367 @@ not only need to add it to the [`Children`] of the parent, but also remove it
 from the [`Children`] of the old one. In this case, you need to create a third
 component that correctly updates the other two when it changes, and only
 replicate that one. This crate provides [`ParentSync`] component that replicates
-Bevy hierarchy. For your custom components with relations you need to write your
+Bevy hierarchy. Use `ParentSyncPlugin::new(true)` to insert it automatically
+for all replicated entities with a parent. For your custom components with relations you need to write your
 own with a similar pattern.
 
+### Stable identity
+
+[`Entity`] is different on server and each client and changes between sessions. Insert
+[`NetworkUuid`] to give an entity an identity that is replicated and could be stored in saves.
+Use [`NetworkUuids`] resource to get an entity by its UUID on both server and client.
+
+### Synced timers
+
+For round timers and respawn countdowns insert [`SyncedTimer`] created from the current [`ServerTick`].
+It stores start and end ticks, so it's replicated once and each machine computes the remaining
+time locally. Use [`SyncedClock`] system parameter to get the current tick, it's estimated
+with [`ServerTickEstimate`] on clients:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+fn countdown_system(clock: SyncedClock, timers: Query<&SyncedTimer>) {
+    for timer in &timers {
+        let remaining_ticks = timer.remaining_ticks(clock.tick());
+        info!("respawn in {remaining_ticks:.0} ticks");
+    }
+}
+```
+
+### Visibility
+
+By default, all replicated entities are visible for all clients. To control it,
+insert [`ClientVisibility`] resource with [`VisibilityPolicy::Blacklist`] or
+[`VisibilityPolicy::Whitelist`] on server and change visibility per client:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.insert_resource(ClientVisibility::new(
+    VisibilityPolicy::Whitelist,
+    LeavePolicy::Despawn,
+))
+.add_systems(Update, visibility_system.run_if(resource_exists::<RenetServer>()));
+
+fn visibility_system(
+    mut visibility: ResMut<ClientVisibility>,
+    server: Res<RenetServer>,
+    players: Query<Entity, Added<Player>>,
+) {
+    for entity in &players {
+        for client_id in server.clients_id() {
+            visibility.set_visibility(client_id, entity, true);
+        }
+    }
+}
+# #[derive(Component)]
+# struct Player;
+```
+
+When an entity becomes visible for a client, it will be sent with all its
+replicated components. When it stops being visible, it will be despawned on the
+client or marked with [`ReplicationHidden`] depending on [`LeavePolicy`].
+
+To integrate a fog of war or line of sight computation, use
+[`ClientVisibility::set_visible_entities()`] to replace all visible entities of a
+client at once or [`ClientVisibility::set_visibility_many()`] to update a subset.
+Only entities whose visibility changed will be sent or removed. Use
+[`ClientVisibility::pending_entered()`] and [`ClientVisibility::pending_left()`] to
+get visibility changes that the client hasn't acknowledged yet.
+
+For open worlds, mark entities with [`ReplicationChunk`] and subscribe clients to chunks
+with [`ChunkStreaming::subscribe()`]. Entities of subscribed chunks are streamed in
+within a per-tick budget and streamed out after unsubscribing. To avoid a storm of spawns and
+despawns for entities moving along a chunk border, use [`ChunkStreaming::with_exit_delay()`].
+It requires [`VisibilityPolicy::Whitelist`]:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.insert_resource(ClientVisibility::new(
+    VisibilityPolicy::Whitelist,
+    LeavePolicy::Despawn,
+))
+.insert_resource(ChunkStreaming::new(64))
+.add_systems(Update, chunk_system);
+
+const CHUNK_SIZE: f32 = 100.0;
+
+fn chunk_system(
+    mut commands: Commands,
+    entities: Query<(Entity, &Transform, Option<&ReplicationChunk>), Changed<Transform>>,
+) {
+    for (entity, transform, current_chunk) in &entities {
+        let chunk = ReplicationChunk((transform.translation / CHUNK_SIZE).floor().as_ivec3());
+        // Avoid triggering change detection when the chunk stays the same.
+        if current_chunk != Some(&chunk) {
+            commands.entity(entity).insert(chunk);
+        }
+    }
+}
+```
+
+To show entities only to some teams or roles, insert [`TagVisibility`] with a [`TagFilter`] over
+[`ClientTags`], for example `TagFilter::equals("team", "red").and(!TagFilter::has("spectator"))`.
+Filters are re-evaluated when tags change or clients connect.
+
+### Priority
+
+Entities can be marked with [`ClientOwned`] to prioritize their replication for
+the owning client. Use [`ReplicationPriority`] component to configure priorities
+per entity. Higher-priority entities are sent first, and for congested clients
+changes of low-priority entities are deferred according to [`CongestionPolicy`].
+Clients whose acknowledged ticks lag far behind are throttled in the same way according to
+[`CatchUpPolicy`], which can be configured with [`ServerPlugin::with_catch_up_policy`].
+To change the send rate of individual clients at runtime, like halving it for clients on mobile
+data, use [`ClientSendRates`].
+To avoid huge messages that fragment on the transport level, insert [`MessageEntityLimit`]
+on server. Entities over the limit are carried over to the next ticks by priority.
+To cap the number of entities visible for each client, insert [`InterestLimit`].
+The lowest-priority entities over the limit are removed from [`ClientVisibility`].
+
+Components that are private to the owning client, like inventories or hands of cards,
+can be registered with [`AppReplicationExt::replicate_to_owner()`]. They are sent only
+to the client from [`ClientOwned`] and never reach other clients.
+
+To send changes of distant entities at a lower rate, insert [`DistanceFalloff`] with distance bands
+and update viewpoints of clients, for example from their cameras.
+
+To send fewer components for less relevant entities, mark components with
+[`ReplicationRuleBuilder::max_lod()`] and set the level of detail of entities for each
+client with [`ClientLods::set_lod()`]. Components that are not included in the current
+LOD stop replicating and are removed on client until the LOD includes them again:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate::<Transform>()
+    .replicate::<Health>()
+    .max_lod(0);
+
+fn lod_system(
+    mut lods: ResMut<ClientLods>,
+    players: Query<(&Player, &Transform)>,
+    entities: Query<(Entity, &Transform), With<Replication>>,
+) {
+    for (player, player_transform) in &players {
+        for (entity, transform) in &entities {
+            let distance = player_transform.translation.distance(transform.translation);
+            let lod = if distance < 50.0 { 0 } else { 1 };
+            lods.set_lod(player.0, entity, lod);
+        }
+    }
+}
+# #[derive(Component, Default, Reflect)]
+# #[reflect(Component)]
+# struct Health(u32);
+# #[derive(Component)]
+# struct Player(u64);
+```
+
+To budget bandwidth, call [`DiffSizeWorldExt::diff_sizes()`] on server. It returns the size
+of the next diff for each client with a breakdown by component without sending anything.
+To monitor memory held by replication, for example to detect leaks on a long-running server,
+call [`ReplicationMemoryWorldExt::replication_memory()`] on server or client.
+To avoid CPU spikes with many clients, insert [`DiffBuildBudget`] on server. It limits the time
+spent on building diffs per frame and sends diffs of the remaining clients on the next frames.
+
+### Interpolation
+
+Replicated values are updated only when the server sends them. To render
+smooth movement, add [`TransformInterpolationPlugin`] and mark entities with
+[`Interpolated`] on server. Clients will store received transforms with server
+ticks and interpolate between them in `Update` with [`InterpolationDelay`].
+Insert [`Extrapolation`] on client to project entities forward when updates are late.
+
+Animations don't need per-frame updates either. Add [`AnimationSyncPlugin`] and insert
+[`AnimationState`] on server when a clip starts. Clients will reconstruct the playback
+position from the synced server tick in [`AnimationPlayback`].
+
+### Suspending replication
+
+World diffs are sent and applied in [`ReplicationSet`]. To replicate only in a
+specific state, call [`AppReplicationExt::replicate_in_state()`] on both server
+and client. Network events are not affected:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.add_state::<GameState>()
+    .replicate_in_state(GameState::Playing);
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    #[default]
+    Lobby,
+    Playing,
+}
+```
+
+Server-driven states, like match phases, can be mirrored to clients with
+[`AppReplicationExt::replicate_state()`]. Clients receive the current state on connection
+and follow server transitions through [`NextState`], so `OnEnter` and `OnExit` schedules run as usual.
+
+To pause or slow down the game for everyone, modify [`ServerTimeScale`] on server.
+It's replicated to clients and used by interpolation and prediction. Use the [`simulation_running`]
+condition and [`ServerTimeScale::delta`] in gameplay systems:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.add_systems(Update, movement_system.run_if(simulation_running()));
+
+fn movement_system(
+    time: Res<Time>,
+    time_scale: Res<ServerTimeScale>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for mut transform in &mut transforms {
+        transform.translation.x += time_scale.delta_seconds(&time);
+    }
+}
+```
+
+### Rule sets
+
+Some components are needed only in specific parts of the game. Register them with
+[`AppReplicationExt::replicate_in_rule_set()`] under a name and switch the active
+rule set on server with [`ReplicationRules::set_active_rule_set()`]. Components that
+start replicating are sent for all entities and clients receive [`RuleSetChanged`]:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate_in_rule_set::<Transform>("match")
+    .add_systems(OnEnter(GameState::Playing), start_match_system);
+
+fn start_match_system(mut replication_rules: ResMut<ReplicationRules>) {
+    replication_rules.set_active_rule_set(Some("match"));
+}
+# #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+# enum GameState {
+#     #[default]
+#     Lobby,
+#     Playing,
+# }
+```
+
+Replication of a single component can be paused with [`ReplicationRules::set_component_enabled()`],
+for example to animate [`Transform`] locally during a cutscene. Clients receive [`ReplicationToggled`]
+and when the component is enabled again, its current values are sent for all entities and it's removed
+from entities that lost it in the meantime:
+
+```rust
+# use bevy::{ecs::component::Components, prelude::*};
+# use bevy_replicon::prelude::*;
+fn start_cutscene_system(
+    mut replication_rules: ResMut<ReplicationRules>,
+    components: &Components,
+) {
+    let transform_id = components
+        .component_id::<Transform>()
+        .expect("transform should be registered for replication");
+    replication_rules.set_component_enabled(transform_id, false);
+}
+```
+
+### Replication contexts
+
+A single connection could carry several independent worlds, for example a persistent meta world
+with parties and chat presence next to the match world. Insert [`ReplicationContext`] on entities
+to put them into a separate context and control which clients receive them with
+[`ReplicationContexts`]. Each context could have its own send interval and rule set:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::{prelude::*, renet::ServerEvent};
+const META: ReplicationContext = ReplicationContext(1);
+
+fn setup_contexts_system(mut contexts: ResMut<ReplicationContexts>) {
+    // Send meta entities only on each 10th tick and replicate only components from "meta" rule set.
+    contexts.set_interval(META, 10);
+    contexts.set_rule_set(META, Some("meta"));
+}
+
+fn join_system(
+    mut contexts: ResMut<ReplicationContexts>,
+    mut server_events: EventReader<ServerEvent>,
+) {
+    for event in &mut server_events {
+        if let ServerEvent::ClientConnected { client_id } = event {
+            contexts.join(*client_id, META);
+        }
+    }
+}
+```
+
+### Shadow worlds
+
+Insert [`ReceivedWorldDiffs`] on client to collect applied world diffs and apply them to
+another [`World`] with [`apply_world_diff_message()`], for example to verify the replicated
+state or run a headless observer.
+
+World diff and event messages start with [`WIRE_FORMAT_VERSION`](wire_format::WIRE_FORMAT_VERSION),
+which documents their layout and is incremented only when it changes. Recorded messages stay
+readable by tools that check the version and messages with a different version are rejected.
+
+### Tick atomicity
+
+Each world diff is applied on client in a single exclusive system inside [`ReplicationSet`],
+so client systems never observe a partially applied server tick. All spawns, insertions,
+removals and despawns of the tick become visible at once, and [`ServerTickApplied`] is
+emitted only after the whole tick is applied. The only exceptions are the opt-in budgets
+described below and components deferred by [`EntityMappingPolicy::Defer`].
+
+After connecting, [`InitialSyncComplete`] is emitted once the whole initial world state is received,
+even if it was split over multiple ticks, so you know when you could drop the loading screen.
+Server emits [`ClientSynced`] when the client acknowledges it, which is a good moment to start
+gameplay for this client.
+
+If multiple diffs arrive in the same frame, only the newest one is applied since it already
+contains all changes from the others. Insert [`DiffCoalescing::Sequential`] on client to apply
+every diff in order if you need to observe all intermediate states.
+Diffs could arrive out of order, so each replicated entity stores the tick of its last update
+in [`ReplicationTick`] and older updates are discarded for it.
+To smooth playback, insert [`DiffPlayback`]. It buffers diffs to apply one per frame and
+catches up at an accelerated rate when the client falls behind, exposing the progress
+for a "catching up" UI. To absorb network jitter, insert [`JitterBuffer`] with a delay in ticks.
+It holds received diffs and releases them at the steady server tick rate.
+
+### Large worlds
+
+Insert [`ClientEntityPool`] on client to spawn entities for replication in advance,
+so a large initial diff or a wave of spawns doesn't cause allocation spikes:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.insert_resource(ClientEntityPool::new(1024));
+```
+
+To spread spawns over multiple frames, insert [`SpawnBudget`] with the maximum number of
+entities spawned per frame. Each entity is still spawned with all its components at once.
+Similarly, insert [`DespawnBudget`] to spread despawns over frames or to defer them
+until [`DespawnBudget::flush()`] is called, so mass despawns don't cause a hitch.
+
+Mark entities of composite objects, like a vehicle with turret children, with the same
+[`SpawnGroup`] to always spawn them on client in the same frame regardless of budgets.
+
 ## Network events
 
 Network event replace RPCs (remote procedure calls) in other engines and,
@@ -207,9 +631,10 @@ contains sender ID and the sent event. We consider the authority machine
 This way your game logic will work the same on client, server and in
 single-player session.
 
-Events include `[SendPolicy]` to configure delivery guarantees (reliability and
-ordering). You can alternatively pass in `[bevy_renet::SendType]` directly if you
-need custom configuration for a reliable policy's `resend_time`.
+Events include `[SendPolicy]` to configure delivery guarantees (reliability,
+ordering and `resend_time` for reliable policies), for example
+`SendPolicy::reliable().ordered().resend(Duration::from_millis(100))`.
+You can alternatively pass in `[bevy_renet::SendType]` directly.
 
 ```rust
 # use bevy::prelude::*;
@@ -217,7 +642,7 @@ need custom configuration for a reliable policy's `resend_time`.
 # use serde::{Deserialize, Serialize};
 # let mut app = App::new();
 # app.add_plugins(ReplicationPlugins);
-app.add_client_event::<DummyEvent>(SendPolicy::Ordered)
+app.add_client_event::<DummyEvent>(SendPolicy::reliable().ordered())
     .add_systems(Update, event_sending_system);
 
 fn event_sending_system(mut dummy_events: EventWriter<DummyEvent>) {
@@ -225,7 +650,9 @@ fn event_sending_system(mut dummy_events: EventWriter<DummyEvent>) {
 }
 
 fn event_receiving_system(mut dummy_events: EventReader<FromClient<DummyEvent>>) {
-    for FromClient { client_id, event } in &mut dummy_events {
+    for FromClient {
+        client_id, event, ..
+    } in &mut dummy_events {
         info!("received event {event:?} from client {client_id}");
     }
 }
@@ -234,35 +661,35 @@ fn event_receiving_system(mut dummy_events: EventReader<FromClient<DummyEvent>>)
 struct DummyEvent;
 ```
 
-Just like components, if an event contains [`Entity`], then the client should
-map it before sending it to the server.
-To do this, use [`ClientEventAppExt::add_mapped_client_event()`] and implement [`MapEventEntities`]:
+For lag compensation, use [`ClientEventAppExt::add_ticked_client_event()`].
+Events will be stamped with the [`ServerTickEstimate`] tick
+at the moment of sending and the server will receive it in [`FromClient::tick`].
+All events are also stamped on server with the [`ServerTick`] at which they were received
+and the channel they arrived on, see [`FromClient::received_tick`] and [`FromClient::channel_id`].
+
+Just like components, if an event contains [`Entity`], then the server should
+map it from client entities after receiving. Clients automatically send their
+entity mappings to the server, so events can reference client entities.
+To do this, use [`ClientEventAppExt::add_mapped_client_event()`] and implement Bevy's [`MapEntities`](bevy::ecs::entity::MapEntities).
+It could be derived with [`MapEventEntities`] for fields marked with `#[entities]`:
 
 ```rust
-# use bevy::{
-#     ecs::{
-#         entity::{EntityMap, MapEntities},
-#         reflect::ReflectMapEntities,
-#     },
-#     prelude::*,
-# };
+# use bevy::prelude::*;
 # use bevy_replicon::prelude::*;
 # use serde::{Deserialize, Serialize};
 # let mut app = App::new();
 # app.add_plugins(ReplicationPlugins);
-app.add_mapped_client_event::<MappedEvent>(SendPolicy::Ordered);
+app.add_mapped_client_event::<MappedEvent>(SendPolicy::reliable().ordered());
 
-#[derive(Debug, Deserialize, Event, Serialize)]
-struct MappedEvent(Entity);
-
-impl MapEventEntities for MappedEvent {
-    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapError> {
-        self.0 = entity_map.get(self.0).ok_or(MapError(self.0))?;
-        Ok(())
-    }
-}
+#[derive(Debug, Deserialize, Event, MapEventEntities, Serialize)]
+struct MappedEvent(#[entities] Entity);
 ```
 
+If an event only needs to point at a single entity, use [`ClientEventAppExt::add_client_entity_event()`]
+and send [`ToEntity`] instead. Only its entity will be mapped, so the event itself doesn't need
+to implement [`MapEntities`](bevy::ecs::entity::MapEntities). Events for entities that no longer
+exist on server are handled according to [`MapFailurePolicy`].
+
 There is also [`ClientEventAppExt::add_client_reflect_event()`] and [`ClientEventAppExt::add_mapped_client_reflect_event()`]
 for events that require reflection for serialization and deserialization (for example, events that contain `Box<dyn Reflect>`).
 To serialize such event you need to write serializer and deserializer manually because for such types you need acess to [`AppTypeRegistry`].
@@ -270,6 +697,24 @@ It's pretty straigtforward but requires some boilerplate. See [`BuildEventSerial
 `common` module in integration tests as example.
 Don't forget to check what inside every `Box<dyn Reflect>` from a client, it could be anything!
 
+For events that should take effect on the sending client immediately, like button presses or door toggles,
+use [`OptimisticEventAppExt::add_optimistic_client_event()`]. The event is applied locally right away
+and the server receives it as [`Optimistic`]. If the server sends [`Rejected`] back within the timeout,
+the effect will be reverted.
+
+When a client needs an answer from the server, use [`ClientRequestAppExt::add_client_request()`].
+The server receives the request as [`Request`] and replies with [`Request::respond()`], which
+sends the [`Response`] only to the requesting client. On the client the response is delivered as
+[`RequestResult`] together with the original request, or with [`RequestTimeout`] if the server
+didn't reply in time.
+
+To know when the server received an event, for example to show "action pending…" in UI,
+register it with [`EventReceiptAppExt::add_receipted_client_event()`] and send it with
+[`ReceiptSender`], which returns a [`ReceiptId`]. The server receives the event as a regular
+[`FromClient`] and the client gets [`EventReceipt`] with the same ID. With
+[`EventReceiptAppExt::add_receipted_client_event_with()`] the server validates events first
+and rejected ones are reported in their receipts.
+
 ### From server to client
 
 A similar technique is used to send events from server to clients. To do this,
@@ -285,7 +730,7 @@ from the send list):
 # use serde::{Deserialize, Serialize};
 # let mut app = App::new();
 # app.add_plugins(ReplicationPlugins);
-app.add_server_event::<DummyEvent>(SendPolicy::Ordered)
+app.add_server_event::<DummyEvent>(SendPolicy::reliable().ordered())
     .add_systems(Update, event_sending_system);
 
 fn event_sending_system(mut dummy_events: EventWriter<ToClients<DummyEvent>>) {
@@ -305,11 +750,84 @@ fn event_receiving_system(mut dummy_events: EventReader<DummyEvent>) {
 struct DummyEvent;
 ```
 
+To send an event only to a room or a team, add its clients to [`ClientGroups`]
+and use [`SendMode::BroadcastGroup`].
+For more complex targeting, tag clients with [`ClientTags`], define a named [`TagFilter`]
+with [`ClientTags::set_filter()`] and use [`SendMode::BroadcastTagged`].
+
 Just like with client events, if the event contains [`Entity`], then
 [`ServerEventAppExt::add_mapped_server_event()`] should be used instead.
 
 And for events with `Box<dyn Reflect>` you can use [`ServerEventAppExt::add_server_reflect_event()`] and [`ServerEventAppExt::add_mapped_server_reflect_event()`].
 
+For events that should happen on all peers at the same tick, like round starts or countdowns,
+use [`ScheduledEventAppExt::add_scheduled_server_event()`] and send [`ToClients::scheduled()`]
+with the target [`ServerTick`]. Clients emit the event when their replicated tick reaches it.
+
+If you have many low-traffic events, register them with [`SharedEventAppExt::add_shared_server_event()`]
+and [`SharedEventAppExt::add_shared_client_event()`]. Such events are sent over a single reliable ordered
+channel per direction with a small type tag instead of allocating a channel per event.
+
+Traffic of all registered events is counted per event type in [`NetworkEventStats`]
+on both sides, so you can find events that use the most bandwidth.
+
+### Inputs
+
+Inputs for prediction can be registered with [`ClientInputAppExt::add_client_input()`].
+They are sent like regular client events, but each input is stamped with the server tick
+for which it was predicted, delayed by [`InputConfig::delay`]. Sent inputs are stored in
+[`InputBuffer`] to replay them after corrections from server, and the server receives them
+as [`FromClient`] with [`TickedInput`]. The server also buffers inputs per client in
+[`InputJitterBuffer`] with an adaptive delay from [`InputJitterConfig`] and emits exactly one
+[`FromClient`] input per client on each [`ServerTick`].
+
+To tune prediction, mark predicted entities with [`Predicted`] on client and call
+[`PredictionAppExt::add_misprediction_metrics()`] for predicted components. Corrections from
+server that differ from the predicted values by more than a tolerance are recorded in
+[`MispredictionDiagnostics`] with their error magnitudes.
+
+To hide corrections visually, add [`CorrectionSmoothingPlugin`], insert [`CorrectionSmoothing`]
+on predicted entities and report snaps with [`CorrectionSmoothing::add_correction()`]
+during reconciliation. Only the rendered [`GlobalTransform`] is offset and the offset fades out.
+
+### Server configuration
+
+Settings that clients need before gameplay, like a world seed or game rules, can be sent
+with [`ServerConfigAppExt::sync_server_config()`]. Insert the resource on server and
+each client receives it right after connection, before any world diff is applied:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# use serde::{Deserialize, Serialize};
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.sync_server_config::<GameRules>();
+
+#[derive(Debug, Deserialize, Resource, Serialize)]
+struct GameRules {
+    seed: u64,
+    max_score: u32,
+}
+```
+
+## Lobby
+
+Add [`LobbyPlugin`] on server and clients to get a replicated player list. The server
+spawns [`LobbyPlayer`] for each connected client, clients change their names and ready
+flags with [`LobbyCommand`]. Send [`StartMatch`] on server to emit [`MatchStarted`]
+everywhere once all players are ready. Set [`LobbyConfig::match_rule_set`] to switch
+[rule sets](#rule-sets) on start:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate_in_rule_set::<Transform>("match")
+    .add_plugins(LobbyPlugin::new(Some("match")));
+```
+
 ## Server and client creation
 
 To connect to the server or create it, you need to initialize the
@@ -338,9 +856,122 @@ let connection_config = ConnectionConfig {
 };
 ```
 
+Or use [`NetworkChannels::connection_config()`] to get the same config.
+
+World diffs are sent over an unreliable channel by default and stale diffs are discarded
+on client. Use [`NetworkChannels::set_replication_policy()`] on both server and client
+to make it ordered.
+
+For the common netcode setup there are [`NetcodeWorldExt::create_netcode_server()`] and
+[`NetcodeWorldExt::create_netcode_client()`] that create both Renet and transport resources
+and insert them:
+
+```rust,no_run
+# use std::net::{Ipv4Addr, SocketAddr};
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+const PROTOCOL_ID: u64 = 0;
+let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 5000);
+app.world
+    .create_netcode_server(addr, 10, PROTOCOL_ID, None)
+    .expect("server should be created");
+```
+
+To host a game where the server is also a player, use [`NetcodeWorldExt::create_listen_server()`].
+It removes client resources if they exist and creates the server, the host then plays as [`SERVER_ID`].
+
+Connection timeout for clients created this way can be configured with [`NetcodeSettings`] resource.
+
+Clients also send [`ProtocolVersion`] in the connect token. Server disconnects clients
+with versions outside of its supported range and records versions of the rest in
+[`ClientProtocolVersions`]. To deploy a server update without kicking connected players,
+increment the version, keep the previous one supported and register the old representations
+of changed components with [`ReplicationRuleBuilder::legacy()`]:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.insert_resource(ProtocolVersion::new(2).with_min_supported(1))
+    .replicate::<Health>()
+    .legacy::<LegacyHealth>(2);
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct Health {
+    current: f32,
+    max: f32,
+}
+
+/// Representation of `Health` for clients with version 1.
+#[derive(Reflect)]
+struct LegacyHealth(f32);
+
+impl From<&Health> for LegacyHealth {
+    fn from(health: &Health) -> Self {
+        Self(health.current)
+    }
+}
+```
+
 For full example of how to initialize server or client see the example in the
 repository.
 
+To replace the transport at runtime, for example to switch from unsecure to authenticated
+connections, call [`TransportSwitchWorldExt::start_transport_switch()`] and create new
+Renet resources. Replicated entities will be kept and [`TransportSwitchStarted`] and
+[`TransportSwitched`] events will be emitted around the transition.
+
+On disconnection clients emit [`ClientDisconnected`] with [`ClientDisconnectReason`].
+To disconnect a client with an app-level code, send [`KickClient`] on server.
+To limit the number of players, insert [`ServerSlots`] on server. It can reserve slots for specific
+clients, like admins or rejoining players, and rejects other connections over the limit with
+[`ClientDisconnectReason::ServerFull`].
+
+To authenticate clients, insert [`ServerAuth`] with a validator on server and [`AuthCredentials`]
+on clients before connecting. Clients send credentials once connected and server starts replication
+to them only after accepting, emitting [`ClientAuthenticated`]. Rejected clients and clients that
+didn't send credentials in time are disconnected with [`ClientDisconnectReason::AuthRejected`].
+
+Connection statistics are available in [`NetworkQuality`] resource on client
+and in [`ClientNetworkQuality`] resource on server, for example to show connection indicator.
+
+To find servers in the local network, add [`LanDiscoveryPlugin`] on server and clients.
+Servers with [`LanServerInfo`] resource respond to discovery requests and clients
+with [`DiscoveredServers`] resource receive [`DiscoveredServer`] events.
+
+To connect peers without port forwarding, enable `nat_punch` feature and use `NatPunchPlugin`.
+Instead of creating Renet resources, insert `PunchHost` on host and `PunchClient` on clients.
+They perform UDP hole punching through a rendezvous server with `RendezvousServer` resource
+and create Renet resources with netcode transport after it.
+
+For headless dedicated servers, enable `dedicated_server` feature and add `DedicatedServerPlugins`
+instead of [`MinimalPlugins`] and [`ReplicationPlugins`]. It runs the app at a fixed rate without
+client systems, shuts down gracefully on SIGINT after emitting `ShutdownRequested` and emits
+`AdminCommand` events for lines typed into stdin.
+
+To scrape metrics into dashboards, enable `metrics` feature and add `MetricsExportPlugin`.
+It records bandwidth per client, update duration, entity counts and event statistics through
+the [`metrics`](https://docs.rs/metrics) facade, so any installed recorder could export them.
+
+## Testing
+
+For integration tests use [`test_app::LockstepApps`]. It connects a server app with any number
+of client apps over localhost and updates them in lockstep with a fixed time step,
+so messages are exchanged on each [`LockstepApps::exchange_with_server()`](test_app::LockstepApps::exchange_with_server)
+and [`LockstepApps::advance_ticks()`](test_app::LockstepApps::advance_ticks).
+To connect separately created apps, use [`ServerTestAppExt::connect_client()`](test_app::ServerTestAppExt::connect_client).
+
+To simulate players in tests and soak runs without the game client, use [`bot_client::BotClient`].
+It receives replication into a bare world and sends client events immediately or after
+a number of updates with [`BotClient::send_event_after()`](bot_client::BotClient::send_event_after).
+For capacity planning, [`load_test::LoadTest`] connects many bots to a server and aggregates
+their connection and bandwidth metrics, while [`load_test::LoadTestServerPlugin`] measures
+server update time.
+
 ## System sets and conditions
 
 When configuring systems for multiplayer game, you often want to run some
@@ -350,6 +981,50 @@ procedural level generation systems. For this just add [`has_authority()`]
 condition on such system. If you want your systems to run only on
 frames when server send updates to clients use [`ServerSet::Send`].
 
+In mixed-authority setups, where some entities are simulated by clients,
+insert [`Authority`] on server to specify who simulates an entity. It's replicated
+automatically, so systems like movement can use [`LocalAuthority`] or
+[`has_authority_over()`] condition to run only on the peer that owns the entity:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+fn movement_system(
+    authority: LocalAuthority,
+    mut transforms: Query<(Entity, &mut Transform), With<Replication>>,
+) {
+    for (entity, mut transform) in &mut transforms {
+        if authority.has_authority_over(entity) {
+            transform.translation.x += 1.0;
+        }
+    }
+}
+```
+
+For games without full server authority, insert [`RelayServer`] on server.
+It performs no simulation and only validates and forwards client-authoritative
+components and events registered with [`RelayEventAppExt::add_relayed_event()`],
+which clients receive as [`FromPeer`]. Use [`is_relay()`] condition to disable
+gameplay systems on relay.
+
+Components can also be replicated from owning clients to server with
+[`ReplicationRuleBuilder::client_authoritative()`]. Server applies received changes,
+optionally validating them, and replicates them to other clients:
+
+```rust
+# use bevy::prelude::*;
+# use bevy_replicon::prelude::*;
+# use serde::{Deserialize, Serialize};
+# let mut app = App::new();
+# app.add_plugins(ReplicationPlugins);
+app.replicate::<Cursor>()
+    .client_authoritative_with(|_client_id, _current, received: &Cursor| received.0.is_finite());
+
+#[derive(Clone, Component, Debug, Default, Deserialize, Reflect, Serialize)]
+#[reflect(Component)]
+struct Cursor(Vec2);
+```
+
 To check if you running server or client, you can use conditions based on
 [`RenetClient`] and [`RenetServer`] resources.
 They rarely used for gameplay systems (since you write the same logic for
@@ -357,34 +1032,151 @@ multiplayer and single-player!), but could be used for server
 creation / connection systems and corresponding UI.
 */
 
+pub mod animation;
+pub mod authority;
+pub mod bot_client;
 pub mod client;
+#[cfg(feature = "dedicated_server")]
+pub mod dedicated_server;
+pub mod entity_mapping;
+pub mod input;
+pub mod interpolation;
+pub mod lan_discovery;
+pub mod load_test;
+pub mod lobby;
+pub mod memory_usage;
+#[cfg(feature = "metrics")]
+pub mod metrics_export;
+#[cfg(feature = "nat_punch")]
+pub mod nat_punch;
+pub mod netcode;
 pub mod network_event;
+pub mod network_quality;
+pub mod network_uuid;
 pub mod parent_sync;
+pub mod prediction;
+pub mod protocol_version;
+pub mod relay;
 pub mod replication_core;
 pub mod server;
-mod world_diff;
+pub mod server_config;
+pub mod synced_timer;
+pub mod test_app;
+pub mod time_scale;
+pub mod traffic_decoder;
+pub mod transport_switch;
+pub mod wire_format;
+pub mod world_diff;
 
 pub mod prelude {
     pub use super::{
-        client::{ClientPlugin, ClientSet, NetworkEntityMap},
+        animation::{AnimationPlayback, AnimationState, AnimationSyncPlugin},
+        authority::{has_authority_over, Authority, ComponentValidator, LocalAuthority},
+        client::{
+            apply_world_diff_message, AuthCredentials, ClientDisconnectReason, ClientDisconnected,
+            ClientEntityPool, ClientPlugin, ClientSet, ComponentReplicated, DespawnBudget,
+            DespawnPolicy, DiffCoalescing, DiffPlayback, EntityMappingPolicy, InitialSyncComplete,
+            JitterBuffer, LastReceivedServerTick, MappedEntity, NetworkEntityMap,
+            ReceivedWorldDiffs, ReplicatedChange, ReplicationDespawned, ReplicationHidden,
+            ReplicationTick, ServerTickApplied, ServerTickEstimate, SpawnBudget,
+        },
+        entity_mapping::{MapEventEntities, MapNestedEntities},
+        input::{
+            ClientInputAppExt, InputBuffer, InputBufferHealth, InputConfig, InputJitterBuffer,
+            InputJitterConfig, TickedInput,
+        },
+        interpolation::{
+            Extrapolation, Interpolated, InterpolationDelay, TransformInterpolationPlugin,
+            TransformSnapshots,
+        },
+        lan_discovery::{
+            DiscoveredServer, DiscoveredServers, LanDiscoveryConfig, LanDiscoveryPlugin,
+            LanServerInfo,
+        },
+        lobby::{LobbyCommand, LobbyConfig, LobbyPlayer, LobbyPlugin, MatchStarted, StartMatch},
+        memory_usage::{ReplicationMemory, ReplicationMemoryWorldExt},
+        netcode::{new_netcode_client, new_netcode_server, NetcodeSettings, NetcodeWorldExt},
         network_event::{
-            client_event::{ClientEventAppExt, FromClient},
+            client_event::{ClientEventAppExt, FromClient, ToEntity},
+            client_request::{
+                ClientRequestAppExt, Request, RequestResult, RequestTimeout, Response,
+            },
+            event_receipt::{
+                EventReceipt, EventReceiptAppExt, ReceiptId, ReceiptSender, Receipted,
+            },
+            optimistic_event::{Optimistic, OptimisticEventAppExt, Rejected},
+            scheduled_event::{Scheduled, ScheduledEventAppExt},
             server_event::{SendMode, ServerEventAppExt, ToClients},
-            BuildEventDeserializer, BuildEventSerializer, MapError, MapEventEntities, SendPolicy,
+            shared_event::SharedEventAppExt,
+            BuildEventDeserializer, BuildEventSerializer, EventMappingFailed, EventStats,
+            MapFailurePolicy, NetworkEventStats, SendPolicy,
         },
+        network_quality::{ClientNetworkQuality, NetworkQuality, NetworkQualityPlugin},
+        network_uuid::{NetworkUuid, NetworkUuidPlugin, NetworkUuids},
         parent_sync::{ParentSync, ParentSyncPlugin},
+        prediction::{
+            CorrectionSmoothing, CorrectionSmoothingPlugin, MispredictionDiagnostics,
+            MispredictionStats, Predicted, PredictionAppExt, PredictionError,
+        },
+        protocol_version::{ClientProtocolVersions, ProtocolVersion, ProtocolVersionPlugin},
+        relay::{is_relay, FromPeer, RelayEventAppExt, RelayServer},
         renet::{RenetClient, RenetServer},
         replication_core::{
-            AppReplicationExt, NetworkChannels, Replication, ReplicationCorePlugin,
-            ReplicationRules,
+            AppReplicationExt, ClientOwned, NetworkChannels, Replicate, Replication,
+            ReplicationContext, ReplicationCorePlugin, ReplicationRecursive,
+            ReplicationRuleBuilder, ReplicationRules, ReplicationSet, ReplicationToggled,
+            RuleSetChanged, SpawnGroup,
+        },
+        server::{
+            has_authority, AckedTicks, AuthValidator, CapturedMessage, CatchUpPolicy,
+            ChunkStreaming, ClientAuthRejected, ClientAuthenticated, ClientCongestion,
+            ClientEntityMap, ClientEntityMaps, ClientGroups, ClientLods, ClientRejected,
+            ClientSendRates, ClientSynced, ClientTags, ClientVisibility, CongestionPolicy,
+            DespawnWithReasonExt, DiffBuildBudget, DiffSize, DiffSizeWorldExt, DistanceFalloff,
+            InterestLimit, KickClient, LeavePolicy, MessageEntityLimit, ReplicationChunk,
+            ReplicationContexts, ReplicationPriority, ServerAuth, ServerPlugin, ServerSet,
+            ServerSlots, ServerTick, TagFilter, TagVisibility, TickPolicy, TrafficDirection,
+            TrafficLog, TrafficRecord, UnreplicatedDiagnostics, VisibilityPolicy, SERVER_ID,
+        },
+        server_config::ServerConfigAppExt,
+        synced_timer::{SyncedClock, SyncedTimer, SyncedTimerPlugin},
+        time_scale::{simulation_running, ServerTimeScale, TimeScalePlugin},
+        traffic_decoder::TrafficDecoder,
+        transport_switch::{
+            TransportSwitch, TransportSwitchPlugin, TransportSwitchStarted,
+            TransportSwitchWorldExt, TransportSwitched,
         },
-        server::{has_authority, AckedTicks, ServerPlugin, ServerSet, TickPolicy, SERVER_ID},
+        world_diff::DespawnReason,
         ReplicationPlugins,
     };
+
+    #[cfg(feature = "dedicated_server")]
+    pub use super::dedicated_server::{
+        AdminCommand, DedicatedServerPlugin, DedicatedServerPlugins, ShutdownHandle,
+        ShutdownRequested,
+    };
+
+    #[cfg(feature = "metrics")]
+    pub use super::metrics_export::MetricsExportPlugin;
+
+    #[cfg(feature = "nat_punch")]
+    pub use super::nat_punch::{
+        NatPunchPlugin, PunchClient, PunchConfig, PunchError, PunchEvent, PunchHost,
+        RendezvousServer,
+    };
 }
 
 use bevy::{app::PluginGroupBuilder, prelude::*};
 pub use bevy_renet::*;
+
+/// Items used by code generated from derive macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use bevy::{
+        app::App,
+        ecs::entity::{EntityMapper, MapEntities},
+    };
+}
 use prelude::*;
 
 pub struct ReplicationPlugins;
@@ -393,8 +1185,14 @@ impl PluginGroup for ReplicationPlugins {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
             .add(ReplicationCorePlugin)
-            .add(ParentSyncPlugin)
+            .add(ParentSyncPlugin::default())
             .add(ClientPlugin)
             .add(ServerPlugin::default())
+            .add(TransportSwitchPlugin)
+            .add(NetworkQualityPlugin)
+            .add(NetworkUuidPlugin)
+            .add(ProtocolVersionPlugin)
+            .add(SyncedTimerPlugin)
+            .add(TimeScalePlugin)
     }
 }
@@ -0,0 +1,216 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::{
+    transport::{NetcodeClientTransport, NetcodeError},
+    RenetClient,
+};
+
+use crate::bot_client::BotClient;
+
+/// Simulated clients for load testing a server.
+///
+/// Creates [`BotClient`]s, connects all of them to the server and updates them one after another on
+/// each [`Self::update`]. Aggregate metrics of the clients are returned by [`Self::metrics`],
+/// add [`LoadTestServerPlugin`] to the server app to also measure its update time.
+///
+/// ```no_run
+/// # use std::net::{Ipv4Addr, SocketAddr};
+/// # use bevy::prelude::*;
+/// # use bevy_replicon::{load_test::LoadTest, prelude::*};
+/// let mut load_test = LoadTest::new(100, |app| {
+///     app.replicate::<Transform>();
+/// });
+/// let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 5000);
+/// load_test.connect(server_addr, 1, 0)?;
+/// for _ in 0..600 {
+///     load_test.update();
+/// }
+/// println!("{}", load_test.metrics());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LoadTest {
+    bots: Vec<BotClient>,
+}
+
+impl LoadTest {
+    /// Creates `clients_count` bots and calls `setup` for each of them.
+    pub fn new(clients_count: usize, mut setup: impl FnMut(&mut App)) -> Self {
+        let bots = (0..clients_count)
+            .map(|_| BotClient::new(&mut setup))
+            .collect();
+
+        Self { bots }
+    }
+
+    /// Starts connecting all bots to the server.
+    ///
+    /// Bots get sequential client IDs starting from `first_client_id`.
+    pub fn connect(
+        &mut self,
+        server_addr: SocketAddr,
+        first_client_id: u64,
+        protocol_id: u64,
+    ) -> Result<(), NetcodeError> {
+        for (client_id, bot) in (first_client_id..).zip(&mut self.bots) {
+            bot.connect(server_addr, client_id, protocol_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Updates all bots.
+    pub fn update(&mut self) {
+        for bot in &mut self.bots {
+            bot.update();
+        }
+    }
+
+    /// Returns all bots, for example to script events for them.
+    pub fn bots_mut(&mut self) -> &mut [BotClient] {
+        &mut self.bots
+    }
+
+    pub fn bots(&self) -> &[BotClient] {
+        &self.bots
+    }
+
+    /// Returns connection and bandwidth statistics of all bots.
+    pub fn metrics(&self) -> LoadTestMetrics {
+        let mut metrics = LoadTestMetrics {
+            clients: self.bots.len(),
+            ..Default::default()
+        };
+        let mut total_rtt = Duration::ZERO;
+        for bot in &self.bots {
+            let Some(transport) = bot.app.world.get_resource::<NetcodeClientTransport>() else {
+                continue;
+            };
+            if transport.is_disconnected() {
+                metrics.failed += 1;
+            } else if transport.is_connected() {
+                metrics.connected += 1;
+                let network_info = bot.app.world.resource::<RenetClient>().network_info();
+                metrics.bytes_sent_per_second += network_info.bytes_sent_per_second;
+                metrics.bytes_received_per_second += network_info.bytes_received_per_second;
+                total_rtt += Duration::from_secs_f64(network_info.rtt);
+            }
+        }
+        if metrics.connected != 0 {
+            metrics.average_rtt = total_rtt / metrics.connected as u32;
+        }
+
+        metrics
+    }
+}
+
+/// Aggregate statistics of bots from [`LoadTest`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadTestMetrics {
+    /// Number of created bots.
+    pub clients: usize,
+    /// Number of connected bots.
+    pub connected: usize,
+    /// Number of bots that failed to connect or were disconnected.
+    pub failed: usize,
+    /// Sum for all connected bots.
+    pub bytes_sent_per_second: f64,
+    /// Sum for all connected bots.
+    pub bytes_received_per_second: f64,
+    /// Round-trip time averaged over connected bots.
+    pub average_rtt: Duration,
+}
+
+impl Display for LoadTestMetrics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} clients connected, {} failed, {:.0} B/s sent, {:.0} B/s received, {:?} average RTT",
+            self.connected,
+            self.clients,
+            self.failed,
+            self.bytes_sent_per_second,
+            self.bytes_received_per_second,
+            self.average_rtt,
+        )
+    }
+}
+
+/// Measures how long each update of the server app takes for load tests.
+///
+/// Inserts [`ServerUpdateTime`] resource.
+pub struct LoadTestServerPlugin;
+
+impl Plugin for LoadTestServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServerUpdateTime>()
+            .add_systems(First, Self::start_system)
+            .add_systems(Last, Self::finish_system);
+    }
+}
+
+impl LoadTestServerPlugin {
+    fn start_system(mut update_time: ResMut<ServerUpdateTime>) {
+        update_time.started_at = Some(Instant::now());
+    }
+
+    pub(crate) fn finish_system(mut update_time: ResMut<ServerUpdateTime>) {
+        if let Some(started_at) = update_time.started_at.take() {
+            update_time.record(started_at.elapsed());
+        }
+    }
+}
+
+/// Durations of server updates from [`First`] to [`Last`].
+#[derive(Default, Resource)]
+pub struct ServerUpdateTime {
+    started_at: Option<Instant>,
+    last: Duration,
+    max: Duration,
+    total: Duration,
+    updates: u32,
+}
+
+impl ServerUpdateTime {
+    /// Returns duration of the last update.
+    pub fn last(&self) -> Duration {
+        self.last
+    }
+
+    /// Returns the longest update since creation or [`Self::reset`].
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns average duration of updates since creation or [`Self::reset`].
+    pub fn average(&self) -> Duration {
+        if self.updates == 0 {
+            return Duration::ZERO;
+        }
+        self.total / self.updates
+    }
+
+    /// Returns the number of measured updates since creation or [`Self::reset`].
+    pub fn updates(&self) -> u32 {
+        self.updates
+    }
+
+    /// Clears measurements, for example after warmup.
+    pub fn reset(&mut self) {
+        self.last = Duration::ZERO;
+        self.max = Duration::ZERO;
+        self.total = Duration::ZERO;
+        self.updates = 0;
+    }
+
+    fn record(&mut self, duration: Duration) {
+        self.last = duration;
+        self.max = self.max.max(duration);
+        self.total += duration;
+        self.updates += 1;
+    }
+}
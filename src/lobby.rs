@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use derive_more::Constructor;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    network_event::{
+        client_event::{ClientEventAppExt, FromClient},
+        server_event::{SendMode, ServerEventAppExt, ToClients},
+        SendPolicy,
+    },
+    replication_core::{AppReplicationExt, Replication, ReplicationRules},
+    server::{has_authority, ServerSet},
+};
+
+/// Manages a replicated lobby with connected players.
+///
+/// On server, an entity with [`LobbyPlayer`] is spawned for each connected client
+/// and despawned after disconnection. Clients modify their players by sending [`LobbyCommand`].
+/// When [`StartMatch`] is sent on server and all players are ready, the rule set
+/// from [`LobbyConfig`] is activated and [`MatchStarted`] is emitted on server and clients.
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately
+/// on both server and clients after them.
+#[derive(Constructor, Default)]
+pub struct LobbyPlugin {
+    /// Initial value for [`LobbyConfig::match_rule_set`].
+    match_rule_set: Option<&'static str>,
+}
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<LobbyPlayer>()
+            .insert_resource(LobbyConfig {
+                match_rule_set: self.match_rule_set,
+            })
+            .add_event::<StartMatch>()
+            .add_client_event::<LobbyCommand>(SendPolicy::reliable().ordered())
+            .add_server_event::<MatchStarted>(SendPolicy::reliable().ordered())
+            .add_systems(
+                PreUpdate,
+                (
+                    Self::connection_system.run_if(resource_exists::<RenetServer>()),
+                    Self::command_system.run_if(has_authority()),
+                )
+                    .chain()
+                    .after(ServerSet::Receive),
+            )
+            .add_systems(
+                PostUpdate,
+                Self::start_system
+                    .before(ServerSet::Send)
+                    .run_if(has_authority()),
+            );
+    }
+}
+
+impl LobbyPlugin {
+    fn connection_system(
+        mut commands: Commands,
+        mut server_events: EventReader<ServerEvent>,
+        players: Query<(Entity, &LobbyPlayer)>,
+    ) {
+        for event in &mut server_events {
+            match *event {
+                ServerEvent::ClientConnected { client_id } => {
+                    commands.spawn((LobbyPlayer::new(client_id), Replication));
+                }
+                ServerEvent::ClientDisconnected { client_id, .. } => {
+                    for (entity, player) in &players {
+                        if player.client_id == client_id {
+                            commands.entity(entity).despawn();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn command_system(
+        mut lobby_commands: EventReader<FromClient<LobbyCommand>>,
+        mut players: Query<&mut LobbyPlayer>,
+    ) {
+        for FromClient {
+            client_id, event, ..
+        } in &mut lobby_commands
+        {
+            let Some(mut player) = players
+                .iter_mut()
+                .find(|player| player.client_id == *client_id)
+            else {
+                warn!("ignoring lobby command {event:?} from client {client_id} without player");
+                continue;
+            };
+
+            match event {
+                LobbyCommand::SetName(name) => player.name = name.clone(),
+                LobbyCommand::SetReady(ready) => player.ready = *ready,
+            }
+        }
+    }
+
+    fn start_system(
+        config: Res<LobbyConfig>,
+        mut start_events: EventReader<StartMatch>,
+        mut match_events: EventWriter<ToClients<MatchStarted>>,
+        mut replication_rules: ResMut<ReplicationRules>,
+        players: Query<&LobbyPlayer>,
+    ) {
+        if start_events.is_empty() {
+            return;
+        }
+        start_events.clear();
+
+        if let Some(player) = players.iter().find(|player| !player.ready) {
+            warn!(
+                "unable to start the match because client {} is not ready",
+                player.client_id
+            );
+            return;
+        }
+
+        if config.match_rule_set.is_some() {
+            replication_rules.set_active_rule_set(config.match_rule_set);
+        }
+        match_events.send(ToClients {
+            mode: SendMode::Broadcast,
+            event: MatchStarted,
+        });
+    }
+}
+
+/// Settings of [`LobbyPlugin`].
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct LobbyConfig {
+    /// Rule set to activate with [`ReplicationRules::set_active_rule_set`] when the match starts.
+    pub match_rule_set: Option<&'static str>,
+}
+
+/// A player in the lobby.
+///
+/// Spawned automatically on server for each connected client and replicated to all clients.
+/// Insert your own replicated components on the same entity to store additional player data.
+#[derive(Clone, Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct LobbyPlayer {
+    pub client_id: u64,
+    pub name: String,
+    pub ready: bool,
+}
+
+impl LobbyPlayer {
+    /// Creates a player that is not ready with the default name.
+    pub fn new(client_id: u64) -> Self {
+        Self {
+            client_id,
+            name: format!("Player {client_id}"),
+            ready: false,
+        }
+    }
+}
+
+/// A client event to change its [`LobbyPlayer`].
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+pub enum LobbyCommand {
+    SetName(String),
+    SetReady(bool),
+}
+
+/// An event to start the match from the lobby.
+///
+/// Should be sent on server, ignored if any player is not ready.
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct StartMatch;
+
+/// An event that emitted on server and clients when the match starts.
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct MatchStarted;
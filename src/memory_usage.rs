@@ -0,0 +1,128 @@
+use std::mem;
+
+use bevy::{
+    ecs::component::{ComponentId, Tick},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::{
+    client::{
+        DespawnBudget, DiffPlayback, JitterBuffer, NetworkEntityMap, ReceivedWorldDiffs,
+        UnconfirmedEntities,
+    },
+    interpolation::TransformSnapshots,
+    server::{
+        despawn_tracker::DespawnTracker, removal_tracker::RemovalTracker, AckedTicks,
+        ChunkStreaming, ClientCongestion, ClientEntityMaps, ClientLods, ClientVisibility,
+        DiffBuildBudget, ReplicationContexts,
+    },
+};
+
+/// An extension trait for [`World`] to inspect memory used by replication.
+pub trait ReplicationMemoryWorldExt {
+    /// Returns the estimated memory held by replication internals.
+    ///
+    /// Works on server and client, state of the other side is reported as zero.
+    /// Sizes are estimated from the number of stored elements, so they don't include
+    /// allocator overhead and unused capacity. Useful to detect leaks on long-running servers
+    /// and to tune history lengths and buffers.
+    fn replication_memory(&mut self) -> ReplicationMemory;
+}
+
+impl ReplicationMemoryWorldExt for World {
+    fn replication_memory(&mut self) -> ReplicationMemory {
+        let mut memory = ReplicationMemory::default();
+
+        if let Some(acked_ticks) = self.get_resource::<AckedTicks>() {
+            memory.tick_history += acked_ticks.len() * mem::size_of::<(u64, Tick)>();
+            for &client_id in acked_ticks.keys() {
+                let mut client_memory = 0;
+                if let Some(visibility) = self.get_resource::<ClientVisibility>() {
+                    client_memory += visibility.memory_usage(client_id);
+                }
+                if let Some(lods) = self.get_resource::<ClientLods>() {
+                    client_memory += lods.memory_usage(client_id);
+                }
+                if let Some(congestion) = self.get_resource::<ClientCongestion>() {
+                    client_memory += congestion.memory_usage(client_id);
+                }
+                if let Some(streaming) = self.get_resource::<ChunkStreaming>() {
+                    client_memory += streaming.memory_usage(client_id);
+                }
+                if let Some(contexts) = self.get_resource::<ReplicationContexts>() {
+                    client_memory += contexts.memory_usage(client_id);
+                }
+                memory.clients.insert(client_id, client_memory);
+            }
+        }
+        if let Some(despawn_tracker) = self.get_resource::<DespawnTracker>() {
+            memory.tick_history += despawn_tracker.memory_usage();
+        }
+        if let Some(unconfirmed) = self.get_resource::<UnconfirmedEntities>() {
+            memory.tick_history += unconfirmed.memory_usage();
+        }
+        memory.tick_history += self
+            .query::<&RemovalTracker>()
+            .iter(self)
+            .map(|removal_tracker| removal_tracker.len() * mem::size_of::<(ComponentId, Tick)>())
+            .sum::<usize>();
+
+        if let Some(diff_budget) = self.get_resource::<DiffBuildBudget>() {
+            memory.buffers += diff_budget.memory_usage();
+        }
+        if let Some(playback) = self.get_resource::<DiffPlayback>() {
+            memory.buffers += playback.memory_usage();
+        }
+        if let Some(jitter_buffer) = self.get_resource::<JitterBuffer>() {
+            memory.buffers += jitter_buffer.memory_usage();
+        }
+        if let Some(received_diffs) = self.get_resource::<ReceivedWorldDiffs>() {
+            memory.buffers += received_diffs
+                .iter()
+                .map(|message| message.len())
+                .sum::<usize>();
+        }
+        if let Some(despawn_budget) = self.get_resource::<DespawnBudget>() {
+            memory.buffers += despawn_budget.memory_usage();
+        }
+        memory.buffers += self
+            .query::<&TransformSnapshots>()
+            .iter(self)
+            .map(|snapshots| snapshots.iter().count() * mem::size_of::<(u32, Transform)>())
+            .sum::<usize>();
+
+        if let Some(entity_map) = self.get_resource::<NetworkEntityMap>() {
+            memory.entity_maps += entity_map.memory_usage();
+        }
+        if let Some(client_entity_maps) = self.get_resource::<ClientEntityMaps>() {
+            memory.entity_maps += client_entity_maps
+                .values()
+                .map(|entity_map| entity_map.memory_usage())
+                .sum::<usize>();
+        }
+
+        memory
+    }
+}
+
+/// Estimated memory in bytes held by replication internals returned by
+/// [`ReplicationMemoryWorldExt::replication_memory`].
+#[derive(Clone, Debug, Default)]
+pub struct ReplicationMemory {
+    /// Pending per-client state on server: visibility and LOD changes, deferred changes and chunk subscriptions.
+    pub clients: HashMap<u64, usize>,
+    /// Acknowledged ticks, tracked removals and despawns on server and unconfirmed entities on client.
+    pub tick_history: usize,
+    /// Buffered diffs, despawns and transform snapshots.
+    pub buffers: usize,
+    /// Entity mappings between server and clients.
+    pub entity_maps: usize,
+}
+
+impl ReplicationMemory {
+    /// Returns the total estimated memory in bytes.
+    pub fn total(&self) -> usize {
+        self.clients.values().sum::<usize>() + self.tick_history + self.buffers + self.entity_maps
+    }
+}
@@ -0,0 +1,134 @@
+use bevy::{prelude::*, utils::get_short_name};
+use bevy_renet::renet::{RenetClient, RenetServer};
+use metrics::{absolute_counter, gauge, histogram};
+
+use crate::{
+    client::NetworkEntityMap,
+    load_test::{LoadTestServerPlugin, ServerUpdateTime},
+    network_event::NetworkEventStats,
+    replication_core::Replication,
+    server::ServerTick,
+};
+
+/// Exports replication and connection metrics through the [`metrics`] facade.
+///
+/// Metrics are recorded every frame in [`Last`] and could be scraped by any installed recorder,
+/// like `metrics-exporter-prometheus`. All names start with `replicon_`.
+///
+/// On server:
+/// - `replicon_connected_clients` and `replicon_replicated_entities` gauges.
+/// - `replicon_server_tick` gauge with the current [`ServerTick`].
+/// - `replicon_update_seconds` histogram with the duration of the whole update measured
+///   by [`LoadTestServerPlugin`], which is added automatically.
+/// - `replicon_client_rtt_seconds`, `replicon_client_packet_loss`, `replicon_client_sent_bytes_per_second`
+///   and `replicon_client_received_bytes_per_second` gauges with `client_id` label.
+///
+/// On client:
+/// - `replicon_rtt_seconds`, `replicon_packet_loss`, `replicon_sent_bytes_per_second`
+///   and `replicon_received_bytes_per_second` gauges.
+/// - `replicon_mapped_entities` gauge with the number of received server entities.
+///
+/// On both:
+/// - `replicon_event_sent_messages`, `replicon_event_sent_bytes`, `replicon_event_received_messages`
+///   and `replicon_event_received_bytes` counters from [`NetworkEventStats`] with `event` label.
+pub struct MetricsExportPlugin;
+
+impl Plugin for MetricsExportPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<LoadTestServerPlugin>() {
+            app.add_plugins(LoadTestServerPlugin);
+        }
+
+        app.add_systems(
+            Last,
+            (
+                Self::server_system
+                    .after(LoadTestServerPlugin::finish_system)
+                    .run_if(resource_exists::<RenetServer>()),
+                Self::client_system.run_if(resource_exists::<RenetClient>()),
+                Self::events_system.run_if(resource_exists::<NetworkEventStats>()),
+            ),
+        );
+    }
+}
+
+impl MetricsExportPlugin {
+    fn server_system(
+        server: Res<RenetServer>,
+        server_tick: Res<ServerTick>,
+        update_time: Res<ServerUpdateTime>,
+        replicated: Query<(), With<Replication>>,
+    ) {
+        let clients_id = server.clients_id();
+        gauge!("replicon_connected_clients", clients_id.len() as f64);
+        gauge!(
+            "replicon_replicated_entities",
+            replicated.iter().count() as f64
+        );
+        gauge!("replicon_server_tick", server_tick.get() as f64);
+        histogram!("replicon_update_seconds", update_time.last());
+
+        for client_id in clients_id {
+            let network_info = server
+                .network_info(client_id)
+                .expect("network info should be requested for connected client");
+            let labels = [("client_id", client_id.to_string())];
+            gauge!("replicon_client_rtt_seconds", network_info.rtt, &labels);
+            gauge!(
+                "replicon_client_packet_loss",
+                network_info.packet_loss,
+                &labels
+            );
+            gauge!(
+                "replicon_client_sent_bytes_per_second",
+                network_info.bytes_sent_per_second,
+                &labels
+            );
+            gauge!(
+                "replicon_client_received_bytes_per_second",
+                network_info.bytes_received_per_second,
+                &labels
+            );
+        }
+    }
+
+    fn client_system(client: Res<RenetClient>, entity_map: Res<NetworkEntityMap>) {
+        let network_info = client.network_info();
+        gauge!("replicon_rtt_seconds", network_info.rtt);
+        gauge!("replicon_packet_loss", network_info.packet_loss);
+        gauge!(
+            "replicon_sent_bytes_per_second",
+            network_info.bytes_sent_per_second
+        );
+        gauge!(
+            "replicon_received_bytes_per_second",
+            network_info.bytes_received_per_second
+        );
+        gauge!(
+            "replicon_mapped_entities",
+            entity_map.to_client().len() as f64
+        );
+    }
+
+    fn events_system(stats: Res<NetworkEventStats>) {
+        for (type_name, event_stats) in stats.iter() {
+            let labels = [("event", get_short_name(type_name))];
+            absolute_counter!(
+                "replicon_event_sent_messages",
+                event_stats.sent_messages,
+                &labels
+            );
+            absolute_counter!("replicon_event_sent_bytes", event_stats.sent_bytes, &labels);
+            absolute_counter!(
+                "replicon_event_received_messages",
+                event_stats.received_messages,
+                &labels
+            );
+            absolute_counter!(
+                "replicon_event_received_bytes",
+                event_stats.received_bytes,
+                &labels
+            );
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use std::{
+    io::{self, ErrorKind},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    netcode::{netcode_client_with_socket, netcode_server_with_socket, NetcodeSettings},
+    protocol_version::ProtocolVersion,
+    replication_core::NetworkChannels,
+};
+
+/// Connects peers behind NATs using UDP hole punching through a rendezvous server.
+///
+/// A host inserts [`PunchHost`] instead of creating server resources. It registers the session
+/// on the rendezvous server, learns its public address and creates [`RenetServer`](crate::renet::RenetServer)
+/// with netcode transport on the same socket. A client inserts [`PunchClient`] instead of creating client
+/// resources. It asks the rendezvous server for the host address, exchanges punch packets with the host
+/// to open both NATs and creates [`RenetClient`](crate::renet::RenetClient) with netcode transport
+/// on the same socket. After that replication works as usual.
+///
+/// The rendezvous server is a separate process with [`RendezvousServer`] resource
+/// (or without Bevy by calling [`RendezvousServer::update`] manually).
+/// Traffic is not relayed, so peers behind symmetric NATs can't connect.
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately.
+/// Available only with `nat_punch` feature.
+pub struct NatPunchPlugin;
+
+impl Plugin for NatPunchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PunchEvent>().add_systems(
+            PreUpdate,
+            (
+                Self::rendezvous_system.run_if(resource_exists::<RendezvousServer>()),
+                Self::host_system.run_if(resource_exists::<PunchHost>()),
+                Self::client_system.run_if(resource_exists::<PunchClient>()),
+            ),
+        );
+    }
+}
+
+impl NatPunchPlugin {
+    fn rendezvous_system(time: Res<Time>, mut rendezvous: ResMut<RendezvousServer>) {
+        rendezvous.update(time.elapsed());
+    }
+
+    fn host_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        network_channels: Res<NetworkChannels>,
+        mut host: ResMut<PunchHost>,
+        mut punch_events: EventWriter<PunchEvent>,
+    ) {
+        let started_at = *host.started_at.get_or_insert(time.elapsed());
+        if host.public_addr.is_none() {
+            for (message, addr) in receive_messages(&host.socket) {
+                let RendezvousMessage::Registered { public_addr } = message else {
+                    debug!("ignoring unexpected message {message:?} from {addr}");
+                    continue;
+                };
+
+                let socket = match host.socket.try_clone() {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        error!("unable to clone host socket: {e}");
+                        return;
+                    }
+                };
+                match netcode_server_with_socket(
+                    &network_channels,
+                    socket,
+                    public_addr,
+                    host.max_clients,
+                    host.protocol_id,
+                    None,
+                ) {
+                    Ok((server, transport)) => {
+                        commands.insert_resource(server);
+                        commands.insert_resource(transport);
+                        host.public_addr = Some(public_addr);
+                        punch_events.send(PunchEvent::Registered(public_addr));
+                    }
+                    Err(e) => {
+                        error!("unable to create server on {public_addr}: {e}");
+                        commands.remove_resource::<PunchHost>();
+                        punch_events.send(PunchEvent::Failed(PunchError::Transport));
+                        return;
+                    }
+                }
+                break;
+            }
+
+            if host.public_addr.is_none()
+                && time.elapsed().saturating_sub(started_at) > host.config.timeout
+            {
+                commands.remove_resource::<PunchHost>();
+                punch_events.send(PunchEvent::Failed(PunchError::TimedOut));
+                return;
+            }
+        }
+
+        for (message, addr) in receive_messages(&host.control_socket) {
+            let RendezvousMessage::PeerAddr { addr: peer_addr } = message else {
+                debug!("ignoring unexpected message {message:?} from {addr}");
+                continue;
+            };
+
+            // Sending to the peer opens host NAT for its packets.
+            send_message(&host.socket, &RendezvousMessage::Punch, peer_addr);
+            punch_events.send(PunchEvent::PeerPunched(peer_addr));
+        }
+
+        if host.last_register.is_some_and(|last_register| {
+            time.elapsed().saturating_sub(last_register) < host.config.interval
+        }) {
+            return;
+        }
+
+        // Also keeps NAT mappings to the rendezvous server alive.
+        let register = RendezvousMessage::Register {
+            session: host.config.session.clone(),
+            reply: host.public_addr.is_none(),
+        };
+        send_message(&host.socket, &register, host.config.rendezvous_addr);
+        let listen = RendezvousMessage::Listen {
+            session: host.config.session.clone(),
+        };
+        send_message(&host.control_socket, &listen, host.config.rendezvous_addr);
+        host.last_register = Some(time.elapsed());
+    }
+
+    fn client_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        network_channels: Res<NetworkChannels>,
+        settings: Res<NetcodeSettings>,
+        protocol_version: Res<ProtocolVersion>,
+        mut client: ResMut<PunchClient>,
+        mut punch_events: EventWriter<PunchEvent>,
+    ) {
+        let started_at = *client.started_at.get_or_insert(time.elapsed());
+        for (message, addr) in receive_messages(&client.socket) {
+            match message {
+                RendezvousMessage::HostAddr { addr: host_addr } => {
+                    client.host_addr = Some(host_addr);
+                }
+                RendezvousMessage::UnknownSession => {
+                    commands.remove_resource::<PunchClient>();
+                    punch_events.send(PunchEvent::Failed(PunchError::UnknownSession));
+                    return;
+                }
+                RendezvousMessage::Punch if Some(addr) == client.host_addr => {
+                    let socket = match client.socket.try_clone() {
+                        Ok(socket) => socket,
+                        Err(e) => {
+                            error!("unable to clone client socket: {e}");
+                            return;
+                        }
+                    };
+                    commands.remove_resource::<PunchClient>();
+                    match netcode_client_with_socket(
+                        &network_channels,
+                        &settings,
+                        socket,
+                        addr,
+                        client.client_id,
+                        client.protocol_id,
+                        protocol_version.current,
+                    ) {
+                        Ok((renet_client, transport)) => {
+                            commands.insert_resource(renet_client);
+                            commands.insert_resource(transport);
+                            punch_events.send(PunchEvent::Connecting(addr));
+                        }
+                        Err(e) => {
+                            error!("unable to create client for {addr}: {e}");
+                            punch_events.send(PunchEvent::Failed(PunchError::Transport));
+                        }
+                    }
+                    return;
+                }
+                _ => debug!("ignoring unexpected message {message:?} from {addr}"),
+            }
+        }
+
+        if time.elapsed().saturating_sub(started_at) > client.config.timeout {
+            commands.remove_resource::<PunchClient>();
+            punch_events.send(PunchEvent::Failed(PunchError::TimedOut));
+            return;
+        }
+
+        if client.last_join.is_some_and(|last_join| {
+            time.elapsed().saturating_sub(last_join) < client.config.interval
+        }) {
+            return;
+        }
+
+        // Joins are repeated until the host punch arrives,
+        // each join makes the rendezvous server to notify the host again.
+        let join = RendezvousMessage::Join {
+            session: client.config.session.clone(),
+        };
+        send_message(&client.socket, &join, client.config.rendezvous_addr);
+        if let Some(host_addr) = client.host_addr {
+            // Sending to the host opens client NAT for its packets.
+            send_message(&client.socket, &RendezvousMessage::Punch, host_addr);
+        }
+        client.last_join = Some(time.elapsed());
+    }
+}
+
+/// Maximum size of rendezvous messages.
+const MAX_MESSAGE_BYTES: usize = 1200;
+
+/// Sessions that weren't refreshed by their hosts during this time are removed.
+const SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings of [`PunchHost`] and [`PunchClient`].
+#[derive(Clone, Debug)]
+pub struct PunchConfig {
+    /// Address of the [`RendezvousServer`].
+    pub rendezvous_addr: SocketAddr,
+    /// Name of the session that host registers and clients join.
+    pub session: String,
+    /// How often messages are resent.
+    pub interval: Duration,
+    /// Time after which [`PunchError::TimedOut`] is emitted if host isn't registered or client isn't connecting.
+    pub timeout: Duration,
+}
+
+impl PunchConfig {
+    pub fn new(rendezvous_addr: SocketAddr, session: impl Into<String>) -> Self {
+        Self {
+            rendezvous_addr,
+            session: session.into(),
+            interval: Duration::from_millis(250),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Registers a session on the rendezvous server and creates unsecure netcode server.
+///
+/// Should be removed together with server resources to unregister the session.
+#[derive(Resource)]
+pub struct PunchHost {
+    config: PunchConfig,
+    max_clients: usize,
+    protocol_id: u64,
+    /// Socket that will be used by the server transport.
+    socket: UdpSocket,
+    /// Socket to receive client addresses from the rendezvous server
+    /// since server transport consumes all packets on the main socket.
+    control_socket: UdpSocket,
+    public_addr: Option<SocketAddr>,
+    started_at: Option<Duration>,
+    last_register: Option<Duration>,
+}
+
+impl PunchHost {
+    /// Binds server socket to `addr`.
+    pub fn bind(
+        addr: SocketAddr,
+        config: PunchConfig,
+        max_clients: usize,
+        protocol_id: u64,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            config,
+            max_clients,
+            protocol_id,
+            socket: bind_nonblocking(addr)?,
+            control_socket: bind_nonblocking(SocketAddr::new(addr.ip(), 0))?,
+            public_addr: None,
+            started_at: None,
+            last_register: None,
+        })
+    }
+
+    /// Returns address of the host as seen by the rendezvous server after registration.
+    pub fn public_addr(&self) -> Option<SocketAddr> {
+        self.public_addr
+    }
+}
+
+/// Finds host of a session on the rendezvous server and creates unsecure netcode client.
+///
+/// Removed automatically after the client creation or failure.
+#[derive(Resource)]
+pub struct PunchClient {
+    config: PunchConfig,
+    client_id: u64,
+    protocol_id: u64,
+    /// Socket that will be used by the client transport.
+    socket: UdpSocket,
+    host_addr: Option<SocketAddr>,
+    started_at: Option<Duration>,
+    last_join: Option<Duration>,
+}
+
+impl PunchClient {
+    /// Binds client socket to any available port.
+    pub fn bind(config: PunchConfig, client_id: u64, protocol_id: u64) -> io::Result<Self> {
+        Ok(Self {
+            config,
+            client_id,
+            protocol_id,
+            socket: bind_nonblocking((Ipv4Addr::UNSPECIFIED, 0).into())?,
+            host_addr: None,
+            started_at: None,
+            last_join: None,
+        })
+    }
+}
+
+/// Matches hosts and clients by session names and exchanges their public addresses.
+///
+/// Keeps no game state and doesn't relay game traffic.
+#[derive(Resource)]
+pub struct RendezvousServer {
+    socket: UdpSocket,
+    sessions: HashMap<String, HostSession>,
+}
+
+impl RendezvousServer {
+    pub fn bind(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: bind_nonblocking(addr)?,
+            sessions: Default::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Processes all received messages and removes expired sessions.
+    ///
+    /// `now` is used only to expire sessions and should be monotonic.
+    pub fn update(&mut self, now: Duration) {
+        for (message, addr) in receive_messages(&self.socket) {
+            match message {
+                RendezvousMessage::Register { session, reply } => {
+                    let host_session = self.sessions.entry(session).or_insert(HostSession {
+                        public_addr: addr,
+                        control_addr: None,
+                        last_seen: now,
+                    });
+                    host_session.public_addr = addr;
+                    host_session.last_seen = now;
+                    if reply {
+                        let registered = RendezvousMessage::Registered { public_addr: addr };
+                        send_message(&self.socket, &registered, addr);
+                    }
+                }
+                RendezvousMessage::Listen { session } => {
+                    if let Some(host_session) = self.sessions.get_mut(&session) {
+                        host_session.control_addr = Some(addr);
+                    }
+                }
+                RendezvousMessage::Join { session } => match self.sessions.get(&session) {
+                    Some(host_session) => {
+                        let host_addr = RendezvousMessage::HostAddr {
+                            addr: host_session.public_addr,
+                        };
+                        send_message(&self.socket, &host_addr, addr);
+                        if let Some(control_addr) = host_session.control_addr {
+                            let peer_addr = RendezvousMessage::PeerAddr { addr };
+                            send_message(&self.socket, &peer_addr, control_addr);
+                        }
+                    }
+                    None => send_message(&self.socket, &RendezvousMessage::UnknownSession, addr),
+                },
+                _ => debug!("ignoring unexpected message {message:?} from {addr}"),
+            }
+        }
+
+        self.sessions.retain(|_, host_session| {
+            now.saturating_sub(host_session.last_seen) <= SESSION_TIMEOUT
+        });
+    }
+}
+
+/// A session registered on [`RendezvousServer`].
+struct HostSession {
+    /// Address of the host server socket.
+    public_addr: SocketAddr,
+    /// Address of the host control socket.
+    control_addr: Option<SocketAddr>,
+    last_seen: Duration,
+}
+
+/// An event that emitted on host and clients during hole punching.
+#[derive(Clone, Copy, Debug, Event, PartialEq, Eq)]
+pub enum PunchEvent {
+    /// Host registered the session and created server resources with the public address.
+    Registered(SocketAddr),
+    /// Host sent a punch packet to a joining client.
+    PeerPunched(SocketAddr),
+    /// Client received a punch from the host and created client resources connecting to it.
+    Connecting(SocketAddr),
+    /// Host or client stopped punching and removed its resource.
+    Failed(PunchError),
+}
+
+/// The reason of [`PunchEvent::Failed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PunchError {
+    /// The rendezvous server doesn't know the session.
+    UnknownSession,
+    /// No progress within [`PunchConfig::timeout`].
+    TimedOut,
+    /// Unable to create Renet resources, the error is logged.
+    Transport,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+enum RendezvousMessage {
+    /// From host to rendezvous, should be answered with [`Self::Registered`] if `reply` is set.
+    Register { session: String, reply: bool },
+    /// From rendezvous to host.
+    Registered { public_addr: SocketAddr },
+    /// From host control socket to rendezvous to receive [`Self::PeerAddr`].
+    Listen { session: String },
+    /// From client to rendezvous.
+    Join { session: String },
+    /// From rendezvous to client.
+    HostAddr { addr: SocketAddr },
+    /// From rendezvous to host control socket.
+    PeerAddr { addr: SocketAddr },
+    /// From rendezvous to client.
+    UnknownSession,
+    /// Between host and client to open NATs.
+    Punch,
+}
+
+fn bind_nonblocking(addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+fn send_message(socket: &UdpSocket, message: &RendezvousMessage, addr: SocketAddr) {
+    let message = bincode::serialize(message).expect("rendezvous message should be serializable");
+    if let Err(e) = socket.send_to(&message, addr) {
+        error!("unable to send rendezvous message to {addr}: {e}");
+    }
+}
+
+fn receive_messages(socket: &UdpSocket) -> Vec<(RendezvousMessage, SocketAddr)> {
+    let mut messages = Vec::new();
+    let mut buffer = [0; MAX_MESSAGE_BYTES];
+    loop {
+        let (len, addr) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            // Returned on Windows when the destination of a previously sent packet is unreachable.
+            Err(e) if e.kind() == ErrorKind::ConnectionReset => continue,
+            Err(e) => {
+                error!("unable to receive rendezvous message: {e}");
+                break;
+            }
+        };
+
+        match bincode::deserialize(&buffer[..len]) {
+            Ok(message) => messages.push((message, addr)),
+            Err(e) => debug!("ignoring invalid rendezvous message from {addr}: {e}"),
+        }
+    }
+
+    messages
+}
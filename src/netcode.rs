@@ -0,0 +1,274 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::{
+    transport::{
+        ClientAuthentication, ConnectToken, NetcodeClientTransport, NetcodeError,
+        NetcodeServerTransport, ServerAuthentication, ServerConfig, NETCODE_KEY_BYTES,
+    },
+    RenetClient, RenetServer,
+};
+
+use crate::{protocol_version::ProtocolVersion, replication_core::NetworkChannels};
+
+/// An extension trait for [`World`] to create Renet server and client with netcode transport.
+pub trait NetcodeWorldExt {
+    /// Creates [`RenetServer`] and [`NetcodeServerTransport`] with [`new_netcode_server`] and inserts them as resources.
+    fn create_netcode_server(
+        &mut self,
+        addr: SocketAddr,
+        max_clients: usize,
+        protocol_id: u64,
+        private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+    ) -> io::Result<()>;
+
+    /// Creates server for a game where the host also plays.
+    ///
+    /// The host player is the server itself with [`SERVER_ID`](crate::server::SERVER_ID): it sees the server world directly,
+    /// its client events are emitted as [`FromClient`](crate::network_event::client_event::FromClient)
+    /// with the same ID and server events sent to it are emitted locally.
+    /// Existing [`RenetClient`] and [`NetcodeClientTransport`] are removed, since client and server
+    /// resources in the same app cause a replication loop.
+    /// Otherwise the same as [`Self::create_netcode_server`].
+    fn create_listen_server(
+        &mut self,
+        addr: SocketAddr,
+        max_clients: usize,
+        protocol_id: u64,
+        private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+    ) -> io::Result<()>;
+
+    /// Creates [`RenetClient`] and [`NetcodeClientTransport`] with [`new_netcode_client`] and inserts them as resources.
+    fn create_netcode_client(
+        &mut self,
+        server_addr: SocketAddr,
+        client_id: u64,
+        protocol_id: u64,
+    ) -> Result<(), NetcodeError>;
+}
+
+impl NetcodeWorldExt for World {
+    fn create_netcode_server(
+        &mut self,
+        addr: SocketAddr,
+        max_clients: usize,
+        protocol_id: u64,
+        private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+    ) -> io::Result<()> {
+        let network_channels = self.resource::<NetworkChannels>();
+        let (server, transport) = new_netcode_server(
+            network_channels,
+            addr,
+            max_clients,
+            protocol_id,
+            private_key,
+        )?;
+        self.insert_resource(server);
+        self.insert_resource(transport);
+
+        Ok(())
+    }
+
+    fn create_listen_server(
+        &mut self,
+        addr: SocketAddr,
+        max_clients: usize,
+        protocol_id: u64,
+        private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+    ) -> io::Result<()> {
+        if let Some(mut transport) = self.remove_resource::<NetcodeClientTransport>() {
+            warn!("removing client transport to host a listen server");
+            transport.disconnect();
+        }
+        self.remove_resource::<RenetClient>();
+
+        self.create_netcode_server(addr, max_clients, protocol_id, private_key)
+    }
+
+    fn create_netcode_client(
+        &mut self,
+        server_addr: SocketAddr,
+        client_id: u64,
+        protocol_id: u64,
+    ) -> Result<(), NetcodeError> {
+        let network_channels = self.resource::<NetworkChannels>();
+        let settings = self.resource::<NetcodeSettings>();
+        let protocol_version = self
+            .get_resource::<ProtocolVersion>()
+            .copied()
+            .unwrap_or_default();
+        let (client, transport) = new_netcode_client(
+            network_channels,
+            settings,
+            server_addr,
+            client_id,
+            protocol_id,
+            protocol_version.current,
+        )?;
+        self.insert_resource(client);
+        self.insert_resource(transport);
+
+        Ok(())
+    }
+}
+
+/// Creates server with channels from [`NetworkChannels`] and transport bound to `addr`.
+///
+/// Port 0 could be used to bind to any available port, the bound address will be used as the public address.
+/// Connections will be secure if `private_key` is specified.
+pub fn new_netcode_server(
+    network_channels: &NetworkChannels,
+    addr: SocketAddr,
+    max_clients: usize,
+    protocol_id: u64,
+    private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+) -> io::Result<(RenetServer, NetcodeServerTransport)> {
+    let socket = UdpSocket::bind(addr)?;
+    let public_addr = socket.local_addr()?;
+    netcode_server_with_socket(
+        network_channels,
+        socket,
+        public_addr,
+        max_clients,
+        protocol_id,
+        private_key,
+    )
+}
+
+/// Creates client with channels from [`NetworkChannels`] and unsecure transport connected to `server_addr`.
+///
+/// The connection uses timeout from `settings`.
+/// `protocol_version` is sent in the connect token user data, see [`ProtocolVersion`].
+pub fn new_netcode_client(
+    network_channels: &NetworkChannels,
+    settings: &NetcodeSettings,
+    server_addr: SocketAddr,
+    client_id: u64,
+    protocol_id: u64,
+    protocol_version: u32,
+) -> Result<(RenetClient, NetcodeClientTransport), NetcodeError> {
+    let socket = UdpSocket::bind((server_addr.ip(), 0))?;
+    netcode_client_with_socket(
+        network_channels,
+        settings,
+        socket,
+        server_addr,
+        client_id,
+        protocol_id,
+        protocol_version,
+    )
+}
+
+/// Like [`new_netcode_server`], but uses already bound `socket` and the specified public address.
+pub(super) fn netcode_server_with_socket(
+    network_channels: &NetworkChannels,
+    socket: UdpSocket,
+    public_addr: SocketAddr,
+    max_clients: usize,
+    protocol_id: u64,
+    private_key: Option<[u8; NETCODE_KEY_BYTES]>,
+) -> io::Result<(RenetServer, NetcodeServerTransport)> {
+    let server = RenetServer::new(network_channels.connection_config());
+
+    let server_config = ServerConfig {
+        max_clients,
+        protocol_id,
+        public_addr,
+        authentication: match private_key {
+            Some(private_key) => ServerAuthentication::Secure { private_key },
+            None => ServerAuthentication::Unsecure,
+        },
+    };
+    let transport = NetcodeServerTransport::new(current_time(), server_config, socket)?;
+
+    Ok((server, transport))
+}
+
+/// Like [`new_netcode_client`], but uses already bound `socket`.
+pub(super) fn netcode_client_with_socket(
+    network_channels: &NetworkChannels,
+    settings: &NetcodeSettings,
+    socket: UdpSocket,
+    server_addr: SocketAddr,
+    client_id: u64,
+    protocol_id: u64,
+    protocol_version: u32,
+) -> Result<(RenetClient, NetcodeClientTransport), NetcodeError> {
+    let client = RenetClient::new(network_channels.connection_config());
+
+    // Generate the token manually instead of using `ClientAuthentication::Unsecure` to specify the timeout.
+    // Unsecure server uses zeroed key.
+    let current_time = current_time();
+    let connect_token = ConnectToken::generate(
+        current_time,
+        protocol_id,
+        settings.token_expiration.as_secs(),
+        client_id,
+        settings.timeout_seconds(),
+        vec![server_addr],
+        Some(&ProtocolVersion::new(protocol_version).user_data()),
+        &[0; NETCODE_KEY_BYTES],
+    )?;
+    let authentication = ClientAuthentication::Secure { connect_token };
+    let transport = NetcodeClientTransport::new(current_time, authentication, socket)?;
+
+    Ok((client, transport))
+}
+
+/// Connection settings for clients created with [`NetcodeWorldExt::create_netcode_client`] or [`new_netcode_client`].
+///
+/// The timeout is stored in the connect token, so the server uses the client value for each connection.
+/// For secure connections server uses the timeout specified during token generation instead.
+/// Client always checks the timeout from this resource.
+///
+/// Netcode sends keep-alive packets every 250 ms when there is nothing else to send and
+/// a single disconnect packet on disconnection, these values are fixed by Renet and can't be configured.
+/// Since Renet processes packets only during app updates, the timeout should be larger than
+/// the longest expected frame, including loading hitches.
+/// Packets are sent every frame regardless of [`TickPolicy`](crate::server::TickPolicy):
+/// clients acknowledge ticks every frame and server keeps the connection alive with keep-alive packets
+/// even on frames without [`ServerSet::Send`](crate::server::ServerSet::Send).
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct NetcodeSettings {
+    /// Time without received packets after which connection is considered lost.
+    ///
+    /// Rounded up to seconds for server, [`None`] disables timeout.
+    /// By default it's 15 seconds.
+    pub timeout: Option<Duration>,
+    /// Time during which the client should connect.
+    ///
+    /// Rounded down to seconds, by default it's 5 minutes.
+    pub token_expiration: Duration,
+}
+
+impl NetcodeSettings {
+    /// Returns timeout in format of the connect token.
+    fn timeout_seconds(&self) -> i32 {
+        match self.timeout {
+            Some(timeout) => {
+                let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+                seconds.clamp(1, i32::MAX as u64) as i32
+            }
+            None => -1,
+        }
+    }
+}
+
+impl Default for NetcodeSettings {
+    fn default() -> Self {
+        Self {
+            timeout: Some(Duration::from_secs(15)),
+            token_expiration: Duration::from_secs(300),
+        }
+    }
+}
+
+fn current_time() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("system time should be after Unix epoch")
+}
@@ -1,31 +1,55 @@
 pub mod client_event;
+pub mod client_request;
+pub mod event_receipt;
+pub mod optimistic_event;
+pub mod scheduled_event;
 pub mod server_event;
+pub mod shared_event;
 
-use std::{
-    fmt::{self, Display, Formatter},
-    marker::PhantomData,
-    time::Duration,
-};
+use std::{any, fmt::Debug, marker::PhantomData, time::Duration};
 
-use bevy::{ecs::entity::EntityMap, prelude::*, reflect::TypeRegistryInternal};
+use bevy::{prelude::*, reflect::TypeRegistryInternal, utils::HashMap};
 use bevy_renet::renet::SendType;
 
-/// Holds a channel ID for `T`.
+use crate::{
+    server::TrafficDirection,
+    traffic_decoder::{DecodeFn, EventDecoders},
+};
+
+/// Holds a channel ID and mapping settings for `T`.
 #[derive(Resource)]
 pub struct EventChannel<T> {
     pub id: u8,
+    map_policy: MapFailurePolicy,
+    /// Whether events are stamped with ticks, see [`ClientEventAppExt::add_ticked_client_event`](client_event::ClientEventAppExt::add_ticked_client_event).
+    ticked: bool,
     marker: PhantomData<T>,
 }
 
 impl<T> EventChannel<T> {
-    fn new(id: u8) -> Self {
+    fn new(id: u8, map_policy: MapFailurePolicy) -> Self {
         Self {
             id,
+            map_policy,
+            ticked: false,
             marker: PhantomData,
         }
     }
 }
 
+/// Sets how captured messages of `T` are decoded by [`TrafficDecoder`](crate::traffic_decoder::TrafficDecoder).
+///
+/// The event should be already registered.
+fn set_event_decode<T: Event>(app: &mut App, direction: TrafficDirection, decode: DecodeFn) {
+    let channel_id = app.world.resource::<EventChannel<T>>().id;
+    app.world.resource_mut::<EventDecoders>().insert::<T>(
+        direction,
+        channel_id,
+        None,
+        Some(decode),
+    );
+}
+
 /// Creates a struct implements serialization for the event using [`TypeRegistryInternal`].
 pub trait BuildEventSerializer<T> {
     type EventSerializer<'a>
@@ -43,39 +67,181 @@ pub trait BuildEventDeserializer {
 }
 
 /// Event delivery guarantee.
-#[derive(Clone, Copy, Debug)]
-pub enum SendPolicy {
-    /// Unreliable and unordered
-    Unreliable,
-    /// Reliable and unordered
-    Unordered,
-    /// Reliable and ordered
-    Ordered,
+///
+/// Constructed with a builder, for example `SendPolicy::reliable().ordered().resend(Duration::from_millis(100))`.
+/// Converts into [`SendType`], which could be passed directly for configurations not covered here.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SendPolicy {
+    reliable: bool,
+    ordered: bool,
+    resend_time: Duration,
+}
+
+impl SendPolicy {
+    /// Unreliable and unordered.
+    pub const fn unreliable() -> Self {
+        Self {
+            reliable: false,
+            ordered: false,
+            resend_time: Duration::from_millis(300),
+        }
+    }
+
+    /// Reliable and unordered.
+    pub const fn reliable() -> Self {
+        Self {
+            reliable: true,
+            ..Self::unreliable()
+        }
+    }
+
+    /// Makes the policy ordered.
+    ///
+    /// Ordering is supported only for reliable policies, so it also makes the policy reliable.
+    pub const fn ordered(mut self) -> Self {
+        self.reliable = true;
+        self.ordered = true;
+        self
+    }
+
+    /// Sets the time after which unacknowledged messages are resent.
+    ///
+    /// Used only by reliable policies, by default it's 300 ms.
+    pub const fn resend(mut self, resend_time: Duration) -> Self {
+        self.resend_time = resend_time;
+        self
+    }
 }
 
 impl From<SendPolicy> for SendType {
     fn from(policy: SendPolicy) -> Self {
-        const RESEND_TIME: Duration = Duration::from_millis(300);
-        match policy {
-            SendPolicy::Unreliable => SendType::Unreliable,
-            SendPolicy::Unordered => SendType::ReliableUnordered {
-                resend_time: RESEND_TIME,
-            },
-            SendPolicy::Ordered => SendType::ReliableOrdered {
-                resend_time: RESEND_TIME,
-            },
+        let resend_time = policy.resend_time;
+        match (policy.reliable, policy.ordered) {
+            (false, _) => SendType::Unreliable,
+            (true, false) => SendType::ReliableUnordered { resend_time },
+            (true, true) => SendType::ReliableOrdered { resend_time },
         }
     }
 }
 
-pub trait MapEventEntities {
-    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapError>;
+/// Defines what happens when an entity inside a received mapped event can't be mapped.
+///
+/// Unmappable entities are routine during despawn races, so only server events panic by default.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MapFailurePolicy {
+    /// Drop the event and emit [`EventMappingFailed`].
+    ///
+    /// Default for client events.
+    Drop,
+    /// Panic.
+    ///
+    /// Default for server events.
+    Panic,
+    /// Deliver the event with unmapped entities replaced with [`Entity::PLACEHOLDER`]
+    /// and emit [`EventMappingFailed`].
+    Placeholder,
+}
+
+/// An event that emitted when a received mapped event contains an entity without mapping.
+///
+/// See [`MapFailurePolicy`] for details.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct EventMappingFailed {
+    /// Type name of the received event.
+    pub event_type: &'static str,
+    /// ID of the sender or [`SERVER_ID`](crate::server::SERVER_ID) for server events.
+    pub client_id: u64,
+    /// The first entity that has no mapping.
+    pub entity: Entity,
+    /// Whether the event was delivered with [`Entity::PLACEHOLDER`] instead of unmapped entities.
+    pub delivered: bool,
+}
+
+/// Handles the result of event mapping according to the policy for `T`.
+///
+/// Returns `true` if the event should be delivered.
+fn handle_map_result<T: Event + Debug>(
+    world: &mut World,
+    result: Result<(), Entity>,
+    event: &T,
+    client_id: u64,
+) -> bool {
+    let Err(entity) = result else {
+        return true;
+    };
+
+    let event_type = any::type_name::<T>();
+    let delivered = match world.resource::<EventChannel<T>>().map_policy {
+        MapFailurePolicy::Panic => {
+            panic!("event {event:?} from {client_id} should be mappable, but {entity:?} has no mapping")
+        }
+        MapFailurePolicy::Drop => {
+            warn!("dropping event {event:?} from {client_id} because {entity:?} has no mapping");
+            false
+        }
+        MapFailurePolicy::Placeholder => {
+            warn!("replacing {entity:?} without mapping with a placeholder in event {event:?} from {client_id}");
+            true
+        }
+    };
+    world.send_event(EventMappingFailed {
+        event_type,
+        client_id,
+        entity,
+        delivered,
+    });
+
+    delivered
 }
 
-pub struct MapError(pub Entity);
+/// Traffic of network events by their types.
+///
+/// Updated on server and client when registered events are sent or received, both directions are
+/// tracked in the same resource. Events sent to multiple clients are counted for each recipient.
+/// Events resent locally when server is also a player are not counted.
+#[derive(Default, Resource)]
+pub struct NetworkEventStats {
+    events: HashMap<&'static str, EventStats>,
+}
 
-impl Display for MapError {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "unable to map entity {:?}", self.0)
+impl NetworkEventStats {
+    /// Returns statistics of event `T`.
+    ///
+    /// For wrapped events, like [`ToEntity<T>`](client_event::ToEntity), use the wrapper type.
+    pub fn get<T: Event>(&self) -> Option<&EventStats> {
+        self.events.get(any::type_name::<T>())
     }
+
+    /// Returns statistics of all events with their type names.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &EventStats)> {
+        self.events
+            .iter()
+            .map(|(&type_name, stats)| (type_name, stats))
+    }
+
+    /// Resets all statistics.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    fn record_sent<T>(&mut self, bytes: usize, recipients: usize) {
+        let stats = self.events.entry(any::type_name::<T>()).or_default();
+        stats.sent_messages += recipients as u64;
+        stats.sent_bytes += (bytes * recipients) as u64;
+    }
+
+    fn record_received<T>(&mut self, bytes: usize) {
+        let stats = self.events.entry(any::type_name::<T>()).or_default();
+        stats.received_messages += 1;
+        stats.received_bytes += bytes as u64;
+    }
+}
+
+/// Sent and received messages of a single event type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventStats {
+    pub sent_messages: u64,
+    pub sent_bytes: u64,
+    pub received_messages: u64,
+    pub received_bytes: u64,
 }
@@ -1,21 +1,34 @@
-use std::fmt::Debug;
+use std::{any, fmt::Debug};
 
-use bevy::{ecs::event::Event, prelude::*};
+use bevy::{
+    ecs::{
+        entity::{EntityMapper, MapEntities},
+        event::Event,
+    },
+    prelude::*,
+};
 use bevy_renet::{
     renet::{RenetClient, RenetServer, SendType},
     transport::client_connected,
 };
-use bincode::{DefaultOptions, Options};
 use serde::{
     de::{DeserializeOwned, DeserializeSeed},
-    Serialize,
+    Deserialize, Serialize,
 };
 
-use super::{BuildEventDeserializer, BuildEventSerializer, EventChannel, MapEventEntities};
+use super::{
+    handle_map_result, set_event_decode, BuildEventDeserializer, BuildEventSerializer,
+    EventChannel, MapFailurePolicy, NetworkEventStats,
+};
 use crate::{
-    client::{ClientSet, NetworkEntityMap},
+    client::{ClientSet, ServerTickEstimate},
     replication_core::NetworkChannels,
-    server::{has_authority, ServerSet, SERVER_ID},
+    server::{
+        has_authority, traffic_log::event_summary, ClientEntityMaps, ServerPlugin, ServerSet,
+        ServerTick, TrafficDirection, TrafficLog, SERVER_ID,
+    },
+    traffic_decoder::{decode_event, decode_reflect_event, decode_ticked_event, EventDecoders},
+    wire_format::{deserialize_message, deserialize_message_seed, serialize_message},
 };
 
 /// An extension trait for [`App`] for creating client events.
@@ -26,8 +39,31 @@ pub trait ClientEventAppExt {
         policy: impl Into<SendType>,
     ) -> &mut Self;
 
-    /// Same as [`Self::add_client_event`], but additionally maps client entities to server before sending.
-    fn add_mapped_client_event<T: Event + Serialize + DeserializeOwned + Debug + MapEventEntities>(
+    /// Same as [`Self::add_client_event`], but each event is stamped with [`ServerTickEstimate::tick`]
+    /// on sending and received with it in [`FromClient::tick`].
+    ///
+    /// Useful for lag compensation to know which historical tick the client saw when the event was sent.
+    /// If the client renders interpolated entities, subtract
+    /// [`InterpolationDelay`](crate::interpolation::InterpolationDelay) from the tick.
+    fn add_ticked_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self;
+
+    /// Same as [`Self::add_client_event`], but additionally maps client entities to server after receiving.
+    ///
+    /// Mapping happens on server using [`ClientEntityMaps`] that is filled from the client's [`NetworkEntityMap`](crate::client::NetworkEntityMap).
+    fn add_mapped_client_event<T: Event + Serialize + DeserializeOwned + Debug + MapEntities>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self;
+
+    /// Registers [`FromClient<ToEntity<T>>`] event that will be emitted on server after sending [`ToEntity<T>`] event on client.
+    ///
+    /// Only [`ToEntity::entity`] is mapped from client to server, so `T` doesn't need to implement [`MapEntities`].
+    /// If the entity has no mapping or no longer exists on server, the event is handled according to
+    /// [`MapFailurePolicy`] just like other mapped events.
+    fn add_client_entity_event<T: Event + Serialize + DeserializeOwned + Debug>(
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self;
@@ -44,18 +80,23 @@ pub trait ClientEventAppExt {
         for<'a> S::EventSerializer<'a>: Serialize,
         for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>;
 
-    /// Same as [`Self::add_client_reflect_event`], but additionally maps client entities to server before sending.
+    /// Same as [`Self::add_client_reflect_event`], but additionally maps client entities to server after receiving.
     fn add_mapped_client_reflect_event<T, S, D>(
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self
     where
-        T: Event + Debug + MapEventEntities,
+        T: Event + Debug + MapEntities,
         S: BuildEventSerializer<T> + 'static,
         D: BuildEventDeserializer + 'static,
         for<'a> S::EventSerializer<'a>: Serialize,
         for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>;
 
+    /// Sets what happens when an entity inside a received mapped event `T` can't be mapped.
+    ///
+    /// The event should be already registered. See [`MapFailurePolicy`] for default.
+    fn set_client_event_map_policy<T: Event>(&mut self, policy: MapFailurePolicy) -> &mut Self;
+
     /// Same as [`Self::add_client_event`], but uses specified sending and receiving systems.
     fn add_client_event_with<T: Event + Debug, Marker1, Marker2>(
         &mut self,
@@ -70,20 +111,54 @@ impl ClientEventAppExt for App {
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self {
-        self.add_client_event_with::<T, _, _>(policy, sending_system::<T>, receiving_system::<T>)
+        self.add_client_event_with::<T, _, _>(policy, sending_system::<T>, receiving_system::<T>);
+        set_event_decode::<T>(self, TrafficDirection::Received, decode_event::<T>);
+        self
     }
 
-    fn add_mapped_client_event<
-        T: Event + Serialize + DeserializeOwned + Debug + MapEventEntities,
-    >(
+    fn add_ticked_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self {
         self.add_client_event_with::<T, _, _>(
             policy,
-            mapping_and_sending_system::<T>,
-            receiving_system::<T>,
-        )
+            ticked_sending_system::<T>,
+            ticked_receiving_system::<T>,
+        );
+        self.world.resource_mut::<EventChannel<T>>().ticked = true;
+        set_event_decode::<T>(self, TrafficDirection::Received, decode_ticked_event::<T>);
+        self
+    }
+
+    fn add_mapped_client_event<T: Event + Serialize + DeserializeOwned + Debug + MapEntities>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self {
+        self.add_client_event_with::<T, _, _>(
+            policy,
+            sending_system::<T>,
+            receiving_and_mapping_system::<T>.after(ServerPlugin::entity_mappings_receiving_system),
+        );
+        set_event_decode::<T>(self, TrafficDirection::Received, decode_event::<T>);
+        self
+    }
+
+    fn add_client_entity_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self {
+        self.add_client_event_with::<ToEntity<T>, _, _>(
+            policy,
+            sending_system::<ToEntity<T>>,
+            receiving_entity_event_system::<T>
+                .after(ServerPlugin::entity_mappings_receiving_system),
+        );
+        set_event_decode::<ToEntity<T>>(
+            self,
+            TrafficDirection::Received,
+            decode_event::<ToEntity<T>>,
+        );
+        self
     }
 
     fn add_client_reflect_event<T, S, D>(&mut self, policy: impl Into<SendType>) -> &mut Self
@@ -98,12 +173,18 @@ impl ClientEventAppExt for App {
             policy,
             sending_reflect_system::<T, S>,
             receiving_reflect_system::<T, D>,
-        )
+        );
+        set_event_decode::<T>(
+            self,
+            TrafficDirection::Received,
+            decode_reflect_event::<T, D>,
+        );
+        self
     }
 
     fn add_mapped_client_reflect_event<T, S, D>(&mut self, policy: impl Into<SendType>) -> &mut Self
     where
-        T: Event + Debug + MapEventEntities,
+        T: Event + Debug + MapEntities,
         S: BuildEventSerializer<T> + 'static,
         D: BuildEventDeserializer + 'static,
         for<'a> S::EventSerializer<'a>: Serialize,
@@ -111,9 +192,29 @@ impl ClientEventAppExt for App {
     {
         self.add_client_event_with::<T, _, _>(
             policy,
-            mapping_and_sending_reflect_system::<T, S>,
-            receiving_reflect_system::<T, D>,
-        )
+            sending_reflect_system::<T, S>,
+            receiving_and_mapping_reflect_system::<T, D>
+                .after(ServerPlugin::entity_mappings_receiving_system),
+        );
+        set_event_decode::<T>(
+            self,
+            TrafficDirection::Received,
+            decode_reflect_event::<T, D>,
+        );
+        self
+    }
+
+    fn set_client_event_map_policy<T: Event>(&mut self, policy: MapFailurePolicy) -> &mut Self {
+        self.world
+            .get_resource_mut::<EventChannel<T>>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} should be registered as a client event",
+                    any::type_name::<T>()
+                )
+            })
+            .map_policy = policy;
+        self
     }
 
     fn add_client_event_with<T: Event + Debug, Marker1, Marker2>(
@@ -125,42 +226,72 @@ impl ClientEventAppExt for App {
         let channel_id = self
             .world
             .resource_mut::<NetworkChannels>()
-            .create_client_channel(policy.into());
-
-        self.add_event::<T>()
-            .init_resource::<Events<FromClient<T>>>()
-            .insert_resource(EventChannel::<T>::new(channel_id))
-            .add_systems(
-                PreUpdate,
-                receiving_system
-                    .in_set(ServerSet::Receive)
-                    .run_if(resource_exists::<RenetServer>()),
-            )
-            .add_systems(
-                PostUpdate,
-                (
-                    sending_system.run_if(client_connected()),
-                    local_resending_system::<T>.run_if(has_authority()),
-                )
-                    .chain()
-                    .in_set(ClientSet::Send),
-            );
+            .create_client_channel::<T>(policy.into());
+
+        register_client_event::<T, _, _>(self, channel_id, sending_system, receiving_system);
+        self.world.resource_mut::<EventDecoders>().insert::<T>(
+            TrafficDirection::Received,
+            channel_id,
+            None,
+            None,
+        );
 
         self
     }
 }
 
+/// Registers client event `T` that will be sent over the channel with the specified ID.
+pub(super) fn register_client_event<T: Event + Debug, Marker1, Marker2>(
+    app: &mut App,
+    channel_id: u8,
+    sending_system: impl IntoSystemConfigs<Marker1>,
+    receiving_system: impl IntoSystemConfigs<Marker2>,
+) {
+    app.add_event::<T>()
+        .init_resource::<Events<FromClient<T>>>()
+        .init_resource::<NetworkEventStats>()
+        .init_resource::<TrafficLog>()
+        .init_resource::<EventDecoders>()
+        .insert_resource(EventChannel::<T>::new(channel_id, MapFailurePolicy::Drop))
+        .add_systems(
+            PreUpdate,
+            receiving_system
+                .in_set(ServerSet::Receive)
+                .run_if(resource_exists::<RenetServer>()),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                sending_system.run_if(client_connected()),
+                local_resending_system::<T>.run_if(has_authority()),
+            )
+                .chain()
+                .in_set(ClientSet::Send),
+        );
+}
+
 fn receiving_system<T: Event + DeserializeOwned + Debug>(
     mut client_events: EventWriter<FromClient<T>>,
     mut server: ResMut<RenetServer>,
     channel: Res<EventChannel<T>>,
+    server_tick: Res<ServerTick>,
+    mut stats: ResMut<NetworkEventStats>,
+    traffic_log: Res<TrafficLog>,
 ) {
     for client_id in server.clients_id() {
         while let Some(message) = server.receive_message(client_id, channel.id) {
-            match bincode::deserialize(&message) {
+            stats.record_received::<T>(message.len());
+            match deserialize_message(&message) {
                 Ok(event) => {
                     debug!("received event {event:?} from client {client_id}");
-                    client_events.send(FromClient { client_id, event });
+                    traffic_log.received(client_id, channel.id, &message, || event_summary(&event));
+                    client_events.send(FromClient {
+                        client_id,
+                        event,
+                        tick: None,
+                        received_tick: server_tick.get(),
+                        channel_id: channel.id,
+                    });
                 }
                 Err(e) => error!("unable to deserialize event from client {client_id}: {e}"),
             }
@@ -173,6 +304,9 @@ fn receiving_reflect_system<T, D>(
     mut server: ResMut<RenetServer>,
     channel: Res<EventChannel<T>>,
     registry: Res<AppTypeRegistry>,
+    server_tick: Res<ServerTick>,
+    mut stats: ResMut<NetworkEventStats>,
+    traffic_log: Res<TrafficLog>,
 ) where
     T: Event + Debug,
     D: BuildEventDeserializer,
@@ -181,16 +315,18 @@ fn receiving_reflect_system<T, D>(
     let registry = registry.read();
     for client_id in server.clients_id() {
         while let Some(message) = server.receive_message(client_id, channel.id) {
-            // Set options to match `bincode::serialize`.
-            // https://docs.rs/bincode/latest/bincode/config/index.html#options-struct-vs-bincode-functions
-            let options = DefaultOptions::new()
-                .with_fixint_encoding()
-                .allow_trailing_bytes();
-            let mut deserializer = bincode::Deserializer::from_slice(&message, options);
-            match D::new(&registry).deserialize(&mut deserializer) {
+            stats.record_received::<T>(message.len());
+            match deserialize_message_seed(&message, D::new(&registry)) {
                 Ok(event) => {
                     debug!("received reflect event {event:?} from client {client_id}");
-                    client_events.send(FromClient { client_id, event });
+                    traffic_log.received(client_id, channel.id, &message, || event_summary(&event));
+                    client_events.send(FromClient {
+                        client_id,
+                        event,
+                        tick: None,
+                        received_tick: server_tick.get(),
+                        channel_id: channel.id,
+                    });
                 }
                 Err(e) => {
                     error!("unable to deserialize reflect event from client {client_id}: {e}")
@@ -200,40 +336,172 @@ fn receiving_reflect_system<T, D>(
     }
 }
 
+fn receiving_and_mapping_system<T: Event + MapEntities + DeserializeOwned + Debug>(
+    world: &mut World,
+) {
+    let channel_id = world.resource::<EventChannel<T>>().id;
+    world.resource_scope(|world, mut server: Mut<RenetServer>| {
+        world.resource_scope(|world, mut client_entity_maps: Mut<ClientEntityMaps>| {
+            for client_id in server.clients_id() {
+                while let Some(message) = server.receive_message(client_id, channel_id) {
+                    world
+                        .resource_mut::<NetworkEventStats>()
+                        .record_received::<T>(message.len());
+                    match deserialize_message::<T>(&message) {
+                        Ok(mut event) => {
+                            debug!("received mapped event {event:?} from client {client_id}");
+                            world.resource::<TrafficLog>().received(
+                                client_id,
+                                channel_id,
+                                &message,
+                                || event_summary(&event),
+                            );
+                            let entity_map = client_entity_maps.0.entry(client_id).or_default();
+                            let result = entity_map.map_to_server(world, &mut event);
+                            if handle_map_result(world, result, &event, client_id) {
+                                let received_tick = world.resource::<ServerTick>().get();
+                                world.send_event(FromClient {
+                                    client_id,
+                                    event,
+                                    tick: None,
+                                    received_tick,
+                                    channel_id,
+                                });
+                            }
+                        }
+                        Err(e) => {
+                            error!(
+                                "unable to deserialize mapped event from client {client_id}: {e}"
+                            )
+                        }
+                    }
+                }
+            }
+        });
+    });
+}
+
+/// Like [`receiving_and_mapping_system`], but additionally checks that the mapped entity still exists.
+fn receiving_entity_event_system<T: Event + DeserializeOwned + Debug>(world: &mut World) {
+    let channel_id = world.resource::<EventChannel<ToEntity<T>>>().id;
+    world.resource_scope(|world, mut server: Mut<RenetServer>| {
+        world.resource_scope(|world, mut client_entity_maps: Mut<ClientEntityMaps>| {
+            for client_id in server.clients_id() {
+                while let Some(message) = server.receive_message(client_id, channel_id) {
+                    world
+                        .resource_mut::<NetworkEventStats>()
+                        .record_received::<ToEntity<T>>(message.len());
+                    match deserialize_message::<ToEntity<T>>(&message) {
+                        Ok(mut event) => {
+                            debug!("received entity event {event:?} from client {client_id}");
+                            world.resource::<TrafficLog>().received(
+                                client_id,
+                                channel_id,
+                                &message,
+                                || event_summary(&event),
+                            );
+                            let client_entity = event.entity;
+                            let entity_map = client_entity_maps.0.entry(client_id).or_default();
+                            let result = entity_map.map_to_server(world, &mut event).and_then(
+                                |_| match world.get_entity(event.entity) {
+                                    Some(_) => Ok(()),
+                                    None => {
+                                        event.entity = Entity::PLACEHOLDER;
+                                        Err(client_entity)
+                                    }
+                                },
+                            );
+                            if handle_map_result(world, result, &event, client_id) {
+                                let received_tick = world.resource::<ServerTick>().get();
+                                world.send_event(FromClient {
+                                    client_id,
+                                    event,
+                                    tick: None,
+                                    received_tick,
+                                    channel_id,
+                                });
+                            }
+                        }
+                        Err(e) => {
+                            error!(
+                                "unable to deserialize entity event from client {client_id}: {e}"
+                            )
+                        }
+                    }
+                }
+            }
+        });
+    });
+}
+
+fn receiving_and_mapping_reflect_system<T, D>(world: &mut World)
+where
+    T: Event + MapEntities + Debug,
+    D: BuildEventDeserializer,
+    for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>,
+{
+    let channel_id = world.resource::<EventChannel<T>>().id;
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    world.resource_scope(|world, mut server: Mut<RenetServer>| {
+        world.resource_scope(|world, mut client_entity_maps: Mut<ClientEntityMaps>| {
+            for client_id in server.clients_id() {
+                while let Some(message) = server.receive_message(client_id, channel_id) {
+                    world
+                        .resource_mut::<NetworkEventStats>()
+                        .record_received::<T>(message.len());
+                    match deserialize_message_seed(&message, D::new(&registry)) {
+                        Ok(mut event) => {
+                            debug!("received mapped reflect event {event:?} from client {client_id}");
+                            world.resource::<TrafficLog>().received(
+                                client_id,
+                                channel_id,
+                                &message,
+                                || event_summary(&event),
+                            );
+                            let entity_map = client_entity_maps.0.entry(client_id).or_default();
+                            let result = entity_map.map_to_server(world, &mut event);
+                            if handle_map_result(world, result, &event, client_id) {
+                                let received_tick = world.resource::<ServerTick>().get();
+                                world.send_event(FromClient {
+                                    client_id,
+                                    event,
+                                    tick: None,
+                                    received_tick,
+                                    channel_id,
+                                });
+                            }
+                        }
+                        Err(e) => error!(
+                            "unable to deserialize mapped reflect event from client {client_id}: {e}"
+                        ),
+                    }
+                }
+            }
+        });
+    });
+}
+
 fn sending_system<T: Event + Serialize + Debug>(
     mut events: EventReader<T>,
     mut client: ResMut<RenetClient>,
     channel: Res<EventChannel<T>>,
+    mut stats: ResMut<NetworkEventStats>,
 ) {
     for event in &mut events {
-        let message = bincode::serialize(&event).expect("client event should be serializable");
+        let message = serialize_message(&event).expect("client event should be serializable");
+        stats.record_sent::<T>(message.len(), 1);
         client.send_message(channel.id, message);
         debug!("sent client event {event:?}");
     }
 }
 
-fn mapping_and_sending_system<T: Event + MapEventEntities + Serialize + Debug>(
-    mut events: ResMut<Events<T>>,
-    mut client: ResMut<RenetClient>,
-    entity_map: Res<NetworkEntityMap>,
-    channel: Res<EventChannel<T>>,
-) {
-    for mut event in events.drain() {
-        event
-            .map_entities(entity_map.to_server())
-            .unwrap_or_else(|e| panic!("client event {event:?} should be mappable: {e}"));
-        let message =
-            bincode::serialize(&event).expect("mapped client event should be serializable");
-        client.send_message(channel.id, message);
-        debug!("sent mapped client event {event:?}");
-    }
-}
-
 fn sending_reflect_system<T, S>(
     mut events: EventReader<T>,
     mut client: ResMut<RenetClient>,
     channel: Res<EventChannel<T>>,
     registry: Res<AppTypeRegistry>,
+    mut stats: ResMut<NetworkEventStats>,
 ) where
     T: Event + Debug,
     S: BuildEventSerializer<T>,
@@ -243,33 +511,57 @@ fn sending_reflect_system<T, S>(
     for event in &mut events {
         let serializer = S::new(event, &registry);
         let message =
-            bincode::serialize(&serializer).expect("client reflect event should be serializable");
+            serialize_message(&serializer).expect("client reflect event should be serializable");
+        stats.record_sent::<T>(message.len(), 1);
         client.send_message(channel.id, message);
         debug!("sent client reflect event {event:?}");
     }
 }
 
-fn mapping_and_sending_reflect_system<T, S>(
-    mut events: ResMut<Events<T>>,
+/// Like [`sending_system`], but stamps each event with the estimated server tick.
+fn ticked_sending_system<T: Event + Serialize + Debug>(
+    mut events: EventReader<T>,
     mut client: ResMut<RenetClient>,
-    entity_map: Res<NetworkEntityMap>,
     channel: Res<EventChannel<T>>,
-    registry: Res<AppTypeRegistry>,
-) where
-    T: Event + MapEventEntities + Debug,
-    S: BuildEventSerializer<T>,
-    for<'a> S::EventSerializer<'a>: Serialize,
-{
-    let registry = registry.read();
-    for mut event in events.drain() {
-        event
-            .map_entities(entity_map.to_server())
-            .unwrap_or_else(|e| panic!("client reflect event {event:?} should be mappable: {e}"));
-        let serializer = S::new(&event, &registry);
-        let message = bincode::serialize(&serializer)
-            .expect("mapped client reflect event should be serializable");
+    estimate: Res<ServerTickEstimate>,
+    mut stats: ResMut<NetworkEventStats>,
+) {
+    let tick = estimate.tick();
+    for event in &mut events {
+        let message =
+            serialize_message(&(tick, event)).expect("client event should be serializable");
+        stats.record_sent::<T>(message.len(), 1);
         client.send_message(channel.id, message);
-        debug!("sent mapped client reflect event {event:?}");
+        debug!("sent client event {event:?} on tick {tick}");
+    }
+}
+
+fn ticked_receiving_system<T: Event + DeserializeOwned + Debug>(
+    mut client_events: EventWriter<FromClient<T>>,
+    mut server: ResMut<RenetServer>,
+    channel: Res<EventChannel<T>>,
+    server_tick: Res<ServerTick>,
+    mut stats: ResMut<NetworkEventStats>,
+    traffic_log: Res<TrafficLog>,
+) {
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, channel.id) {
+            stats.record_received::<T>(message.len());
+            match deserialize_message::<(u32, T)>(&message) {
+                Ok((tick, event)) => {
+                    debug!("received event {event:?} from client {client_id} on tick {tick}");
+                    traffic_log.received(client_id, channel.id, &message, || event_summary(&event));
+                    client_events.send(FromClient {
+                        client_id,
+                        event,
+                        tick: Some(tick),
+                        received_tick: server_tick.get(),
+                        channel_id: channel.id,
+                    });
+                }
+                Err(e) => error!("unable to deserialize event from client {client_id}: {e}"),
+            }
+        }
     }
 }
 
@@ -278,12 +570,17 @@ fn mapping_and_sending_reflect_system<T, S>(
 fn local_resending_system<T: Event + Debug>(
     mut events: ResMut<Events<T>>,
     mut client_events: EventWriter<FromClient<T>>,
+    channel: Res<EventChannel<T>>,
+    server_tick: Res<ServerTick>,
 ) {
     for event in events.drain() {
         debug!("converted client event {event:?} into a local");
         client_events.send(FromClient {
             client_id: SERVER_ID,
             event,
+            tick: channel.ticked.then(|| server_tick.get()),
+            received_tick: server_tick.get(),
+            channel_id: channel.id,
         })
     }
 }
@@ -294,4 +591,33 @@ fn local_resending_system<T: Event + Debug>(
 pub struct FromClient<T> {
     pub client_id: u64,
     pub event: T,
+    /// Estimated server tick on client at the moment of sending.
+    ///
+    /// Available only for events registered with [`ClientEventAppExt::add_ticked_client_event`].
+    /// For events sent by the server itself it's the current [`ServerTick`].
+    pub tick: Option<u32>,
+    /// [`ServerTick`] at the moment of receiving.
+    ///
+    /// Useful to reason about when events landed relative to the simulation, for example in replays.
+    pub received_tick: u32,
+    /// ID of the client channel on which the event arrived.
+    ///
+    /// Events sent by the server itself have the ID of the channel they would be sent on.
+    pub channel_id: u8,
+}
+
+/// A client event addressed to an entity.
+///
+/// Registered by [`ClientEventAppExt::add_client_entity_event`].
+/// Send it on client with the client entity, the server will receive it with the corresponding server entity.
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct ToEntity<T> {
+    pub entity: Entity,
+    pub event: T,
+}
+
+impl<T> MapEntities for ToEntity<T> {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.entity = entity_mapper.get_or_reserve(self.entity);
+    }
 }
@@ -0,0 +1,189 @@
+use std::{collections::VecDeque, fmt::Debug, marker::PhantomData, time::Duration};
+
+use bevy::{ecs::event::Event, prelude::*};
+use bevy_renet::{
+    renet::{RenetClient, SendType},
+    transport::client_connected,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    client_event::ClientEventAppExt,
+    server_event::{SendMode, ServerEventAppExt, ToClients},
+};
+use crate::{client::ClientSet, server::has_authority};
+
+/// An extension trait for [`App`] for creating client requests with server responses.
+pub trait ClientRequestAppExt {
+    /// Registers request `Req` from client with response `Resp` from server.
+    ///
+    /// Send `Req` as a regular event on client, the server will receive it as
+    /// [`FromClient<Request<Req>>`](super::client_event::FromClient) and should reply
+    /// with [`Request::respond`]. The response will be delivered only to the requesting client
+    /// as [`RequestResult<Req, Resp>`] together with the original request.
+    /// If the response doesn't arrive within `timeout`, [`RequestResult`] with
+    /// [`RequestTimeout`] will be emitted instead and the late response will be ignored.
+    fn add_client_request<Req, Resp>(
+        &mut self,
+        policy: impl Into<SendType>,
+        timeout: Duration,
+    ) -> &mut Self
+    where
+        Req: Event + Serialize + DeserializeOwned + Debug + Clone,
+        Resp: Send + Sync + Serialize + DeserializeOwned + Debug + 'static;
+}
+
+impl ClientRequestAppExt for App {
+    fn add_client_request<Req, Resp>(
+        &mut self,
+        policy: impl Into<SendType>,
+        timeout: Duration,
+    ) -> &mut Self
+    where
+        Req: Event + Serialize + DeserializeOwned + Debug + Clone,
+        Resp: Send + Sync + Serialize + DeserializeOwned + Debug + 'static,
+    {
+        let policy = policy.into();
+        self.add_event::<Req>()
+            .add_event::<RequestResult<Req, Resp>>()
+            .add_client_event::<Request<Req>>(policy.clone())
+            .add_server_event::<Response<Req, Resp>>(policy)
+            .insert_resource(PendingRequests::<Req> {
+                timeout,
+                next_id: 0,
+                requests: Default::default(),
+            })
+            .add_systems(
+                PreUpdate,
+                response_system::<Req, Resp>.after(ClientSet::Receive),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    sending_system::<Req>
+                        .before(ClientSet::Send)
+                        .run_if(has_authority().or_else(client_connected())),
+                    reset_system::<Req>.run_if(resource_removed::<RenetClient>()),
+                ),
+            );
+
+        self
+    }
+}
+
+/// Assigns IDs to sent requests and forwards them to server as [`Request<Req>`].
+fn sending_system<Req: Event + Clone>(
+    time: Res<Time>,
+    mut requests: ResMut<Events<Req>>,
+    mut network_requests: EventWriter<Request<Req>>,
+    mut pending: ResMut<PendingRequests<Req>>,
+) {
+    for request in requests.drain() {
+        let id = pending.next_id;
+        pending.next_id = pending.next_id.wrapping_add(1);
+        pending
+            .requests
+            .push_back((id, time.elapsed(), request.clone()));
+        network_requests.send(Request { id, request });
+    }
+}
+
+/// Matches responses with pending requests and emits timeouts.
+fn response_system<Req: Event, Resp: Send + Sync + 'static>(
+    time: Res<Time>,
+    mut responses: ResMut<Events<Response<Req, Resp>>>,
+    mut results: EventWriter<RequestResult<Req, Resp>>,
+    mut pending: ResMut<PendingRequests<Req>>,
+) {
+    for response in responses.drain() {
+        if let Some(index) = pending
+            .requests
+            .iter()
+            .position(|&(id, ..)| id == response.id)
+        {
+            let (_, _, request) = pending
+                .requests
+                .remove(index)
+                .expect("index should be obtained from the same queue");
+            results.send(RequestResult {
+                request,
+                result: Ok(response.response),
+            });
+        }
+    }
+
+    let timeout = pending.timeout;
+    while pending
+        .requests
+        .front()
+        .is_some_and(|&(_, sent_at, _)| time.elapsed().saturating_sub(sent_at) > timeout)
+    {
+        let (_, _, request) = pending
+            .requests
+            .pop_front()
+            .expect("front request should exist");
+        results.send(RequestResult {
+            request,
+            result: Err(RequestTimeout),
+        });
+    }
+}
+
+fn reset_system<Req: Send + Sync + 'static>(mut pending: ResMut<PendingRequests<Req>>) {
+    pending.requests.clear();
+}
+
+/// Requests that were sent and wait for response.
+#[derive(Resource)]
+struct PendingRequests<Req> {
+    timeout: Duration,
+    next_id: u32,
+    /// Sent requests with their IDs and times when they were sent.
+    requests: VecDeque<(u32, Duration, Req)>,
+}
+
+/// A client request with its ID.
+///
+/// Sent from client as a client event by [`ClientRequestAppExt::add_client_request`].
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct Request<Req> {
+    pub id: u32,
+    pub request: Req,
+}
+
+impl<Req> Request<Req> {
+    /// Creates a response to this request that will be sent only to the requesting client.
+    pub fn respond<Resp>(&self, client_id: u64, response: Resp) -> ToClients<Response<Req, Resp>> {
+        ToClients {
+            mode: SendMode::Direct(client_id),
+            event: Response {
+                id: self.id,
+                response,
+                marker: PhantomData,
+            },
+        }
+    }
+}
+
+/// A server response to [`Request<Req>`] with the same ID.
+///
+/// Created with [`Request::respond`].
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct Response<Req, Resp> {
+    pub id: u32,
+    pub response: Resp,
+    #[serde(skip)]
+    marker: PhantomData<Req>,
+}
+
+/// An event that emitted on client when a response to `Req` arrives or the request times out.
+#[derive(Debug, Event)]
+pub struct RequestResult<Req, Resp> {
+    /// The sent request.
+    pub request: Req,
+    pub result: Result<Resp, RequestTimeout>,
+}
+
+/// An error indicating that the server didn't respond to a request within the timeout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestTimeout;
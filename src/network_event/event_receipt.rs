@@ -0,0 +1,197 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use bevy::{
+    ecs::{event::Event, system::SystemParam},
+    prelude::*,
+    utils::HashSet,
+};
+use bevy_renet::renet::{RenetClient, SendType};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    client_event::{ClientEventAppExt, FromClient},
+    server_event::{SendMode, ServerEventAppExt, ToClients},
+};
+use crate::{
+    client::ClientSet,
+    server::{has_authority, ServerSet},
+};
+
+/// An extension trait for [`App`] for creating client events with delivery receipts.
+pub trait EventReceiptAppExt {
+    /// Registers client event `T` that server acknowledges with [`EventReceipt<T>`].
+    ///
+    /// Send `T` on client with [`ReceiptSender<T>`], which returns [`ReceiptId`] assigned to the event.
+    /// Server receives accepted events as regular [`FromClient<T>`] and replies to the sender
+    /// with [`EventReceipt<T>`] with the same ID, so UIs could show pending states until it arrives.
+    /// Should be called on both server and clients.
+    fn add_receipted_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self;
+
+    /// Same as [`Self::add_receipted_client_event`], but server accepts only events approved by `validator`.
+    ///
+    /// Validator is called on server with the sender ID and the event.
+    /// Rejected events are not emitted as [`FromClient<T>`] and their receipts have
+    /// [`EventReceipt::accepted`] set to `false`.
+    fn add_receipted_client_event_with<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+        validator: fn(u64, &T) -> bool,
+    ) -> &mut Self;
+}
+
+impl EventReceiptAppExt for App {
+    fn add_receipted_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self {
+        self.add_receipted_client_event_with::<T>(policy, |_, _| true)
+    }
+
+    fn add_receipted_client_event_with<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+        validator: fn(u64, &T) -> bool,
+    ) -> &mut Self {
+        let policy = policy.into();
+        self.add_client_event::<Receipted<T>>(policy.clone())
+            .add_server_event::<EventReceipt<T>>(policy)
+            .add_event::<FromClient<T>>()
+            .insert_resource(ReceiptValidator(validator))
+            .insert_resource(PendingReceipts::<T> {
+                next_id: 0,
+                ids: Default::default(),
+                marker: PhantomData,
+            })
+            .add_systems(
+                PreUpdate,
+                (
+                    acknowledgment_system::<T>
+                        .after(ServerSet::Receive)
+                        .run_if(has_authority()),
+                    receipt_system::<T>.after(ClientSet::Receive),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                reset_system::<T>.run_if(resource_removed::<RenetClient>()),
+            )
+    }
+}
+
+/// Validates received events, forwards accepted ones as [`FromClient<T>`] and replies with receipts.
+fn acknowledgment_system<T: Event + Debug>(
+    mut receipted_events: ResMut<Events<FromClient<Receipted<T>>>>,
+    mut client_events: EventWriter<FromClient<T>>,
+    mut receipts: EventWriter<ToClients<EventReceipt<T>>>,
+    validator: Res<ReceiptValidator<T>>,
+) {
+    for FromClient {
+        client_id,
+        event: Receipted { id, event },
+        tick,
+        received_tick,
+        channel_id,
+    } in receipted_events.drain()
+    {
+        let accepted = (validator.0)(client_id, &event);
+        if accepted {
+            client_events.send(FromClient {
+                client_id,
+                event,
+                tick,
+                received_tick,
+                channel_id,
+            });
+        } else {
+            debug!("rejected receipted event {event:?} from client {client_id}");
+        }
+        receipts.send(ToClients {
+            mode: SendMode::Direct(client_id),
+            event: EventReceipt {
+                id,
+                accepted,
+                marker: PhantomData,
+            },
+        });
+    }
+}
+
+/// Forgets events whose receipts arrived.
+fn receipt_system<T: Event>(
+    mut receipts: EventReader<EventReceipt<T>>,
+    mut pending: ResMut<PendingReceipts<T>>,
+) {
+    for receipt in &mut receipts {
+        pending.ids.remove(&receipt.id);
+    }
+}
+
+fn reset_system<T: Send + Sync + 'static>(mut pending: ResMut<PendingReceipts<T>>) {
+    pending.ids.clear();
+}
+
+#[derive(Resource)]
+struct ReceiptValidator<T>(fn(u64, &T) -> bool);
+
+/// Events that were sent and wait for receipts.
+#[derive(Resource)]
+struct PendingReceipts<T> {
+    next_id: u32,
+    ids: HashSet<ReceiptId>,
+    marker: PhantomData<T>,
+}
+
+/// A system parameter to send client events registered with
+/// [`EventReceiptAppExt::add_receipted_client_event`].
+#[derive(SystemParam)]
+pub struct ReceiptSender<'w, T: Event> {
+    events: EventWriter<'w, Receipted<T>>,
+    pending: ResMut<'w, PendingReceipts<T>>,
+}
+
+impl<T: Event> ReceiptSender<'_, T> {
+    /// Sends the event to server and returns its ID to match with [`EventReceipt<T>`].
+    pub fn send(&mut self, event: T) -> ReceiptId {
+        let id = ReceiptId(self.pending.next_id);
+        self.pending.next_id = self.pending.next_id.wrapping_add(1);
+        self.pending.ids.insert(id);
+        self.events.send(Receipted { id, event });
+        id
+    }
+
+    /// Returns `true` if the event was sent, but its receipt hasn't arrived yet.
+    ///
+    /// Pending events are forgotten on disconnect.
+    pub fn is_pending(&self, id: ReceiptId) -> bool {
+        self.pending.ids.contains(&id)
+    }
+}
+
+/// ID assigned to a client event sent with [`ReceiptSender`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ReceiptId(u32);
+
+/// A client event with its receipt ID.
+///
+/// Sent by [`ReceiptSender`] and unpacked into [`FromClient<T>`] on server.
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct Receipted<T> {
+    pub id: ReceiptId,
+    pub event: T,
+}
+
+/// A server event that acknowledges a client event sent with [`ReceiptSender`].
+///
+/// Emitted only on the client that sent the event.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct EventReceipt<T> {
+    /// ID returned by [`ReceiptSender::send`].
+    pub id: ReceiptId,
+    /// Whether server accepted the event.
+    pub accepted: bool,
+    #[serde(skip)]
+    marker: PhantomData<T>,
+}
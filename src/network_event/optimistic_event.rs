@@ -0,0 +1,168 @@
+use std::{collections::VecDeque, fmt::Debug, marker::PhantomData, time::Duration};
+
+use bevy::{
+    ecs::event::{Event, ManualEventReader},
+    prelude::*,
+};
+use bevy_renet::{renet::SendType, transport::client_connected};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{client_event::ClientEventAppExt, server_event::ServerEventAppExt};
+use crate::{client::ClientSet, server::has_authority};
+
+/// An extension trait for [`App`] for creating client events that are applied optimistically.
+pub trait OptimisticEventAppExt {
+    /// Registers client event `T` that is applied on the sending client immediately.
+    ///
+    /// Send `T` as a regular event on client. `apply` will be called right away and the event
+    /// will be sent to server as [`Optimistic<T>`], so the server will receive
+    /// [`FromClient<Optimistic<T>>`](super::client_event::FromClient). To reject it, the server
+    /// should send [`Rejected<T>`] back to the client, for example using [`Optimistic::rejection`].
+    /// If the rejection arrives within `timeout`, `revert` will be called on client.
+    /// Events without rejection are considered accepted after `timeout`.
+    fn add_optimistic_client_event<T>(
+        &mut self,
+        policy: impl Into<SendType>,
+        timeout: Duration,
+        apply: fn(&mut World, &T),
+        revert: fn(&mut World, &T),
+    ) -> &mut Self
+    where
+        T: Event + Serialize + DeserializeOwned + Debug + Clone;
+}
+
+impl OptimisticEventAppExt for App {
+    fn add_optimistic_client_event<T>(
+        &mut self,
+        policy: impl Into<SendType>,
+        timeout: Duration,
+        apply: fn(&mut World, &T),
+        revert: fn(&mut World, &T),
+    ) -> &mut Self
+    where
+        T: Event + Serialize + DeserializeOwned + Debug + Clone,
+    {
+        let policy = policy.into();
+        self.add_event::<T>()
+            .add_client_event::<Optimistic<T>>(policy.clone())
+            .add_server_event::<Rejected<T>>(policy)
+            .insert_resource(PendingOptimistic::<T> {
+                timeout,
+                apply,
+                revert,
+                next_id: 0,
+                events: Default::default(),
+            })
+            .add_systems(PreUpdate, rejection_system::<T>.after(ClientSet::Receive))
+            .add_systems(
+                PostUpdate,
+                applying_system::<T>
+                    .before(ClientSet::Send)
+                    .run_if(has_authority().or_else(client_connected())),
+            );
+
+        self
+    }
+}
+
+/// Applies sent events and forwards them to server as [`Optimistic<T>`].
+fn applying_system<T: Event + Clone>(world: &mut World) {
+    let events: Vec<_> = world.resource_mut::<Events<T>>().drain().collect();
+    if events.is_empty() {
+        return;
+    }
+
+    let now = world.resource::<Time>().elapsed();
+    world.resource_scope(|world, mut pending: Mut<PendingOptimistic<T>>| {
+        for event in events {
+            (pending.apply)(world, &event);
+            let id = pending.next_id;
+            pending.next_id = pending.next_id.wrapping_add(1);
+            pending.events.push_back((id, now, event.clone()));
+            world.send_event(Optimistic { id, event });
+        }
+    });
+}
+
+/// Reverts rejected events and forgets events that exceeded the timeout.
+fn rejection_system<T: Event>(
+    world: &mut World,
+    mut reader: Local<ManualEventReader<Rejected<T>>>,
+) {
+    let rejected: Vec<_> = reader
+        .iter(world.resource::<Events<Rejected<T>>>())
+        .map(|rejected| rejected.id)
+        .collect();
+
+    let now = world.resource::<Time>().elapsed();
+    world.resource_scope(|world, mut pending: Mut<PendingOptimistic<T>>| {
+        for id in rejected {
+            if let Some(index) = pending
+                .events
+                .iter()
+                .position(|&(pending_id, ..)| pending_id == id)
+            {
+                let (_, _, event) = pending
+                    .events
+                    .remove(index)
+                    .expect("index should be obtained from the same queue");
+                (pending.revert)(world, &event);
+            }
+        }
+
+        let timeout = pending.timeout;
+        while pending
+            .events
+            .front()
+            .is_some_and(|&(_, sent_at, _)| now.saturating_sub(sent_at) > timeout)
+        {
+            pending.events.pop_front();
+        }
+    });
+}
+
+/// Client events that were applied and can still be rejected by server.
+#[derive(Resource)]
+struct PendingOptimistic<T> {
+    timeout: Duration,
+    apply: fn(&mut World, &T),
+    revert: fn(&mut World, &T),
+    next_id: u32,
+    /// Applied events with their IDs and times when they were sent.
+    events: VecDeque<(u32, Duration, T)>,
+}
+
+/// An optimistically applied client event with its ID.
+///
+/// Sent from client as a client event by [`OptimisticEventAppExt::add_optimistic_client_event`].
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct Optimistic<T> {
+    pub id: u32,
+    pub event: T,
+}
+
+impl<T> Optimistic<T> {
+    /// Creates a rejection of this event to send back to the client.
+    pub fn rejection(&self) -> Rejected<T> {
+        Rejected::new(self.id)
+    }
+}
+
+/// A server event that rejects [`Optimistic<T>`] with the specified ID.
+///
+/// Should be sent only to the client that sent the event.
+#[derive(Debug, Deserialize, Event, Serialize)]
+pub struct Rejected<T> {
+    pub id: u32,
+    #[serde(skip)]
+    marker: PhantomData<T>,
+}
+
+impl<T> Rejected<T> {
+    pub fn new(id: u32) -> Self {
+        Self {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Debug;
+
+use bevy::{ecs::event::Event, prelude::*};
+use bevy_renet::renet::{RenetClient, SendType};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::server_event::{SendMode, ServerEventAppExt, ToClients};
+use crate::{
+    client::ClientSet,
+    server::{ServerSet, ServerTick},
+};
+
+/// An extension trait for [`App`] for creating server events that are emitted at a specific tick.
+pub trait ScheduledEventAppExt {
+    /// Registers server event `T` that is emitted when [`ServerTick`] reaches the scheduled tick.
+    ///
+    /// Send [`ToClients::scheduled`] on server. Clients buffer received events and emit them as `T`
+    /// once the replicated [`ServerTick`] reaches the tick, so all peers observe them together with
+    /// the same world state. Useful for countdowns, round starts and cutscene triggers.
+    /// Events that arrive after their tick are emitted right away.
+    /// If server is also a player, it emits them locally according to its own [`ServerTick`].
+    fn add_scheduled_server_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self;
+}
+
+impl ScheduledEventAppExt for App {
+    fn add_scheduled_server_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self {
+        self.add_event::<T>()
+            .add_server_event::<Scheduled<T>>(policy)
+            .insert_resource(ScheduledEvents::<T>(Default::default()))
+            .add_systems(
+                PreUpdate,
+                emission_system::<T>
+                    .after(ClientSet::Receive)
+                    .after(ServerSet::Receive),
+            )
+            .add_systems(
+                PostUpdate,
+                reset_system::<T>.run_if(resource_removed::<RenetClient>()),
+            )
+    }
+}
+
+/// Buffers received events and emits those whose tick was reached.
+fn emission_system<T: Event + Debug>(
+    mut scheduled_events: ResMut<Events<Scheduled<T>>>,
+    mut events: EventWriter<T>,
+    mut buffer: ResMut<ScheduledEvents<T>>,
+    server_tick: Res<ServerTick>,
+) {
+    let current_tick = server_tick.get();
+    let (mut due, pending): (Vec<_>, Vec<_>) = buffer
+        .0
+        .drain(..)
+        .chain(scheduled_events.drain())
+        .partition(|scheduled| scheduled.tick.wrapping_sub(current_tick) as i32 <= 0);
+    buffer.0 = pending;
+
+    // Events for skipped ticks are emitted in the order of their ticks.
+    due.sort_by_key(|scheduled| scheduled.tick.wrapping_sub(current_tick) as i32);
+    for scheduled in due {
+        debug!(
+            "emitted scheduled event {:?} for tick {}",
+            scheduled.event, scheduled.tick
+        );
+        events.send(scheduled.event);
+    }
+}
+
+fn reset_system<T: Send + Sync + 'static>(mut buffer: ResMut<ScheduledEvents<T>>) {
+    buffer.0.clear();
+}
+
+/// Received events that wait for their ticks.
+#[derive(Resource)]
+struct ScheduledEvents<T>(Vec<Scheduled<T>>);
+
+/// A server event that should be emitted at the specified tick.
+///
+/// Registered by [`ScheduledEventAppExt::add_scheduled_server_event`].
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct Scheduled<T> {
+    /// [`ServerTick`] at which the event should be emitted.
+    pub tick: u32,
+    pub event: T,
+}
+
+impl<T> ToClients<Scheduled<T>> {
+    /// Creates an event that will be emitted on clients when they reach `tick`.
+    pub fn scheduled(mode: SendMode, tick: u32, event: T) -> Self {
+        Self {
+            mode,
+            event: Scheduled { tick, event },
+        }
+    }
+}
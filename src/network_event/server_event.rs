@@ -1,21 +1,31 @@
-use std::fmt::Debug;
+use std::{any, fmt::Debug};
 
-use bevy::{ecs::event::Event, prelude::*};
+use bevy::{
+    ecs::{entity::MapEntities, event::Event, system::SystemParam},
+    prelude::*,
+};
 use bevy_renet::{
     renet::{RenetClient, RenetServer, SendType},
     transport::client_connected,
 };
-use bincode::{DefaultOptions, Options};
 use serde::{
     de::{DeserializeOwned, DeserializeSeed},
     Serialize,
 };
 
-use super::{BuildEventDeserializer, BuildEventSerializer, EventChannel, MapEventEntities};
+use super::{
+    handle_map_result, set_event_decode, BuildEventDeserializer, BuildEventSerializer,
+    EventChannel, MapFailurePolicy, NetworkEventStats,
+};
 use crate::{
     client::{ClientSet, NetworkEntityMap},
     replication_core::NetworkChannels,
-    server::{has_authority, ServerSet, SERVER_ID},
+    server::{
+        has_authority, traffic_log::event_summary, ClientGroups, ClientTags, ServerSet, TagFilter,
+        TrafficDirection, TrafficLog, SERVER_ID,
+    },
+    traffic_decoder::{decode_event, decode_reflect_event, EventDecoders},
+    wire_format::{deserialize_message, deserialize_message_seed, serialize_message},
 };
 
 /// An extension trait for [`App`] for creating server events.
@@ -27,7 +37,7 @@ pub trait ServerEventAppExt {
     ) -> &mut Self;
 
     /// Same as [`Self::add_server_event`], but additionally maps server entities to client after receiving.
-    fn add_mapped_server_event<T: Event + Serialize + DeserializeOwned + Debug + MapEventEntities>(
+    fn add_mapped_server_event<T: Event + Serialize + DeserializeOwned + Debug + MapEntities>(
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self;
@@ -50,12 +60,17 @@ pub trait ServerEventAppExt {
         policy: impl Into<SendType>,
     ) -> &mut Self
     where
-        T: Event + Debug + MapEventEntities,
+        T: Event + Debug + MapEntities,
         S: BuildEventSerializer<T> + 'static,
         D: BuildEventDeserializer + 'static,
         for<'a> S::EventSerializer<'a>: Serialize,
         for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>;
 
+    /// Sets what happens when an entity inside a received mapped event `T` can't be mapped.
+    ///
+    /// The event should be already registered. See [`MapFailurePolicy`] for default.
+    fn set_server_event_map_policy<T: Event>(&mut self, policy: MapFailurePolicy) -> &mut Self;
+
     /// Same as [`Self::add_server_event`], but uses specified sending and receiving systems.
     fn add_server_event_with<T: Event + Debug, Marker1, Marker2>(
         &mut self,
@@ -70,12 +85,12 @@ impl ServerEventAppExt for App {
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self {
-        self.add_server_event_with::<T, _, _>(policy, sending_system::<T>, receiving_system::<T>)
+        self.add_server_event_with::<T, _, _>(policy, sending_system::<T>, receiving_system::<T>);
+        set_event_decode::<T>(self, TrafficDirection::Sent, decode_event::<T>);
+        self
     }
 
-    fn add_mapped_server_event<
-        T: Event + Serialize + DeserializeOwned + Debug + MapEventEntities,
-    >(
+    fn add_mapped_server_event<T: Event + Serialize + DeserializeOwned + Debug + MapEntities>(
         &mut self,
         policy: impl Into<SendType>,
     ) -> &mut Self {
@@ -83,7 +98,9 @@ impl ServerEventAppExt for App {
             policy,
             sending_system::<T>,
             receiving_and_mapping_system::<T>,
-        )
+        );
+        set_event_decode::<T>(self, TrafficDirection::Sent, decode_event::<T>);
+        self
     }
 
     fn add_server_reflect_event<T, S, D>(&mut self, policy: impl Into<SendType>) -> &mut Self
@@ -98,12 +115,14 @@ impl ServerEventAppExt for App {
             policy,
             sending_reflect_system::<T, S>,
             receiving_reflect_system::<T, D>,
-        )
+        );
+        set_event_decode::<T>(self, TrafficDirection::Sent, decode_reflect_event::<T, D>);
+        self
     }
 
     fn add_mapped_server_reflect_event<T, S, D>(&mut self, policy: impl Into<SendType>) -> &mut Self
     where
-        T: Event + Debug + MapEventEntities,
+        T: Event + Debug + MapEntities,
         S: BuildEventSerializer<T> + 'static,
         D: BuildEventDeserializer + 'static,
         for<'a> S::EventSerializer<'a>: Serialize,
@@ -113,7 +132,22 @@ impl ServerEventAppExt for App {
             policy,
             sending_reflect_system::<T, S>,
             receiving_and_mapping_reflect_system::<T, D>,
-        )
+        );
+        set_event_decode::<T>(self, TrafficDirection::Sent, decode_reflect_event::<T, D>);
+        self
+    }
+
+    fn set_server_event_map_policy<T: Event>(&mut self, policy: MapFailurePolicy) -> &mut Self {
+        self.world
+            .get_resource_mut::<EventChannel<T>>()
+            .unwrap_or_else(|| {
+                panic!(
+                    "{} should be registered as a server event",
+                    any::type_name::<T>()
+                )
+            })
+            .map_policy = policy;
+        self
     }
 
     fn add_server_event_with<T: Event + Debug, Marker1, Marker2>(
@@ -125,58 +159,86 @@ impl ServerEventAppExt for App {
         let channel_id = self
             .world
             .resource_mut::<NetworkChannels>()
-            .create_server_channel(policy.into());
-
-        self.add_event::<T>()
-            .init_resource::<Events<ToClients<T>>>()
-            .insert_resource(EventChannel::<T>::new(channel_id))
-            .add_systems(
-                PreUpdate,
-                receiving_system
-                    .in_set(ClientSet::Receive)
-                    .run_if(client_connected()),
-            )
-            .add_systems(
-                PostUpdate,
-                (
-                    sending_system.run_if(resource_exists::<RenetServer>()),
-                    local_resending_system::<T>.run_if(has_authority()),
-                )
-                    .chain()
-                    .in_set(ServerSet::Send),
-            );
+            .create_server_channel::<T>(policy.into());
+
+        register_server_event::<T, _, _>(self, channel_id, sending_system, receiving_system);
+        self.world.resource_mut::<EventDecoders>().insert::<T>(
+            TrafficDirection::Sent,
+            channel_id,
+            None,
+            None,
+        );
 
         self
     }
 }
 
+/// Registers server event `T` that will be sent over the channel with the specified ID.
+pub(super) fn register_server_event<T: Event + Debug, Marker1, Marker2>(
+    app: &mut App,
+    channel_id: u8,
+    sending_system: impl IntoSystemConfigs<Marker1>,
+    receiving_system: impl IntoSystemConfigs<Marker2>,
+) {
+    app.add_event::<T>()
+        .init_resource::<Events<ToClients<T>>>()
+        .init_resource::<ClientGroups>()
+        .init_resource::<ClientTags>()
+        .init_resource::<NetworkEventStats>()
+        .init_resource::<TrafficLog>()
+        .init_resource::<EventDecoders>()
+        .insert_resource(EventChannel::<T>::new(channel_id, MapFailurePolicy::Panic))
+        .add_systems(
+            PreUpdate,
+            receiving_system
+                .in_set(ClientSet::Receive)
+                .run_if(client_connected()),
+        )
+        .add_systems(
+            PostUpdate,
+            (
+                sending_system.run_if(resource_exists::<RenetServer>()),
+                local_resending_system::<T>.run_if(has_authority()),
+            )
+                .chain()
+                .in_set(ServerSet::Send),
+        );
+}
+
 fn receiving_system<T: Event + DeserializeOwned + Debug>(
     mut server_events: EventWriter<T>,
     mut client: ResMut<RenetClient>,
     channel: Res<EventChannel<T>>,
+    mut stats: ResMut<NetworkEventStats>,
 ) {
     while let Some(message) = client.receive_message(channel.id) {
-        let event = bincode::deserialize(&message).expect("server should send valid events");
+        stats.record_received::<T>(message.len());
+        let event = deserialize_message(&message).expect("server should send valid events");
         debug!("received event {event:?} from server");
         server_events.send(event);
     }
 }
 
-fn receiving_and_mapping_system<T: Event + MapEventEntities + DeserializeOwned + Debug>(
-    mut server_events: EventWriter<T>,
-    mut client: ResMut<RenetClient>,
-    entity_map: Res<NetworkEntityMap>,
-    channel: Res<EventChannel<T>>,
+fn receiving_and_mapping_system<T: Event + MapEntities + DeserializeOwned + Debug>(
+    world: &mut World,
 ) {
-    while let Some(message) = client.receive_message(channel.id) {
-        let mut event: T =
-            bincode::deserialize(&message).expect("server should send valid mapped events");
-        debug!("received mapped event {event:?} from server");
-        event
-            .map_entities(entity_map.to_client())
-            .unwrap_or_else(|e| panic!("server event {event:?} should be mappable: {e}"));
-        server_events.send(event);
-    }
+    let channel_id = world.resource::<EventChannel<T>>().id;
+    world.resource_scope(|world, mut client: Mut<RenetClient>| {
+        world.resource_scope(|world, entity_map: Mut<NetworkEntityMap>| {
+            while let Some(message) = client.receive_message(channel_id) {
+                world
+                    .resource_mut::<NetworkEventStats>()
+                    .record_received::<T>(message.len());
+                let mut event: T =
+                    deserialize_message(&message).expect("server should send valid mapped events");
+                debug!("received mapped event {event:?} from server");
+                let result = entity_map.map_to_client(world, &mut event);
+                if handle_map_result(world, result, &event, SERVER_ID) {
+                    world.send_event(event);
+                }
+            }
+        });
+    });
 }
 
 fn receiving_reflect_system<T, D>(
@@ -184,6 +246,7 @@ fn receiving_reflect_system<T, D>(
     mut client: ResMut<RenetClient>,
     channel: Res<EventChannel<T>>,
     registry: Res<AppTypeRegistry>,
+    mut stats: ResMut<NetworkEventStats>,
 ) where
     T: Event + Debug,
     D: BuildEventDeserializer,
@@ -191,78 +254,56 @@ fn receiving_reflect_system<T, D>(
 {
     let registry = registry.read();
     while let Some(message) = client.receive_message(channel.id) {
-        // Set options to match `bincode::serialize`.
-        // https://docs.rs/bincode/latest/bincode/config/index.html#options-struct-vs-bincode-functions
-        let options = DefaultOptions::new()
-            .with_fixint_encoding()
-            .allow_trailing_bytes();
-        let mut deserializer = bincode::Deserializer::from_slice(&message, options);
-        let event = D::new(&registry)
-            .deserialize(&mut deserializer)
+        stats.record_received::<T>(message.len());
+        let event = deserialize_message_seed(&message, D::new(&registry))
             .expect("server should send valid reflect events");
         debug!("received reflect event {event:?} from server");
         server_events.send(event);
     }
 }
 
-fn receiving_and_mapping_reflect_system<T, D>(
-    mut server_events: EventWriter<T>,
-    mut client: ResMut<RenetClient>,
-    entity_map: Res<NetworkEntityMap>,
-    channel: Res<EventChannel<T>>,
-    registry: Res<AppTypeRegistry>,
-) where
-    T: Event + MapEventEntities + Debug,
+fn receiving_and_mapping_reflect_system<T, D>(world: &mut World)
+where
+    T: Event + MapEntities + Debug,
     D: BuildEventDeserializer,
     for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>,
 {
+    let channel_id = world.resource::<EventChannel<T>>().id;
+    let registry = world.resource::<AppTypeRegistry>().clone();
     let registry = registry.read();
-    while let Some(message) = client.receive_message(channel.id) {
-        // Set options to match `bincode::serialize`.
-        // https://docs.rs/bincode/latest/bincode/config/index.html#options-struct-vs-bincode-functions
-        let options = DefaultOptions::new()
-            .with_fixint_encoding()
-            .allow_trailing_bytes();
-        let mut deserializer = bincode::Deserializer::from_slice(&message, options);
-        let mut event = D::new(&registry)
-            .deserialize(&mut deserializer)
-            .expect("server should send valid mapped reflect events");
-        debug!("received mapped reflect event {event:?} from server");
-        event
-            .map_entities(entity_map.to_client())
-            .unwrap_or_else(|e| panic!("server reflect event {event:?} should be mappable: {e}"));
-        server_events.send(event);
-    }
+    world.resource_scope(|world, mut client: Mut<RenetClient>| {
+        world.resource_scope(|world, entity_map: Mut<NetworkEntityMap>| {
+            while let Some(message) = client.receive_message(channel_id) {
+                world
+                    .resource_mut::<NetworkEventStats>()
+                    .record_received::<T>(message.len());
+                let mut event = deserialize_message_seed(&message, D::new(&registry))
+                    .expect("server should send valid mapped reflect events");
+                debug!("received mapped reflect event {event:?} from server");
+                let result = entity_map.map_to_client(world, &mut event);
+                if handle_map_result(world, result, &event, SERVER_ID) {
+                    world.send_event(event);
+                }
+            }
+        });
+    });
 }
 
 fn sending_system<T: Event + Serialize + Debug>(
     mut server: ResMut<RenetServer>,
     mut server_events: EventReader<ToClients<T>>,
     channel: Res<EventChannel<T>>,
+    targets: SendTargets,
+    mut stats: ResMut<NetworkEventStats>,
 ) {
     for ToClients { event, mode } in &mut server_events {
-        let message = bincode::serialize(&event).expect("server event should be serializable");
+        let message = serialize_message(&event).expect("server event should be serializable");
+        stats.record_sent::<T>(message.len(), targets.recipients_count(&server, *mode));
 
-        match *mode {
-            SendMode::Broadcast => {
-                server.broadcast_message(channel.id, message);
-                debug!("broadcasted server event {event:?}");
-            }
-            SendMode::BroadcastExcept(client_id) => {
-                if client_id == SERVER_ID {
-                    server.broadcast_message(channel.id, message);
-                } else {
-                    server.broadcast_message_except(client_id, channel.id, message);
-                }
-                debug!("broadcasted server event {event:?} except client {client_id}");
-            }
-            SendMode::Direct(client_id) => {
-                if client_id != SERVER_ID {
-                    server.send_message(client_id, channel.id, message);
-                    debug!("sent direct server event {event:?} to client {client_id}");
-                }
-            }
-        }
+        targets.send(&mut server, channel.id, *mode, message, || {
+            event_summary(event)
+        });
+        debug!("sent server event {event:?} with {mode:?}");
     }
 }
 
@@ -270,7 +311,9 @@ fn sending_reflect_system<T, S>(
     mut server: ResMut<RenetServer>,
     mut server_events: EventReader<ToClients<T>>,
     channel: Res<EventChannel<T>>,
+    targets: SendTargets,
     registry: Res<AppTypeRegistry>,
+    mut stats: ResMut<NetworkEventStats>,
 ) where
     T: Event + Debug,
     S: BuildEventSerializer<T>,
@@ -279,28 +322,13 @@ fn sending_reflect_system<T, S>(
     let registry = registry.read();
     for ToClients { event, mode } in &mut server_events {
         let serializer = S::new(event, &registry);
-        let message = bincode::serialize(&serializer).expect("server event should be serializable");
+        let message = serialize_message(&serializer).expect("server event should be serializable");
+        stats.record_sent::<T>(message.len(), targets.recipients_count(&server, *mode));
 
-        match *mode {
-            SendMode::Broadcast => {
-                server.broadcast_message(channel.id, message);
-                debug!("broadcasted server reflect event {event:?}");
-            }
-            SendMode::BroadcastExcept(client_id) => {
-                if client_id == SERVER_ID {
-                    server.broadcast_message(channel.id, message);
-                } else {
-                    server.broadcast_message_except(client_id, channel.id, message);
-                }
-                debug!("broadcasted server reflect event {event:?} except client {client_id}");
-            }
-            SendMode::Direct(client_id) => {
-                if client_id != SERVER_ID {
-                    server.send_message(client_id, channel.id, message);
-                    debug!("sent direct server reflect event {event:?} to client {client_id}");
-                }
-            }
-        }
+        targets.send(&mut server, channel.id, *mode, message, || {
+            event_summary(event)
+        });
+        debug!("sent server reflect event {event:?} with {mode:?}");
     }
 }
 
@@ -309,6 +337,7 @@ fn sending_reflect_system<T, S>(
 fn local_resending_system<T: Event + Debug>(
     mut server_events: ResMut<Events<ToClients<T>>>,
     mut local_events: EventWriter<T>,
+    targets: SendTargets,
 ) {
     for ToClients { event, mode } in server_events.drain() {
         match mode {
@@ -328,7 +357,135 @@ fn local_resending_system<T: Event + Debug>(
                     local_events.send(event);
                 }
             }
+            SendMode::BroadcastGroup(group) => {
+                if targets.groups.contains(group, SERVER_ID) {
+                    debug!("converted server event {event:?} for group {group} into a local");
+                    local_events.send(event);
+                }
+            }
+            SendMode::BroadcastTagged(filter) => {
+                if targets
+                    .filter(filter)
+                    .is_some_and(|filter| targets.tags.matches(SERVER_ID, filter))
+                {
+                    debug!("converted server event {event:?} for tags {filter} into a local");
+                    local_events.send(event);
+                }
+            }
+        }
+    }
+}
+
+/// Resources that select clients for [`SendMode`].
+#[derive(SystemParam)]
+pub(super) struct SendTargets<'w> {
+    groups: Res<'w, ClientGroups>,
+    tags: Res<'w, ClientTags>,
+    traffic_log: Res<'w, TrafficLog>,
+}
+
+impl SendTargets<'_> {
+    /// Sends `message` on the channel to clients selected by `mode`.
+    ///
+    /// `summary` is used to log the message for clients watched by [`TrafficLog`].
+    pub(super) fn send(
+        &self,
+        server: &mut RenetServer,
+        channel_id: u8,
+        mode: SendMode,
+        message: Vec<u8>,
+        summary: impl Fn() -> String,
+    ) {
+        if self.traffic_log.is_enabled() {
+            for client_id in server.clients_id() {
+                if self.is_recipient(server, mode, client_id) {
+                    self.traffic_log
+                        .sent(client_id, channel_id, &message, &summary);
+                }
+            }
+        }
+
+        match mode {
+            SendMode::Broadcast => server.broadcast_message(channel_id, message),
+            SendMode::BroadcastExcept(client_id) => {
+                if client_id == SERVER_ID {
+                    server.broadcast_message(channel_id, message);
+                } else {
+                    server.broadcast_message_except(client_id, channel_id, message);
+                }
+            }
+            SendMode::Direct(client_id) => {
+                if client_id != SERVER_ID {
+                    server.send_message(client_id, channel_id, message);
+                }
+            }
+            SendMode::BroadcastGroup(group) => {
+                for client_id in self.groups.clients(group) {
+                    if client_id != SERVER_ID {
+                        server.send_message(client_id, channel_id, message.clone());
+                    }
+                }
+            }
+            SendMode::BroadcastTagged(filter) => {
+                let Some(filter) = self.filter(filter) else {
+                    return;
+                };
+                for client_id in server.clients_id() {
+                    if self.tags.matches(client_id, filter) {
+                        server.send_message(client_id, channel_id, message.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the number of connected clients that will receive a message sent with `mode`.
+    pub(super) fn recipients_count(&self, server: &RenetServer, mode: SendMode) -> usize {
+        let is_connected = |client_id| client_id != SERVER_ID && server.is_connected(client_id);
+        match mode {
+            SendMode::Broadcast => server.clients_id().len(),
+            SendMode::BroadcastExcept(client_id) => {
+                server.clients_id().len() - usize::from(is_connected(client_id))
+            }
+            SendMode::Direct(client_id) => usize::from(is_connected(client_id)),
+            SendMode::BroadcastGroup(group) => self
+                .groups
+                .clients(group)
+                .filter(|&client_id| is_connected(client_id))
+                .count(),
+            SendMode::BroadcastTagged(filter) => self.filter(filter).map_or(0, |filter| {
+                server
+                    .clients_id()
+                    .into_iter()
+                    .filter(|&client_id| self.tags.matches(client_id, filter))
+                    .count()
+            }),
+        }
+    }
+
+    /// Returns `true` if a connected client will receive a message sent with `mode`.
+    fn is_recipient(&self, server: &RenetServer, mode: SendMode, client_id: u64) -> bool {
+        if client_id == SERVER_ID || !server.is_connected(client_id) {
+            return false;
+        }
+        match mode {
+            SendMode::Broadcast => true,
+            SendMode::BroadcastExcept(except_id) => client_id != except_id,
+            SendMode::Direct(target_id) => client_id == target_id,
+            SendMode::BroadcastGroup(group) => self.groups.clients(group).any(|id| id == client_id),
+            SendMode::BroadcastTagged(filter) => self
+                .tags
+                .filter(filter)
+                .is_some_and(|filter| self.tags.matches(client_id, filter)),
+        }
+    }
+
+    fn filter(&self, name: &'static str) -> Option<&TagFilter> {
+        let filter = self.tags.filter(name);
+        if filter.is_none() {
+            error!("tag filter `{name}` should be defined in `ClientTags`");
         }
+        filter
     }
 }
 
@@ -345,4 +502,8 @@ pub enum SendMode {
     Broadcast,
     BroadcastExcept(u64),
     Direct(u64),
+    /// Sends to all clients of a group from [`ClientGroups`].
+    BroadcastGroup(&'static str),
+    /// Sends to all clients that match a named filter from [`ClientTags`].
+    BroadcastTagged(&'static str),
 }
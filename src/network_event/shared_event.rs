@@ -0,0 +1,281 @@
+use std::{any, fmt::Debug, marker::PhantomData};
+
+use bevy::{ecs::event::Event, prelude::*, utils::HashMap};
+use bevy_renet::{
+    renet::{Bytes, RenetClient, RenetServer},
+    transport::client_connected,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    client_event::{self, FromClient},
+    server_event::{self, SendTargets, ToClients},
+    EventChannel, NetworkEventStats,
+};
+use crate::{
+    client::ClientSet,
+    replication_core::NetworkChannels,
+    server::{traffic_log::event_summary, ServerSet, ServerTick, TrafficDirection, TrafficLog},
+    traffic_decoder::{decode_shared_event, EventDecoders},
+    wire_format::{deserialize_message, message_deserializer, serialize_message},
+};
+
+/// An extension trait for [`App`] for creating events that share a single channel.
+///
+/// Each [`ServerEventAppExt::add_server_event`](super::server_event::ServerEventAppExt::add_server_event)
+/// and [`ClientEventAppExt::add_client_event`](super::client_event::ClientEventAppExt::add_client_event)
+/// allocates a separate channel with its own memory limit. Events registered with this trait
+/// are instead multiplexed over one reliable ordered channel per direction and prefixed with a `u16`
+/// tag assigned in the order of registration, so registration order should match on server and clients.
+/// Useful for many low-traffic events, like notifications or UI actions.
+pub trait SharedEventAppExt {
+    /// Same as [`ServerEventAppExt::add_server_event`](super::server_event::ServerEventAppExt::add_server_event),
+    /// but the event is sent over the shared server channel.
+    fn add_shared_server_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Same as [`ClientEventAppExt::add_client_event`](super::client_event::ClientEventAppExt::add_client_event),
+    /// but the event is sent over the shared client channel.
+    fn add_shared_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self;
+}
+
+impl SharedEventAppExt for App {
+    fn add_shared_server_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self {
+        let (channel_id, tag) = self
+            .world
+            .resource_mut::<NetworkChannels>()
+            .assign_shared_server_tag::<T>();
+
+        if !self.world.contains_resource::<SharedServerMessages>() {
+            self.insert_resource(SharedServerMessages {
+                channel_id,
+                messages: Default::default(),
+            })
+            .add_systems(
+                PreUpdate,
+                server_dispatch_system
+                    .in_set(ClientSet::Receive)
+                    .run_if(client_connected()),
+            );
+        }
+
+        self.insert_resource(SharedTag::<T>::new(tag));
+        server_event::register_server_event::<T, _, _>(
+            self,
+            channel_id,
+            server_sending_system::<T>,
+            server_receiving_system::<T>.after(server_dispatch_system),
+        );
+        self.world.resource_mut::<EventDecoders>().insert::<T>(
+            TrafficDirection::Sent,
+            channel_id,
+            Some(tag),
+            Some(decode_shared_event::<T>),
+        );
+
+        self
+    }
+
+    fn add_shared_client_event<T: Event + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self {
+        let (channel_id, tag) = self
+            .world
+            .resource_mut::<NetworkChannels>()
+            .assign_shared_client_tag::<T>();
+
+        if !self.world.contains_resource::<SharedClientMessages>() {
+            self.insert_resource(SharedClientMessages {
+                channel_id,
+                messages: Default::default(),
+            })
+            .add_systems(
+                PreUpdate,
+                client_dispatch_system
+                    .in_set(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>()),
+            );
+        }
+
+        self.insert_resource(SharedTag::<T>::new(tag));
+        client_event::register_client_event::<T, _, _>(
+            self,
+            channel_id,
+            client_sending_system::<T>,
+            client_receiving_system::<T>.after(client_dispatch_system),
+        );
+        self.world.resource_mut::<EventDecoders>().insert::<T>(
+            TrafficDirection::Received,
+            channel_id,
+            Some(tag),
+            Some(decode_shared_event::<T>),
+        );
+
+        self
+    }
+}
+
+/// Groups messages from the shared server channel by their tags.
+fn server_dispatch_system(
+    mut client: ResMut<RenetClient>,
+    mut shared: ResMut<SharedServerMessages>,
+) {
+    // Drop messages with unknown tags from the previous update.
+    shared.messages.clear();
+    while let Some(message) = client.receive_message(shared.channel_id) {
+        match read_tag(&message) {
+            Ok(tag) => shared.messages.entry(tag).or_default().push(message),
+            Err(e) => error!("unable to read shared event tag from server: {e}"),
+        }
+    }
+}
+
+/// Groups messages from the shared client channel by their tags.
+fn client_dispatch_system(
+    mut server: ResMut<RenetServer>,
+    mut shared: ResMut<SharedClientMessages>,
+) {
+    // Drop messages with unknown tags from the previous update.
+    shared.messages.clear();
+    for client_id in server.clients_id() {
+        while let Some(message) = server.receive_message(client_id, shared.channel_id) {
+            match read_tag(&message) {
+                Ok(tag) => shared
+                    .messages
+                    .entry(tag)
+                    .or_default()
+                    .push((client_id, message)),
+                Err(e) => {
+                    error!("unable to read shared event tag from client {client_id}: {e}")
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn read_tag(message: &[u8]) -> bincode::Result<u16> {
+    let mut deserializer = message_deserializer(message)?;
+    u16::deserialize(&mut deserializer)
+}
+
+fn server_sending_system<T: Event + Serialize + Debug>(
+    mut server: ResMut<RenetServer>,
+    mut server_events: EventReader<ToClients<T>>,
+    channel: Res<EventChannel<T>>,
+    tag: Res<SharedTag<T>>,
+    targets: SendTargets,
+    mut stats: ResMut<NetworkEventStats>,
+) {
+    for ToClients { event, mode } in &mut server_events {
+        let message =
+            serialize_message(&(tag.id, event)).expect("server event should be serializable");
+        stats.record_sent::<T>(message.len(), targets.recipients_count(&server, *mode));
+        targets.send(&mut server, channel.id, *mode, message, || {
+            event_summary(event)
+        });
+        debug!("sent shared server event {event:?} with {mode:?}");
+    }
+}
+
+fn server_receiving_system<T: Event + DeserializeOwned + Debug>(
+    mut server_events: EventWriter<T>,
+    mut shared: ResMut<SharedServerMessages>,
+    tag: Res<SharedTag<T>>,
+    mut stats: ResMut<NetworkEventStats>,
+) {
+    for message in shared.messages.remove(&tag.id).into_iter().flatten() {
+        stats.record_received::<T>(message.len());
+        match deserialize_message::<(u16, T)>(&message) {
+            Ok((_, event)) => {
+                debug!("received shared event {event:?} from server");
+                server_events.send(event);
+            }
+            Err(e) => error!(
+                "unable to deserialize shared event {} from server: {e}",
+                any::type_name::<T>()
+            ),
+        }
+    }
+}
+
+fn client_sending_system<T: Event + Serialize + Debug>(
+    mut events: EventReader<T>,
+    mut client: ResMut<RenetClient>,
+    channel: Res<EventChannel<T>>,
+    tag: Res<SharedTag<T>>,
+    mut stats: ResMut<NetworkEventStats>,
+) {
+    for event in &mut events {
+        let message =
+            serialize_message(&(tag.id, event)).expect("client event should be serializable");
+        stats.record_sent::<T>(message.len(), 1);
+        client.send_message(channel.id, message);
+        debug!("sent shared client event {event:?}");
+    }
+}
+
+fn client_receiving_system<T: Event + DeserializeOwned + Debug>(
+    mut client_events: EventWriter<FromClient<T>>,
+    mut shared: ResMut<SharedClientMessages>,
+    channel: Res<EventChannel<T>>,
+    tag: Res<SharedTag<T>>,
+    server_tick: Res<ServerTick>,
+    mut stats: ResMut<NetworkEventStats>,
+    traffic_log: Res<TrafficLog>,
+) {
+    for (client_id, message) in shared.messages.remove(&tag.id).into_iter().flatten() {
+        stats.record_received::<T>(message.len());
+        match deserialize_message::<(u16, T)>(&message) {
+            Ok((_, event)) => {
+                debug!("received shared event {event:?} from client {client_id}");
+                traffic_log.received(client_id, channel.id, &message, || event_summary(&event));
+                client_events.send(FromClient {
+                    client_id,
+                    event,
+                    tick: None,
+                    received_tick: server_tick.get(),
+                    channel_id: channel.id,
+                });
+            }
+            Err(e) => error!(
+                "unable to deserialize shared event {} from client {client_id}: {e}",
+                any::type_name::<T>()
+            ),
+        }
+    }
+}
+
+/// Tag that identifies event `T` inside a shared channel.
+#[derive(Resource)]
+struct SharedTag<T> {
+    id: u16,
+    marker: PhantomData<T>,
+}
+
+impl<T> SharedTag<T> {
+    fn new(id: u16) -> Self {
+        Self {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Messages received on the shared server channel grouped by tags.
+#[derive(Resource)]
+struct SharedServerMessages {
+    channel_id: u8,
+    messages: HashMap<u16, Vec<Bytes>>,
+}
+
+/// Messages received on the shared client channel grouped by tags with their senders.
+#[derive(Resource)]
+struct SharedClientMessages {
+    channel_id: u8,
+    messages: HashMap<u16, Vec<(u64, Bytes)>>,
+}
@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::{NetworkInfo, RenetClient, RenetServer};
+
+use crate::{client::ClientSet, server::ServerSet};
+
+/// Publishes connection statistics from Renet every frame.
+///
+/// Inserts [`NetworkQuality`] resource on client and fills [`ClientNetworkQuality`] on server.
+pub struct NetworkQualityPlugin;
+
+impl Plugin for NetworkQualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientNetworkQuality>()
+            .add_systems(
+                PreUpdate,
+                (
+                    Self::client_system
+                        .after(ClientSet::Receive)
+                        .run_if(resource_exists::<RenetClient>()),
+                    Self::server_system
+                        .after(ServerSet::Receive)
+                        .run_if(resource_exists::<RenetServer>()),
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    Self::client_reset_system.run_if(resource_removed::<RenetClient>()),
+                    Self::server_reset_system.run_if(resource_removed::<RenetServer>()),
+                ),
+            );
+    }
+}
+
+impl NetworkQualityPlugin {
+    fn client_system(
+        mut commands: Commands,
+        client: Res<RenetClient>,
+        quality: Option<ResMut<NetworkQuality>>,
+    ) {
+        let network_info = client.network_info();
+        match quality {
+            Some(mut quality) => *quality = network_info.into(),
+            None => commands.insert_resource(NetworkQuality::from(network_info)),
+        }
+    }
+
+    fn server_system(server: Res<RenetServer>, mut quality: ResMut<ClientNetworkQuality>) {
+        quality.0.clear();
+        for client_id in server.clients_id() {
+            let network_info = server
+                .network_info(client_id)
+                .expect("network info should be requested for connected client");
+            quality.0.insert(client_id, network_info.into());
+        }
+    }
+
+    fn client_reset_system(mut commands: Commands) {
+        commands.remove_resource::<NetworkQuality>();
+    }
+
+    fn server_reset_system(mut quality: ResMut<ClientNetworkQuality>) {
+        quality.0.clear();
+    }
+}
+
+/// Statistics of a single connection.
+///
+/// Exists as a resource only on connected or connecting client, see [`ClientNetworkQuality`] for server.
+/// Renet doesn't expose the amount of unacknowledged reliable data, so it's not included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource)]
+pub struct NetworkQuality {
+    /// Round-trip time.
+    pub rtt: Duration,
+    /// Ratio of lost packets from 0.0 to 1.0.
+    pub packet_loss: f64,
+    pub bytes_sent_per_second: f64,
+    pub bytes_received_per_second: f64,
+}
+
+impl From<NetworkInfo> for NetworkQuality {
+    fn from(value: NetworkInfo) -> Self {
+        Self {
+            rtt: Duration::from_secs_f64(value.rtt),
+            packet_loss: value.packet_loss,
+            bytes_sent_per_second: value.bytes_sent_per_second,
+            bytes_received_per_second: value.bytes_received_per_second,
+        }
+    }
+}
+
+/// Statistics of connections to all clients.
+///
+/// Used only on server.
+#[derive(Default, Deref, Resource)]
+pub struct ClientNetworkQuality(HashMap<u64, NetworkQuality>);
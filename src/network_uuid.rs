@@ -0,0 +1,97 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, Uuid},
+};
+
+use crate::{client::ClientSet, replication_core::AppReplicationExt, server::ServerSet};
+
+/// Replicates [`NetworkUuid`] and keeps [`NetworkUuids`] up to date on server and client.
+pub struct NetworkUuidPlugin;
+
+impl Plugin for NetworkUuidPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Uuid>()
+            .register_type::<NetworkUuid>()
+            .replicate::<NetworkUuid>()
+            .init_resource::<NetworkUuids>()
+            .add_systems(PreUpdate, Self::update_system.after(ClientSet::Receive))
+            .add_systems(PostUpdate, Self::update_system.before(ServerSet::Send));
+    }
+}
+
+impl NetworkUuidPlugin {
+    fn update_system(
+        mut uuids: ResMut<NetworkUuids>,
+        mut removed_uuids: RemovedComponents<NetworkUuid>,
+        changed_uuids: Query<(Entity, &NetworkUuid), Changed<NetworkUuid>>,
+    ) {
+        for entity in &mut removed_uuids {
+            uuids.remove(entity);
+        }
+        for (entity, uuid) in &changed_uuids {
+            uuids.insert(uuid.0, entity);
+        }
+    }
+}
+
+/// Stable identity of an entity that is the same on server, clients and across sessions.
+///
+/// Unlike [`Entity`], it could be stored in saves and used to find the entity after loading.
+/// Use [`NetworkUuids`] to get an entity by its UUID.
+#[derive(Clone, Component, Copy, Debug, Eq, Hash, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct NetworkUuid(pub Uuid);
+
+impl NetworkUuid {
+    /// Creates a random UUID.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for NetworkUuid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maps [`NetworkUuid`]s to entities.
+///
+/// Updated after [`ClientSet::Receive`] and before [`ServerSet::Send`].
+#[derive(Default, Resource)]
+pub struct NetworkUuids {
+    entities: HashMap<Uuid, Entity>,
+    uuids: HashMap<Entity, Uuid>,
+}
+
+impl NetworkUuids {
+    /// Returns an entity with the UUID.
+    pub fn get(&self, uuid: Uuid) -> Option<Entity> {
+        self.entities.get(&uuid).copied()
+    }
+
+    /// Returns the UUID of an entity.
+    pub fn uuid(&self, entity: Entity) -> Option<Uuid> {
+        self.uuids.get(&entity).copied()
+    }
+
+    fn insert(&mut self, uuid: Uuid, entity: Entity) {
+        if let Some(previous_uuid) = self.uuids.insert(entity, uuid) {
+            self.remove_entity(previous_uuid, entity);
+        }
+        self.entities.insert(uuid, entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(uuid) = self.uuids.remove(&entity) {
+            self.remove_entity(uuid, entity);
+        }
+    }
+
+    /// Removes the mapping only if the UUID wasn't taken by another entity.
+    fn remove_entity(&mut self, uuid: Uuid, entity: Entity) {
+        if self.entities.get(&uuid) == Some(&entity) {
+            self.entities.remove(&uuid);
+        }
+    }
+}
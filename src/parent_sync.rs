@@ -6,28 +6,59 @@ use bevy::{
     prelude::*,
 };
 
+use derive_more::Constructor;
+
 use crate::{
     client::ClientSet,
-    replication_core::AppReplicationExt,
-    server::{has_authority, ServerSet},
+    replication_core::{AppReplicationExt, Replication},
+    server::{has_authority, removal_tracker::RemovalTrackerPlugin, ServerSet},
 };
 
-pub struct ParentSyncPlugin;
-
 /// Automatically updates hierarchy on client if [`ParentSync`] component is present on entity.
 ///
 /// This allows to save / replicate hierarchy using only single component.
+#[derive(Constructor, Default)]
+pub struct ParentSyncPlugin {
+    /// Insert [`ParentSync`] to all entities with [`Replication`] and [`Parent`] on server and remove it when unparented.
+    ///
+    /// Disabled by default, so the component should be inserted manually.
+    auto_insert: bool,
+}
+
 impl Plugin for ParentSyncPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Option<Entity>>()
             .replicate::<ParentSync>()
-            .add_systems(PreUpdate, Self::sync_system.after(ClientSet::Receive))
             .add_systems(
+                PreUpdate,
+                (
+                    Self::sync_system,
+                    Self::removal_sync_system.run_if(not(has_authority())),
+                )
+                    .after(ClientSet::Receive),
+            );
+
+        if self.auto_insert {
+            app.add_systems(
+                PostUpdate,
+                (
+                    (Self::insertion_system, Self::auto_removal_system),
+                    apply_deferred,
+                    Self::update_system,
+                )
+                    .chain()
+                    .run_if(has_authority())
+                    .before(RemovalTrackerPlugin::detection_system)
+                    .before(ServerSet::Send),
+            );
+        } else {
+            app.add_systems(
                 PostUpdate,
                 (Self::update_system, Self::removal_system)
                     .run_if(has_authority())
                     .before(ServerSet::Send),
             );
+        }
     }
 }
 
@@ -50,12 +81,56 @@ impl ParentSyncPlugin {
         }
     }
 
+    /// Removes parent if [`ParentSync`] was removed by server.
+    fn removal_sync_system(
+        mut commands: Commands,
+        mut removed_syncs: RemovedComponents<ParentSync>,
+        hierarchy: Query<(), With<Parent>>,
+    ) {
+        for entity in &mut removed_syncs {
+            if hierarchy.contains(entity) {
+                commands.entity(entity).remove_parent();
+            }
+        }
+    }
+
     fn update_system(mut hierarchy: Query<(&Parent, &mut ParentSync), Changed<Parent>>) {
         for (parent, mut parent_sync) in &mut hierarchy {
             parent_sync.0 = Some(**parent);
         }
     }
 
+    /// Inserts [`ParentSync`] for newly parented or replicated entities.
+    #[allow(clippy::type_complexity)]
+    fn insertion_system(
+        mut commands: Commands,
+        hierarchy: Query<
+            (Entity, &Parent),
+            (
+                With<Replication>,
+                Without<ParentSync>,
+                Or<(Added<Parent>, Added<Replication>)>,
+            ),
+        >,
+    ) {
+        for (entity, parent) in &hierarchy {
+            commands.entity(entity).insert(ParentSync(Some(**parent)));
+        }
+    }
+
+    /// Removes [`ParentSync`] from unparented entities.
+    fn auto_removal_system(
+        mut commands: Commands,
+        mut removed_parents: RemovedComponents<Parent>,
+        hierarchy: Query<(), (With<ParentSync>, Without<Parent>)>,
+    ) {
+        for entity in &mut removed_parents {
+            if hierarchy.contains(entity) {
+                commands.entity(entity).remove::<ParentSync>();
+            }
+        }
+    }
+
     fn removal_system(
         mut removed_parents: RemovedComponents<Parent>,
         mut hierarchy: Query<&mut ParentSync>,
@@ -70,7 +145,7 @@ impl ParentSyncPlugin {
 
 /// Updates entity parent on change.
 ///
-/// Removes the parent if `None`.
+/// Removes the parent if `None` or if the component is removed by server.
 /// The component captures changes in `PostUpdate` on server before sending
 /// and applies them on `PreUpdate` after receive on clients or scene deserialization.
 #[derive(Component, Default, Reflect, Clone, Copy)]
@@ -95,7 +170,7 @@ mod tests {
     #[test]
     fn update() {
         let mut app = App::new();
-        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin));
+        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin::default()));
 
         let child_entity = app.world.spawn_empty().id();
         app.world.spawn_empty().add_child(child_entity);
@@ -116,7 +191,7 @@ mod tests {
     #[test]
     fn removal() {
         let mut app = App::new();
-        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin));
+        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin::default()));
 
         let parent_entity = app.world.spawn_empty().id();
         let child_entity = app
@@ -142,7 +217,7 @@ mod tests {
     #[test]
     fn update_sync() {
         let mut app = App::new();
-        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin));
+        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin::default()));
 
         let parent_entity = app.world.spawn_empty().id();
         let child_entity = app.world.spawn(ParentSync(Some(parent_entity))).id();
@@ -158,7 +233,7 @@ mod tests {
     #[test]
     fn removal_sync() {
         let mut app = App::new();
-        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin));
+        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin::default()));
 
         let child_entity = app.world.spawn_empty().id();
         app.world.spawn_empty().add_child(child_entity);
@@ -176,6 +251,29 @@ mod tests {
         assert!(child_entity.get::<ParentSync>().unwrap().0.is_none());
     }
 
+    #[test]
+    fn auto_insertion() {
+        let mut app = App::new();
+        app.add_plugins((ReplicationCorePlugin, ParentSyncPlugin::new(true)));
+
+        let parent_entity = app.world.spawn_empty().id();
+        let child_entity = app.world.spawn(Replication).set_parent(parent_entity).id();
+
+        app.update();
+
+        let parent_sync = app.world.get::<ParentSync>(child_entity).unwrap();
+        assert_eq!(parent_sync.0, Some(parent_entity));
+
+        // Let `sync_system` observe the inserted component.
+        app.update();
+
+        app.world.entity_mut(child_entity).remove_parent();
+
+        app.update();
+
+        assert!(!app.world.entity(child_entity).contains::<ParentSync>());
+    }
+
     #[test]
     fn scene_update_sync() {
         let mut app = App::new();
@@ -183,7 +281,7 @@ mod tests {
             AssetPlugin::default(),
             ScenePlugin,
             ReplicationCorePlugin,
-            ParentSyncPlugin,
+            ParentSyncPlugin::default(),
         ));
 
         let mut scene_world = World::new();
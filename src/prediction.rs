@@ -0,0 +1,354 @@
+use std::{any, collections::VecDeque, time::Duration};
+
+use bevy::{ecs::component::Tick, prelude::*, transform::TransformSystem, utils::HashMap};
+use bevy_renet::{renet::RenetClient, transport::client_connected};
+
+use crate::{client::ClientSet, time_scale::ServerTimeScale};
+
+/// An extension trait for [`App`] for tracking mispredictions of client-side predicted components.
+pub trait PredictionAppExt {
+    /// Records mispredictions of component `C` into [`MispredictionDiagnostics`].
+    ///
+    /// On client, the value of `C` on entities with [`Predicted`] is remembered before
+    /// [`ClientSet::Receive`]. When replication overwrites it, the error between the predicted
+    /// and the received values is calculated with [`PredictionError`] and counted as a misprediction
+    /// if it exceeds `tolerance`.
+    fn add_misprediction_metrics<C>(&mut self, tolerance: f32) -> &mut Self
+    where
+        C: Component + PredictionError + Clone;
+}
+
+impl PredictionAppExt for App {
+    fn add_misprediction_metrics<C>(&mut self, tolerance: f32) -> &mut Self
+    where
+        C: Component + PredictionError + Clone,
+    {
+        self.init_resource::<MispredictionDiagnostics>()
+            .insert_resource(PredictionSnapshots::<C> {
+                tolerance,
+                values: Default::default(),
+            })
+            .add_systems(
+                PreUpdate,
+                (
+                    snapshot_system::<C>.before(ClientSet::Receive),
+                    comparison_system::<C>.after(ClientSet::Receive),
+                )
+                    .run_if(client_connected()),
+            )
+            .add_systems(
+                PostUpdate,
+                reset_system::<C>.run_if(resource_removed::<RenetClient>()),
+            )
+    }
+}
+
+/// Remembers predicted values before replication.
+fn snapshot_system<C: Component + Clone>(
+    mut snapshots: ResMut<PredictionSnapshots<C>>,
+    components: Query<(Entity, Ref<C>), With<Predicted>>,
+) {
+    snapshots.values.clear();
+    for (entity, component) in &components {
+        snapshots
+            .values
+            .insert(entity, (component.clone(), component.last_changed()));
+    }
+}
+
+/// Compares predicted values with values written by replication.
+fn comparison_system<C: Component + PredictionError>(
+    time: Res<Time>,
+    mut snapshots: ResMut<PredictionSnapshots<C>>,
+    mut diagnostics: ResMut<MispredictionDiagnostics>,
+    components: Query<Ref<C>, With<Predicted>>,
+) {
+    let stats = diagnostics
+        .components
+        .entry(any::type_name::<C>())
+        .or_default();
+    let tolerance = snapshots.tolerance;
+    for (entity, (predicted, changed_at)) in snapshots.values.drain() {
+        let Ok(component) = components.get(entity) else {
+            continue;
+        };
+        if component.last_changed() == changed_at {
+            continue;
+        }
+
+        let error = predicted.prediction_error(&component);
+        if error > tolerance {
+            stats.push(time.elapsed(), error);
+        }
+    }
+    stats.cleanup(time.elapsed());
+}
+
+fn reset_system<C: Send + Sync + 'static>(
+    mut snapshots: ResMut<PredictionSnapshots<C>>,
+    mut diagnostics: ResMut<MispredictionDiagnostics>,
+) {
+    snapshots.values.clear();
+    diagnostics.components.clear();
+}
+
+/// Smooths visual corrections of predicted entities with [`CorrectionSmoothing`].
+///
+/// Not included in [`ReplicationPlugins`](crate::ReplicationPlugins) and should be added separately.
+pub struct CorrectionSmoothingPlugin;
+
+impl Plugin for CorrectionSmoothingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServerTimeScale>().add_systems(
+            PostUpdate,
+            (
+                Self::restoring_system.before(TransformSystem::TransformPropagate),
+                Self::smoothing_system.after(TransformSystem::TransformPropagate),
+            ),
+        );
+    }
+}
+
+impl CorrectionSmoothingPlugin {
+    /// Restores global transforms offset on the previous frame.
+    ///
+    /// Propagation recomputes only changed transforms, so offsets would accumulate otherwise.
+    fn restoring_system(
+        mut smoothed: Query<&mut CorrectionSmoothing>,
+        mut global_transforms: Query<&mut GlobalTransform>,
+    ) {
+        for mut smoothing in &mut smoothed {
+            for (entity, original) in smoothing.originals.drain(..) {
+                if let Ok(mut global_transform) = global_transforms.get_mut(entity) {
+                    *global_transform = original;
+                }
+            }
+        }
+    }
+
+    /// Decays correction offsets and applies them to [`GlobalTransform`] of entities and their descendants.
+    fn smoothing_system(
+        time: Res<Time>,
+        time_scale: Res<ServerTimeScale>,
+        mut smoothed: Query<(Entity, &mut CorrectionSmoothing)>,
+        mut global_transforms: Query<&mut GlobalTransform>,
+        children: Query<&Children>,
+    ) {
+        for (entity, mut smoothing) in &mut smoothed {
+            if smoothing.offset == Vec3::ZERO {
+                continue;
+            }
+
+            let offset = GlobalTransform::from_translation(smoothing.offset);
+            for entity in [entity]
+                .into_iter()
+                .chain(children.iter_descendants(entity))
+            {
+                if let Ok(mut global_transform) = global_transforms.get_mut(entity) {
+                    smoothing.originals.push((entity, *global_transform));
+                    *global_transform = offset * *global_transform;
+                }
+            }
+
+            smoothing.decay(time_scale.delta(&time));
+        }
+    }
+}
+
+/// Visual offset that hides prediction corrections of an entity.
+///
+/// When reconciliation snaps a predicted entity to the authoritative state, report the correction
+/// with [`Self::add_correction`]. The rendered [`GlobalTransform`] of the entity and its descendants
+/// is shifted back to the predicted position and the offset decays over [`Self::duration`]
+/// of simulation time scaled by [`ServerTimeScale`],
+/// while [`Transform`] stays authoritative for gameplay.
+/// The offset is applied as a world-space translation, so it's intended for entities without parents.
+///
+/// Requires [`CorrectionSmoothingPlugin`].
+#[derive(Component, Clone, Debug)]
+pub struct CorrectionSmoothing {
+    /// Time over which a correction fades out.
+    pub duration: Duration,
+    /// Difference between the rendered and the actual translations.
+    offset: Vec3,
+    /// Global transforms of the entity and its descendants before the offset was applied.
+    originals: Vec<(Entity, GlobalTransform)>,
+}
+
+impl CorrectionSmoothing {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            offset: Vec3::ZERO,
+            originals: Default::default(),
+        }
+    }
+
+    /// Accumulates a correction from `previous` translation to `corrected`.
+    pub fn add_correction(&mut self, previous: Vec3, corrected: Vec3) {
+        self.offset += previous - corrected;
+    }
+
+    /// Returns the current difference between the rendered and the actual translations.
+    pub fn offset(&self) -> Vec3 {
+        self.offset
+    }
+
+    fn decay(&mut self, delta: Duration) {
+        let decay = if self.duration > Duration::ZERO {
+            (1.0 - delta.as_secs_f32() / self.duration.as_secs_f32()).max(0.0)
+        } else {
+            0.0
+        };
+        self.offset *= decay;
+        if self.offset.length_squared() < f32::EPSILON {
+            self.offset = Vec3::ZERO;
+        }
+    }
+}
+
+impl Default for CorrectionSmoothing {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(200))
+    }
+}
+
+/// Marks entity as predicted on client for [`PredictionAppExt::add_misprediction_metrics`].
+///
+/// Should be inserted only on client, it's not replicated.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct Predicted;
+
+/// Magnitude of the difference between a predicted and a received component values.
+pub trait PredictionError {
+    /// Returns the error of this predicted value relative to the `received` one.
+    fn prediction_error(&self, received: &Self) -> f32;
+}
+
+impl PredictionError for Transform {
+    fn prediction_error(&self, received: &Self) -> f32 {
+        self.translation.distance(received.translation)
+    }
+}
+
+/// Predicted values of `C` before replication with their change ticks.
+#[derive(Resource)]
+struct PredictionSnapshots<C> {
+    tolerance: f32,
+    values: HashMap<Entity, (C, Tick)>,
+}
+
+/// Misprediction statistics of all components registered with
+/// [`PredictionAppExt::add_misprediction_metrics`].
+///
+/// Updated on client every frame, can be used to tune prediction windows
+/// and to detect growing drift between server and client simulations.
+#[derive(Default, Resource)]
+pub struct MispredictionDiagnostics {
+    components: HashMap<&'static str, MispredictionStats>,
+}
+
+impl MispredictionDiagnostics {
+    /// Returns statistics of component `C`.
+    pub fn get<C: Component>(&self) -> Option<&MispredictionStats> {
+        self.components.get(any::type_name::<C>())
+    }
+
+    /// Returns statistics of all components with their type names.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &MispredictionStats)> {
+        self.components
+            .iter()
+            .map(|(&type_name, stats)| (type_name, stats))
+    }
+}
+
+/// Mispredictions of a single component over the last second.
+#[derive(Clone, Debug, Default)]
+pub struct MispredictionStats {
+    /// Mispredictions within [`MISPREDICTION_WINDOW`] with their times and errors.
+    samples: VecDeque<(Duration, f32)>,
+    total_count: usize,
+}
+
+/// Period over which per-second statistics are calculated.
+const MISPREDICTION_WINDOW: Duration = Duration::from_secs(1);
+
+impl MispredictionStats {
+    /// Returns the number of mispredictions during the last second.
+    pub fn count_per_second(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns the sum of errors during the last second.
+    pub fn error_per_second(&self) -> f32 {
+        self.samples.iter().map(|&(_, error)| error).sum()
+    }
+
+    /// Returns the average error of mispredictions during the last second.
+    pub fn mean_error(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.error_per_second() / self.samples.len() as f32
+    }
+
+    /// Returns the largest error during the last second.
+    pub fn max_error(&self) -> f32 {
+        self.samples
+            .iter()
+            .map(|&(_, error)| error)
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns the number of mispredictions since connection.
+    pub fn total_count(&self) -> usize {
+        self.total_count
+    }
+
+    fn push(&mut self, time: Duration, error: f32) {
+        self.samples.push_back((time, error));
+        self.total_count += 1;
+    }
+
+    fn cleanup(&mut self, time: Duration) {
+        while self.samples.front().is_some_and(|&(sample_time, _)| {
+            time.saturating_sub(sample_time) >= MISPREDICTION_WINDOW
+        }) {
+            self.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correction_decay() {
+        let mut smoothing = CorrectionSmoothing::new(Duration::from_secs(1));
+        smoothing.add_correction(Vec3::new(2.0, 0.0, 0.0), Vec3::ZERO);
+        assert_eq!(smoothing.offset(), Vec3::new(2.0, 0.0, 0.0));
+
+        smoothing.decay(Duration::from_millis(500));
+        assert_eq!(smoothing.offset(), Vec3::new(1.0, 0.0, 0.0));
+
+        smoothing.decay(Duration::from_secs(1));
+        assert_eq!(smoothing.offset(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn stats_window() {
+        let mut stats = MispredictionStats::default();
+        stats.push(Duration::ZERO, 1.0);
+        stats.push(Duration::from_millis(500), 3.0);
+        stats.cleanup(Duration::from_millis(500));
+        assert_eq!(stats.count_per_second(), 2);
+        assert_eq!(stats.error_per_second(), 4.0);
+        assert_eq!(stats.mean_error(), 2.0);
+        assert_eq!(stats.max_error(), 3.0);
+
+        stats.cleanup(Duration::from_secs(1));
+        assert_eq!(stats.count_per_second(), 1, "old samples should be removed");
+        assert_eq!(stats.total_count(), 2);
+    }
+}
@@ -0,0 +1,281 @@
+use std::any::TypeId;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::{
+    transport::{NetcodeServerTransport, NETCODE_USER_DATA_BYTES},
+    RenetServer, ServerEvent,
+};
+
+use crate::{
+    server::ServerSet,
+    world_diff::{ComponentDiff, ComponentValue, WorldDiff},
+};
+
+/// Negotiates protocol versions of connecting clients and keeps [`ClientProtocolVersions`] up to date.
+///
+/// Clients send [`ProtocolVersion::current`] in the netcode connect token user data.
+/// Server disconnects clients with unsupported versions and sends replicated components to supported
+/// older clients in their legacy representations registered with
+/// [`ReplicationRuleBuilder::legacy`](crate::replication_core::ReplicationRuleBuilder::legacy).
+/// This way server updates can be deployed without kicking every connected player.
+pub struct ProtocolVersionPlugin;
+
+impl Plugin for ProtocolVersionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProtocolVersion>()
+            .init_resource::<ClientProtocolVersions>()
+            .add_systems(
+                PreUpdate,
+                Self::negotiation_system
+                    .in_set(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>()),
+            )
+            .add_systems(
+                PostUpdate,
+                Self::reset_system.run_if(resource_removed::<RenetServer>()),
+            );
+    }
+}
+
+impl ProtocolVersionPlugin {
+    fn negotiation_system(
+        mut server_events: EventReader<ServerEvent>,
+        mut server: ResMut<RenetServer>,
+        mut client_versions: ResMut<ClientProtocolVersions>,
+        transport: Option<Res<NetcodeServerTransport>>,
+        protocol_version: Res<ProtocolVersion>,
+    ) {
+        for event in &mut server_events {
+            match *event {
+                ServerEvent::ClientConnected { client_id } => {
+                    let version = transport
+                        .as_ref()
+                        .and_then(|transport| transport.user_data(client_id))
+                        .map(|user_data| ProtocolVersion::from_user_data(&user_data))
+                        .unwrap_or(protocol_version.current);
+                    if protocol_version.is_supported(version) {
+                        client_versions.0.insert(client_id, version);
+                    } else {
+                        warn!("disconnecting client {client_id} with unsupported protocol version {version}");
+                        server.disconnect(client_id);
+                    }
+                }
+                ServerEvent::ClientDisconnected { client_id, .. } => {
+                    client_versions.0.remove(&client_id);
+                }
+            }
+        }
+    }
+
+    fn reset_system(mut client_versions: ResMut<ClientProtocolVersions>) {
+        client_versions.0.clear();
+    }
+}
+
+/// Version of the replication protocol spoken by this app.
+///
+/// Should be incremented when replicated components change their serialized representation.
+/// Server accepts clients with versions from [`Self::min_supported`] to [`Self::current`].
+#[derive(Clone, Copy, Debug, Default, Resource)]
+pub struct ProtocolVersion {
+    /// Version of this app, sent by clients on connection.
+    pub current: u32,
+    /// Minimum client version accepted by server.
+    pub min_supported: u32,
+}
+
+impl ProtocolVersion {
+    /// Creates a version that accepts only clients with the same version.
+    pub fn new(current: u32) -> Self {
+        Self {
+            current,
+            min_supported: current,
+        }
+    }
+
+    /// Sets minimum client version accepted by server.
+    #[must_use]
+    pub fn with_min_supported(mut self, min_supported: u32) -> Self {
+        self.min_supported = min_supported;
+        self
+    }
+
+    /// Returns `true` if server accepts clients with `version`.
+    pub fn is_supported(&self, version: u32) -> bool {
+        (self.min_supported..=self.current).contains(&version)
+    }
+
+    /// Returns connect token user data with [`Self::current`] in the first 4 bytes.
+    ///
+    /// Used for clients created with [`NetcodeWorldExt`](crate::netcode::NetcodeWorldExt).
+    /// For secure connections, tokens should be generated with this data or with data
+    /// that starts with the version in little-endian.
+    pub fn user_data(&self) -> [u8; NETCODE_USER_DATA_BYTES] {
+        let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+        user_data[..4].copy_from_slice(&self.current.to_le_bytes());
+        user_data
+    }
+
+    /// Reads version from the first 4 bytes of connect token user data.
+    pub fn from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&user_data[..4]);
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Protocol versions of connected clients.
+///
+/// Can be used to send events in a representation supported by a client.
+///
+/// Used only on server.
+#[derive(Default, Deref, Resource)]
+pub struct ClientProtocolVersions(HashMap<u64, u32>);
+
+/// Conversions of replicated components into legacy representations for older clients.
+#[derive(Default, Resource)]
+pub(crate) struct LegacyCodecs(HashMap<TypeId, Vec<(u32, LegacyConversion)>>);
+
+/// Converts a component value into its legacy representation.
+pub(crate) type LegacyConversion = fn(&dyn Reflect) -> Box<dyn Reflect>;
+
+impl LegacyCodecs {
+    /// Registers `conversion` for clients with versions below `since`.
+    pub(crate) fn insert(&mut self, type_id: TypeId, since: u32, conversion: LegacyConversion) {
+        let codecs = self.0.entry(type_id).or_default();
+        codecs.retain(|&(codec_since, _)| codec_since != since);
+        codecs.push((since, conversion));
+        codecs.sort_unstable_by_key(|&(codec_since, _)| codec_since);
+    }
+
+    /// Returns the conversion for the oldest representation that is newer than `version`.
+    fn get(&self, type_id: TypeId, version: u32) -> Option<LegacyConversion> {
+        self.0
+            .get(&type_id)?
+            .iter()
+            .find(|&&(since, _)| version < since)
+            .map(|&(_, conversion)| conversion)
+    }
+
+    /// Replaces changed components of `world_diff` with their representations for `version`.
+    ///
+    /// Component type names are kept, so older clients deserialize legacy values into the types
+    /// they know under these names.
+    fn downgrade(&self, world_diff: &mut WorldDiff, version: u32) {
+        for archetype_diff in &mut world_diff.archetypes {
+            let changed_diffs = archetype_diff
+                .components
+                .iter()
+                .filter(|component_diff| matches!(component_diff, ComponentDiff::Changed(_)));
+            for (index, component_diff) in changed_diffs.enumerate() {
+                let type_id = component_diff.registration().type_id();
+                let Some(conversion) = self.get(type_id, version) else {
+                    continue;
+                };
+                for entity_diff in &mut archetype_diff.entities {
+                    let legacy = conversion(&*entity_diff.components[index]);
+                    entity_diff.components[index] = ComponentValue::Owned(legacy);
+                }
+            }
+        }
+    }
+}
+
+/// Converts components of `world_diff` into representations supported by the client.
+///
+/// Does nothing if the client version is unknown or there are no legacy codecs.
+pub(crate) fn downgrade_for_client(world: &World, client_id: u64, world_diff: &mut WorldDiff) {
+    let Some(codecs) = world.get_resource::<LegacyCodecs>() else {
+        return;
+    };
+    if codecs.0.is_empty() {
+        return;
+    }
+    let Some(&version) = world
+        .get_resource::<ClientProtocolVersions>()
+        .and_then(|client_versions| client_versions.get(&client_id))
+    else {
+        return;
+    };
+    codecs.downgrade(world_diff, version);
+}
+
+/// Converts `T` into its legacy representation `L`.
+pub(crate) fn convert_legacy<T, L>(value: &dyn Reflect) -> Box<dyn Reflect>
+where
+    T: Reflect,
+    L: Reflect + for<'a> From<&'a T>,
+{
+    let value = value
+        .downcast_ref::<T>()
+        .expect("legacy component should be converted from its current type");
+    Box::new(L::from(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any;
+
+    use bevy::{ecs::component::Tick, reflect::TypeRegistryInternal};
+
+    use super::*;
+    use crate::world_diff::{ArchetypeDiff, EntityDiff};
+
+    #[test]
+    fn legacy_selection() {
+        let mut codecs = LegacyCodecs::default();
+        codecs.insert(
+            TypeId::of::<Health>(),
+            3,
+            convert_legacy::<Health, LegacyHealth>,
+        );
+        codecs.insert(
+            TypeId::of::<Health>(),
+            2,
+            convert_legacy::<Health, OldestHealth>,
+        );
+
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Health>();
+        let registration = registry.get(TypeId::of::<Health>()).unwrap();
+        let health = Health(1.0);
+        let value = |version| {
+            let mut world_diff = WorldDiff::new(Tick::new(0));
+            let mut archetype_diff = ArchetypeDiff::new(vec![ComponentDiff::Changed(registration)]);
+            archetype_diff.entities.push(EntityDiff {
+                entity: Entity::PLACEHOLDER,
+                components: vec![ComponentValue::Borrowed(&health)],
+            });
+            world_diff.archetypes.push(archetype_diff);
+            codecs.downgrade(&mut world_diff, version);
+            world_diff.archetypes[0].entities[0].components[0]
+                .type_name()
+                .to_string()
+        };
+
+        assert_eq!(value(1), any::type_name::<OldestHealth>());
+        assert_eq!(value(2), any::type_name::<LegacyHealth>());
+        assert_eq!(value(3), any::type_name::<Health>());
+    }
+
+    #[derive(Reflect)]
+    struct Health(f32);
+
+    #[derive(Reflect)]
+    struct LegacyHealth(f64);
+
+    impl From<&Health> for LegacyHealth {
+        fn from(health: &Health) -> Self {
+            Self(health.0.into())
+        }
+    }
+
+    #[derive(Reflect)]
+    struct OldestHealth(u32);
+
+    impl From<&Health> for OldestHealth {
+        fn from(health: &Health) -> Self {
+            Self(health.0 as u32)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt::Debug;
+
+use bevy::prelude::*;
+use bevy_renet::renet::{RenetServer, SendType};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    network_event::{
+        client_event::{ClientEventAppExt, FromClient},
+        server_event::{SendMode, ServerEventAppExt, ToClients},
+    },
+    server::ServerSet,
+};
+
+/// An extension trait for [`App`] for relaying events between clients through server.
+pub trait RelayEventAppExt {
+    /// Registers event `T` that clients send to each other through server.
+    ///
+    /// Clients send `T` events, server forwards them to all other clients,
+    /// where they are emitted as [`FromPeer<T>`] events.
+    /// If server is also a player, it receives them as [`FromPeer<T>`] too.
+    /// Should be called on both server and clients.
+    fn add_relayed_event<T: Event + Serialize + DeserializeOwned + Debug + Clone>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self;
+
+    /// Same as [`Self::add_relayed_event`], but server forwards only events approved by `validator`.
+    ///
+    /// Validator is called on server with the sender ID and the event.
+    fn add_relayed_event_with<T: Event + Serialize + DeserializeOwned + Debug + Clone>(
+        &mut self,
+        policy: impl Into<SendType>,
+        validator: fn(u64, &T) -> bool,
+    ) -> &mut Self;
+}
+
+impl RelayEventAppExt for App {
+    fn add_relayed_event<T: Event + Serialize + DeserializeOwned + Debug + Clone>(
+        &mut self,
+        policy: impl Into<SendType>,
+    ) -> &mut Self {
+        self.add_relayed_event_with::<T>(policy, |_, _| true)
+    }
+
+    fn add_relayed_event_with<T: Event + Serialize + DeserializeOwned + Debug + Clone>(
+        &mut self,
+        policy: impl Into<SendType>,
+        validator: fn(u64, &T) -> bool,
+    ) -> &mut Self {
+        let policy = policy.into();
+        self.add_client_event::<T>(policy.clone())
+            .add_server_event::<FromPeer<T>>(policy)
+            .insert_resource(RelayValidator(validator))
+            .add_systems(
+                PreUpdate,
+                relay_system::<T>
+                    .after(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>()),
+            )
+    }
+}
+
+/// Forwards events received from clients to all other clients.
+fn relay_system<T: Event + Debug + Clone>(
+    mut client_events: EventReader<FromClient<T>>,
+    mut relayed_events: EventWriter<ToClients<FromPeer<T>>>,
+    validator: Res<RelayValidator<T>>,
+) {
+    for FromClient {
+        client_id, event, ..
+    } in &mut client_events
+    {
+        if !(validator.0)(*client_id, event) {
+            debug!("rejected relayed event {event:?} from client {client_id}");
+            continue;
+        }
+        relayed_events.send(ToClients {
+            mode: SendMode::BroadcastExcept(*client_id),
+            event: FromPeer {
+                peer_id: *client_id,
+                event: event.clone(),
+            },
+        });
+    }
+}
+
+#[derive(Resource)]
+struct RelayValidator<T>(fn(u64, &T) -> bool);
+
+/// An event relayed by server from another peer.
+///
+/// See [`RelayEventAppExt::add_relayed_event`].
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub struct FromPeer<T> {
+    /// ID of the client that sent the event.
+    pub peer_id: u64,
+    pub event: T,
+}
+
+/// Marks server as a relay that performs no simulation.
+///
+/// In this mode authority is hosted by clients: they own entities with
+/// [`Authority::Client`](crate::authority::Authority::Client), send changes of
+/// [client-authoritative](crate::replication_core::ReplicationRuleBuilder::client_authoritative)
+/// components and relayed events, while server only validates and forwards them using
+/// its usual channels, visibility and event routing.
+/// [`LocalAuthority`](crate::authority::LocalAuthority) reports no authority over any entity on relay,
+/// and gameplay systems could be disabled with [`is_relay`] condition.
+///
+/// Should be inserted only on server.
+#[derive(Clone, Copy, Debug, Default, Resource)]
+pub struct RelayServer;
+
+/// Condition that returns `true` if the app is a relay server.
+///
+/// See [`RelayServer`].
+pub fn is_relay() -> impl FnMut(Option<Res<RelayServer>>) -> bool + Clone {
+    move |relay| relay.is_some()
+}
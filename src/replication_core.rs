@@ -1,84 +1,392 @@
+use std::{
+    any::{self, TypeId},
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
 use bevy::{
-    ecs::{archetype::Archetype, component::ComponentId},
+    ecs::{
+        archetype::Archetype, component::ComponentId, entity::MapEntities, query::Has,
+        reflect::ReflectMapEntities,
+    },
     prelude::*,
-    reflect::GetTypeRegistration,
+    reflect::{GetTypeRegistration, ReflectFromPtr, TypeInfo, TypePath},
     utils::{HashMap, HashSet},
 };
-use bevy_renet::renet::{ChannelConfig, SendType};
+use bevy_renet::{
+    renet::{ChannelConfig, ConnectionConfig, RenetServer, SendType, ServerEvent},
+    transport::client_connected,
+};
+#[cfg(feature = "bevy_save")]
+use bevy_save::AppSaveableExt;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    authority::{self, Authority, ComponentValidator},
+    client::{ClientPlugin, ClientSet, ComponentReplicated, ReplicationNotifiers},
+    network_event::{
+        client_event::ClientEventAppExt,
+        server_event::{SendMode, ServerEventAppExt, ToClients},
+        EventMappingFailed, SendPolicy,
+    },
+    protocol_version::{convert_legacy, LegacyCodecs},
+    server::{has_authority, ServerSet, ServerTick},
+};
 
 pub struct ReplicationCorePlugin;
 
 impl Plugin for ReplicationCorePlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Replication>()
+            .register_type::<ReplicationRecursive>()
+            .register_type::<ClientOwned>()
+            .add_event::<EventMappingFailed>()
             .init_resource::<NetworkChannels>()
-            .init_resource::<ReplicationRules>();
+            .init_resource::<ReplicationRules>()
+            .init_resource::<LegacyCodecs>()
+            .init_resource::<ReplicationNotifiers>()
+            .init_resource::<ServerTick>()
+            .register_type::<SpawnGroup>()
+            .replicate::<SpawnGroup>()
+            .register_type::<ReplicationContext>()
+            .replicate::<ReplicationContext>()
+            .register_type::<Authority>()
+            .replicate::<Authority>()
+            .add_server_event::<RuleSetChanged>(SendPolicy::reliable().ordered())
+            .add_server_event::<Kicked>(SendPolicy::reliable().ordered())
+            .add_server_event::<ReplicationToggled>(SendPolicy::reliable().ordered())
+            .add_server_event::<InitialSynced>(SendPolicy::reliable().ordered())
+            .add_server_event::<AuthRequired>(SendPolicy::reliable().ordered())
+            .add_client_event::<AuthRequest>(SendPolicy::reliable().ordered())
+            .add_systems(
+                PostUpdate,
+                (Self::propagation_system, apply_deferred)
+                    .chain()
+                    .run_if(has_authority())
+                    .before(ServerSet::Send),
+            );
+
+        // Saved entities should be replicated after loading.
+        #[cfg(feature = "bevy_save")]
+        app.register_saveable::<Replication>();
+    }
+
+    fn finish(&self, app: &mut App) {
+        let errors = Self::validation_errors(&app.world);
+        if !errors.is_empty() {
+            panic!(
+                "replication registration should be valid:\n{}",
+                errors.join("\n")
+            );
+        }
+    }
+}
+
+impl ReplicationCorePlugin {
+    /// Checks that all replicated components and network events can be sent.
+    ///
+    /// Returns a message for each problem, so all of them could be reported at once
+    /// instead of panicking on the first diff.
+    fn validation_errors(world: &World) -> Vec<String> {
+        let mut errors = Vec::new();
+        let replication_rules = world.resource::<ReplicationRules>();
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let mut component_ids: Vec<_> = replication_rules
+            .replicated
+            .iter()
+            .chain(replication_rules.rule_sets.values().flatten())
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        component_ids.sort_unstable();
+        for component_id in component_ids {
+            let component_info = world
+                .components()
+                .get_info(component_id)
+                .expect("replicated component should be initialized");
+            let name = component_info.name();
+            let Some(registration) = component_info
+                .type_id()
+                .and_then(|type_id| registry.get(type_id))
+            else {
+                errors.push(format!("- {name} is not registered in `AppTypeRegistry`"));
+                continue;
+            };
+            if registration.data::<ReflectComponent>().is_none() {
+                errors.push(format!(
+                    "- {name} has no `ReflectComponent`, add `#[reflect(Component)]`"
+                ));
+            }
+            if registration.data::<ReflectFromPtr>().is_none() {
+                errors.push(format!(
+                    "- {name} has no `ReflectFromPtr`, derive `Reflect` for it"
+                ));
+            }
+            if has_entity_fields(registration.type_info())
+                && registration.data::<ReflectMapEntities>().is_none()
+            {
+                errors.push(format!(
+                    "- {name} contains entities, but has no `ReflectMapEntities`, \
+                    add `#[reflect(MapEntities)]` or use `ReplicationRuleBuilder::mapped`"
+                ));
+            }
+        }
+
+        let network_channels = world.resource::<NetworkChannels>();
+        for (kind, events) in [
+            ("server", &network_channels.server_events),
+            ("client", &network_channels.client_events),
+        ] {
+            let mut registered = HashSet::new();
+            for &event in events {
+                if !registered.insert(event) {
+                    errors.push(format!(
+                        "- {event} is registered as a {kind} event multiple times"
+                    ));
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Marks [`ReplicationRecursive`] entities and all their descendants for replication.
+    ///
+    /// Descendants are marked with [`ReplicationRecursive`] too, so changes of their children are also tracked.
+    #[allow(clippy::type_complexity)]
+    fn propagation_system(
+        mut commands: Commands,
+        roots: Query<
+            (Entity, Has<Replication>),
+            (
+                With<ReplicationRecursive>,
+                Or<(Added<ReplicationRecursive>, Changed<Children>)>,
+            ),
+        >,
+        children: Query<&Children>,
+        marked: Query<(), With<ReplicationRecursive>>,
+    ) {
+        for (root, replicated) in &roots {
+            if !replicated {
+                commands.entity(root).insert(Replication);
+            }
+            for entity in children.iter_descendants(root) {
+                if !marked.contains(entity) {
+                    commands
+                        .entity(entity)
+                        .insert((Replication, ReplicationRecursive));
+                }
+            }
+        }
     }
 }
 
 pub(super) const REPLICATION_CHANNEL_ID: u8 = 0;
 
+/// Server channel for changes of components marked with [`ReplicationRuleBuilder::reliable`].
+pub(super) const RELIABLE_REPLICATION_CHANNEL_ID: u8 = 1;
+
+/// Client channel for entity mappings that should be delivered reliably.
+pub(super) const ENTITY_MAPPING_CHANNEL_ID: u8 = 1;
+
+/// Send type of the channels for events registered with
+/// [`SharedEventAppExt`](crate::network_event::shared_event::SharedEventAppExt).
+const SHARED_EVENTS_SEND_TYPE: SendType = SendType::ReliableOrdered {
+    resend_time: Duration::from_millis(300),
+};
+
 /// A resource to create channels for [`bevy_renet::renet::ConnectionConfig`]
 /// based on number of added server and client events.
-#[derive(Clone, Default, Resource)]
+#[derive(Clone, Resource)]
 pub struct NetworkChannels {
+    /// Send type of the server channel for world diffs.
+    replication: SendType,
     /// Grows with each server event registration.
     server: Vec<SendType>,
     /// Grows with each client event registration.
     client: Vec<SendType>,
+    /// Type names of registered server events.
+    server_events: Vec<&'static str>,
+    /// Type names of registered client events.
+    client_events: Vec<&'static str>,
+    /// ID of the server channel for shared events and the number of assigned tags.
+    shared_server: Option<(u8, u16)>,
+    /// ID of the client channel for shared events and the number of assigned tags.
+    shared_client: Option<(u8, u16)>,
 }
 
 impl NetworkChannels {
+    /// Sets send type of the server channel for world diffs.
+    ///
+    /// By default it's [`SendType::Unreliable`] which is unordered, stale diffs are discarded on client
+    /// by their server ticks. Reliable types trade latency for fewer resends of the same changes.
+    /// Should be set to the same value on server and client before creating them.
+    pub fn set_replication_policy(&mut self, policy: impl Into<SendType>) {
+        self.replication = policy.into();
+    }
+
     pub fn server_channels(&self) -> Vec<ChannelConfig> {
-        channel_configs(&self.server)
+        channel_configs(
+            &[
+                self.replication.clone(),
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(300),
+                },
+            ],
+            &self.server,
+        )
     }
 
     pub fn client_channels(&self) -> Vec<ChannelConfig> {
-        channel_configs(&self.client)
+        channel_configs(
+            &[
+                SendType::Unreliable,
+                SendType::ReliableOrdered {
+                    resend_time: Duration::from_millis(300),
+                },
+            ],
+            &self.client,
+        )
+    }
+
+    /// Returns connection config with channels for both server and client.
+    pub fn connection_config(&self) -> ConnectionConfig {
+        ConnectionConfig {
+            server_channels_config: self.server_channels(),
+            client_channels_config: self.client_channels(),
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn create_client_channel<T>(&mut self, send_type: SendType) -> u8 {
+        self.client_events.push(any::type_name::<T>());
+        self.push_client_channel(send_type)
+    }
+
+    pub(super) fn create_server_channel<T>(&mut self, send_type: SendType) -> u8 {
+        self.server_events.push(any::type_name::<T>());
+        self.push_server_channel(send_type)
+    }
+
+    /// Returns ID of the client channel for shared events and a new tag for `T`.
+    ///
+    /// The channel is created on the first call.
+    pub(super) fn assign_shared_client_tag<T>(&mut self) -> (u8, u16) {
+        self.client_events.push(any::type_name::<T>());
+        let (channel_id, tag) = match self.shared_client {
+            Some(shared) => shared,
+            None => (self.push_client_channel(SHARED_EVENTS_SEND_TYPE), 0),
+        };
+        let next_tag = tag
+            .checked_add(1)
+            .expect("shared client events should not exceed u16::MAX");
+        self.shared_client = Some((channel_id, next_tag));
+        (channel_id, tag)
+    }
+
+    /// Returns ID of the server channel for shared events and a new tag for `T`.
+    ///
+    /// The channel is created on the first call.
+    pub(super) fn assign_shared_server_tag<T>(&mut self) -> (u8, u16) {
+        self.server_events.push(any::type_name::<T>());
+        let (channel_id, tag) = match self.shared_server {
+            Some(shared) => shared,
+            None => (self.push_server_channel(SHARED_EVENTS_SEND_TYPE), 0),
+        };
+        let next_tag = tag
+            .checked_add(1)
+            .expect("shared server events should not exceed u16::MAX");
+        self.shared_server = Some((channel_id, next_tag));
+        (channel_id, tag)
     }
 
-    pub(super) fn create_client_channel(&mut self, send_type: SendType) -> u8 {
-        if self.client.len() == REPLICATION_CHANNEL_ID as usize + u8::MAX as usize {
+    fn push_client_channel(&mut self, send_type: SendType) -> u8 {
+        if self.client.len() == u8::MAX as usize - ENTITY_MAPPING_CHANNEL_ID as usize {
             panic!("max client channels exceeded u8::MAX");
         }
         self.client.push(send_type);
-        self.client.len() as u8 + REPLICATION_CHANNEL_ID
+        self.client.len() as u8 + ENTITY_MAPPING_CHANNEL_ID
     }
 
-    pub(super) fn create_server_channel(&mut self, send_type: SendType) -> u8 {
-        if self.server.len() == REPLICATION_CHANNEL_ID as usize + u8::MAX as usize {
+    fn push_server_channel(&mut self, send_type: SendType) -> u8 {
+        if self.server.len() == u8::MAX as usize - RELIABLE_REPLICATION_CHANNEL_ID as usize {
             panic!("max server channels exceeded u8::MAX");
         }
         self.server.push(send_type);
-        self.server.len() as u8 + REPLICATION_CHANNEL_ID
+        self.server.len() as u8 + RELIABLE_REPLICATION_CHANNEL_ID
     }
 }
 
-fn channel_configs(channels: &[SendType]) -> Vec<ChannelConfig> {
-    let mut channel_configs = Vec::with_capacity(channels.len() + 1);
+impl Default for NetworkChannels {
+    fn default() -> Self {
+        Self {
+            replication: SendType::Unreliable,
+            server: Default::default(),
+            client: Default::default(),
+            server_events: Default::default(),
+            client_events: Default::default(),
+            shared_server: None,
+            shared_client: None,
+        }
+    }
+}
+
+/// Returns `true` if a struct has fields of type [`Entity`].
+///
+/// Such components reference server entities and need mapping on clients.
+fn has_entity_fields(type_info: &TypeInfo) -> bool {
+    let entity_id = TypeId::of::<Entity>();
+    match type_info {
+        TypeInfo::Struct(info) => info.iter().any(|field| field.type_id() == entity_id),
+        TypeInfo::TupleStruct(info) => info.iter().any(|field| field.type_id() == entity_id),
+        _ => false,
+    }
+}
+
+/// Creates configs for `reserved` channels used by replication followed by event `channels`.
+fn channel_configs(reserved: &[SendType], channels: &[SendType]) -> Vec<ChannelConfig> {
     // TODO: Make it configurable.
     // Values from `DefaultChannel::config()`.
-    channel_configs.push(ChannelConfig {
-        channel_id: REPLICATION_CHANNEL_ID,
-        max_memory_usage_bytes: 5 * 1024 * 1024,
-        send_type: SendType::Unreliable,
-    });
-    for (idx, send_type) in channels.iter().enumerate() {
-        channel_configs.push(ChannelConfig {
-            channel_id: REPLICATION_CHANNEL_ID + 1 + idx as u8,
+    reserved
+        .iter()
+        .chain(channels)
+        .enumerate()
+        .map(|(channel_id, send_type)| ChannelConfig {
+            channel_id: channel_id as u8,
             max_memory_usage_bytes: 5 * 1024 * 1024,
             send_type: send_type.clone(),
-        });
-    }
-    channel_configs
+        })
+        .collect()
 }
 
 pub trait AppReplicationExt {
     /// Marks component for replication.
     ///
-    /// Also registers the type in [`AppTypeRegistry`].
+    /// Also registers the type in [`AppTypeRegistry`] and, with the `bevy_save` feature, as saveable
+    /// in `bevy_save`, so the same registration is used for saving and networking.
     /// The component should implement [`Reflect`] and have `#[reflect(Component)]`.
-    fn replicate<T: Component + GetTypeRegistration>(&mut self) -> &mut Self;
+    /// Components with [`Entity`] fields should also have `#[reflect(MapEntities)]`
+    /// or use [`ReplicationRuleBuilder::mapped`]. This is validated together with registered network events
+    /// when plugins are finished, so mistakes are reported on startup instead of during the first diff.
+    /// Returns [`ReplicationRuleBuilder`] to configure the component replication
+    /// that dereferences to [`App`] for further chaining.
+    fn replicate<T: Component + GetTypeRegistration>(&mut self) -> ReplicationRuleBuilder<'_, T>;
+
+    /// Marks component for replication with options declared by [`Replicate`] derive.
+    ///
+    /// Equivalent to calling [`Self::replicate`] with the declared [`ReplicationRuleBuilder`] options.
+    fn replicate_derived<T: Replicate>(&mut self) -> &mut Self;
+
+    /// Same as [`Self::replicate`], but the component is sent only to the owner of the entity.
+    ///
+    /// See [`ReplicationRuleBuilder::owner_only`].
+    fn replicate_to_owner<T: Component + GetTypeRegistration>(
+        &mut self,
+    ) -> ReplicationRuleBuilder<'_, T>;
 
     /// Ignores component `T` replication if component `U` is present on the same entity.
     ///
@@ -86,17 +394,59 @@ pub trait AppReplicationExt {
     /// Could be called multiple times for the same component to disable replication
     /// for different presented components.
     fn not_replicate_if_present<T: Component, U: Component>(&mut self) -> &mut Self;
+
+    /// Marks component for replication only while the specified rule set is active.
+    ///
+    /// Could be called multiple times to add the component to different rule sets.
+    /// See [`ReplicationRules::set_active_rule_set`] for switching.
+    fn replicate_in_rule_set<T: Component + GetTypeRegistration>(
+        &mut self,
+        rule_set: &'static str,
+    ) -> &mut Self;
+
+    /// Runs [`ReplicationSet`] only in the specified state.
+    ///
+    /// Should be called on both server and client.
+    /// While suspended, server accumulates changes and client discards received diffs,
+    /// so after resumption all changes since the last acknowledged tick will be sent.
+    fn replicate_in_state<S: States>(&mut self, state: S) -> &mut Self;
+
+    /// Mirrors state `S` from server to clients.
+    ///
+    /// Server sends the current state to newly connected clients and each transition after that.
+    /// Clients set received states into [`NextState<S>`], so [`OnEnter`], [`OnExit`] and [`OnTransition`]
+    /// schedules run just like for local transitions. If multiple transitions arrive at once,
+    /// only the last one is applied. The state is added if it wasn't.
+    /// Should be called on both server and client.
+    fn replicate_state<S: States + Serialize + DeserializeOwned>(&mut self) -> &mut Self;
 }
 
 impl AppReplicationExt for App {
-    fn replicate<T: Component + GetTypeRegistration>(&mut self) -> &mut Self {
+    fn replicate<T: Component + GetTypeRegistration>(&mut self) -> ReplicationRuleBuilder<'_, T> {
         self.register_type::<T>();
+        #[cfg(feature = "bevy_save")]
+        self.register_saveable::<T>();
         let component_id = self.world.init_component::<T>();
         let mut replication_rules = self.world.resource_mut::<ReplicationRules>();
         replication_rules.replicated.insert(component_id);
+        ReplicationRuleBuilder {
+            app: self,
+            component_id,
+            marker: PhantomData,
+        }
+    }
+
+    fn replicate_derived<T: Replicate>(&mut self) -> &mut Self {
+        T::replicate(self);
         self
     }
 
+    fn replicate_to_owner<T: Component + GetTypeRegistration>(
+        &mut self,
+    ) -> ReplicationRuleBuilder<'_, T> {
+        self.replicate::<T>().owner_only()
+    }
+
     fn not_replicate_if_present<T: Component, U: Component>(&mut self) -> &mut Self {
         let ignore_id = self.world.init_component::<T>();
         let present_id = self.world.init_component::<U>();
@@ -108,8 +458,296 @@ impl AppReplicationExt for App {
             .push(present_id);
         self
     }
+
+    fn replicate_in_rule_set<T: Component + GetTypeRegistration>(
+        &mut self,
+        rule_set: &'static str,
+    ) -> &mut Self {
+        self.register_type::<T>();
+        #[cfg(feature = "bevy_save")]
+        self.register_saveable::<T>();
+        let component_id = self.world.init_component::<T>();
+        let mut replication_rules = self.world.resource_mut::<ReplicationRules>();
+        replication_rules
+            .rule_sets
+            .entry(rule_set)
+            .or_default()
+            .insert(component_id);
+        self
+    }
+
+    fn replicate_in_state<S: States>(&mut self, state: S) -> &mut Self {
+        self.configure_set(PreUpdate, ReplicationSet.run_if(in_state(state.clone())))
+            .configure_set(PostUpdate, ReplicationSet.run_if(in_state(state.clone())))
+            .add_systems(
+                PreUpdate,
+                ClientPlugin::diff_discarding_system
+                    .in_set(ClientSet::Receive)
+                    .run_if(client_connected())
+                    .run_if(not(in_state(state))),
+            )
+    }
+
+    fn replicate_state<S: States + Serialize + DeserializeOwned>(&mut self) -> &mut Self {
+        if !self.world.contains_resource::<State<S>>() {
+            self.add_state::<S>();
+        }
+
+        self.add_server_event::<StateReplicated<S>>(SendPolicy::reliable().ordered())
+            .add_systems(
+                PreUpdate,
+                state_receiving_system::<S>
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected()),
+            )
+            .add_systems(
+                PostUpdate,
+                state_sending_system::<S>
+                    .before(ServerSet::Send)
+                    .run_if(resource_exists::<RenetServer>()),
+            )
+    }
+}
+
+/// Sends transitions of state `S` to all clients and the current state to newly connected clients.
+fn state_sending_system<S: States>(
+    mut server_events: EventReader<ServerEvent>,
+    mut state_events: EventWriter<ToClients<StateReplicated<S>>>,
+    state: Res<State<S>>,
+) {
+    if state.is_changed() {
+        // Newly connected clients will receive the broadcast.
+        server_events.clear();
+        state_events.send(ToClients {
+            mode: SendMode::Broadcast,
+            event: StateReplicated(state.get().clone()),
+        });
+    } else {
+        for event in &mut server_events {
+            if let ServerEvent::ClientConnected { client_id } = *event {
+                state_events.send(ToClients {
+                    mode: SendMode::Direct(client_id),
+                    event: StateReplicated(state.get().clone()),
+                });
+            }
+        }
+    }
+}
+
+/// Queues the last received state of `S`.
+fn state_receiving_system<S: States>(
+    mut state_events: EventReader<StateReplicated<S>>,
+    mut next_state: ResMut<NextState<S>>,
+) {
+    if let Some(StateReplicated(state)) = state_events.iter().last() {
+        debug!("received state {state:?}");
+        next_state.set(state.clone());
+    }
+}
+
+/// A component with replication options declared next to its definition.
+///
+/// Usually derived with `#[derive(Replicate)]` and registered with [`AppReplicationExt::replicate_derived`].
+/// Options are set with the `#[replicate(...)]` attribute and correspond to [`ReplicationRuleBuilder`] methods:
+/// `once`, `reliable`, `owner_only`, `mapped`, `notify` and `max_lod = N`.
+/// Entity priority is not a component option, use [`ReplicationPriority`](crate::server::ReplicationPriority) for it.
+///
+/// ```
+/// use bevy::{
+///     ecs::entity::{EntityMapper, MapEntities},
+///     prelude::*,
+/// };
+/// use bevy_replicon::prelude::*;
+///
+/// #[derive(Component, Reflect, Replicate)]
+/// #[reflect(Component)]
+/// #[replicate(once, mapped)]
+/// struct Owner(Entity);
+///
+/// impl MapEntities for Owner {
+///     fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+///         self.0 = entity_mapper.get_or_reserve(self.0);
+///     }
+/// }
+/// # impl FromWorld for Owner {
+/// #     fn from_world(_world: &mut World) -> Self {
+/// #         Self(Entity::PLACEHOLDER)
+/// #     }
+/// # }
+///
+/// # let mut app = App::new();
+/// # app.add_plugins(ReplicationPlugins);
+/// app.replicate_derived::<Owner>();
+/// ```
+pub trait Replicate: Component + GetTypeRegistration {
+    /// Marks the component for replication with the declared options.
+    fn replicate(app: &mut App);
+}
+
+pub use bevy_replicon_derive::Replicate;
+
+/// Options for a component marked for replication with [`AppReplicationExt::replicate`].
+///
+/// Dereferences to [`App`], so other methods could be chained after the options.
+pub struct ReplicationRuleBuilder<'a, T> {
+    app: &'a mut App,
+    component_id: ComponentId,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> ReplicationRuleBuilder<'_, T> {
+    /// Replicates the component only on insertion, later changes are not sent.
+    ///
+    /// Useful for components that are set once on spawn, like names or colors.
+    pub fn once(self) -> Self {
+        let mut replication_rules = self.app.world.resource_mut::<ReplicationRules>();
+        replication_rules.once.insert(self.component_id);
+        self
+    }
+
+    /// Sends changes of the component over a reliable channel instead of the unreliable one.
+    ///
+    /// Useful for gameplay-critical data where a lost final value is unacceptable, like scores,
+    /// inventories or match states. Each change is sent once and delivered in order at the cost of latency,
+    /// while unreliable changes are resent until the client acknowledges them.
+    /// Removals of the component are sent over the same channel, so insertions, changes and removals
+    /// of it arrive in the same order as on server. Despawns are still sent unreliably.
+    pub fn reliable(self) -> Self {
+        let mut replication_rules = self.app.world.resource_mut::<ReplicationRules>();
+        replication_rules.reliable.insert(self.component_id);
+        self
+    }
+
+    /// Replicates the component only for clients for which the entity LOD doesn't exceed `lod`.
+    ///
+    /// LODs are set per client and entity with [`ClientLods`](crate::server::ClientLods).
+    /// By default components are replicated on all LODs.
+    pub fn max_lod(self, lod: u8) -> Self {
+        let mut replication_rules = self.app.world.resource_mut::<ReplicationRules>();
+        replication_rules.max_lods.insert(self.component_id, lod);
+        self
+    }
+
+    /// Replicates the component only to the client that owns the entity according to [`ClientOwned`].
+    ///
+    /// Other clients never receive the component, even when the entity is visible to them.
+    /// Useful for private data, like inventories, hands of cards or ability cooldowns.
+    /// When [`ClientOwned`] changes, the component is sent to the new owner and removed from other clients.
+    pub fn owner_only(self) -> Self {
+        let mut replication_rules = self.app.world.resource_mut::<ReplicationRules>();
+        replication_rules.owner_only.insert(self.component_id);
+        self
+    }
+
+    /// Same as [`AppReplicationExt::not_replicate_if_present`] for this component.
+    pub fn not_if_present<U: Component>(self) -> Self {
+        self.app.not_replicate_if_present::<T, U>();
+        self
+    }
+
+    /// Sends the component as `L` to clients with [`ProtocolVersion::current`](crate::protocol_version::ProtocolVersion::current)
+    /// below `since`.
+    ///
+    /// `L` should mirror the layout the component had before version `since`, it's serialized
+    /// under the component type name, so older clients deserialize it into their version of the component.
+    /// Can be called multiple times to support several older versions, each client receives
+    /// the oldest representation that is newer than its version.
+    /// See [`ProtocolVersionPlugin`](crate::protocol_version::ProtocolVersionPlugin) for version negotiation.
+    pub fn legacy<L>(self, since: u32) -> Self
+    where
+        T: Reflect,
+        L: Reflect + GetTypeRegistration + for<'a> From<&'a T>,
+    {
+        self.app.register_type::<L>();
+        let mut codecs = self.app.world.resource_mut::<LegacyCodecs>();
+        codecs.insert(TypeId::of::<T>(), since, convert_legacy::<T, L>);
+        self
+    }
+
+    /// Makes the component authoritative on clients for entities they own.
+    ///
+    /// Ownership is defined by [`Authority::Client`]. Server inserts the component, after that
+    /// the owning client sends its changes to server with a reliable ordered channel and server applies them
+    /// and replicates to other clients, but not back to the owner. Useful for cursor positions, emotes
+    /// or trusted-client tools. Changes from clients without authority over the entity are ignored.
+    ///
+    /// Should be called on both server and client.
+    pub fn client_authoritative(self) -> Self
+    where
+        T: Clone + Debug + Serialize + DeserializeOwned,
+    {
+        self.add_client_authoritative(None)
+    }
+
+    /// Same as [`Self::client_authoritative`], but server accepts only changes approved by `validator`.
+    ///
+    /// Rejected changes are corrected on the owner by sending it the current server value
+    /// with a reliable ordered channel.
+    pub fn client_authoritative_with(self, validator: ComponentValidator<T>) -> Self
+    where
+        T: Clone + Debug + Serialize + DeserializeOwned,
+    {
+        self.add_client_authoritative(Some(validator))
+    }
+
+    fn add_client_authoritative(self, validator: Option<ComponentValidator<T>>) -> Self
+    where
+        T: Clone + Debug + Serialize + DeserializeOwned,
+    {
+        authority::add_client_authoritative::<T>(self.app, validator);
+        let mut replication_rules = self.app.world.resource_mut::<ReplicationRules>();
+        replication_rules
+            .client_authoritative
+            .insert(self.component_id);
+        self
+    }
+
+    /// Emits [`ComponentReplicated<T>`] on client when replication inserts, updates or removes the component.
+    ///
+    /// Useful for systems that react only to network-driven changes, since change detection also fires for local edits.
+    pub fn notify(self) -> Self {
+        self.app.add_event::<ComponentReplicated<T>>();
+        self.app
+            .world
+            .resource_mut::<ReplicationNotifiers>()
+            .insert::<T>();
+        self
+    }
+
+    /// Registers [`ReflectMapEntities`] for the component, so `#[reflect(MapEntities)]` could be omitted.
+    pub fn mapped(self) -> Self
+    where
+        T: MapEntities + Reflect + TypePath,
+    {
+        self.app.register_type_data::<T, ReflectMapEntities>();
+        self
+    }
+}
+
+impl<T> Deref for ReplicationRuleBuilder<'_, T> {
+    type Target = App;
+
+    fn deref(&self) -> &Self::Target {
+        self.app
+    }
 }
 
+impl<T> DerefMut for ReplicationRuleBuilder<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.app
+    }
+}
+
+/// Set with world diff sending on server and applying on client.
+///
+/// On client each received diff is applied in a single exclusive system,
+/// so systems outside of the set never observe a partially applied server tick.
+/// Network events are not included.
+/// Runs in `PostUpdate` on server as part of [`ServerSet::Send`]
+/// and in `PreUpdate` on client as part of [`ClientSet::Receive`].
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct ReplicationSet;
+
 /// Contains [`ComponentId`]'s that used to decide
 /// if a component should be replicated.
 #[derive(Resource)]
@@ -120,6 +758,30 @@ pub struct ReplicationRules {
     /// Ignore a key component if any of its value components are present in an archetype.
     ignored_if_present: HashMap<ComponentId, Vec<ComponentId>>,
 
+    /// Components that should be replicated only on insertion.
+    once: HashSet<ComponentId>,
+
+    /// Components that should be sent over the reliable channel.
+    reliable: HashSet<ComponentId>,
+
+    /// Maximum LODs for components that are not replicated on all LODs.
+    max_lods: HashMap<ComponentId, u8>,
+
+    /// Components whose changes are sent by clients that own entities.
+    client_authoritative: HashSet<ComponentId>,
+
+    /// Components that should be sent only to owners of entities.
+    owner_only: HashSet<ComponentId>,
+
+    /// Components that should be replicated only while their rule set is active.
+    rule_sets: HashMap<&'static str, HashSet<ComponentId>>,
+
+    /// Name of the active rule set from [`Self::rule_sets`].
+    active_rule_set: Option<&'static str>,
+
+    /// Components whose replication is temporarily disabled.
+    pub(super) disabled: HashSet<ComponentId>,
+
     /// ID of [`Replication`] component, only entities with this components should be replicated.
     replication_id: ComponentId,
 }
@@ -130,13 +792,178 @@ impl ReplicationRules {
         archetype.contains(self.replication_id)
     }
 
+    /// Returns components that are always replicated.
+    ///
+    /// All replicated components are serialized with reflection and sent over the first unreliable
+    /// channel from [`NetworkChannels::server_channels`] or the second reliable channel if marked with
+    /// [`ReplicationRuleBuilder::reliable`], other channels are used by events.
+    pub fn replicated_components(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.replicated.iter().copied()
+    }
+
+    /// Returns components whose presence disables replication of the component.
+    ///
+    /// See [`AppReplicationExt::not_replicate_if_present`].
+    pub fn ignored_if_present(&self, component_id: ComponentId) -> &[ComponentId] {
+        self.ignored_if_present
+            .get(&component_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the component is replicated only on insertion.
+    ///
+    /// See [`ReplicationRuleBuilder::once`].
+    pub fn is_once(&self, component_id: ComponentId) -> bool {
+        self.once.contains(&component_id)
+    }
+
+    /// Returns `true` if the component is sent over the reliable channel.
+    ///
+    /// See [`ReplicationRuleBuilder::reliable`].
+    pub fn is_reliable(&self, component_id: ComponentId) -> bool {
+        self.reliable.contains(&component_id)
+    }
+
+    /// Returns the maximum LOD on which the component is replicated.
+    ///
+    /// See [`ReplicationRuleBuilder::max_lod`].
+    pub fn max_lod(&self, component_id: ComponentId) -> u8 {
+        self.max_lods.get(&component_id).copied().unwrap_or(u8::MAX)
+    }
+
+    /// Returns `true` if changes of the component are sent by clients that own entities.
+    ///
+    /// See [`ReplicationRuleBuilder::client_authoritative`].
+    pub fn is_client_authoritative(&self, component_id: ComponentId) -> bool {
+        self.client_authoritative.contains(&component_id)
+    }
+
+    /// Returns `true` if the component should be sent only to the owner of the entity.
+    ///
+    /// See [`ReplicationRuleBuilder::owner_only`].
+    pub fn is_owner_only(&self, component_id: ComponentId) -> bool {
+        self.owner_only.contains(&component_id)
+    }
+
+    /// Returns `true` if the component is marked for replication directly or in any rule set.
+    #[cfg(debug_assertions)]
+    pub(crate) fn is_marked(&self, component_id: ComponentId) -> bool {
+        self.replicated.contains(&component_id)
+            || self
+                .rule_sets
+                .values()
+                .any(|components| components.contains(&component_id))
+    }
+
+    /// Returns registered rule sets with their components.
+    ///
+    /// See [`AppReplicationExt::replicate_in_rule_set`].
+    pub fn rule_sets(&self) -> impl Iterator<Item = (&'static str, &HashSet<ComponentId>)> {
+        self.rule_sets
+            .iter()
+            .map(|(&rule_set, components)| (rule_set, components))
+    }
+
+    /// Returns name of the active rule set.
+    pub fn active_rule_set(&self) -> Option<&'static str> {
+        self.active_rule_set
+    }
+
+    /// Switches the active rule set, [`None`] disables all rule sets.
+    ///
+    /// Should be changed on server, clients will switch automatically and receive [`RuleSetChanged`].
+    /// Components that start replicating will be sent for all replicated entities.
+    /// Components that stop replicating will no longer be updated, but not removed on clients.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rule set wasn't registered with [`AppReplicationExt::replicate_in_rule_set`].
+    pub fn set_active_rule_set(&mut self, rule_set: Option<&str>) {
+        self.active_rule_set = rule_set.map(|rule_set| {
+            let (&name, _) = self
+                .rule_sets
+                .get_key_value(rule_set)
+                .unwrap_or_else(|| panic!("rule set {rule_set} should be registered"));
+            name
+        });
+    }
+
+    /// Returns `true` if replication of the component wasn't disabled with [`Self::set_component_enabled`].
+    pub fn is_component_enabled(&self, component_id: ComponentId) -> bool {
+        !self.disabled.contains(&component_id)
+    }
+
+    /// Returns `true` if the component is replicated with the rule set.
+    ///
+    /// Components that are replicated regardless of rule sets are not included.
+    pub(super) fn is_in_rule_set(&self, component_id: ComponentId, rule_set: Option<&str>) -> bool {
+        rule_set
+            .and_then(|rule_set| self.rule_sets.get(rule_set))
+            .is_some_and(|components| components.contains(&component_id))
+    }
+
+    /// Returns components whose replication is disabled.
+    pub fn disabled_components(&self) -> impl Iterator<Item = ComponentId> + '_ {
+        self.disabled.iter().copied()
+    }
+
+    /// Temporarily disables or enables replication of a registered component.
+    ///
+    /// Should be changed on server, clients will update automatically and receive [`ReplicationToggled`].
+    /// While disabled, the component is not updated on clients, so they could change it locally,
+    /// like animating [`Transform`] during a cutscene. Insertions and removals are not sent too.
+    /// When enabled again, current values of the component will be sent for all replicated entities
+    /// and the component will be removed on clients from entities that lost it while it was disabled.
+    pub fn set_component_enabled(&mut self, component_id: ComponentId, enabled: bool) {
+        if enabled {
+            self.disabled.remove(&component_id);
+        } else {
+            self.disabled.insert(component_id);
+        }
+    }
+
+    /// Returns components that are replicated with the rule sets when `disabled` components are excluded.
+    pub(super) fn active_components<'a>(
+        &'a self,
+        rule_sets: &'a HashSet<&'static str>,
+        disabled: &'a HashSet<ComponentId>,
+    ) -> impl Iterator<Item = ComponentId> + 'a {
+        let rule_set_components = rule_sets
+            .iter()
+            .filter_map(|rule_set| self.rule_sets.get(rule_set))
+            .flatten();
+        self.replicated
+            .iter()
+            .chain(rule_set_components)
+            .copied()
+            .filter(|component_id| !disabled.contains(component_id))
+    }
+
     /// Returns `true` if a component of an archetype should be replicated.
     pub fn is_replicated_component(
         &self,
         archetype: &Archetype,
         component_id: ComponentId,
     ) -> bool {
-        if self.replicated.contains(&component_id) {
+        self.is_replicated_with_rule_sets(archetype, component_id, self.active_rule_set)
+    }
+
+    /// Same as [`Self::is_replicated_component`], but checks the specified rule sets instead of the active one.
+    pub(super) fn is_replicated_with_rule_sets<'a>(
+        &self,
+        archetype: &Archetype,
+        component_id: ComponentId,
+        rule_sets: impl IntoIterator<Item = &'a str>,
+    ) -> bool {
+        if self.disabled.contains(&component_id) {
+            return false;
+        }
+
+        let in_rule_sets = rule_sets
+            .into_iter()
+            .any(|rule_set| self.is_in_rule_set(component_id, Some(rule_set)));
+        if self.replicated.contains(&component_id) || in_rule_sets {
             if let Some(ignore_ids) = self.ignored_if_present.get(&component_id) {
                 for &ignore_id in ignore_ids {
                     if archetype.contains(ignore_id) {
@@ -156,12 +983,114 @@ impl FromWorld for ReplicationRules {
         Self {
             replicated: Default::default(),
             ignored_if_present: Default::default(),
+            once: Default::default(),
+            reliable: Default::default(),
+            max_lods: Default::default(),
+            client_authoritative: Default::default(),
+            owner_only: Default::default(),
+            rule_sets: Default::default(),
+            active_rule_set: None,
+            disabled: Default::default(),
             replication_id: world.init_component::<Replication>(),
         }
     }
 }
 
+/// An event that emitted on clients when server toggles replication of a component.
+///
+/// Emitted for newly connected clients too for each disabled component.
+/// See [`ReplicationRules::set_component_enabled`] for details.
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+pub struct ReplicationToggled {
+    /// Type name of the component.
+    pub component: String,
+    /// Whether replication was enabled or disabled.
+    pub enabled: bool,
+}
+
+/// An event that emitted on clients when server switches the active rule set.
+///
+/// Emitted for newly connected clients too if a rule set is active.
+/// See [`ReplicationRules::set_active_rule_set`] for details.
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+pub struct RuleSetChanged {
+    /// Name of the new active rule set.
+    pub rule_set: Option<String>,
+}
+
+/// A server event with the current value of a state registered with [`AppReplicationExt::replicate_state`].
+#[derive(Debug, Deserialize, Event, Serialize)]
+struct StateReplicated<S>(S);
+
+/// A server event that asks the client to disconnect.
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub(super) enum Kicked {
+    /// Sent on [`KickClient`](crate::server::KickClient) with its app-level code.
+    Code(u32),
+    /// Sent on [`ClientRejected`](crate::server::ClientRejected).
+    ServerFull,
+    /// Sent on [`ClientAuthRejected`](crate::server::ClientAuthRejected) with its app-level code.
+    AuthRejected(u32),
+}
+
+/// A server event sent to a client together with [`ClientSynced`](crate::server::ClientSynced).
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub(super) struct InitialSynced;
+
+/// A server event sent to connected clients if [`ServerAuth`](crate::server::ServerAuth) is present.
+#[derive(Clone, Copy, Debug, Deserialize, Event, Serialize)]
+pub(super) struct AuthRequired;
+
+/// A client event with [`AuthCredentials`](crate::client::AuthCredentials) sent once after connection.
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+pub(super) struct AuthRequest {
+    pub(super) credentials: Vec<u8>,
+}
+
 /// Marks entity for replication.
 #[derive(Component, Default, Reflect, Clone, Copy)]
 #[reflect(Component)]
 pub struct Replication;
+
+/// Marks entity and all its descendants for replication.
+///
+/// Children added later are marked automatically on server by inserting [`Replication`]
+/// and this component, entities keep them after detaching from the hierarchy.
+/// To replicate the hierarchy itself use [`ParentSync`](crate::parent_sync::ParentSync).
+#[derive(Component, Default, Reflect, Clone, Copy)]
+#[reflect(Component)]
+pub struct ReplicationRecursive;
+
+/// Marks entity as owned by a client with the specified ID.
+///
+/// Used on server to prioritize replication of the entity for its owner
+/// and to send [owner-only](ReplicationRuleBuilder::owner_only) components.
+#[derive(Component, Default, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct ClientOwned(pub u64);
+
+/// Marks entities that should be spawned on client in the same frame.
+///
+/// Useful for composite objects, like a vehicle with turret children. All entities with the same
+/// group ID that are spawned in the same tick will never be split by [`SpawnBudget`](crate::client::SpawnBudget)
+/// or [`ChunkStreaming`](crate::server::ChunkStreaming) budgets.
+#[derive(Component, Default, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct SpawnGroup(pub u64);
+
+/// Replication context of an entity.
+///
+/// Contexts are independent entity spaces multiplexed over a single connection, like a persistent
+/// meta world with parties and chat presence and the world of the current match.
+/// Entities without this component belong to [`ReplicationContext::DEFAULT`].
+/// Clients receive only entities of contexts they joined, each context could have its own send
+/// interval and rule set, see [`ReplicationContexts`](crate::server::ReplicationContexts).
+/// The component itself is replicated, so clients could query entities of a context.
+#[derive(Component, Default, Reflect, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[reflect(Component)]
+pub struct ReplicationContext(pub u8);
+
+impl ReplicationContext {
+    /// Context of entities without [`ReplicationContext`], all clients join it on connection.
+    pub const DEFAULT: Self = Self(0);
+}
@@ -1,48 +1,122 @@
+pub(super) mod chunk_streaming;
+pub(super) mod client_auth;
+pub(super) mod client_groups;
+pub(super) mod client_lods;
+pub(super) mod client_tags;
+pub(super) mod client_visibility;
+pub(super) mod congestion;
 pub(super) mod despawn_tracker;
+pub(super) mod diff_budget;
+pub(super) mod diff_size;
+pub(super) mod interest_limit;
 pub(super) mod removal_tracker;
+pub(super) mod replication_contexts;
+pub(super) mod replication_priority;
+pub(super) mod server_slots;
+pub(super) mod traffic_log;
+pub(super) mod unreplicated_diagnostics;
 
-use std::time::Duration;
+use std::{mem, time::Duration};
 
 use bevy::{
     ecs::{
-        archetype::ArchetypeId,
-        component::{ComponentTicks, StorageType, Tick},
+        archetype::{ArchetypeEntity, ArchetypeId, Archetypes},
+        component::{ComponentId, ComponentTicks, Components, StorageType, Tick},
+        entity::{Entities, EntityMap, MapEntities},
+        storage::{Column, ComponentSparseSet, Table},
         system::SystemChangeTick,
-        world::EntityRef,
     },
     prelude::*,
-    reflect::TypeRegistryInternal,
+    reflect::{ReflectFromPtr, TypeRegistration, TypeRegistryInternal},
+    tasks::ComputeTaskPool,
     time::common_conditions::on_timer,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bevy_renet::{
     renet::{RenetClient, RenetServer, ServerEvent},
     transport::NetcodeServerPlugin,
     RenetServerPlugin,
 };
-use derive_more::Constructor;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    client::LastTick,
-    replication_core::{ReplicationRules, REPLICATION_CHANNEL_ID},
-    world_diff::{ComponentDiff, WorldDiff, WorldDiffSerializer},
+    authority::Authority,
+    client::{self, ReplicationAck},
+    network_event::server_event::{SendMode, ToClients},
+    protocol_version::downgrade_for_client,
+    replication_core::{
+        ClientOwned, InitialSynced, Kicked, Replication, ReplicationContext, ReplicationRules,
+        ReplicationSet, ReplicationToggled, RuleSetChanged, ENTITY_MAPPING_CHANNEL_ID,
+        RELIABLE_REPLICATION_CHANNEL_ID, REPLICATION_CHANNEL_ID,
+    },
+    transport_switch::TransportSwitch,
+    wire_format::serialize_message,
+    world_diff::{
+        ArchetypeDiff, ComponentDiff, ComponentValue, DespawnReason, EntityDiff, WorldDiff,
+        WorldDiffSerializer,
+    },
 };
+use chunk_streaming::ChunkStreamingPlugin;
+pub use chunk_streaming::{ChunkStreaming, ReplicationChunk};
+use client_auth::ClientAuthPlugin;
+pub use client_auth::{AuthValidator, ClientAuthRejected, ClientAuthenticated, ServerAuth};
+pub use client_groups::ClientGroups;
+pub use client_lods::ClientLods;
+use client_lods::ClientLodsPlugin;
+use client_tags::ClientTagsPlugin;
+pub use client_tags::{ClientTags, TagFilter, TagVisibility};
+use client_visibility::ClientVisibilityPlugin;
+pub use client_visibility::{ClientVisibility, LeavePolicy, VisibilityPolicy};
+pub use congestion::{CatchUpPolicy, ClientCongestion, ClientSendRates, CongestionPolicy};
+use congestion::{CongestionPlugin, DeferralChange};
+pub use despawn_tracker::DespawnWithReasonExt;
 use despawn_tracker::{DespawnTracker, DespawnTrackerPlugin};
+pub use diff_budget::DiffBuildBudget;
+use diff_budget::{collect_budgeted_messages, DiffBudgetPlugin};
+pub use diff_size::{DiffSize, DiffSizeWorldExt};
+pub use interest_limit::InterestLimit;
+use interest_limit::InterestLimitPlugin;
 use removal_tracker::{RemovalTracker, RemovalTrackerPlugin};
+pub use replication_contexts::ReplicationContexts;
+use replication_contexts::ReplicationContextsPlugin;
+use replication_priority::entity_priority;
+pub use replication_priority::{DistanceFalloff, MessageEntityLimit, ReplicationPriority};
+use server_slots::ServerSlotsPlugin;
+pub use server_slots::{ClientRejected, ServerSlots};
+use traffic_log::{ack_summary, diff_summary, TrafficLogPlugin};
+pub use traffic_log::{CapturedMessage, TrafficDirection, TrafficLog, TrafficRecord};
+pub use unreplicated_diagnostics::UnreplicatedDiagnostics;
+use unreplicated_diagnostics::UnreplicatedDiagnosticsPlugin;
 
 pub const SERVER_ID: u64 = 0;
 
-#[derive(Constructor)]
 pub struct ServerPlugin {
     tick_policy: TickPolicy,
+    catch_up_policy: CatchUpPolicy,
 }
 
-impl Default for ServerPlugin {
-    fn default() -> Self {
+impl ServerPlugin {
+    pub fn new(tick_policy: TickPolicy) -> Self {
         Self {
-            tick_policy: TickPolicy::MaxTickRate(30),
+            tick_policy,
+            catch_up_policy: Default::default(),
         }
     }
+
+    /// Sets how server throttles replication for clients that lag behind.
+    ///
+    /// Inserted as a resource, so it can be changed at runtime too.
+    #[must_use]
+    pub fn with_catch_up_policy(mut self, catch_up_policy: CatchUpPolicy) -> Self {
+        self.catch_up_policy = catch_up_policy;
+        self
+    }
+}
+
+impl Default for ServerPlugin {
+    fn default() -> Self {
+        Self::new(TickPolicy::MaxTickRate(30))
+    }
 }
 
 impl Plugin for ServerPlugin {
@@ -52,8 +126,29 @@ impl Plugin for ServerPlugin {
             NetcodeServerPlugin,
             RemovalTrackerPlugin,
             DespawnTrackerPlugin,
+            ClientVisibilityPlugin,
+            ClientLodsPlugin,
+            ChunkStreamingPlugin,
+            CongestionPlugin,
+            DiffBudgetPlugin,
+            UnreplicatedDiagnosticsPlugin,
+            ClientTagsPlugin,
+            InterestLimitPlugin,
+            TrafficLogPlugin,
+            ServerSlotsPlugin,
+            ClientAuthPlugin,
         ))
+        .add_plugins(ReplicationContextsPlugin)
+        .register_type::<ReplicationPriority>()
         .init_resource::<AckedTicks>()
+        .init_resource::<ClientGroups>()
+        .init_resource::<ClientEntityMaps>()
+        .init_resource::<DistanceFalloff>()
+        .init_resource::<ServerTick>()
+        .insert_resource(self.catch_up_policy)
+        .init_resource::<ClientSyncs>()
+        .add_event::<KickClient>()
+        .add_event::<ClientSynced>()
         .configure_set(
             PreUpdate,
             ServerSet::Receive.after(NetcodeServerPlugin::update_system),
@@ -64,15 +159,33 @@ impl Plugin for ServerPlugin {
         )
         .add_systems(
             PreUpdate,
-            (Self::acks_receiving_system, Self::acks_cleanup_system)
+            (
+                Self::acks_receiving_system,
+                Self::entity_mappings_receiving_system,
+                Self::client_entity_maps_cleanup_system,
+                Self::acks_cleanup_system,
+            )
+                .chain()
                 .in_set(ServerSet::Receive)
                 .run_if(resource_exists::<RenetServer>()),
         )
         .add_systems(
             PostUpdate,
             (
+                (
+                    Self::rule_set_sending_system,
+                    Self::toggle_sending_system,
+                    Self::kick_system,
+                )
+                    .before(ServerSet::Send)
+                    .run_if(resource_exists::<RenetServer>()),
+                Self::tick_increment_system
+                    .in_set(ServerSet::Send)
+                    .run_if(has_authority())
+                    .before(Self::diffs_sending_system),
                 Self::diffs_sending_system
                     .in_set(ServerSet::Send)
+                    .in_set(ReplicationSet)
                     .run_if(resource_exists::<RenetServer>()),
                 Self::reset_system.run_if(resource_removed::<RenetServer>()),
             ),
@@ -86,27 +199,166 @@ impl Plugin for ServerPlugin {
 }
 
 impl ServerPlugin {
-    fn acks_receiving_system(mut acked_ticks: ResMut<AckedTicks>, mut server: ResMut<RenetServer>) {
+    /// Receives acknowledgments from clients.
+    ///
+    /// Changes of entities that clients reported as unconfirmed are deferred to be resent since their ticks.
+    /// Emits [`ClientSynced`] on the first acknowledgment after which no entities from the initial
+    /// world state are deferred or unconfirmed for the client.
+    /// Acknowledgments from clients that are not authenticated by [`ServerAuth`] are ignored.
+    #[allow(clippy::too_many_arguments)]
+    fn acks_receiving_system(
+        change_tick: SystemChangeTick,
+        mut acked_ticks: ResMut<AckedTicks>,
+        mut congestion: ResMut<ClientCongestion>,
+        mut server: ResMut<RenetServer>,
+        visibility: Res<ClientVisibility>,
+        contexts: Res<ReplicationContexts>,
+        lods: Res<ClientLods>,
+        traffic_log: Res<TrafficLog>,
+        mut client_syncs: ResMut<ClientSyncs>,
+        mut sync_events: EventWriter<ClientSynced>,
+        mut synced_events: EventWriter<ToClients<InitialSynced>>,
+        auth: Option<Res<ServerAuth>>,
+        diff_budget: Option<Res<DiffBuildBudget>>,
+        entities: &Entities,
+    ) {
         for client_id in server.clients_id() {
             let mut last_message = None;
             while let Some(message) = server.receive_message(client_id, REPLICATION_CHANNEL_ID) {
+                traffic_log.received(client_id, REPLICATION_CHANNEL_ID, &message, || {
+                    ack_summary(&message)
+                });
                 last_message = Some(message);
             }
+            if auth
+                .as_ref()
+                .is_some_and(|auth| !auth.is_authenticated(client_id))
+            {
+                continue;
+            }
 
             if let Some(last_message) = last_message {
-                match bincode::deserialize::<LastTick>(&last_message) {
-                    Ok(tick) => {
-                        acked_ticks.0.insert(client_id, tick.into());
+                match bincode::deserialize::<ReplicationAck>(&last_message) {
+                    Ok(ack) => {
+                        let last_tick = ack.tick.into();
+                        let sync_state = client_syncs
+                            .0
+                            .entry(client_id)
+                            .or_insert(SyncState::Pending(last_tick));
+                        if let SyncState::Pending(initial_tick) = *sync_state {
+                            // The initial world could be split over multiple ticks by
+                            // `MessageEntityLimit` or carried over by `DiffBuildBudget`.
+                            if ack.server_tick.is_some()
+                                && ack.unconfirmed.is_empty()
+                                && !congestion.has_deferred_since(
+                                    client_id,
+                                    initial_tick,
+                                    last_tick,
+                                    change_tick.this_run(),
+                                )
+                                && !diff_budget.as_ref().is_some_and(|budget| {
+                                    budget.pending_clients().any(|id| id == client_id)
+                                })
+                            {
+                                *sync_state = SyncState::Synced;
+                                sync_events.send(ClientSynced { client_id });
+                                synced_events.send(ToClients {
+                                    mode: SendMode::Direct(client_id),
+                                    event: InitialSynced,
+                                });
+                            }
+                        }
+                        acked_ticks.0.insert(client_id, last_tick);
+                        if let Some(server_tick) = ack.server_tick {
+                            congestion.set_acked_server_tick(client_id, server_tick);
+                        }
+                        let this_run = change_tick.this_run();
+                        congestion.apply(
+                            ack.unconfirmed
+                                .into_iter()
+                                .filter(|&(entity, _)| entities.contains(entity))
+                                .map(|(entity, tick)| {
+                                    let since = Tick::new(tick);
+                                    DeferralChange::Deferred {
+                                        client_id,
+                                        entity,
+                                        since,
+                                        entered: visibility
+                                            .is_entered(client_id, entity, since, this_run)
+                                            || contexts
+                                                .is_entered(client_id, entity, since, this_run)
+                                            || ClientLods::is_changed(
+                                                &lods, client_id, entity, since, this_run,
+                                            ),
+                                    }
+                                }),
+                        );
+                    }
+                    Err(e) => error!("unable to deserialize ack from client {client_id}: {e}"),
+                }
+            }
+        }
+    }
+
+    pub(super) fn entity_mappings_receiving_system(
+        mut client_entity_maps: ResMut<ClientEntityMaps>,
+        mut server: ResMut<RenetServer>,
+        traffic_log: Res<TrafficLog>,
+    ) {
+        for client_id in server.clients_id() {
+            while let Some(message) = server.receive_message(client_id, ENTITY_MAPPING_CHANNEL_ID) {
+                let result = bincode::deserialize::<Vec<(Entity, Entity)>>(&message);
+                traffic_log.received(client_id, ENTITY_MAPPING_CHANNEL_ID, &message, || {
+                    match &result {
+                        Ok(mappings) => format!("{} entity mappings", mappings.len()),
+                        Err(e) => format!("invalid entity mappings: {e}"),
+                    }
+                });
+                match result {
+                    Ok(mappings) => {
+                        let entity_map = client_entity_maps.0.entry(client_id).or_default();
+                        for (server_entity, client_entity) in mappings {
+                            entity_map.insert(server_entity, client_entity);
+                        }
                     }
-                    Err(e) => error!("unable to deserialize tick from client {client_id}: {e}"),
+                    Err(e) => {
+                        error!("unable to deserialize entity mappings from client {client_id}: {e}")
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes mappings for despawned server entities.
+    fn client_entity_maps_cleanup_system(
+        mut removed_replications: RemovedComponents<Replication>,
+        mut client_entity_maps: ResMut<ClientEntityMaps>,
+        entities: &Entities,
+    ) {
+        for server_entity in &mut removed_replications {
+            if !entities.contains(server_entity) {
+                for entity_map in client_entity_maps.0.values_mut() {
+                    entity_map.remove_by_server(server_entity);
                 }
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn acks_cleanup_system(
         mut server_events: EventReader<ServerEvent>,
         mut acked_ticks: ResMut<AckedTicks>,
+        mut client_syncs: ResMut<ClientSyncs>,
+        mut client_entity_maps: ResMut<ClientEntityMaps>,
+        mut client_groups: ResMut<ClientGroups>,
+        mut client_tags: ResMut<ClientTags>,
+        mut visibility: ResMut<ClientVisibility>,
+        mut contexts: ResMut<ReplicationContexts>,
+        mut lods: ResMut<ClientLods>,
+        mut streaming: ResMut<ChunkStreaming>,
+        mut congestion: ResMut<ClientCongestion>,
+        mut send_rates: ResMut<ClientSendRates>,
+        mut falloff: ResMut<DistanceFalloff>,
     ) {
         for event in &mut server_events {
             if let ServerEvent::ClientDisconnected {
@@ -115,194 +367,1187 @@ impl ServerPlugin {
             } = event
             {
                 acked_ticks.0.remove(id);
+                client_syncs.0.remove(id);
+                client_entity_maps.0.remove(id);
+                client_groups.remove_client(*id);
+                client_tags.remove_client(*id);
+                visibility.remove_client(*id);
+                contexts.remove_client(*id);
+                lods.remove_client(*id);
+                streaming.remove_client(*id);
+                congestion.remove_client(*id);
+                send_rates.remove_override(*id);
+                falloff.remove_client(*id);
+            }
+        }
+    }
+
+    /// Notifies clients about switches of the active rule set.
+    ///
+    /// Newly connected clients are notified about the current rule set too.
+    fn rule_set_sending_system(
+        mut previous_rule_set: Local<Option<&'static str>>,
+        mut server_events: EventReader<ServerEvent>,
+        mut rule_set_events: EventWriter<ToClients<RuleSetChanged>>,
+        replication_rules: Res<ReplicationRules>,
+    ) {
+        let rule_set = replication_rules.active_rule_set();
+        let event = RuleSetChanged {
+            rule_set: rule_set.map(Into::into),
+        };
+        if rule_set != *previous_rule_set {
+            *previous_rule_set = rule_set;
+            rule_set_events.send(ToClients {
+                mode: SendMode::Broadcast,
+                event: event.clone(),
+            });
+        }
+
+        for server_event in &mut server_events {
+            if let ServerEvent::ClientConnected { client_id } = *server_event {
+                if rule_set.is_some() {
+                    rule_set_events.send(ToClients {
+                        mode: SendMode::Direct(client_id),
+                        event: event.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Notifies clients about components whose replication was toggled.
+    ///
+    /// Newly connected clients are notified about all disabled components.
+    fn toggle_sending_system(
+        mut previous_disabled: Local<HashSet<ComponentId>>,
+        mut server_events: EventReader<ServerEvent>,
+        mut toggle_events: EventWriter<ToClients<ReplicationToggled>>,
+        replication_rules: Res<ReplicationRules>,
+        components: &Components,
+    ) {
+        let event = |component_id, enabled| ReplicationToggled {
+            component: components
+                .get_info(component_id)
+                .expect("toggled component should be registered")
+                .name()
+                .to_string(),
+            enabled,
+        };
+
+        let disabled = &replication_rules.disabled;
+        if *disabled != *previous_disabled {
+            for &component_id in disabled.difference(&previous_disabled) {
+                toggle_events.send(ToClients {
+                    mode: SendMode::Broadcast,
+                    event: event(component_id, false),
+                });
+            }
+            for &component_id in previous_disabled.difference(disabled) {
+                toggle_events.send(ToClients {
+                    mode: SendMode::Broadcast,
+                    event: event(component_id, true),
+                });
+            }
+            *previous_disabled = disabled.clone();
+        }
+
+        for server_event in &mut server_events {
+            if let ServerEvent::ClientConnected { client_id } = *server_event {
+                for &component_id in disabled {
+                    toggle_events.send(ToClients {
+                        mode: SendMode::Direct(client_id),
+                        event: event(component_id, false),
+                    });
+                }
             }
         }
     }
 
+    fn tick_increment_system(mut server_tick: ResMut<ServerTick>) {
+        server_tick.increment();
+    }
+
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     fn diffs_sending_system(
         change_tick: SystemChangeTick,
-        mut set: ParamSet<(&World, ResMut<RenetServer>)>,
+        mut set: ParamSet<(
+            &World,
+            ResMut<RenetServer>,
+            ResMut<ClientCongestion>,
+            Option<ResMut<DiffBuildBudget>>,
+        )>,
+        mut replicated_archetypes: Local<ReplicatedArchetypes>,
+        mut reliable_ticks: Local<HashMap<u64, Tick>>,
+        server_tick: Res<ServerTick>,
         acked_ticks: Res<AckedTicks>,
         registry: Res<AppTypeRegistry>,
         replication_rules: Res<ReplicationRules>,
         despawn_tracker: Res<DespawnTracker>,
-        removal_trackers: Query<(Entity, &RemovalTracker)>,
+        visibility: Res<ClientVisibility>,
     ) {
-        // Initialize [`WorldDiff`]s with latest acknowledged tick for each client.
         let registry = registry.read();
-        let mut client_diffs: HashMap<_, _> = acked_ticks
+        let world = set.p0();
+        let congestion = world.resource::<ClientCongestion>();
+        let mut deferral_changes = Vec::new();
+        replicated_archetypes.cleanup_switch(&acked_ticks, change_tick.this_run());
+        replicated_archetypes.update(
+            world.archetypes(),
+            &replication_rules,
+            world.resource::<ReplicationContexts>(),
+            change_tick.this_run(),
+        );
+
+        let current_tick = world.read_change_tick();
+        let mut messages = Vec::new();
+        let mut world_diffs = Vec::with_capacity(acked_ticks.len());
+        let mut pending = None;
+        if let Some(budget) = world.get_resource::<DiffBuildBudget>() {
+            let (budgeted_messages, budget_pending) = collect_budgeted_messages(
+                world,
+                &change_tick,
+                &registry,
+                &replicated_archetypes,
+                &acked_ticks,
+                &despawn_tracker,
+                &visibility,
+                *server_tick,
+                budget.queue(acked_ticks.keys().copied()),
+                budget.max_duration,
+                &mut deferral_changes,
+            );
+            messages.extend(
+                budgeted_messages
+                    .into_iter()
+                    .map(|(client_id, message)| (client_id, REPLICATION_CHANNEL_ID, message)),
+            );
+            pending = Some(budget_pending);
+        } else {
+            let client_diffs = collect_diffs(
+                world,
+                &change_tick,
+                &registry,
+                &replicated_archetypes,
+                &acked_ticks,
+                &despawn_tracker,
+                &visibility,
+                &mut deferral_changes,
+            );
+            for (client_id, mut world_diff) in client_diffs {
+                if is_skipped(&world_diff, congestion, client_id) {
+                    continue;
+                }
+                world_diff.tick = current_tick; // Replace last acknowledged tick with the current.
+                downgrade_for_client(world, client_id, &mut world_diff);
+                world_diffs.push((client_id, REPLICATION_CHANNEL_ID, world_diff));
+            }
+        }
+
+        // New clients receive reliable changes since their first acknowledged tick.
+        reliable_ticks.retain(|client_id, _| acked_ticks.contains_key(client_id));
+        for (&client_id, &acked_tick) in acked_ticks.iter() {
+            reliable_ticks.entry(client_id).or_insert(acked_tick);
+        }
+        let reliable_diffs = collect_reliable_diffs(
+            world,
+            &change_tick,
+            &registry,
+            &replicated_archetypes,
+            &reliable_ticks,
+            &visibility,
+        );
+        for (client_id, mut world_diff) in reliable_diffs {
+            if congestion.is_throttled(client_id) {
+                // Keep the tick to send accumulated changes in a single diff later.
+                continue;
+            }
+            reliable_ticks.insert(client_id, current_tick);
+            if world_diff.is_empty() {
+                continue;
+            }
+            world_diff.tick = current_tick;
+            downgrade_for_client(world, client_id, &mut world_diff);
+            world_diffs.push((client_id, RELIABLE_REPLICATION_CHANNEL_ID, world_diff));
+        }
+        let traffic_log = world.resource::<TrafficLog>();
+        let mut summaries: HashMap<_, _> = world_diffs
             .iter()
-            .map(|(&client_id, &last_tick)| (client_id, WorldDiff::new(last_tick)))
+            .filter(|&&(client_id, ..)| traffic_log.is_watched(client_id))
+            .map(|(client_id, channel_id, world_diff)| {
+                ((*client_id, *channel_id), diff_summary(world_diff))
+            })
             .collect();
-        collect_changes(&mut client_diffs, set.p0(), &registry, &replication_rules);
-        collect_removals(&mut client_diffs, set.p0(), &change_tick, &removal_trackers);
-        collect_despawns(&mut client_diffs, &change_tick, &despawn_tracker);
+        let diff_messages = serialize_diffs(world_diffs, *server_tick, &registry);
+        for (client_id, channel_id, message) in &diff_messages {
+            traffic_log.sent(*client_id, *channel_id, message, || {
+                summaries
+                    .remove(&(*client_id, *channel_id))
+                    .unwrap_or_default()
+            });
+        }
+        messages.extend(diff_messages);
+
+        let mut server = set.p1();
+        for (client_id, channel_id, message) in messages {
+            server.send_message(client_id, channel_id, message);
+        }
+
+        set.p2().apply(deferral_changes);
+        if let (Some(mut budget), Some(pending)) = (set.p3(), pending) {
+            budget.carry_over(pending);
+        }
+    }
 
-        let current_tick = set.p0().read_change_tick();
-        for (client_id, mut world_diff) in client_diffs {
-            world_diff.tick = current_tick; // Replace last acknowledged tick with the current.
-            let serializer = WorldDiffSerializer::new(&world_diff, &registry);
-            let message =
-                bincode::serialize(&serializer).expect("world diff should be serializable");
-            set.p1()
-                .send_message(client_id, REPLICATION_CHANNEL_ID, message);
+    /// Asks kicked and rejected clients to disconnect and disconnects them forcibly after [`KICK_TIMEOUT`].
+    fn kick_system(
+        time: Res<Time>,
+        mut pending_kicks: Local<Vec<(u64, Duration)>>,
+        mut kick_events: EventReader<KickClient>,
+        mut rejected_events: EventReader<ClientRejected>,
+        mut auth_rejected_events: EventReader<ClientAuthRejected>,
+        mut kicked_events: EventWriter<ToClients<Kicked>>,
+        mut server: ResMut<RenetServer>,
+    ) {
+        let kicks =
+            kick_events
+                .iter()
+                .map(|&KickClient { client_id, code }| (client_id, Kicked::Code(code)))
+                .chain(
+                    rejected_events
+                        .iter()
+                        .map(|&ClientRejected { client_id }| (client_id, Kicked::ServerFull)),
+                )
+                .chain(auth_rejected_events.iter().map(
+                    |&ClientAuthRejected { client_id, code }| {
+                        (client_id, Kicked::AuthRejected(code))
+                    },
+                ));
+        for (client_id, event) in kicks {
+            kicked_events.send(ToClients {
+                mode: SendMode::Direct(client_id),
+                event,
+            });
+            pending_kicks.push((client_id, time.elapsed()));
         }
+
+        pending_kicks.retain(|&(client_id, kicked_at)| {
+            if !server.is_connected(client_id) {
+                return false;
+            }
+            if time.elapsed().saturating_sub(kicked_at) < KICK_TIMEOUT {
+                return true;
+            }
+            server.disconnect(client_id);
+            false
+        });
     }
 
-    fn reset_system(mut acked_ticks: ResMut<AckedTicks>) {
+    #[allow(clippy::too_many_arguments)]
+    fn reset_system(
+        mut acked_ticks: ResMut<AckedTicks>,
+        mut client_syncs: ResMut<ClientSyncs>,
+        mut client_entity_maps: ResMut<ClientEntityMaps>,
+        mut client_groups: ResMut<ClientGroups>,
+        mut client_tags: ResMut<ClientTags>,
+        mut visibility: ResMut<ClientVisibility>,
+        mut contexts: ResMut<ReplicationContexts>,
+        mut lods: ResMut<ClientLods>,
+        mut streaming: ResMut<ChunkStreaming>,
+        mut congestion: ResMut<ClientCongestion>,
+        mut send_rates: ResMut<ClientSendRates>,
+        mut falloff: ResMut<DistanceFalloff>,
+        mut server_tick: ResMut<ServerTick>,
+        diff_budget: Option<ResMut<DiffBuildBudget>>,
+        switch: Option<Res<TransportSwitch>>,
+    ) {
         acked_ticks.0.clear();
+        client_syncs.0.clear();
+        client_entity_maps.0.clear();
+        visibility.clear();
+        contexts.clear();
+        lods.clear();
+        streaming.clear();
+        congestion.clear();
+        send_rates.clear();
+        falloff.clear();
+        if let Some(mut diff_budget) = diff_budget {
+            diff_budget.clear();
+        }
+        if switch.is_none() {
+            client_groups.clear();
+            client_tags.clear();
+            *server_tick = Default::default();
+        }
     }
 }
 
-fn collect_changes(
-    client_diffs: &mut HashMap<u64, WorldDiff>,
-    world: &World,
+/// Serializes world diffs for clients in parallel on [`ComputeTaskPool`].
+///
+/// Diffs of different clients are independent, so each one is serialized in a separate task.
+fn serialize_diffs(
+    world_diffs: Vec<(u64, u8, WorldDiff)>,
+    server_tick: ServerTick,
     registry: &TypeRegistryInternal,
-    replication_rules: &ReplicationRules,
-) {
-    for archetype in world
-        .archetypes()
+) -> Vec<(u64, u8, Vec<u8>)> {
+    ComputeTaskPool::get().scope(|scope| {
+        for (client_id, channel_id, world_diff) in &world_diffs {
+            scope.spawn(async move {
+                let message = serialize_diff(world_diff, server_tick, registry);
+                (*client_id, *channel_id, message)
+            });
+        }
+    })
+}
+
+/// Serializes a world diff message.
+///
+/// Diffs without changes are serialized as keepalives that contain only the ticks,
+/// so clients still advance [`ServerTick`] and acknowledge the tick.
+pub(super) fn serialize_diff(
+    world_diff: &WorldDiff,
+    server_tick: ServerTick,
+    registry: &TypeRegistryInternal,
+) -> Vec<u8> {
+    let message = if world_diff.is_empty() {
+        serialize_message(&(server_tick, world_diff.tick.get()))
+    } else {
+        let serializer = WorldDiffSerializer::new(world_diff, registry);
+        serialize_message(&(server_tick, &serializer))
+    };
+    message.expect("world diff should be serializable")
+}
+
+/// Collects diffs for all clients since their last acknowledged ticks.
+///
+/// Changes that were deferred or sent for congested clients are stored into `deferral_changes`.
+#[allow(clippy::too_many_arguments)]
+fn collect_diffs<'a>(
+    world: &'a World,
+    change_tick: &SystemChangeTick,
+    registry: &'a TypeRegistryInternal,
+    replicated_archetypes: &ReplicatedArchetypes,
+    acked_ticks: &AckedTicks,
+    despawn_tracker: &DespawnTracker,
+    visibility: &ClientVisibility,
+    deferral_changes: &mut Vec<DeferralChange>,
+) -> HashMap<u64, WorldDiff<'a>> {
+    // Initialize [`WorldDiff`]s with latest acknowledged tick for each client.
+    let mut client_diffs: HashMap<_, _> = acked_ticks
+        .iter()
+        .map(|(&client_id, &last_tick)| (client_id, WorldDiff::new(last_tick)))
+        .collect();
+    collect_changes(
+        &mut client_diffs,
+        world,
+        change_tick,
+        registry,
+        replicated_archetypes,
+        false,
+        visibility,
+        world.resource::<ClientCongestion>(),
+        deferral_changes,
+    );
+    collect_despawns(
+        &mut client_diffs,
+        change_tick,
+        despawn_tracker,
+        visibility,
+        world.resource::<ReplicationContexts>(),
+    );
+    prioritize_diffs(&mut client_diffs, world);
+    if let Some(&limit) = world.get_resource::<MessageEntityLimit>() {
+        limit.apply(
+            &mut client_diffs,
+            world,
+            change_tick,
+            visibility,
+            deferral_changes,
+        );
+    }
+
+    client_diffs
+}
+
+/// Collects changes of reliable components for all clients since the last sent ticks.
+///
+/// Reliable messages are always delivered, so each change is sent only once and congestion is ignored.
+fn collect_reliable_diffs<'a>(
+    world: &'a World,
+    change_tick: &SystemChangeTick,
+    registry: &'a TypeRegistryInternal,
+    replicated_archetypes: &ReplicatedArchetypes,
+    reliable_ticks: &HashMap<u64, Tick>,
+    visibility: &ClientVisibility,
+) -> HashMap<u64, WorldDiff<'a>> {
+    let mut client_diffs: HashMap<_, _> = reliable_ticks
         .iter()
-        .filter(|archetype| archetype.id() != ArchetypeId::EMPTY)
-        .filter(|archetype| archetype.id() != ArchetypeId::INVALID)
-        .filter(|archetype| replication_rules.is_replicated_archetype(archetype))
-    {
+        .map(|(&client_id, &last_tick)| (client_id, WorldDiff::new(last_tick)))
+        .collect();
+    collect_changes(
+        &mut client_diffs,
+        world,
+        change_tick,
+        registry,
+        replicated_archetypes,
+        true,
+        visibility,
+        &ClientCongestion::default(),
+        &mut Vec::new(),
+    );
+    prioritize_diffs(&mut client_diffs, world);
+
+    client_diffs
+}
+
+/// Returns `true` if the tick should be skipped for a congested client because there is nothing essential to send.
+fn is_skipped(world_diff: &WorldDiff, congestion: &ClientCongestion, client_id: u64) -> bool {
+    world_diff.is_empty() && congestion.min_priority(client_id) == Some(f32::INFINITY)
+}
+
+/// Collects component changes and removals into groups with the same set of changes.
+///
+/// Uses component change ticks to detect what changed since the last acknowledged tick of each client.
+/// Entities that have no changes since the oldest acknowledged tick are skipped before looking at
+/// individual clients, so the per-client work depends only on the number of changed entities.
+///
+/// Only changes and removals of components sent over the channel selected by `reliable` are collected.
+///
+/// Entities with equal sets of changes are stored inside the same [`ArchetypeDiff`]
+/// even if they belong to different archetypes.
+/// Component values are borrowed directly from the ECS storage without cloning.
+#[allow(clippy::too_many_arguments)]
+fn collect_changes<'a>(
+    client_diffs: &mut HashMap<u64, WorldDiff<'a>>,
+    world: &'a World,
+    change_tick: &SystemChangeTick,
+    registry: &'a TypeRegistryInternal,
+    replicated_archetypes: &ReplicatedArchetypes,
+    reliable: bool,
+    visibility: &ClientVisibility,
+    congestion: &ClientCongestion,
+    deferral_changes: &mut Vec<DeferralChange>,
+) {
+    let Some(oldest_tick) = client_diffs
+        .values()
+        .map(|world_diff| world_diff.tick)
+        .chain(congestion.oldest_deferred_tick(change_tick.this_run()))
+        .reduce(|oldest_tick, tick| {
+            if oldest_tick.is_newer_than(tick, change_tick.this_run()) {
+                tick
+            } else {
+                oldest_tick
+            }
+        })
+    else {
+        return;
+    };
+
+    let mut client_groups: HashMap<u64, HashMap<Vec<ComponentDiff>, usize>> = client_diffs
+        .keys()
+        .map(|&client_id| (client_id, Default::default()))
+        .collect();
+    let lods = world.resource::<ClientLods>();
+    let contexts = world.resource::<ReplicationContexts>();
+    let mut entered_entities = visibility.entered_entities();
+    entered_entities.extend(lods.changed_entities());
+    entered_entities.extend(contexts.entered_entities());
+    entered_entities.extend(congestion.deferred_entries());
+    let removal_tracker_id = world.component_id::<RemovalTracker>();
+    let replication_rules = world.resource::<ReplicationRules>();
+    let falloff = world.resource::<DistanceFalloff>();
+    let server_tick = world.resource::<ServerTick>().get();
+    let mut components = Vec::new();
+    let mut changes = Vec::new();
+    let mut key = Vec::new();
+    for replicated_archetype in &replicated_archetypes.archetypes {
+        let archetype = world
+            .archetypes()
+            .get(replicated_archetype.id)
+            .expect("cached archetype should exist");
         let table = world
             .storages()
             .tables
             .get(archetype.table_id())
             .expect("archetype should be in storage");
 
-        for component_id in archetype.components().filter(|&component_id| {
-            replication_rules.is_replicated_component(archetype, component_id)
-        }) {
-            let storage_type = archetype
-                .get_storage_type(component_id)
-                .expect("component should be a part of the archetype");
-
-            // SAFETY: `component_id` obtained from the world.
-            let component_info = unsafe { world.components().get_info_unchecked(component_id) };
-            let type_name = component_info.name();
-            let type_id = component_info
-                .type_id()
-                .unwrap_or_else(|| panic!("{type_name} should have registered TypeId"));
-            let registration = registry
-                .get(type_id)
-                .unwrap_or_else(|| panic!("{type_name} should be registered"));
-            let reflect_component = registration
-                .data::<ReflectComponent>()
-                .unwrap_or_else(|| panic!("{type_name} should have reflect(Component)"));
-
-            match storage_type {
-                StorageType::Table => {
-                    let column = table
-                        .get_column(component_id)
-                        .unwrap_or_else(|| panic!("{type_name} should have a valid column"));
-
-                    for archetype_entity in archetype.entities() {
-                        // SAFETY: the table row obtained from the world state.
-                        let ticks =
-                            unsafe { column.get_ticks_unchecked(archetype_entity.table_row()) };
-                        collect_if_changed(
-                            client_diffs,
-                            world.entity(archetype_entity.entity()),
-                            world,
-                            ticks,
-                            reflect_component,
-                            type_name,
-                        );
+        components.clear();
+        for archetype_component in replicated_archetype
+            .components
+            .iter()
+            .filter(|archetype_component| archetype_component.reliable == reliable)
+        {
+            components.push(ReplicatedComponent::new(
+                world,
+                registry,
+                table,
+                archetype_component,
+                replicated_archetypes.switched_at(archetype_component.component_id),
+            ));
+        }
+        let removal_column =
+            removal_tracker_id.and_then(|component_id| table.get_column(component_id));
+
+        let has_owner_only = components
+            .iter()
+            .any(|component| component.send_info.owner_only);
+        for archetype_entity in archetype.entities() {
+            let entity = archetype_entity.entity();
+            // Entities that recently became visible or changed LOD for any client need all components.
+            let entered = entered_entities.contains(&entity);
+            // Owner-only components are sent to a new owner after ownership transfer.
+            let (recipient, owner_ticks) = if has_owner_only {
+                let entity_ref = world.entity(entity);
+                (
+                    entity_ref.get::<ClientOwned>().map(|owner| owner.0),
+                    entity_ref.get_change_ticks::<ClientOwned>(),
+                )
+            } else {
+                (None, None)
+            };
+            let owner_changed = |tick| {
+                owner_ticks.is_some_and(|ticks| ticks.is_changed(tick, change_tick.this_run()))
+            };
+            let context = contexts.context(entity);
+            // Contexts could replace the active rule set for their entities.
+            let rule_set = contexts
+                .rule_set(context)
+                .or(replication_rules.active_rule_set());
+            changes.clear();
+            for component in &components {
+                let send_info = component.send_info;
+                if !send_info.always
+                    && !replication_rules.is_in_rule_set(send_info.component_id, rule_set)
+                {
+                    continue;
+                }
+                let ticks = component.ticks(archetype_entity);
+                if entered
+                    || send_info.switched_at.is_some()
+                    || ticks.is_changed(oldest_tick, change_tick.this_run())
+                    || send_info.owner_only && owner_changed(oldest_tick)
+                {
+                    changes.push(ComponentChange {
+                        send_info,
+                        ticks,
+                        value: component.value(archetype_entity),
+                    });
+                }
+            }
+
+            // SAFETY: the table row obtained from the world state and the column contains `RemovalTracker`.
+            let removal_tracker = removal_column
+                .map(|column| unsafe {
+                    column
+                        .get_data_unchecked(archetype_entity.table_row())
+                        .deref::<RemovalTracker>()
+                })
+                .filter(|removal_tracker| {
+                    removal_tracker
+                        .values()
+                        .any(|tick| tick.is_newer_than(oldest_tick, change_tick.this_run()))
+                });
+
+            if changes.is_empty() && removal_tracker.is_none() {
+                continue;
+            }
+
+            let owner = match world.get::<Authority>(entity) {
+                Some(&Authority::Client(client_id))
+                    if changes
+                        .iter()
+                        .any(|change| change.send_info.client_authoritative) =>
+                {
+                    Some(client_id)
+                }
+                _ => None,
+            };
+            for (&client_id, world_diff) in client_diffs.iter_mut() {
+                if !visibility.is_visible(client_id, entity)
+                    || !contexts.is_joined(client_id, context)
+                {
+                    continue;
+                }
+                let lod_changed =
+                    lods.is_changed(client_id, entity, world_diff.tick, change_tick.this_run());
+                let entered = lod_changed
+                    || entered
+                        && (visibility.is_entered(
+                            client_id,
+                            entity,
+                            world_diff.tick,
+                            change_tick.this_run(),
+                        ) || contexts.is_entered(
+                            client_id,
+                            entity,
+                            world_diff.tick,
+                            change_tick.this_run(),
+                        ) || congestion.is_deferred_entry(client_id, entity));
+                let lod = lods.lod(client_id, entity);
+
+                // Components that started replicating after a rule set switch or toggle
+                // are new for clients that haven't acknowledged it yet.
+                let last_tick = world_diff.tick;
+                let is_switched = |switched_at: Option<Tick>| {
+                    switched_at
+                        .is_some_and(|tick| tick.is_newer_than(last_tick, change_tick.this_run()))
+                };
+
+                // Changes of low-priority entities are deferred for congested clients
+                // and changes of distant entities are sent at a lower rate,
+                // but spawns and insertions are always sent.
+                // Reliable changes are sent only once, so they can't be deferred.
+                let deferred_tick = congestion.deferred_tick(client_id, entity);
+                let changes_tick = deferred_tick.unwrap_or(last_tick);
+                let skip_changes = !reliable
+                    && !entered
+                    && (congestion
+                        .min_priority(client_id)
+                        .is_some_and(|min_priority| {
+                            entity_priority(world, entity, client_id) < min_priority
+                        })
+                        || !falloff.is_due(world, entity, client_id, server_tick)
+                        || !contexts.is_due(entity, server_tick))
+                    && !changes.iter().any(|change| {
+                        is_switched(change.send_info.switched_at)
+                            || change.ticks.is_added(changes_tick, change_tick.this_run())
+                    });
+                if skip_changes {
+                    if changes.iter().any(|change| {
+                        change
+                            .ticks
+                            .is_changed(changes_tick, change_tick.this_run())
+                    }) {
+                        deferral_changes.push(DeferralChange::Deferred {
+                            client_id,
+                            entity,
+                            since: changes_tick,
+                            entered: false,
+                        });
                     }
+                } else if deferred_tick.is_some() {
+                    deferral_changes.push(DeferralChange::Sent {
+                        client_id,
+                        entity,
+                        tick: change_tick.this_run(),
+                    });
                 }
-                StorageType::SparseSet => {
-                    let sparse_set = world
-                        .storages()
-                        .sparse_sets
-                        .get(component_id)
-                        .unwrap_or_else(|| panic!("{type_name} should exists in a sparse set"));
-
-                    for archetype_entity in archetype.entities() {
-                        let ticks = sparse_set
-                            .get_ticks(archetype_entity.entity())
-                            .expect("{type_name} should have ticks");
-                        collect_if_changed(
-                            client_diffs,
-                            world.entity(archetype_entity.entity()),
-                            world,
-                            ticks,
-                            reflect_component,
-                            type_name,
-                        );
+                // Changes of client-authoritative components originate from their owner,
+                // so only insertions are sent back to it.
+                let is_owner = owner == Some(client_id);
+                // Owner-only components are sent only to the client from `ClientOwned`.
+                let is_recipient = recipient == Some(client_id);
+                let is_changed = |change: &ComponentChange| {
+                    let send_info = change.send_info;
+                    !skip_changes
+                        && lod <= send_info.max_lod
+                        && (!send_info.owner_only || is_recipient)
+                        && (entered
+                            || is_switched(send_info.switched_at)
+                            || change
+                                .ticks
+                                .is_changed(changes_tick, change_tick.this_run())
+                            || send_info.owner_only && owner_changed(changes_tick))
+                        && (!send_info.client_authoritative
+                            || !is_owner
+                            || entered
+                            || change.ticks.is_added(changes_tick, change_tick.this_run()))
+                };
+
+                key.clear();
+                if let Some(removal_tracker) = removal_tracker {
+                    for (&component_id, &tick) in removal_tracker.iter() {
+                        if replication_rules.is_reliable(component_id) == reliable
+                            && replication_rules.is_component_enabled(component_id)
+                            && tick.is_newer_than(last_tick, change_tick.this_run())
+                        {
+                            key.push(ComponentDiff::Removed(registration(
+                                world,
+                                registry,
+                                component_id,
+                            )));
+                        }
+                    }
+                }
+                for change in &changes {
+                    let send_info = change.send_info;
+                    if is_changed(change) {
+                        key.push(ComponentDiff::Changed(send_info.registration));
+                    } else if lod_changed && lod > send_info.max_lod
+                        || send_info.owner_only && !is_recipient && owner_changed(last_tick)
+                    {
+                        // Components that are not included in the new LOD
+                        // or owner-only components of entities that changed their owner.
+                        key.push(ComponentDiff::Removed(send_info.registration));
                     }
                 }
+
+                if key.is_empty() {
+                    continue;
+                }
+
+                let groups = client_groups
+                    .get_mut(&client_id)
+                    .expect("groups should be initialized for each client");
+                let group_index = match groups.get(key.as_slice()) {
+                    Some(&index) => index,
+                    None => {
+                        let index = world_diff.archetypes.len();
+                        world_diff.archetypes.push(ArchetypeDiff::new(key.clone()));
+                        groups.insert(key.clone(), index);
+                        index
+                    }
+                };
+
+                let components = changes
+                    .iter()
+                    .filter(|change| is_changed(change))
+                    .map(|change| ComponentValue::Borrowed(change.value))
+                    .collect();
+                world_diff.archetypes[group_index]
+                    .entities
+                    .push(EntityDiff { entity, components });
             }
         }
     }
 }
 
-fn collect_if_changed(
-    client_diffs: &mut HashMap<u64, WorldDiff>,
-    entity: EntityRef,
-    world: &World,
-    ticks: ComponentTicks,
-    reflect_component: &ReflectComponent,
-    type_name: &str,
-) {
-    for world_diff in client_diffs.values_mut() {
-        if ticks.is_changed(world_diff.tick, world.read_change_tick()) {
-            let component = reflect_component
-                .reflect(entity)
-                .unwrap_or_else(|| panic!("entity should have {type_name}"))
-                .clone_value();
-            world_diff
-                .entities
-                .entry(entity.id())
-                .or_default()
-                .push(ComponentDiff::Changed(component));
-        }
-    }
+/// Cached archetypes with entities that should be replicated.
+///
+/// Archetypes are never removed from the world, so only newly created archetypes
+/// are checked against [`ReplicationRules`] on each update.
+/// The cache is rebuilt when the active rule set is switched or replication of a component is toggled.
+#[derive(Default)]
+struct ReplicatedArchetypes {
+    /// Number of world archetypes that were already processed.
+    generation: usize,
+    archetypes: Vec<ReplicatedArchetype>,
+
+    /// Active rule set for which archetypes were cached.
+    rule_set: Option<&'static str>,
+
+    /// Rule sets of contexts for which archetypes were cached.
+    context_rule_sets: HashMap<ReplicationContext, &'static str>,
+
+    /// Disabled components for which archetypes were cached.
+    disabled: HashSet<ComponentId>,
+
+    /// Tick of the last rule set switch or toggle and components that started replicating since then.
+    ///
+    /// Cleared after all clients acknowledge the switch.
+    switch: Option<(Tick, HashSet<ComponentId>)>,
 }
 
-fn collect_removals(
-    client_diffs: &mut HashMap<u64, WorldDiff>,
-    world: &World,
-    change_tick: &SystemChangeTick,
-    removal_trackers: &Query<(Entity, &RemovalTracker)>,
-) {
-    for (entity, removal_tracker) in removal_trackers {
-        for world_diff in client_diffs.values_mut() {
-            for (&component_id, &tick) in removal_tracker.iter() {
-                if tick.is_newer_than(world_diff.tick, change_tick.this_run()) {
-                    // SAFETY: `component_id` obtained from `RemovalTracker` that always contains valid components.
-                    let component_info =
-                        unsafe { world.components().get_info_unchecked(component_id) };
-                    world_diff
-                        .entities
-                        .entry(entity)
-                        .or_default()
-                        .push(ComponentDiff::Removed(component_info.name().to_string()));
+impl ReplicatedArchetypes {
+    /// Caches archetypes that were created since the last call.
+    ///
+    /// Components of all rule sets that are used by any context are cached,
+    /// they are filtered for each entity based on its context.
+    fn update(
+        &mut self,
+        archetypes: &Archetypes,
+        replication_rules: &ReplicationRules,
+        contexts: &ReplicationContexts,
+        this_run: Tick,
+    ) {
+        let rule_set = replication_rules.active_rule_set();
+        let context_rule_sets = contexts.context_rule_sets();
+        if rule_set != self.rule_set
+            || *context_rule_sets != self.context_rule_sets
+            || replication_rules.disabled != self.disabled
+        {
+            let previous_rule_sets = used_rule_sets(self.rule_set, &self.context_rule_sets);
+            let current_rule_sets = used_rule_sets(rule_set, context_rule_sets);
+            let previous: HashSet<_> = replication_rules
+                .active_components(&previous_rule_sets, &self.disabled)
+                .collect();
+            let current: HashSet<_> = replication_rules
+                .active_components(&current_rule_sets, &replication_rules.disabled)
+                .collect();
+
+            // Rule sets that became used by contexts which used other rule sets before.
+            let mut switched_rule_sets = HashSet::new();
+            if rule_set != self.rule_set {
+                switched_rule_sets.extend(rule_set);
+            }
+            for context in self
+                .context_rule_sets
+                .keys()
+                .chain(context_rule_sets.keys())
+            {
+                let previous_rule_set = self.context_rule_sets.get(context).copied();
+                let current_rule_set = context_rule_sets.get(context).copied();
+                if previous_rule_set.or(self.rule_set) != current_rule_set.or(rule_set) {
+                    switched_rule_sets.extend(current_rule_set.or(rule_set));
                 }
             }
+
+            let mut switched = self
+                .switch
+                .take()
+                .map(|(_, switched)| switched)
+                .unwrap_or_default();
+            switched.retain(|component_id| current.contains(component_id));
+            switched.extend(current.difference(&previous));
+            switched.extend(
+                replication_rules
+                    .active_components(&switched_rule_sets, &replication_rules.disabled)
+                    .filter(|component_id| !replication_rules.replicated.contains(component_id)),
+            );
+            if !switched.is_empty() {
+                self.switch = Some((this_run, switched));
+            }
+
+            self.rule_set = rule_set;
+            self.context_rule_sets = context_rule_sets.clone();
+            self.disabled = replication_rules.disabled.clone();
+            self.generation = 0;
+            self.archetypes.clear();
+        }
+        let rule_sets = used_rule_sets(self.rule_set, &self.context_rule_sets);
+
+        for archetype in archetypes
+            .iter()
+            .skip(self.generation)
+            .filter(|archetype| replication_rules.is_replicated_archetype(archetype))
+        {
+            let components = archetype
+                .components()
+                .filter(|&component_id| {
+                    replication_rules.is_replicated_with_rule_sets(
+                        archetype,
+                        component_id,
+                        rule_sets.iter().copied(),
+                    )
+                })
+                .map(|component_id| {
+                    let storage_type = archetype
+                        .get_storage_type(component_id)
+                        .expect("component should be a part of the archetype");
+                    ArchetypeComponent {
+                        component_id,
+                        storage_type,
+                        once: replication_rules.is_once(component_id),
+                        max_lod: replication_rules.max_lod(component_id),
+                        reliable: replication_rules.is_reliable(component_id),
+                        client_authoritative: replication_rules
+                            .is_client_authoritative(component_id),
+                        owner_only: replication_rules.is_owner_only(component_id),
+                        always: replication_rules.replicated.contains(&component_id),
+                    }
+                })
+                .collect();
+
+            self.archetypes.push(ReplicatedArchetype {
+                id: archetype.id(),
+                components,
+            });
+        }
+        self.generation = archetypes.len();
+    }
+
+    /// Returns the tick of the last rule set switch or toggle if the component started replicating on it.
+    fn switched_at(&self, component_id: ComponentId) -> Option<Tick> {
+        self.switch
+            .as_ref()
+            .filter(|(_, switched)| switched.contains(&component_id))
+            .map(|&(tick, _)| tick)
+    }
+
+    /// Forgets the last rule set switch or toggle if all clients acknowledged it.
+    fn cleanup_switch(&mut self, acked_ticks: &AckedTicks, this_run: Tick) {
+        if let Some((switch_tick, _)) = self.switch {
+            if acked_ticks
+                .values()
+                .all(|tick| tick.is_newer_than(switch_tick, this_run))
+            {
+                self.switch = None;
+            }
+        }
+    }
+}
+
+/// Returns the active rule set and rule sets of all contexts.
+fn used_rule_sets(
+    rule_set: Option<&'static str>,
+    context_rule_sets: &HashMap<ReplicationContext, &'static str>,
+) -> HashSet<&'static str> {
+    rule_set
+        .into_iter()
+        .chain(context_rule_sets.values().copied())
+        .collect()
+}
+
+/// Archetype with entities that should be replicated and its replicated components.
+struct ReplicatedArchetype {
+    id: ArchetypeId,
+    components: Vec<ArchetypeComponent>,
+}
+
+/// Replicated component of an archetype with its replication options.
+struct ArchetypeComponent {
+    component_id: ComponentId,
+    storage_type: StorageType,
+    /// Replicated only on insertion, see [`ReplicationRuleBuilder::once`](crate::replication_core::ReplicationRuleBuilder::once).
+    once: bool,
+    /// See [`ReplicationRuleBuilder::max_lod`](crate::replication_core::ReplicationRuleBuilder::max_lod).
+    max_lod: u8,
+    /// Sent over the reliable channel, see [`ReplicationRuleBuilder::reliable`](crate::replication_core::ReplicationRuleBuilder::reliable).
+    reliable: bool,
+    /// Changes are not sent to the owner, see [`ReplicationRuleBuilder::client_authoritative`](crate::replication_core::ReplicationRuleBuilder::client_authoritative).
+    client_authoritative: bool,
+    /// Sent only to the owner, see [`ReplicationRuleBuilder::owner_only`](crate::replication_core::ReplicationRuleBuilder::owner_only).
+    owner_only: bool,
+    /// Replicated regardless of rule sets, other components are sent only for entities
+    /// whose context uses their rule set.
+    always: bool,
+}
+
+/// Replicated component of an archetype with resolved storage and reflection data.
+struct ReplicatedComponent<'a> {
+    storage: ComponentStorage<'a>,
+    reflect_from_ptr: &'a ReflectFromPtr,
+    once: bool,
+    send_info: ComponentSendInfo<'a>,
+}
+
+impl<'a> ReplicatedComponent<'a> {
+    fn new(
+        world: &'a World,
+        registry: &'a TypeRegistryInternal,
+        table: &'a Table,
+        archetype_component: &ArchetypeComponent,
+        switched_at: Option<Tick>,
+    ) -> Self {
+        let component_id = archetype_component.component_id;
+        let registration = registration(world, registry, component_id);
+        let type_name = registration.type_name();
+        let reflect_from_ptr = registration
+            .data::<ReflectFromPtr>()
+            .unwrap_or_else(|| panic!("{type_name} should have `ReflectFromPtr`"));
+        let storage = match archetype_component.storage_type {
+            StorageType::Table => ComponentStorage::Table(
+                table
+                    .get_column(component_id)
+                    .unwrap_or_else(|| panic!("{type_name} should have a valid column")),
+            ),
+            StorageType::SparseSet => ComponentStorage::SparseSet(
+                world
+                    .storages()
+                    .sparse_sets
+                    .get(component_id)
+                    .unwrap_or_else(|| panic!("{type_name} should exists in a sparse set")),
+            ),
+        };
+
+        Self {
+            storage,
+            reflect_from_ptr,
+            once: archetype_component.once,
+            send_info: ComponentSendInfo {
+                component_id,
+                registration,
+                switched_at,
+                always: archetype_component.always,
+                max_lod: archetype_component.max_lod,
+                client_authoritative: archetype_component.client_authoritative,
+                owner_only: archetype_component.owner_only,
+            },
+        }
+    }
+
+    /// Returns component ticks.
+    ///
+    /// For components replicated only on insertion changes are ignored.
+    fn ticks(&self, archetype_entity: &ArchetypeEntity) -> ComponentTicks {
+        let mut ticks = match self.storage {
+            // SAFETY: the table row obtained from the world state.
+            ComponentStorage::Table(column) => unsafe {
+                column.get_ticks_unchecked(archetype_entity.table_row())
+            },
+            ComponentStorage::SparseSet(sparse_set) => sparse_set
+                .get_ticks(archetype_entity.entity())
+                .unwrap_or_else(|| panic!("{} should have ticks", self.type_name())),
+        };
+        if self.once {
+            ticks.set_changed(ticks.added_tick());
         }
+        ticks
+    }
+
+    fn value(&self, archetype_entity: &ArchetypeEntity) -> &'a dyn Reflect {
+        let ptr = match self.storage {
+            // SAFETY: the table row obtained from the world state.
+            ComponentStorage::Table(column) => unsafe {
+                column.get_data_unchecked(archetype_entity.table_row())
+            },
+            ComponentStorage::SparseSet(sparse_set) => sparse_set
+                .get(archetype_entity.entity())
+                .unwrap_or_else(|| panic!("{} should exists in a sparse set", self.type_name())),
+        };
+        // SAFETY: the pointer obtained for the same component type for which `ReflectFromPtr` was created.
+        unsafe { self.reflect_from_ptr.as_reflect_ptr(ptr) }
+    }
+
+    fn type_name(&self) -> &str {
+        self.send_info.registration.type_name()
     }
 }
 
+/// Options of a replicated component that decide to which clients its changes are sent.
+///
+/// Built once per archetype component in [`ReplicatedComponent`].
+#[derive(Clone, Copy)]
+struct ComponentSendInfo<'a> {
+    component_id: ComponentId,
+    registration: &'a TypeRegistration,
+    /// Tick of the last rule set switch or toggle if the component started replicating on it.
+    switched_at: Option<Tick>,
+    /// See [`ArchetypeComponent::always`].
+    always: bool,
+    /// See [`ArchetypeComponent::max_lod`].
+    max_lod: u8,
+    /// See [`ArchetypeComponent::client_authoritative`].
+    client_authoritative: bool,
+    /// See [`ArchetypeComponent::owner_only`].
+    owner_only: bool,
+}
+
+/// Component of an entity that changed since the oldest acknowledged tick.
+struct ComponentChange<'a> {
+    send_info: ComponentSendInfo<'a>,
+    ticks: ComponentTicks,
+    value: &'a dyn Reflect,
+}
+
+enum ComponentStorage<'a> {
+    Table(&'a Column),
+    SparseSet(&'a ComponentSparseSet),
+}
+
+/// Returns registration for a component.
+fn registration<'a>(
+    world: &World,
+    registry: &'a TypeRegistryInternal,
+    component_id: ComponentId,
+) -> &'a TypeRegistration {
+    // SAFETY: `component_id` obtained from the world.
+    let component_info = unsafe { world.components().get_info_unchecked(component_id) };
+    let type_name = component_info.name();
+    let type_id = component_info
+        .type_id()
+        .unwrap_or_else(|| panic!("{type_name} should have registered TypeId"));
+    registry
+        .get(type_id)
+        .unwrap_or_else(|| panic!("{type_name} should be registered"))
+}
+
 fn collect_despawns(
     client_diffs: &mut HashMap<u64, WorldDiff>,
     change_tick: &SystemChangeTick,
     despawn_tracker: &DespawnTracker,
+    visibility: &ClientVisibility,
+    contexts: &ReplicationContexts,
 ) {
-    for (entity, tick) in despawn_tracker.despawns.iter().copied() {
+    for (entity, tick, reason) in despawn_tracker.despawns.iter().copied() {
         for world_diff in client_diffs.values_mut() {
             if tick.is_newer_than(world_diff.tick, change_tick.this_run()) {
-                world_diff.despawns.push(entity);
+                world_diff.despawns.push((entity, reason));
+            }
+        }
+    }
+
+    for (&client_id, world_diff) in client_diffs.iter_mut() {
+        for (entity, tick) in visibility.left_entities(client_id) {
+            if tick.is_newer_than(world_diff.tick, change_tick.this_run()) {
+                match visibility.leave_policy() {
+                    LeavePolicy::Despawn => world_diff
+                        .despawns
+                        .push((entity, DespawnReason::LeftVisibility)),
+                    LeavePolicy::Hide => world_diff.hidden.push(entity),
+                }
             }
         }
+        for (entity, tick) in contexts.left_entities(client_id) {
+            if tick.is_newer_than(world_diff.tick, change_tick.this_run()) {
+                world_diff
+                    .despawns
+                    .push((entity, DespawnReason::LeftContext));
+            }
+        }
+    }
+}
+
+/// Orders entities in each diff by their [`ReplicationPriority`] for the client.
+///
+/// Entities inside each [`ArchetypeDiff`] are sorted in descending order of priority
+/// and groups are sorted by their highest priority, so the most important changes come first.
+fn prioritize_diffs(client_diffs: &mut HashMap<u64, WorldDiff>, world: &World) {
+    for (&client_id, world_diff) in client_diffs.iter_mut() {
+        let priority = |entity| entity_priority(world, entity, client_id);
+
+        let max_priority = |archetype_diff: &ArchetypeDiff| {
+            archetype_diff
+                .entities
+                .first()
+                .map_or(f32::MIN, |entity_diff| priority(entity_diff.entity))
+        };
+
+        for archetype_diff in &mut world_diff.archetypes {
+            archetype_diff
+                .entities
+                .sort_by(|a, b| priority(b.entity).total_cmp(&priority(a.entity)));
+        }
+        world_diff
+            .archetypes
+            .sort_by(|a, b| max_priority(b).total_cmp(&max_priority(a)));
     }
 }
 
+/// Time after which kicked clients that didn't disconnect themselves will be disconnected by server.
+const KICK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An event that emitted on server once after a client acknowledges its initial world state.
+///
+/// Emitted only when no entities of the initial world are deferred for the client, for example
+/// by [`MessageEntityLimit`] or [`DiffBuildBudget`], or unconfirmed because of
+/// [`SpawnBudget`](crate::client::SpawnBudget).
+/// The client receives [`InitialSyncComplete`](crate::client::InitialSyncComplete) right after it,
+/// so it's a good moment to start gameplay for it, like spawning its character.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ClientSynced {
+    pub client_id: u64,
+}
+
+/// Initial world state progress of connected clients.
+#[derive(Default, Resource)]
+struct ClientSyncs(HashMap<u64, SyncState>);
+
+enum SyncState {
+    /// Waiting for the world state since the tick of the first acknowledgment.
+    Pending(Tick),
+    /// [`ClientSynced`] was emitted.
+    Synced,
+}
+
+/// An event to disconnect a client with an app-level code.
+///
+/// The client receives the code as [`ClientDisconnectReason::Kicked`](crate::client::ClientDisconnectReason::Kicked)
+/// and disconnects itself. If it doesn't disconnect within a second, the server disconnects it without the code.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct KickClient {
+    pub client_id: u64,
+    pub code: u32,
+}
+
 /// Condition that returns `true` for server or in singleplayer and `false` for client.
-pub fn has_authority() -> impl FnMut(Option<Res<RenetClient>>) -> bool + Clone {
-    move |client| client.is_none()
+///
+/// Also returns `false` while client resources are being replaced with [`TransportSwitch`].
+pub fn has_authority(
+) -> impl FnMut(Option<Res<RenetClient>>, Option<Res<TransportSwitch>>) -> bool + Clone {
+    move |client, switch| client.is_none() && !switch.is_some_and(|switch| switch.is_client())
 }
 
 /// Set with replication and event systems related to server.
@@ -318,6 +1563,10 @@ pub enum ServerSet {
     Send,
 }
 
+/// Controls how often [`ServerSet::Send`] runs.
+///
+/// Doesn't affect connection timeouts since Renet keeps connections alive on every frame,
+/// see [`NetcodeSettings`](crate::netcode::NetcodeSettings).
 pub enum TickPolicy {
     /// Max number of updates sent from server per second. May be lower if update cycle duration is too long.
     ///
@@ -332,3 +1581,85 @@ pub enum TickPolicy {
 /// Used only on server.
 #[derive(Default, Deref, Resource)]
 pub struct AckedTicks(pub(super) HashMap<u64, Tick>);
+
+/// Maps client entities to server entities for each client.
+///
+/// Filled from mappings that clients send after spawning replicated entities,
+/// used to map entities inside client events.
+/// Mappings are removed automatically when server entities are despawned.
+///
+/// Used only on server.
+#[derive(Default, Deref, Resource)]
+pub struct ClientEntityMaps(pub(super) HashMap<u64, ClientEntityMap>);
+
+/// Maps entities of a single client to server entities and vice versa.
+#[derive(Default)]
+pub struct ClientEntityMap {
+    client_to_server: EntityMap,
+    server_to_client: EntityMap,
+}
+
+impl ClientEntityMap {
+    /// Returns the estimated memory in bytes used by the mappings.
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.client_to_server.len() + self.server_to_client.len())
+            * mem::size_of::<(Entity, Entity)>()
+    }
+
+    fn insert(&mut self, server_entity: Entity, client_entity: Entity) {
+        self.client_to_server.insert(client_entity, server_entity);
+        self.server_to_client.insert(server_entity, client_entity);
+    }
+
+    fn remove_by_server(&mut self, server_entity: Entity) {
+        if let Some(client_entity) = self.server_to_client.remove(server_entity) {
+            self.client_to_server.remove(client_entity);
+        }
+    }
+
+    /// Maps client entities inside `value` to server entities using [`MapEntities`].
+    ///
+    /// Returns the first client entity that has no server mapping as an error.
+    /// Unmapped entities are replaced with [`Entity::PLACEHOLDER`].
+    pub fn map_to_server<T: MapEntities>(
+        &self,
+        world: &mut World,
+        value: &mut T,
+    ) -> Result<(), Entity> {
+        client::try_map_entities(world, &self.client_to_server, value)
+    }
+
+    pub fn to_server(&self) -> &EntityMap {
+        &self.client_to_server
+    }
+
+    pub fn to_client(&self) -> &EntityMap {
+        &self.server_to_client
+    }
+}
+
+/// A tick that increments each time the server sends updates, according to [`TickPolicy`].
+///
+/// Sent to clients with every world diff and replicated into the same resource on client,
+/// so it can be used to synchronize cooldowns, timers and animations.
+/// Unlike the change tick from Bevy it doesn't depend on the number of systems.
+/// See also [`ServerTickEstimate`](crate::client::ServerTickEstimate) to get the tick between updates on client.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Resource, Serialize)]
+pub struct ServerTick(u32);
+
+impl ServerTick {
+    /// Returns the current tick value.
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Returns `true` if the tick is newer than `other`, taking wrapping into account.
+    pub(super) fn is_newer_than(self, other: Self) -> bool {
+        (self.0.wrapping_sub(other.0) as i32) > 0
+    }
+
+    /// Increments the tick, wrapping around on overflow.
+    pub(super) fn increment(&mut self) {
+        self.0 = self.0.wrapping_add(1);
+    }
+}
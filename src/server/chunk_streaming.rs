@@ -0,0 +1,312 @@
+use std::mem;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::RenetServer;
+
+use super::{client_visibility::ClientVisibilityPlugin, ClientVisibility, ServerTick};
+use crate::replication_core::SpawnGroup;
+
+/// Streams entities of subscribed chunks in and out of client visibility.
+///
+/// Used only on server.
+pub(super) struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkStreaming>().add_systems(
+            PostUpdate,
+            (Self::indexing_system, Self::streaming_system)
+                .chain()
+                .before(ClientVisibilityPlugin::despawn_cleanup_system)
+                .run_if(resource_exists::<RenetServer>()),
+        );
+    }
+}
+
+impl ChunkStreamingPlugin {
+    fn indexing_system(
+        mut streaming: ResMut<ChunkStreaming>,
+        mut removed_chunks: RemovedComponents<ReplicationChunk>,
+        chunks: Query<(Entity, &ReplicationChunk), Changed<ReplicationChunk>>,
+    ) {
+        for entity in &mut removed_chunks {
+            streaming.remove_entity(entity);
+        }
+        for (entity, chunk) in &chunks {
+            streaming.move_entity(entity, chunk.0);
+        }
+    }
+
+    fn streaming_system(
+        mut streaming: ResMut<ChunkStreaming>,
+        mut visibility: ResMut<ClientVisibility>,
+        server_tick: Res<ServerTick>,
+        groups: Query<&SpawnGroup>,
+    ) {
+        let streaming = &mut *streaming;
+        let tick = server_tick.get();
+        for (&client_id, client_chunks) in &mut streaming.clients {
+            let subscribed_entities: HashSet<_> = client_chunks
+                .subscribed
+                .iter()
+                .filter_map(|chunk| streaming.chunk_entities.get(chunk))
+                .flatten()
+                .copied()
+                .collect();
+
+            client_chunks
+                .leaving
+                .retain(|entity, _| !subscribed_entities.contains(entity));
+
+            let mut left = Vec::new();
+            for &entity in client_chunks.streamed.difference(&subscribed_entities) {
+                if !streaming.entity_chunks.contains_key(&entity) {
+                    // Entity is no longer controlled by chunks.
+                    left.push(entity);
+                    continue;
+                }
+                let left_tick = *client_chunks.leaving.entry(entity).or_insert(tick);
+                if tick.wrapping_sub(left_tick) >= streaming.exit_delay {
+                    left.push(entity);
+                }
+            }
+            if !left.is_empty() {
+                for entity in &left {
+                    client_chunks.streamed.remove(entity);
+                    client_chunks.leaving.remove(entity);
+                }
+                visibility.set_visibility_many(client_id, left, false);
+            }
+
+            let mut entered: Vec<_> = subscribed_entities
+                .difference(&client_chunks.streamed)
+                .copied()
+                .collect();
+            if entered.len() > streaming.budget {
+                entered = take_budgeted(entered, streaming.budget, &groups);
+            }
+            if !entered.is_empty() {
+                client_chunks.streamed.extend(&entered);
+                visibility.set_visibility_many(client_id, entered, true);
+            }
+        }
+    }
+}
+
+/// Takes entities within the budget, keeping entities with the same [`SpawnGroup`] together.
+///
+/// A group larger than the budget is taken alone to not block it forever.
+fn take_budgeted(entities: Vec<Entity>, budget: usize, groups: &Query<&SpawnGroup>) -> Vec<Entity> {
+    let mut group_members = HashMap::<_, Vec<_>>::new();
+    for &entity in &entities {
+        if let Ok(group) = groups.get(entity) {
+            group_members.entry(*group).or_default().push(entity);
+        }
+    }
+
+    let mut taken = Vec::new();
+    for entity in entities {
+        let unit = match groups.get(entity) {
+            Ok(group) => match group_members.remove(group) {
+                Some(members) => members,
+                None => continue,
+            },
+            Err(_) => vec![entity],
+        };
+        if !taken.is_empty() && taken.len() + unit.len() > budget {
+            break;
+        }
+        taken.extend(unit);
+        if taken.len() >= budget {
+            break;
+        }
+    }
+
+    taken
+}
+
+/// Spatial chunk of a replicated entity for [`ChunkStreaming`].
+///
+/// Coordinates are arbitrary, for example a position divided by the chunk size.
+/// Update it when the entity moves to another chunk.
+#[derive(Clone, Component, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ReplicationChunk(pub IVec3);
+
+/// Controls which chunks clients are subscribed to.
+///
+/// Entities with [`ReplicationChunk`] become visible for a client when it subscribes to their chunk
+/// and stop being visible when it unsubscribes or when the entity moves to a chunk without subscription.
+/// Requires [`VisibilityPolicy::Whitelist`](super::VisibilityPolicy::Whitelist), visibility of entities with
+/// [`ReplicationChunk`] is fully controlled by this resource, while other entities are unaffected.
+///
+/// The number of entities that become visible for a client per tick is limited by the budget,
+/// the rest will be streamed in on the next ticks. Entities with the same [`SpawnGroup`] are always
+/// streamed in together. Entities stream out without the limit.
+///
+/// To avoid spawning and despawning entities that move back and forth across a chunk border,
+/// streaming out could be delayed with [`Self::with_exit_delay`]. Entities that return to
+/// a subscribed chunk within the delay stay visible without any messages.
+///
+/// Used only on server.
+#[derive(Resource)]
+pub struct ChunkStreaming {
+    budget: usize,
+    exit_delay: u32,
+    clients: HashMap<u64, ClientChunks>,
+    chunk_entities: HashMap<IVec3, HashSet<Entity>>,
+    entity_chunks: HashMap<Entity, IVec3>,
+}
+
+impl Default for ChunkStreaming {
+    fn default() -> Self {
+        Self::new(usize::MAX)
+    }
+}
+
+impl ChunkStreaming {
+    /// Creates streaming that makes at most `budget` entities visible for each client per tick.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            exit_delay: 0,
+            clients: Default::default(),
+            chunk_entities: Default::default(),
+            entity_chunks: Default::default(),
+        }
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Keeps entities visible for `ticks` of [`ServerTick`] after they leave subscribed chunks.
+    ///
+    /// Entities are streamed out immediately by default.
+    #[must_use]
+    pub fn with_exit_delay(mut self, ticks: u32) -> Self {
+        self.exit_delay = ticks;
+        self
+    }
+
+    pub fn exit_delay(&self) -> u32 {
+        self.exit_delay
+    }
+
+    /// Subscribes a client to a chunk.
+    pub fn subscribe(&mut self, client_id: u64, chunk: IVec3) {
+        self.clients
+            .entry(client_id)
+            .or_default()
+            .subscribed
+            .insert(chunk);
+    }
+
+    /// Unsubscribes a client from a chunk.
+    pub fn unsubscribe(&mut self, client_id: u64, chunk: IVec3) {
+        if let Some(client_chunks) = self.clients.get_mut(&client_id) {
+            client_chunks.subscribed.remove(&chunk);
+        }
+    }
+
+    /// Returns `true` if a client is subscribed to a chunk.
+    pub fn is_subscribed(&self, client_id: u64, chunk: IVec3) -> bool {
+        self.clients
+            .get(&client_id)
+            .is_some_and(|client_chunks| client_chunks.subscribed.contains(&chunk))
+    }
+
+    /// Returns all chunks a client is subscribed to.
+    pub fn chunks(&self, client_id: u64) -> impl Iterator<Item = IVec3> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_chunks| client_chunks.subscribed.iter().copied())
+    }
+
+    /// Returns the number of entities that are subscribed by a client, but not streamed in yet.
+    pub fn pending_count(&self, client_id: u64) -> usize {
+        let Some(client_chunks) = self.clients.get(&client_id) else {
+            return 0;
+        };
+        client_chunks
+            .subscribed
+            .iter()
+            .filter_map(|chunk| self.chunk_entities.get(chunk))
+            .flatten()
+            .filter(|&entity| !client_chunks.streamed.contains(entity))
+            .count()
+    }
+
+    fn move_entity(&mut self, entity: Entity, chunk: IVec3) {
+        self.remove_entity(entity);
+        self.chunk_entities.entry(chunk).or_default().insert(entity);
+        self.entity_chunks.insert(entity, chunk);
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        let Some(chunk) = self.entity_chunks.remove(&entity) else {
+            return;
+        };
+        if let Some(entities) = self.chunk_entities.get_mut(&chunk) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.chunk_entities.remove(&chunk);
+            }
+        }
+    }
+
+    /// Returns the estimated memory in bytes used by chunk subscriptions of a client.
+    pub(crate) fn memory_usage(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |client_chunks| {
+            client_chunks.subscribed.len() * mem::size_of::<IVec3>()
+                + client_chunks.streamed.len() * mem::size_of::<Entity>()
+                + client_chunks.leaving.len() * mem::size_of::<(Entity, u32)>()
+        })
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// Chunks state for a single client.
+#[derive(Default)]
+struct ClientChunks {
+    subscribed: HashSet<IVec3>,
+
+    /// Entities that were made visible for the client.
+    streamed: HashSet<Entity>,
+
+    /// Streamed entities outside of subscribed chunks with ticks at which they left.
+    leaving: HashMap<Entity, u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_indexing() {
+        let mut streaming = ChunkStreaming::default();
+        const CLIENT_ID: u64 = 1;
+        let entity = Entity::PLACEHOLDER;
+        streaming.subscribe(CLIENT_ID, IVec3::ZERO);
+        assert!(streaming.is_subscribed(CLIENT_ID, IVec3::ZERO));
+
+        streaming.move_entity(entity, IVec3::ZERO);
+        assert_eq!(streaming.pending_count(CLIENT_ID), 1);
+
+        streaming.move_entity(entity, IVec3::X);
+        assert_eq!(streaming.pending_count(CLIENT_ID), 0);
+
+        streaming.unsubscribe(CLIENT_ID, IVec3::ZERO);
+        assert_eq!(streaming.chunks(CLIENT_ID).count(), 0);
+    }
+}
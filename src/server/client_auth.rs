@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::{RenetServer, ServerEvent};
+
+use super::ServerSet;
+use crate::{
+    network_event::{
+        client_event::FromClient,
+        server_event::{SendMode, ToClients},
+    },
+    replication_core::{AuthRequest, AuthRequired},
+};
+
+/// Validates credentials of connected clients with [`ServerAuth`].
+///
+/// Used only on server.
+pub(super) struct ClientAuthPlugin;
+
+impl Plugin for ClientAuthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClientAuthenticated>()
+            .add_event::<ClientAuthRejected>()
+            .add_systems(
+                PreUpdate,
+                (
+                    Self::connections_system,
+                    Self::validation_system,
+                    Self::timeout_system,
+                )
+                    .chain()
+                    .after(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>())
+                    .run_if(resource_exists::<ServerAuth>()),
+            )
+            .add_systems(
+                PostUpdate,
+                Self::reset_system
+                    .run_if(resource_removed::<RenetServer>())
+                    .run_if(resource_exists::<ServerAuth>()),
+            );
+    }
+}
+
+impl ClientAuthPlugin {
+    fn validation_system(
+        mut auth_events: EventReader<FromClient<AuthRequest>>,
+        mut authenticated_events: EventWriter<ClientAuthenticated>,
+        mut rejected_events: EventWriter<ClientAuthRejected>,
+        mut auth: ResMut<ServerAuth>,
+    ) {
+        for FromClient {
+            client_id, event, ..
+        } in &mut auth_events
+        {
+            let client_id = *client_id;
+            if auth.is_authenticated(client_id) {
+                debug!("ignoring repeated credentials from client {client_id}");
+                continue;
+            }
+
+            if auth.pending.remove(&client_id).is_none() {
+                debug!("ignoring credentials from client {client_id} that was already rejected");
+                continue;
+            }
+
+            match (auth.validator)(client_id, &event.credentials) {
+                Ok(()) => {
+                    auth.authenticated.insert(client_id);
+                    authenticated_events.send(ClientAuthenticated { client_id });
+                }
+                Err(code) => {
+                    debug!("rejecting credentials of client {client_id} with code {code}");
+                    rejected_events.send(ClientAuthRejected { client_id, code });
+                }
+            }
+        }
+    }
+
+    /// Notifies connected clients that they need to authenticate.
+    fn connections_system(
+        time: Res<Time>,
+        mut server_events: EventReader<ServerEvent>,
+        mut required_events: EventWriter<ToClients<AuthRequired>>,
+        mut auth: ResMut<ServerAuth>,
+    ) {
+        for event in &mut server_events {
+            match *event {
+                ServerEvent::ClientConnected { client_id } => {
+                    auth.pending.insert(client_id, time.elapsed());
+                    required_events.send(ToClients {
+                        mode: SendMode::Direct(client_id),
+                        event: AuthRequired,
+                    });
+                }
+                ServerEvent::ClientDisconnected { client_id, .. } => {
+                    auth.pending.remove(&client_id);
+                    auth.authenticated.remove(&client_id);
+                }
+            }
+        }
+    }
+
+    /// Rejects clients that didn't send credentials within [`ServerAuth::timeout`].
+    fn timeout_system(
+        time: Res<Time>,
+        mut rejected_events: EventWriter<ClientAuthRejected>,
+        mut auth: ResMut<ServerAuth>,
+    ) {
+        let timeout = auth.timeout;
+        auth.pending.retain(|&client_id, connected_at| {
+            if time.elapsed().saturating_sub(*connected_at) < timeout {
+                return true;
+            }
+            debug!("rejecting client {client_id} because it didn't send credentials in time");
+            rejected_events.send(ClientAuthRejected {
+                client_id,
+                code: ServerAuth::TIMEOUT_CODE,
+            });
+            false
+        });
+    }
+
+    fn reset_system(mut auth: ResMut<ServerAuth>) {
+        auth.pending.clear();
+        auth.authenticated.clear();
+    }
+}
+
+/// Validates credentials sent by a client.
+///
+/// Accepts the client ID and [`AuthCredentials`](crate::client::AuthCredentials) of the client.
+/// Returns an app-level code on rejection.
+pub type AuthValidator = Box<dyn Fn(u64, &[u8]) -> Result<(), u32> + Send + Sync>;
+
+/// Authentication of clients after the transport connection.
+///
+/// Clients send their [`AuthCredentials`](crate::client::AuthCredentials) once connected
+/// and server checks them with the validator. Until accepted, acknowledgments from the client
+/// are ignored, so it doesn't receive replication. Accepted clients emit [`ClientAuthenticated`]
+/// and start receiving the world from scratch. Rejected clients emit [`ClientAuthRejected`]
+/// and are disconnected with [`ClientDisconnectReason::AuthRejected`](crate::client::ClientDisconnectReason::AuthRejected).
+/// Clients that don't send credentials within [`Self::timeout`] are rejected with [`Self::TIMEOUT_CODE`].
+///
+/// Network events are not affected, use [`Self::is_authenticated`] to ignore events from clients
+/// that are not authenticated yet.
+///
+/// Insert as a resource on server before starting it to enable.
+///
+/// Used only on server.
+#[derive(Resource)]
+pub struct ServerAuth {
+    validator: AuthValidator,
+    timeout: Duration,
+    /// Connection times of clients that didn't send credentials yet.
+    pending: HashMap<u64, Duration>,
+    authenticated: HashSet<u64>,
+}
+
+impl ServerAuth {
+    /// Rejection code for clients that didn't send credentials within [`Self::timeout`].
+    pub const TIMEOUT_CODE: u32 = u32::MAX;
+
+    /// Creates authentication that checks credentials with `validator`.
+    ///
+    /// The validator could capture state, like a list of issued session tokens.
+    pub fn new(validator: impl Fn(u64, &[u8]) -> Result<(), u32> + Send + Sync + 'static) -> Self {
+        Self {
+            validator: Box::new(validator),
+            timeout: Duration::from_secs(5),
+            pending: Default::default(),
+            authenticated: Default::default(),
+        }
+    }
+
+    /// Sets the time after connection during which clients should send credentials.
+    ///
+    /// 5 seconds by default.
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the time after connection during which clients should send credentials.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns `true` if credentials of the client were accepted.
+    pub fn is_authenticated(&self, client_id: u64) -> bool {
+        self.authenticated.contains(&client_id)
+    }
+
+    /// Returns iterator over authenticated clients.
+    pub fn authenticated(&self) -> impl Iterator<Item = u64> + '_ {
+        self.authenticated.iter().copied()
+    }
+}
+
+/// An event that emitted on server when credentials of a client are accepted by [`ServerAuth`].
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ClientAuthenticated {
+    pub client_id: u64,
+}
+
+/// An event that emitted on server when credentials of a client are rejected by [`ServerAuth`].
+///
+/// The client will be disconnected by server with the code returned by the validator
+/// or [`ServerAuth::TIMEOUT_CODE`] if it didn't send credentials in time.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ClientAuthRejected {
+    pub client_id: u64,
+    pub code: u32,
+}
@@ -0,0 +1,62 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+/// Named groups of clients, like rooms or teams.
+///
+/// Server events could be sent to all clients of a group with
+/// [`SendMode::BroadcastGroup`](crate::network_event::server_event::SendMode::BroadcastGroup).
+/// Add [`SERVER_ID`](super::SERVER_ID) to a group to receive its events locally when the server is also a player.
+/// Disconnected clients are removed from all groups automatically.
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ClientGroups(HashMap<&'static str, HashSet<u64>>);
+
+impl ClientGroups {
+    /// Adds a client to a group, creating the group if needed.
+    pub fn insert(&mut self, group: &'static str, client_id: u64) {
+        self.0.entry(group).or_default().insert(client_id);
+    }
+
+    /// Removes a client from a group.
+    ///
+    /// Groups without clients are removed.
+    pub fn remove(&mut self, group: &'static str, client_id: u64) {
+        if let Some(clients) = self.0.get_mut(group) {
+            clients.remove(&client_id);
+            if clients.is_empty() {
+                self.0.remove(group);
+            }
+        }
+    }
+
+    /// Returns `true` if a client is in a group.
+    pub fn contains(&self, group: &'static str, client_id: u64) -> bool {
+        self.0
+            .get(group)
+            .is_some_and(|clients| clients.contains(&client_id))
+    }
+
+    /// Returns all clients of a group.
+    pub fn clients(&self, group: &'static str) -> impl Iterator<Item = u64> + '_ {
+        self.0.get(group).into_iter().flatten().copied()
+    }
+
+    /// Returns names of all groups that have clients.
+    pub fn groups(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.keys().copied()
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.0.retain(|_, clients| {
+            clients.remove(&client_id);
+            !clients.is_empty()
+        });
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.0.clear();
+    }
+}
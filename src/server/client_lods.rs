@@ -0,0 +1,188 @@
+use std::mem;
+
+use bevy::{
+    ecs::{component::Tick, system::SystemChangeTick},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::RenetServer;
+
+use super::{AckedTicks, ServerSet};
+use crate::replication_core::Replication;
+
+/// Assigns ticks to LOD changes and cleanups acknowledged ones.
+///
+/// Used only on server.
+pub(super) struct ClientLodsPlugin;
+
+impl Plugin for ClientLodsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientLods>().add_systems(
+            PostUpdate,
+            (
+                Self::despawn_cleanup_system,
+                Self::cleanup_system,
+                Self::tick_assignment_system,
+            )
+                .chain()
+                .before(ServerSet::Send)
+                .run_if(resource_exists::<RenetServer>()),
+        );
+    }
+}
+
+impl ClientLodsPlugin {
+    fn despawn_cleanup_system(
+        mut removed_replications: RemovedComponents<Replication>,
+        mut lods: ResMut<ClientLods>,
+    ) {
+        for entity in &mut removed_replications {
+            for client_lods in lods.clients.values_mut() {
+                client_lods.levels.remove(&entity);
+                client_lods.changed.remove(&entity);
+            }
+        }
+    }
+
+    /// Removes LOD changes that were acknowledged by clients.
+    fn cleanup_system(
+        change_tick: SystemChangeTick,
+        mut lods: ResMut<ClientLods>,
+        acked_ticks: Res<AckedTicks>,
+    ) {
+        for (client_id, client_lods) in &mut lods.clients {
+            let Some(&last_tick) = acked_ticks.get(client_id) else {
+                continue;
+            };
+            client_lods.changed.retain(|_, tick| {
+                tick.map_or(true, |tick| {
+                    tick.is_newer_than(last_tick, change_tick.this_run())
+                })
+            });
+        }
+    }
+
+    /// Assigns the current tick to LOD changes that were made since the last run.
+    fn tick_assignment_system(change_tick: SystemChangeTick, mut lods: ResMut<ClientLods>) {
+        for client_lods in lods.clients.values_mut() {
+            for tick in client_lods
+                .changed
+                .values_mut()
+                .filter(|tick| tick.is_none())
+            {
+                *tick = Some(change_tick.this_run());
+            }
+        }
+    }
+}
+
+/// Level of detail of replicated entities for each client.
+///
+/// Components marked with [`ReplicationRuleBuilder::max_lod`](crate::replication_core::ReplicationRuleBuilder::max_lod)
+/// are replicated only while the entity LOD for a client doesn't exceed their maximum.
+/// When the LOD of an entity changes, the client receives all components of the new level
+/// and components that are no longer included are removed on client.
+///
+/// All entities have LOD 0, the most detailed level, until it's changed.
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ClientLods {
+    clients: HashMap<u64, ClientEntityLods>,
+}
+
+impl ClientLods {
+    /// Sets LOD of an entity for a client, for example based on distance to its camera.
+    ///
+    /// Setting the same LOD again has no effect.
+    pub fn set_lod(&mut self, client_id: u64, entity: Entity, lod: u8) {
+        let client_lods = self.clients.entry(client_id).or_default();
+        let previous = if lod == 0 {
+            client_lods.levels.remove(&entity)
+        } else {
+            client_lods.levels.insert(entity, lod)
+        };
+        if previous.unwrap_or_default() != lod {
+            client_lods.changed.insert(entity, None);
+        }
+    }
+
+    /// Returns LOD of an entity for a client.
+    pub fn lod(&self, client_id: u64, entity: Entity) -> u8 {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_lods| client_lods.levels.get(&entity))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if LOD of an entity for a client changed after `last_tick`.
+    pub(super) fn is_changed(
+        &self,
+        client_id: u64,
+        entity: Entity,
+        last_tick: Tick,
+        this_run: Tick,
+    ) -> bool {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_lods| client_lods.changed.get(&entity))
+            .is_some_and(|tick| tick.map_or(true, |tick| tick.is_newer_than(last_tick, this_run)))
+    }
+
+    /// Returns all entities whose LOD recently changed for any client.
+    pub(super) fn changed_entities(&self) -> HashSet<Entity> {
+        self.clients
+            .values()
+            .flat_map(|client_lods| client_lods.changed.keys().copied())
+            .collect()
+    }
+
+    /// Returns the estimated memory in bytes used by the LOD state of a client.
+    pub(crate) fn memory_usage(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |client_lods| {
+            client_lods.levels.len() * mem::size_of::<(Entity, u8)>()
+                + client_lods.changed.len() * mem::size_of::<(Entity, Option<Tick>)>()
+        })
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// LOD state of entities for a single client.
+#[derive(Default)]
+struct ClientEntityLods {
+    /// LODs of entities that are not 0.
+    levels: HashMap<Entity, u8>,
+
+    /// Entities whose LOD changed and ticks when it was detected.
+    ///
+    /// Such entities are sent with all components of their level until the tick is acknowledged.
+    changed: HashMap<Entity, Option<Tick>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lod_changes() {
+        let mut lods = ClientLods::default();
+        const CLIENT_ID: u64 = 1;
+        let entity = Entity::PLACEHOLDER;
+        assert_eq!(lods.lod(CLIENT_ID, entity), 0);
+
+        lods.set_lod(CLIENT_ID, entity, 0);
+        assert!(lods.changed_entities().is_empty());
+
+        lods.set_lod(CLIENT_ID, entity, 2);
+        assert_eq!(lods.lod(CLIENT_ID, entity), 2);
+        assert!(lods.changed_entities().contains(&entity));
+    }
+}
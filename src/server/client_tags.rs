@@ -0,0 +1,191 @@
+use std::ops::Not;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::{RenetServer, ServerEvent};
+
+use super::{ClientVisibility, ClientVisibilityPlugin, ServerSet};
+
+/// Updates [`ClientVisibility`] of entities with [`TagVisibility`].
+///
+/// Used only on server.
+pub(super) struct ClientTagsPlugin;
+
+impl Plugin for ClientTagsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientTags>().add_systems(
+            PostUpdate,
+            Self::visibility_system
+                .before(ClientVisibilityPlugin::tick_assignment_system)
+                .before(ServerSet::Send)
+                .run_if(resource_exists::<RenetServer>()),
+        );
+    }
+}
+
+impl ClientTagsPlugin {
+    /// Evaluates filters of changed entities, or of all entities if tags changed or clients connected.
+    pub(super) fn visibility_system(
+        mut server_events: EventReader<ServerEvent>,
+        server: Res<RenetServer>,
+        tags: Res<ClientTags>,
+        mut visibility: ResMut<ClientVisibility>,
+        entities: Query<(Entity, Ref<TagVisibility>)>,
+    ) {
+        let clients_connected = server_events
+            .iter()
+            .any(|event| matches!(event, ServerEvent::ClientConnected { .. }));
+        let reevaluate_all = clients_connected || tags.is_changed();
+        for (entity, tag_visibility) in &entities {
+            if !reevaluate_all && !tag_visibility.is_changed() {
+                continue;
+            }
+            for client_id in server.clients_id() {
+                let visible = tags.matches(client_id, &tag_visibility.0);
+                visibility.set_visibility(client_id, entity, visible);
+            }
+        }
+    }
+}
+
+/// Arbitrary tags of clients, like team, region or spectator flag.
+///
+/// Each tag is a key with a value, flags are tags with empty values.
+/// Server events could be sent to clients matching a named [`TagFilter`] with
+/// [`SendMode::BroadcastTagged`](crate::network_event::server_event::SendMode::BroadcastTagged)
+/// and entities could be made visible only to them with [`TagVisibility`].
+/// Filters are also evaluated for [`SERVER_ID`](super::SERVER_ID) to receive matching events locally
+/// when the server is also a player.
+/// Tags of disconnected clients are removed automatically, while filters are kept.
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ClientTags {
+    clients: HashMap<u64, HashMap<&'static str, String>>,
+    filters: HashMap<&'static str, TagFilter>,
+}
+
+impl ClientTags {
+    /// Sets value of a client tag.
+    pub fn insert(&mut self, client_id: u64, key: &'static str, value: impl Into<String>) {
+        self.clients
+            .entry(client_id)
+            .or_default()
+            .insert(key, value.into());
+    }
+
+    /// Sets a client tag without a value.
+    pub fn insert_flag(&mut self, client_id: u64, key: &'static str) {
+        self.insert(client_id, key, String::new());
+    }
+
+    /// Removes a client tag.
+    pub fn remove(&mut self, client_id: u64, key: &'static str) {
+        if let Some(tags) = self.clients.get_mut(&client_id) {
+            tags.remove(key);
+            if tags.is_empty() {
+                self.clients.remove(&client_id);
+            }
+        }
+    }
+
+    /// Returns value of a client tag.
+    pub fn get(&self, client_id: u64, key: &'static str) -> Option<&str> {
+        self.clients
+            .get(&client_id)
+            .and_then(|tags| tags.get(key))
+            .map(String::as_str)
+    }
+
+    /// Returns `true` if tags of a client match the filter.
+    pub fn matches(&self, client_id: u64, filter: &TagFilter) -> bool {
+        filter.evaluate(self.clients.get(&client_id))
+    }
+
+    /// Defines a named filter for [`SendMode::BroadcastTagged`](crate::network_event::server_event::SendMode::BroadcastTagged).
+    ///
+    /// Replaces the previous filter with the same name.
+    pub fn set_filter(&mut self, name: &'static str, filter: TagFilter) {
+        self.filters.insert(name, filter);
+    }
+
+    /// Returns a named filter.
+    pub fn filter(&self, name: &'static str) -> Option<&TagFilter> {
+        self.filters.get(name)
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// An expression over [`ClientTags`].
+///
+/// For example, red team players that are not spectators:
+///
+/// ```
+/// # use bevy_replicon::prelude::*;
+/// let filter = TagFilter::equals("team", "red").and(!TagFilter::has("spectator"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TagFilter {
+    /// Client has the tag with any value.
+    Has(&'static str),
+    /// Client has the tag with the value.
+    Equals(&'static str, String),
+    And(Box<TagFilter>, Box<TagFilter>),
+    Or(Box<TagFilter>, Box<TagFilter>),
+    Not(Box<TagFilter>),
+}
+
+impl TagFilter {
+    pub fn has(key: &'static str) -> Self {
+        Self::Has(key)
+    }
+
+    pub fn equals(key: &'static str, value: impl Into<String>) -> Self {
+        Self::Equals(key, value.into())
+    }
+
+    #[must_use]
+    pub fn and(self, other: Self) -> Self {
+        Self::And(Box::new(self), Box::new(other))
+    }
+
+    #[must_use]
+    pub fn or(self, other: Self) -> Self {
+        Self::Or(Box::new(self), Box::new(other))
+    }
+
+    fn evaluate(&self, tags: Option<&HashMap<&'static str, String>>) -> bool {
+        match self {
+            TagFilter::Has(key) => tags.is_some_and(|tags| tags.contains_key(key)),
+            TagFilter::Equals(key, value) => tags
+                .and_then(|tags| tags.get(key))
+                .is_some_and(|tag| tag == value),
+            TagFilter::And(lhs, rhs) => lhs.evaluate(tags) && rhs.evaluate(tags),
+            TagFilter::Or(lhs, rhs) => lhs.evaluate(tags) || rhs.evaluate(tags),
+            TagFilter::Not(filter) => !filter.evaluate(tags),
+        }
+    }
+}
+
+impl Not for TagFilter {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self::Not(Box::new(self))
+    }
+}
+
+/// Makes a replicated entity visible only to clients whose [`ClientTags`] match the filter.
+///
+/// Re-evaluated when the component or tags change. Requires [`VisibilityPolicy::Blacklist`](super::VisibilityPolicy::Blacklist)
+/// or [`VisibilityPolicy::Whitelist`](super::VisibilityPolicy::Whitelist).
+///
+/// Used only on server.
+#[derive(Component, Clone, Debug)]
+pub struct TagVisibility(pub TagFilter);
@@ -0,0 +1,399 @@
+use std::mem;
+
+use bevy::{
+    ecs::{component::Tick, system::SystemChangeTick},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::RenetServer;
+
+use super::{AckedTicks, ServerSet};
+use crate::replication_core::Replication;
+
+/// Assigns ticks to visibility changes and cleanups acknowledged ones.
+///
+/// Used only on server.
+pub(super) struct ClientVisibilityPlugin;
+
+impl Plugin for ClientVisibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ClientVisibility>().add_systems(
+            PostUpdate,
+            (
+                Self::despawn_cleanup_system,
+                Self::cleanup_system,
+                Self::tick_assignment_system,
+            )
+                .chain()
+                .before(ServerSet::Send)
+                .run_if(resource_exists::<RenetServer>()),
+        );
+    }
+}
+
+impl ClientVisibilityPlugin {
+    pub(super) fn despawn_cleanup_system(
+        mut removed_replications: RemovedComponents<Replication>,
+        mut visibility: ResMut<ClientVisibility>,
+    ) {
+        for entity in &mut removed_replications {
+            for client_entities in visibility.clients.values_mut() {
+                client_entities.remove(entity);
+            }
+        }
+    }
+
+    /// Removes visibility changes that were acknowledged by clients.
+    fn cleanup_system(
+        change_tick: SystemChangeTick,
+        mut visibility: ResMut<ClientVisibility>,
+        acked_ticks: Res<AckedTicks>,
+    ) {
+        for (client_id, client_entities) in &mut visibility.clients {
+            let Some(&last_tick) = acked_ticks.get(client_id) else {
+                continue;
+            };
+            let is_unacked = |tick: &mut Option<Tick>| {
+                tick.map_or(true, |tick| {
+                    tick.is_newer_than(last_tick, change_tick.this_run())
+                })
+            };
+            client_entities.entered.retain(|_, tick| is_unacked(tick));
+            client_entities.left.retain(|_, tick| is_unacked(tick));
+        }
+    }
+
+    /// Assigns the current tick to visibility changes that were made since the last run.
+    pub(super) fn tick_assignment_system(
+        change_tick: SystemChangeTick,
+        mut visibility: ResMut<ClientVisibility>,
+    ) {
+        for client_entities in visibility.clients.values_mut() {
+            for tick in client_entities
+                .entered
+                .values_mut()
+                .chain(client_entities.left.values_mut())
+                .filter(|tick| tick.is_none())
+            {
+                *tick = Some(change_tick.this_run());
+            }
+        }
+    }
+}
+
+/// Controls which replicated entities are visible for each client.
+///
+/// When an entity becomes visible for a client, it will be sent with all its replicated components,
+/// and when it stops being visible, the client will receive a despawn or hide depending on [`LeavePolicy`].
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ClientVisibility {
+    policy: VisibilityPolicy,
+    leave_policy: LeavePolicy,
+    clients: HashMap<u64, ClientEntities>,
+}
+
+impl ClientVisibility {
+    pub fn new(policy: VisibilityPolicy, leave_policy: LeavePolicy) -> Self {
+        Self {
+            policy,
+            leave_policy,
+            clients: Default::default(),
+        }
+    }
+
+    pub fn policy(&self) -> VisibilityPolicy {
+        self.policy
+    }
+
+    pub fn leave_policy(&self) -> LeavePolicy {
+        self.leave_policy
+    }
+
+    /// Sets visibility of an entity for a client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy is [`VisibilityPolicy::All`].
+    pub fn set_visibility(&mut self, client_id: u64, entity: Entity, visible: bool) {
+        self.set_visibility_many(client_id, [entity], visible);
+    }
+
+    /// Same as [`Self::set_visibility`], but for multiple entities at once.
+    ///
+    /// Only entities whose visibility actually changed will be sent or removed on client,
+    /// so it's fine to pass all entities that should have the visibility, for example from a fog of war.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy is [`VisibilityPolicy::All`].
+    pub fn set_visibility_many(
+        &mut self,
+        client_id: u64,
+        entities: impl IntoIterator<Item = Entity>,
+        visible: bool,
+    ) {
+        let listed = match self.policy {
+            VisibilityPolicy::All => {
+                panic!("visibility can't be changed with `VisibilityPolicy::All`")
+            }
+            VisibilityPolicy::Blacklist => !visible,
+            VisibilityPolicy::Whitelist => visible,
+        };
+
+        let client_entities = self.clients.entry(client_id).or_default();
+        for entity in entities {
+            client_entities.set_listed(entity, listed, visible);
+        }
+    }
+
+    /// Replaces all visible entities for a client.
+    ///
+    /// Entities that weren't visible before will be sent and entities that are no longer visible
+    /// will be removed on client according to [`LeavePolicy`], the rest will be unaffected.
+    /// Useful to apply the result of a line of sight or a fog of war computation each tick.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy is not [`VisibilityPolicy::Whitelist`].
+    pub fn set_visible_entities(
+        &mut self,
+        client_id: u64,
+        entities: impl IntoIterator<Item = Entity>,
+    ) {
+        if self.policy != VisibilityPolicy::Whitelist {
+            panic!("visible entities can be replaced only with `VisibilityPolicy::Whitelist`");
+        }
+
+        let client_entities = self.clients.entry(client_id).or_default();
+        let visible: HashSet<_> = entities.into_iter().collect();
+        let hidden: Vec<_> = client_entities
+            .listed
+            .difference(&visible)
+            .copied()
+            .collect();
+        for entity in hidden {
+            client_entities.set_listed(entity, false, false);
+        }
+        for entity in visible {
+            client_entities.set_listed(entity, true, true);
+        }
+    }
+
+    /// Returns `true` if an entity is visible for a client.
+    pub fn is_visible(&self, client_id: u64, entity: Entity) -> bool {
+        match self.policy {
+            VisibilityPolicy::All => true,
+            VisibilityPolicy::Blacklist => self
+                .clients
+                .get(&client_id)
+                .map_or(true, |client_entities| {
+                    !client_entities.listed.contains(&entity)
+                }),
+            VisibilityPolicy::Whitelist => self
+                .clients
+                .get(&client_id)
+                .is_some_and(|client_entities| client_entities.listed.contains(&entity)),
+        }
+    }
+
+    /// Returns entities explicitly listed for a client.
+    ///
+    /// These are visible entities for [`VisibilityPolicy::Whitelist`] and hidden entities for
+    /// [`VisibilityPolicy::Blacklist`]. Always empty for [`VisibilityPolicy::All`].
+    pub fn listed_entities(&self, client_id: u64) -> impl Iterator<Item = Entity> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_entities| client_entities.listed.iter().copied())
+    }
+
+    /// Returns entities that became visible for a client, but the client hasn't acknowledged it yet.
+    ///
+    /// Useful to diff interest of a client, for example to send related data only for newly visible entities.
+    pub fn pending_entered(&self, client_id: u64) -> impl Iterator<Item = Entity> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_entities| client_entities.entered.keys().copied())
+    }
+
+    /// Returns entities that stopped being visible for a client, but the client hasn't acknowledged it yet.
+    pub fn pending_left(&self, client_id: u64) -> impl Iterator<Item = Entity> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_entities| client_entities.left.keys().copied())
+    }
+
+    /// Returns `true` if an entity became visible for a client after `last_tick`.
+    pub(super) fn is_entered(
+        &self,
+        client_id: u64,
+        entity: Entity,
+        last_tick: Tick,
+        this_run: Tick,
+    ) -> bool {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_entities| client_entities.entered.get(&entity))
+            .is_some_and(|tick| tick.map_or(true, |tick| tick.is_newer_than(last_tick, this_run)))
+    }
+
+    /// Returns all entities that recently became visible for any client.
+    pub(super) fn entered_entities(&self) -> HashSet<Entity> {
+        self.clients
+            .values()
+            .flat_map(|client_entities| client_entities.entered.keys().copied())
+            .collect()
+    }
+
+    /// Returns entities that stopped being visible for a client with ticks when it happened.
+    pub(super) fn left_entities(
+        &self,
+        client_id: u64,
+    ) -> impl Iterator<Item = (Entity, Tick)> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_entities| client_entities.left.iter())
+            .filter_map(|(&entity, tick)| tick.map(|tick| (entity, tick)))
+    }
+
+    /// Returns the estimated memory in bytes used by the visibility state of a client.
+    pub(crate) fn memory_usage(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |client_entities| {
+            client_entities.listed.len() * mem::size_of::<Entity>()
+                + (client_entities.entered.len() + client_entities.left.len())
+                    * mem::size_of::<(Entity, Option<Tick>)>()
+        })
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// Visibility state of entities for a single client.
+#[derive(Default)]
+struct ClientEntities {
+    /// Visible entities for [`VisibilityPolicy::Whitelist`] or hidden for [`VisibilityPolicy::Blacklist`].
+    listed: HashSet<Entity>,
+
+    /// Entities that became visible and ticks when it was detected.
+    ///
+    /// Such entities are sent with all their components until the tick is acknowledged.
+    entered: HashMap<Entity, Option<Tick>>,
+
+    /// Entities that stopped being visible and ticks when it was detected.
+    left: HashMap<Entity, Option<Tick>>,
+}
+
+impl ClientEntities {
+    /// Updates the list and records the visibility change if the entity wasn't already in the requested state.
+    fn set_listed(&mut self, entity: Entity, listed: bool, visible: bool) {
+        let changed = if listed {
+            self.listed.insert(entity)
+        } else {
+            self.listed.remove(&entity)
+        };
+        if !changed {
+            return;
+        }
+
+        if visible {
+            self.left.remove(&entity);
+            self.entered.insert(entity, None);
+        } else {
+            self.entered.remove(&entity);
+            self.left.insert(entity, None);
+        }
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        self.listed.remove(&entity);
+        self.entered.remove(&entity);
+        self.left.remove(&entity);
+    }
+}
+
+/// Defines which entities are visible for clients by default.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum VisibilityPolicy {
+    /// All entities are visible, visibility can't be changed.
+    #[default]
+    All,
+    /// All entities are visible by default, visibility should be explicitly disabled.
+    Blacklist,
+    /// All entities are hidden by default, visibility should be explicitly enabled.
+    Whitelist,
+}
+
+/// Defines what clients receive when an entity stops being visible for them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LeavePolicy {
+    /// The entity will be despawned on client.
+    #[default]
+    Despawn,
+    /// The entity will be kept on client and marked with [`ReplicationHidden`](crate::client::ReplicationHidden).
+    Hide,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visibility_changes() {
+        let mut visibility =
+            ClientVisibility::new(VisibilityPolicy::Whitelist, LeavePolicy::Despawn);
+        const CLIENT_ID: u64 = 1;
+        let entity = Entity::PLACEHOLDER;
+        assert!(!visibility.is_visible(CLIENT_ID, entity));
+
+        visibility.set_visibility(CLIENT_ID, entity, true);
+        assert!(visibility.is_visible(CLIENT_ID, entity));
+        assert!(visibility.entered_entities().contains(&entity));
+
+        visibility.set_visibility(CLIENT_ID, entity, false);
+        assert!(!visibility.is_visible(CLIENT_ID, entity));
+        assert!(visibility.entered_entities().is_empty());
+    }
+
+    #[test]
+    fn visible_entities_replacement() {
+        let mut visibility =
+            ClientVisibility::new(VisibilityPolicy::Whitelist, LeavePolicy::Despawn);
+        const CLIENT_ID: u64 = 1;
+        let kept_entity = Entity::from_raw(0);
+        let left_entity = Entity::from_raw(1);
+        let entered_entity = Entity::from_raw(2);
+        visibility.set_visibility_many(CLIENT_ID, [kept_entity, left_entity], true);
+        visibility
+            .clients
+            .get_mut(&CLIENT_ID)
+            .unwrap()
+            .entered
+            .clear();
+
+        visibility.set_visible_entities(CLIENT_ID, [kept_entity, entered_entity]);
+        assert!(visibility.is_visible(CLIENT_ID, kept_entity));
+        assert!(visibility.is_visible(CLIENT_ID, entered_entity));
+        assert!(!visibility.is_visible(CLIENT_ID, left_entity));
+
+        let entered_entities = visibility.entered_entities();
+        assert_eq!(entered_entities.len(), 1);
+        assert!(entered_entities.contains(&entered_entity));
+
+        let pending_left: Vec<_> = visibility.pending_left(CLIENT_ID).collect();
+        assert_eq!(pending_left, [left_entity]);
+        let pending_entered: Vec<_> = visibility.pending_entered(CLIENT_ID).collect();
+        assert_eq!(pending_entered, [entered_entity]);
+        assert_eq!(visibility.listed_entities(CLIENT_ID).count(), 2);
+    }
+}
@@ -0,0 +1,520 @@
+use std::{mem, time::Duration};
+
+use bevy::{
+    ecs::{component::Tick, system::SystemChangeTick},
+    prelude::*,
+    utils::HashMap,
+};
+use bevy_renet::renet::RenetServer;
+
+use super::{
+    replication_priority::ReplicationPriority, AckedTicks, ServerPlugin, ServerSet, ServerTick,
+};
+use crate::replication_core::Replication;
+
+/// Detects congested clients and tracks changes that were deferred for them.
+///
+/// Used only on server.
+pub(super) struct CongestionPlugin;
+
+impl Plugin for CongestionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CongestionPolicy>()
+            .init_resource::<ClientCongestion>()
+            .init_resource::<ClientSendRates>()
+            .add_systems(
+                PostUpdate,
+                (
+                    (Self::despawn_cleanup_system, Self::cleanup_system)
+                        .chain()
+                        .before(ServerSet::Send),
+                    Self::detection_system
+                        .in_set(ServerSet::Send)
+                        .after(ServerPlugin::tick_increment_system)
+                        .before(ServerPlugin::diffs_sending_system),
+                )
+                    .run_if(resource_exists::<RenetServer>()),
+            );
+    }
+}
+
+impl CongestionPlugin {
+    fn despawn_cleanup_system(
+        mut removed_replications: RemovedComponents<Replication>,
+        mut congestion: ResMut<ClientCongestion>,
+    ) {
+        for entity in &mut removed_replications {
+            for client_state in congestion.clients.values_mut() {
+                client_state.deferred.remove(&entity);
+            }
+        }
+    }
+
+    /// Removes deferred changes that were sent and acknowledged by clients.
+    fn cleanup_system(
+        change_tick: SystemChangeTick,
+        mut congestion: ResMut<ClientCongestion>,
+        acked_ticks: Res<AckedTicks>,
+    ) {
+        for (client_id, client_state) in &mut congestion.clients {
+            let Some(&last_tick) = acked_ticks.get(client_id) else {
+                continue;
+            };
+            client_state.deferred.retain(|_, deferred| {
+                deferred.sent.map_or(true, |sent| {
+                    sent.is_newer_than(last_tick, change_tick.this_run())
+                })
+            });
+        }
+    }
+
+    /// Updates congestion state of each client from its network statistics, acknowledged ticks
+    /// and [`ClientSendRates`].
+    ///
+    /// Runs only on ticks when the server sends data.
+    fn detection_system(
+        policy: Res<CongestionPolicy>,
+        catch_up_policy: Res<CatchUpPolicy>,
+        send_rates: Res<ClientSendRates>,
+        server_tick: Res<ServerTick>,
+        mut congestion: ResMut<ClientCongestion>,
+        server: Res<RenetServer>,
+    ) {
+        for client_id in server.clients_id_iter() {
+            let Ok(network_info) = server.network_info(client_id) else {
+                continue;
+            };
+            let congested = network_info.packet_loss > policy.max_packet_loss
+                || network_info.rtt > policy.max_rtt.as_secs_f64();
+
+            let client_state = congestion.clients.entry(client_id).or_default();
+            client_state.lagging = client_state.acked_server_tick.is_some_and(|acked_tick| {
+                server_tick.get().wrapping_sub(acked_tick.get()) > catch_up_policy.max_lag
+            });
+            client_state.congested = congested || client_state.lagging;
+            client_state.min_priority = if client_state.lagging {
+                if server_tick.get() % catch_up_policy.send_interval.max(1) == 0 {
+                    Some(catch_up_policy.min_priority)
+                } else {
+                    Some(f32::INFINITY)
+                }
+            } else if !send_rates.is_send_tick(client_id, *server_tick) {
+                Some(f32::INFINITY)
+            } else if !congested {
+                None
+            } else if client_state.min_priority == Some(policy.min_priority) {
+                // Halve the send rate by sending only essential data on every second tick.
+                Some(f32::INFINITY)
+            } else {
+                Some(policy.min_priority)
+            };
+        }
+    }
+}
+
+/// Thresholds to detect congested clients and how replication degrades for them.
+///
+/// For congested clients, component changes of entities with [`ReplicationPriority`] lower than
+/// [`Self::min_priority`] are deferred, and changes of all entities are deferred on every second tick,
+/// which halves the effective send rate. Spawns, component insertions, removals and despawns are
+/// never deferred, network events are not affected.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct CongestionPolicy {
+    /// Packet loss in range from 0.0 to 1.0 after which a client considered congested.
+    pub max_packet_loss: f64,
+    /// Round-trip time after which a client considered congested.
+    pub max_rtt: Duration,
+    /// Minimal priority of entities whose changes are sent to congested clients.
+    pub min_priority: f32,
+}
+
+impl Default for CongestionPolicy {
+    fn default() -> Self {
+        Self {
+            max_packet_loss: 0.1,
+            max_rtt: Duration::from_millis(500),
+            min_priority: ReplicationPriority::BASE,
+        }
+    }
+}
+
+/// Thresholds to detect clients whose acknowledged ticks lag far behind and how replication is throttled for them.
+///
+/// Sending more data to such clients only piles up unacknowledged changes and makes the lag worse.
+/// So lagging clients receive diffs only on every [`Self::send_interval`] tick with changes of entities
+/// with [`ReplicationPriority`] lower than [`Self::min_priority`] deferred. On other ticks only
+/// essential data is sent, and changes of reliable components are accumulated and sent together later.
+/// Replication returns to normal once the lag drops below [`Self::max_lag`].
+///
+/// Configured in [`ServerPlugin::with_catch_up_policy`] and can be changed at runtime as a resource.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Resource)]
+pub struct CatchUpPolicy {
+    /// Number of server ticks by which the last acknowledged tick can lag behind before a client is throttled.
+    ///
+    /// Use [`u32::MAX`] to disable throttling.
+    pub max_lag: u32,
+    /// Minimal priority of entities whose changes are sent to lagging clients.
+    pub min_priority: f32,
+    /// Interval in server ticks between diffs with changes sent to lagging clients.
+    pub send_interval: u32,
+}
+
+impl Default for CatchUpPolicy {
+    fn default() -> Self {
+        Self {
+            max_lag: 30,
+            min_priority: ReplicationPriority::BASE,
+            send_interval: 4,
+        }
+    }
+}
+
+/// Per-client overrides of the replication send rate.
+///
+/// The rate is expressed as an interval in server ticks between diffs with component changes,
+/// so the base rate is defined by [`TickPolicy`](super::TickPolicy). On other ticks only essential
+/// data is sent, like for congested clients: spawns, insertions, removals and despawns.
+/// Ticks are offset by client ID to spread sending of different clients with the same interval.
+///
+/// To boost a client, like a tournament observer, increase the server tick rate and set
+/// [`Self::default_interval`] for all other clients to keep their rate. For example,
+/// with 60 ticks per second and default interval 2, clients receive changes 30 times per second,
+/// while a client with interval 1 receives them 60 times per second.
+///
+/// Overrides are removed on disconnect.
+///
+/// Used only on server.
+#[derive(Debug, Resource)]
+pub struct ClientSendRates {
+    /// Interval for clients without an override.
+    pub default_interval: u32,
+    intervals: HashMap<u64, u32>,
+}
+
+impl ClientSendRates {
+    /// Creates rates with the interval for clients without an override.
+    pub fn new(default_interval: u32) -> Self {
+        Self {
+            default_interval,
+            intervals: Default::default(),
+        }
+    }
+
+    /// Overrides the interval in server ticks between diffs with changes for a client.
+    ///
+    /// Interval 0 is treated as 1.
+    pub fn set_interval(&mut self, client_id: u64, interval: u32) {
+        self.intervals.insert(client_id, interval);
+    }
+
+    /// Removes the override for a client, returning it to [`Self::default_interval`].
+    pub fn remove_override(&mut self, client_id: u64) {
+        self.intervals.remove(&client_id);
+    }
+
+    /// Returns the effective interval for a client.
+    pub fn interval(&self, client_id: u64) -> u32 {
+        self.intervals
+            .get(&client_id)
+            .copied()
+            .unwrap_or(self.default_interval)
+            .max(1)
+    }
+
+    /// Returns `true` if changes should be sent to a client on the tick.
+    pub(super) fn is_send_tick(&self, client_id: u64, server_tick: ServerTick) -> bool {
+        let offset = client_id as u32;
+        server_tick.get().wrapping_add(offset) % self.interval(client_id) == 0
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.intervals.clear();
+    }
+}
+
+impl Default for ClientSendRates {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Congestion state of connected clients.
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ClientCongestion {
+    clients: HashMap<u64, ClientState>,
+}
+
+impl ClientCongestion {
+    /// Returns `true` if a client is congested according to [`CongestionPolicy`] or lagging according to [`CatchUpPolicy`].
+    pub fn is_congested(&self, client_id: u64) -> bool {
+        self.clients
+            .get(&client_id)
+            .is_some_and(|client_state| client_state.congested)
+    }
+
+    /// Returns `true` if the acknowledged tick of a client lags behind according to [`CatchUpPolicy`].
+    pub fn is_lagging(&self, client_id: u64) -> bool {
+        self.clients
+            .get(&client_id)
+            .is_some_and(|client_state| client_state.lagging)
+    }
+
+    /// Returns `true` if a lagging client should receive only essential data on this tick.
+    pub(super) fn is_throttled(&self, client_id: u64) -> bool {
+        self.is_lagging(client_id) && self.min_priority(client_id) == Some(f32::INFINITY)
+    }
+
+    /// Updates the server tick of the last world diff applied by a client.
+    pub(super) fn set_acked_server_tick(&mut self, client_id: u64, server_tick: ServerTick) {
+        self.clients.entry(client_id).or_default().acked_server_tick = Some(server_tick);
+    }
+
+    /// Returns minimal priority of entities whose changes should be sent to a client.
+    ///
+    /// Returns [`None`] if the client isn't congested and it's a send tick for it according to [`ClientSendRates`].
+    pub(super) fn min_priority(&self, client_id: u64) -> Option<f32> {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_state| client_state.min_priority)
+    }
+
+    /// Returns the tick since which changes of an entity were deferred for a client.
+    pub(super) fn deferred_tick(&self, client_id: u64, entity: Entity) -> Option<Tick> {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_state| client_state.deferred.get(&entity))
+            .map(|deferred| deferred.since)
+    }
+
+    /// Returns `true` if an entity became visible for a client, but wasn't sent yet because of
+    /// [`MessageEntityLimit`](super::MessageEntityLimit).
+    pub(super) fn is_deferred_entry(&self, client_id: u64, entity: Entity) -> bool {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_state| client_state.deferred.get(&entity))
+            .is_some_and(|deferred| deferred.entered)
+    }
+
+    /// Returns `true` if changes of a client deferred since `tick` or earlier weren't acknowledged
+    /// with `last_tick` yet.
+    pub(super) fn has_deferred_since(
+        &self,
+        client_id: u64,
+        tick: Tick,
+        last_tick: Tick,
+        this_run: Tick,
+    ) -> bool {
+        self.clients.get(&client_id).is_some_and(|client_state| {
+            client_state.deferred.values().any(|deferred| {
+                !deferred.since.is_newer_than(tick, this_run)
+                    && deferred
+                        .sent
+                        .map_or(true, |sent| sent.is_newer_than(last_tick, this_run))
+            })
+        })
+    }
+
+    /// Returns all entities whose entry was deferred for any client.
+    pub(super) fn deferred_entries(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.clients.values().flat_map(|client_state| {
+            client_state
+                .deferred
+                .iter()
+                .filter(|(_, deferred)| deferred.entered)
+                .map(|(&entity, _)| entity)
+        })
+    }
+
+    /// Returns the oldest tick since which changes were deferred for any client.
+    pub(super) fn oldest_deferred_tick(&self, this_run: Tick) -> Option<Tick> {
+        self.clients
+            .values()
+            .flat_map(|client_state| client_state.deferred.values())
+            .map(|deferred| deferred.since)
+            .reduce(|oldest_tick, tick| {
+                if oldest_tick.is_newer_than(tick, this_run) {
+                    tick
+                } else {
+                    oldest_tick
+                }
+            })
+    }
+
+    /// Applies changes collected during sending.
+    pub(super) fn apply(&mut self, changes: impl IntoIterator<Item = DeferralChange>) {
+        for change in changes {
+            match change {
+                DeferralChange::Deferred {
+                    client_id,
+                    entity,
+                    since,
+                    entered,
+                } => {
+                    let client_state = self.clients.entry(client_id).or_default();
+                    let deferred = client_state
+                        .deferred
+                        .entry(entity)
+                        .or_insert(DeferredChanges {
+                            since,
+                            sent: None,
+                            entered: false,
+                        });
+                    deferred.sent = None;
+                    deferred.entered |= entered;
+                }
+                DeferralChange::Sent {
+                    client_id,
+                    entity,
+                    tick,
+                } => {
+                    if let Some(deferred) = self
+                        .clients
+                        .get_mut(&client_id)
+                        .and_then(|client_state| client_state.deferred.get_mut(&entity))
+                    {
+                        deferred.sent = Some(tick);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the estimated memory in bytes used by deferred changes of a client.
+    pub(crate) fn memory_usage(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |client_state| {
+            client_state.deferred.len() * mem::size_of::<(Entity, DeferredChanges)>()
+        })
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// Change of deferred entity changes for a client.
+///
+/// Collected while the world is borrowed and applied with [`ClientCongestion::apply`] afterwards.
+pub(super) enum DeferralChange {
+    /// Changes of an entity that happened after `since` were not sent.
+    ///
+    /// If `entered` is `true`, the entity should be sent with all components.
+    Deferred {
+        client_id: u64,
+        entity: Entity,
+        since: Tick,
+        entered: bool,
+    },
+    /// Deferred changes of an entity were sent on `tick`.
+    Sent {
+        client_id: u64,
+        entity: Entity,
+        tick: Tick,
+    },
+}
+
+#[derive(Default)]
+struct ClientState {
+    /// Minimal priority of entities whose changes should be sent, [`None`] if all changes should be sent.
+    min_priority: Option<f32>,
+
+    /// Congested according to [`CongestionPolicy`] or lagging according to [`CatchUpPolicy`].
+    congested: bool,
+
+    /// Server tick of the last world diff applied by the client.
+    acked_server_tick: Option<ServerTick>,
+
+    /// Acknowledged tick lags behind according to [`CatchUpPolicy`].
+    lagging: bool,
+
+    /// Entities whose changes were skipped and should be sent later.
+    deferred: HashMap<Entity, DeferredChanges>,
+}
+
+struct DeferredChanges {
+    /// Tick since which changes were not sent.
+    since: Tick,
+
+    /// Tick of the diff which included the deferred changes, they will be cleaned after its acknowledgment.
+    sent: Option<Tick>,
+
+    /// The entity became visible or changed LOD, but wasn't sent yet.
+    entered: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deferred_changes() {
+        const CLIENT_ID: u64 = 1;
+        let entity = Entity::PLACEHOLDER;
+        let mut congestion = ClientCongestion::default();
+
+        let deferred = |since| DeferralChange::Deferred {
+            client_id: CLIENT_ID,
+            entity,
+            since,
+            entered: false,
+        };
+
+        congestion.apply([deferred(Tick::new(1)), deferred(Tick::new(2))]);
+        assert_eq!(
+            congestion.deferred_tick(CLIENT_ID, entity),
+            Some(Tick::new(1)),
+            "deferral should keep the oldest tick"
+        );
+        assert_eq!(
+            congestion.oldest_deferred_tick(Tick::new(3)),
+            Some(Tick::new(1))
+        );
+
+        congestion.apply([DeferralChange::Sent {
+            client_id: CLIENT_ID,
+            entity,
+            tick: Tick::new(3),
+        }]);
+        let client_state = &congestion.clients[&CLIENT_ID];
+        assert_eq!(client_state.deferred[&entity].sent, Some(Tick::new(3)));
+
+        congestion.apply([deferred(Tick::new(3))]);
+        let client_state = &congestion.clients[&CLIENT_ID];
+        assert_eq!(
+            client_state.deferred[&entity].sent, None,
+            "deferring again should reset the sent tick"
+        );
+    }
+
+    #[test]
+    fn send_rates() {
+        const CLIENT_ID: u64 = 1;
+        let mut send_rates = ClientSendRates::default();
+        assert_eq!(send_rates.interval(CLIENT_ID), 1);
+
+        send_rates.set_interval(CLIENT_ID, 2);
+        let send_ticks: Vec<_> = (0..4)
+            .map(|tick| send_rates.is_send_tick(CLIENT_ID, ServerTick(tick)))
+            .collect();
+        assert_eq!(send_ticks, [false, true, false, true]);
+
+        send_rates.set_interval(CLIENT_ID, 0);
+        assert_eq!(
+            send_rates.interval(CLIENT_ID),
+            1,
+            "zero interval should act as 1"
+        );
+
+        send_rates.remove_override(CLIENT_ID);
+        assert_eq!(send_rates.interval(CLIENT_ID), send_rates.default_interval);
+    }
+}
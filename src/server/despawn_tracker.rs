@@ -1,12 +1,18 @@
+use std::mem;
+
 use bevy::{
-    ecs::{component::Tick, system::SystemChangeTick},
+    ecs::{
+        component::Tick,
+        system::{EntityCommands, SystemChangeTick},
+        world::EntityMut,
+    },
     prelude::*,
-    utils::HashSet,
+    utils::{HashMap, HashSet},
 };
 use bevy_renet::renet::RenetServer;
 
 use super::{AckedTicks, ServerSet};
-use crate::replication_core::Replication;
+use crate::{replication_core::Replication, world_diff::DespawnReason};
 
 /// Tracks entity despawns of entities with [`Replication`] component in [`DespawnTracker`] resource.
 ///
@@ -46,7 +52,7 @@ impl DespawnTrackerPlugin {
         mut despawn_tracker: ResMut<DespawnTracker>,
         client_acks: Res<AckedTicks>,
     ) {
-        despawn_tracker.despawns.retain(|(_, tick)| {
+        despawn_tracker.despawns.retain(|(_, tick, _)| {
             client_acks
                 .values()
                 .any(|last_tick| tick.is_newer_than(*last_tick, change_tick.this_run()))
@@ -61,24 +67,74 @@ impl DespawnTrackerPlugin {
         let DespawnTracker {
             ref mut tracked_entities,
             ref mut despawns,
+            ref mut codes,
         } = *tracker;
 
         tracked_entities.retain(|&entity| {
             if entities.get(entity).is_err() {
-                despawns.push((entity, change_tick.this_run()));
+                let reason = codes
+                    .remove(&entity)
+                    .map_or(DespawnReason::Despawned, DespawnReason::Custom);
+                despawns.push((entity, change_tick.this_run(), reason));
                 false
             } else {
                 true
             }
         });
+
+        // Codes are recorded together with despawns, so the remaining ones belong to non-replicated entities.
+        codes.clear();
     }
 }
 
 #[derive(Default, Resource)]
 pub(crate) struct DespawnTracker {
     tracked_entities: HashSet<Entity>,
-    /// Entities and ticks when they were despawned.
-    pub(crate) despawns: Vec<(Entity, Tick)>,
+    /// Entities, ticks when they were despawned and reasons.
+    pub(crate) despawns: Vec<(Entity, Tick, DespawnReason)>,
+    /// Codes passed to [`DespawnWithReasonExt::despawn_with_reason`] for not yet detected despawns.
+    codes: HashMap<Entity, u16>,
+}
+
+impl DespawnTracker {
+    /// Returns the estimated memory in bytes used by tracked entities and despawns.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.tracked_entities.len() * mem::size_of::<Entity>()
+            + self.despawns.len() * mem::size_of::<(Entity, Tick, DespawnReason)>()
+    }
+}
+
+/// An extension trait to despawn replicated entities with an application-defined reason.
+pub trait DespawnWithReasonExt {
+    /// Despawns the entity and sends `code` to clients as [`DespawnReason::Custom`].
+    ///
+    /// Useful to let clients pick the right feedback, like a death animation for killed entities
+    /// and a fade out for expired ones. The code is sent only if the entity is despawned on server,
+    /// on clients it's a regular despawn.
+    fn despawn_with_reason(self, code: u16);
+}
+
+impl DespawnWithReasonExt for EntityMut<'_> {
+    fn despawn_with_reason(mut self, code: u16) {
+        let entity = self.id();
+        self.world_scope(|world| {
+            if let Some(mut tracker) = world.get_resource_mut::<DespawnTracker>() {
+                tracker.codes.insert(entity, code);
+            }
+        });
+        self.despawn();
+    }
+}
+
+impl DespawnWithReasonExt for &mut EntityCommands<'_, '_, '_> {
+    fn despawn_with_reason(self, code: u16) {
+        let entity = self.id();
+        self.commands().add(move |world: &mut World| {
+            if let Some(entity) = world.get_entity_mut(entity) {
+                entity.despawn_with_reason(code);
+            }
+        });
+    }
 }
 
 #[cfg(test)]
@@ -111,9 +167,26 @@ mod tests {
 
         let despawn_tracker = app.world.resource::<DespawnTracker>();
         assert_eq!(despawn_tracker.despawns.len(), 1);
-        assert_eq!(
-            despawn_tracker.despawns.first().unwrap().0,
-            replicated_entity
-        );
+        let (entity, _, reason) = *despawn_tracker.despawns.first().unwrap();
+        assert_eq!(entity, replicated_entity);
+        assert_eq!(reason, DespawnReason::Despawned);
+
+        const CODE: u16 = 1;
+        let replicated_entity = app.world.spawn(Replication).id();
+
+        app.update();
+
+        app.world
+            .entity_mut(replicated_entity)
+            .despawn_with_reason(CODE);
+
+        app.update();
+
+        let despawn_tracker = app.world.resource::<DespawnTracker>();
+        assert_eq!(despawn_tracker.despawns.len(), 2);
+        let (entity, _, reason) = *despawn_tracker.despawns.last().unwrap();
+        assert_eq!(entity, replicated_entity);
+        assert_eq!(reason, DespawnReason::Custom(CODE));
+        assert!(despawn_tracker.codes.is_empty());
     }
 }
@@ -0,0 +1,241 @@
+use std::{collections::VecDeque, mem, time::Duration};
+
+use bevy::{
+    ecs::system::SystemChangeTick,
+    prelude::*,
+    reflect::TypeRegistryInternal,
+    utils::{HashSet, Instant},
+};
+use bevy_renet::{renet::RenetServer, transport::NetcodeServerPlugin};
+
+use super::{
+    collect_diffs, is_skipped, serialize_diff, traffic_log::diff_summary, AckedTicks,
+    ClientCongestion, ClientVisibility, DeferralChange, DespawnTracker, ReplicatedArchetypes,
+    ReplicationContexts, ServerSet, ServerTick, TrafficLog,
+};
+use crate::{
+    protocol_version::downgrade_for_client,
+    replication_core::{ReplicationRules, ReplicationSet, REPLICATION_CHANNEL_ID},
+};
+
+/// Sends diffs of clients that didn't fit into [`DiffBuildBudget`] on the previous frames.
+///
+/// Used only on server.
+pub(super) struct DiffBudgetPlugin;
+
+impl Plugin for DiffBudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            Self::pending_sending_system
+                .after(ServerSet::Send)
+                .before(NetcodeServerPlugin::send_packets)
+                .in_set(ReplicationSet)
+                .run_if(resource_exists::<RenetServer>())
+                .run_if(resource_exists::<DiffBuildBudget>()),
+        );
+    }
+}
+
+impl DiffBudgetPlugin {
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    fn pending_sending_system(
+        change_tick: SystemChangeTick,
+        mut set: ParamSet<(
+            &World,
+            ResMut<RenetServer>,
+            ResMut<ClientCongestion>,
+            ResMut<DiffBuildBudget>,
+        )>,
+        mut replicated_archetypes: Local<ReplicatedArchetypes>,
+        server_tick: Res<ServerTick>,
+        acked_ticks: Res<AckedTicks>,
+        registry: Res<AppTypeRegistry>,
+        replication_rules: Res<ReplicationRules>,
+        despawn_tracker: Res<DespawnTracker>,
+        visibility: Res<ClientVisibility>,
+    ) {
+        let mut budget = set.p3();
+        if budget.started_this_frame {
+            // Already spent by the tick that started this frame.
+            budget.started_this_frame = false;
+            return;
+        }
+        if budget.pending.is_empty() {
+            return;
+        }
+        let clients: Vec<_> = budget.pending.drain(..).collect();
+        let max_duration = budget.max_duration;
+
+        let registry = registry.read();
+        let world = set.p0();
+        let mut deferral_changes = Vec::new();
+        replicated_archetypes.cleanup_switch(&acked_ticks, change_tick.this_run());
+        replicated_archetypes.update(
+            world.archetypes(),
+            &replication_rules,
+            world.resource::<ReplicationContexts>(),
+            change_tick.this_run(),
+        );
+        let (messages, pending) = collect_budgeted_messages(
+            world,
+            &change_tick,
+            &registry,
+            &replicated_archetypes,
+            &acked_ticks,
+            &despawn_tracker,
+            &visibility,
+            *server_tick,
+            clients,
+            max_duration,
+            &mut deferral_changes,
+        );
+
+        let mut server = set.p1();
+        for (client_id, message) in messages {
+            server.send_message(client_id, REPLICATION_CHANNEL_ID, message);
+        }
+
+        set.p2().apply(deferral_changes);
+        set.p3().pending = pending;
+    }
+}
+
+/// Limits the time the server spends building unreliable world diffs per frame.
+///
+/// Diffs are built for clients one by one until [`Self::max_duration`] is exceeded.
+/// The remaining clients are carried over and their diffs are built and sent on the next frames,
+/// before the clients of the next tick. At least one client is processed per frame.
+/// Carried over diffs contain the state of the frame they are built in, with the same [`ServerTick`].
+///
+/// Since each client is processed separately, the total time is higher than without the budget,
+/// but it's spread over frames to avoid spikes with many clients.
+/// Reliable diffs are always sent on their tick.
+///
+/// Used only on server.
+#[derive(Resource)]
+pub struct DiffBuildBudget {
+    /// Maximum time per frame spent on building diffs.
+    pub max_duration: Duration,
+    pending: VecDeque<u64>,
+    started_this_frame: bool,
+}
+
+impl DiffBuildBudget {
+    pub fn new(max_duration: Duration) -> Self {
+        Self {
+            max_duration,
+            pending: Default::default(),
+            started_this_frame: false,
+        }
+    }
+
+    /// Returns clients whose diffs were carried over to the next frame.
+    pub fn pending_clients(&self) -> impl Iterator<Item = u64> + '_ {
+        self.pending.iter().copied()
+    }
+
+    /// Returns carried over clients first, followed by the rest of `clients`.
+    pub(super) fn queue(&self, clients: impl Iterator<Item = u64>) -> Vec<u64> {
+        let pending: HashSet<_> = self.pending.iter().copied().collect();
+        self.pending
+            .iter()
+            .copied()
+            .chain(clients.filter(|client_id| !pending.contains(client_id)))
+            .collect()
+    }
+
+    /// Stores clients that didn't fit into the budget on the current tick.
+    pub(super) fn carry_over(&mut self, pending: VecDeque<u64>) {
+        self.pending = pending;
+        self.started_this_frame = true;
+    }
+
+    /// Returns the estimated memory in bytes used by carried over clients.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.pending.len() * mem::size_of::<u64>()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.pending.clear();
+        self.started_this_frame = false;
+    }
+}
+
+/// Collects and serializes unreliable diffs for `clients` one by one until `max_duration` is exceeded.
+///
+/// Returns messages and clients that didn't fit into the duration.
+/// Clients that are no longer connected are skipped.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn collect_budgeted_messages(
+    world: &World,
+    change_tick: &SystemChangeTick,
+    registry: &TypeRegistryInternal,
+    replicated_archetypes: &ReplicatedArchetypes,
+    acked_ticks: &AckedTicks,
+    despawn_tracker: &DespawnTracker,
+    visibility: &ClientVisibility,
+    server_tick: ServerTick,
+    clients: Vec<u64>,
+    max_duration: Duration,
+    deferral_changes: &mut Vec<DeferralChange>,
+) -> (Vec<(u64, Vec<u8>)>, VecDeque<u64>) {
+    let started_at = Instant::now();
+    let congestion = world.resource::<ClientCongestion>();
+    let traffic_log = world.resource::<TrafficLog>();
+    let current_tick = world.read_change_tick();
+    let mut messages = Vec::new();
+    let mut clients: VecDeque<_> = clients.into();
+    while let Some(client_id) = clients.pop_front() {
+        let Some(&acked_tick) = acked_ticks.get(&client_id) else {
+            continue;
+        };
+        let client_ticks = AckedTicks([(client_id, acked_tick)].into_iter().collect());
+        let client_diffs = collect_diffs(
+            world,
+            change_tick,
+            registry,
+            replicated_archetypes,
+            &client_ticks,
+            despawn_tracker,
+            visibility,
+            deferral_changes,
+        );
+        for (client_id, mut world_diff) in client_diffs {
+            if is_skipped(&world_diff, congestion, client_id) {
+                continue;
+            }
+            world_diff.tick = current_tick;
+            downgrade_for_client(world, client_id, &mut world_diff);
+            let message = serialize_diff(&world_diff, server_tick, registry);
+            traffic_log.sent(client_id, REPLICATION_CHANNEL_ID, &message, || {
+                diff_summary(&world_diff)
+            });
+            messages.push((client_id, message));
+        }
+
+        if started_at.elapsed() >= max_duration {
+            break;
+        }
+    }
+
+    (messages, clients)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carried_over_first() {
+        let mut budget = DiffBuildBudget::new(Duration::ZERO);
+        assert_eq!(budget.queue([1, 2, 3].into_iter()), [1, 2, 3]);
+
+        budget.carry_over([3].into());
+        assert_eq!(budget.queue([1, 2, 3].into_iter()), [3, 1, 2]);
+        assert!(budget.pending_clients().eq([3]));
+
+        budget.clear();
+        assert_eq!(budget.pending_clients().count(), 0);
+    }
+}
@@ -0,0 +1,159 @@
+use bevy::{
+    ecs::system::{SystemChangeTick, SystemState},
+    prelude::*,
+    reflect::serde::TypedReflectSerializer,
+    utils::HashMap,
+};
+
+use super::{
+    collect_diffs, is_skipped, AckedTicks, ClientCongestion, ClientVisibility, DespawnTracker,
+    ReplicatedArchetypes, ReplicationContexts, ServerTick,
+};
+use crate::{
+    replication_core::ReplicationRules,
+    wire_format::serialized_message_size,
+    world_diff::{ComponentDiff, WorldDiffSerializer},
+};
+
+/// An extension trait for [`World`] to estimate replication bandwidth on server.
+pub trait DiffSizeWorldExt {
+    /// Returns sizes of world diffs that would be sent to each client if [`ServerSet::Send`](super::ServerSet::Send) ran now.
+    ///
+    /// Nothing is sent and the replication state isn't changed, so it could be called at any time,
+    /// for example in tests to catch bandwidth regressions.
+    /// Diffs contain changes since the last acknowledged tick of each client, just like the sent ones.
+    /// Components that started replicating after a rule set switch are not considered new.
+    /// Components sent over the reliable channel are not included.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`ServerPlugin`](super::ServerPlugin) wasn't added.
+    fn diff_sizes(&mut self) -> HashMap<u64, DiffSize>;
+}
+
+impl DiffSizeWorldExt for World {
+    fn diff_sizes(&mut self) -> HashMap<u64, DiffSize> {
+        let mut state = SystemState::<SystemChangeTick>::new(self);
+        let change_tick = state.get(self);
+        let registry = self.resource::<AppTypeRegistry>().read();
+        let mut replicated_archetypes = ReplicatedArchetypes::default();
+        replicated_archetypes.update(
+            self.archetypes(),
+            self.resource::<ReplicationRules>(),
+            self.resource::<ReplicationContexts>(),
+            change_tick.this_run(),
+        );
+        let client_diffs = collect_diffs(
+            self,
+            &change_tick,
+            &registry,
+            &replicated_archetypes,
+            self.resource::<AckedTicks>(),
+            self.resource::<DespawnTracker>(),
+            self.resource::<ClientVisibility>(),
+            &mut Vec::new(),
+        );
+
+        let server_tick = *self.resource::<ServerTick>();
+        let congestion = self.resource::<ClientCongestion>();
+        client_diffs
+            .into_iter()
+            .map(|(client_id, mut world_diff)| {
+                if is_skipped(&world_diff, congestion, client_id) {
+                    return (client_id, DiffSize::default());
+                }
+                world_diff.tick = self.read_change_tick();
+
+                let total = if world_diff.is_empty() {
+                    serialized_message_size(&(server_tick, world_diff.tick.get()))
+                } else {
+                    let serializer = WorldDiffSerializer::new(&world_diff, &registry);
+                    serialized_message_size(&(server_tick, &serializer))
+                }
+                .expect("world diff should be serializable");
+
+                let mut components = HashMap::<_, u64>::new();
+                for archetype_diff in &world_diff.archetypes {
+                    let registrations =
+                        archetype_diff
+                            .components
+                            .iter()
+                            .filter_map(|component_diff| match component_diff {
+                                ComponentDiff::Changed(registration) => Some(registration),
+                                ComponentDiff::Removed(_) => None,
+                            });
+                    for entity_diff in &archetype_diff.entities {
+                        for (registration, component) in
+                            registrations.clone().zip(&entity_diff.components)
+                        {
+                            let size = bincode::serialized_size(&TypedReflectSerializer::new(
+                                &**component,
+                                &registry,
+                            ))
+                            .unwrap_or_else(|e| {
+                                panic!("{} should be serializable: {e}", registration.type_name())
+                            });
+                            *components.entry(registration.type_name()).or_default() += size;
+                        }
+                    }
+                }
+
+                (client_id, DiffSize { total, components })
+            })
+            .collect()
+    }
+}
+
+/// Size of a world diff for a single client returned by [`DiffSizeWorldExt::diff_sizes`].
+#[derive(Clone, Debug, Default)]
+pub struct DiffSize {
+    /// Size of the whole message in bytes.
+    ///
+    /// Zero if nothing will be sent to the client.
+    pub total: u64,
+    /// Sizes of changed component values in bytes by their type names.
+    ///
+    /// The rest of the message is taken by entities, component names, removals and despawns.
+    pub components: HashMap<&'static str, u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any;
+
+    use bevy::ecs::component::Tick;
+
+    use super::*;
+    use crate::{
+        replication_core::{AppReplicationExt, Replication},
+        server::{ServerPlugin, TickPolicy},
+        ReplicationPlugins,
+    };
+
+    #[test]
+    fn sizes() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+
+        const DUMMY_CLIENT_ID: u64 = 0;
+        app.world
+            .resource_mut::<AckedTicks>()
+            .0
+            .insert(DUMMY_CLIENT_ID, Tick::new(0));
+
+        app.world.spawn((Replication, Transform::default()));
+
+        let sizes = app.world.diff_sizes();
+        let diff_size = sizes.get(&DUMMY_CLIENT_ID).unwrap();
+        let transform_size = *diff_size
+            .components
+            .get(any::type_name::<Transform>())
+            .unwrap();
+        assert!(transform_size > 0);
+        assert!(diff_size.total > transform_size);
+    }
+}
@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+
+use super::{
+    client_tags::ClientTagsPlugin, client_visibility::ClientVisibilityPlugin, ClientVisibility,
+    ReplicationContexts, ReplicationPriority, VisibilityPolicy,
+};
+use crate::replication_core::{ClientOwned, Replication};
+
+/// Hides the lowest-priority entities for clients that exceed [`InterestLimit`].
+///
+/// Used only on server.
+pub(super) struct InterestLimitPlugin;
+
+impl Plugin for InterestLimitPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            Self::limit_system
+                .after(ClientVisibilityPlugin::despawn_cleanup_system)
+                .after(ClientTagsPlugin::visibility_system)
+                .before(ClientVisibilityPlugin::tick_assignment_system)
+                .run_if(resource_exists::<RenetServer>())
+                .run_if(resource_exists::<InterestLimit>()),
+        );
+    }
+}
+
+impl InterestLimitPlugin {
+    #[allow(clippy::type_complexity)]
+    fn limit_system(
+        limit: Res<InterestLimit>,
+        server: Res<RenetServer>,
+        mut visibility: ResMut<ClientVisibility>,
+        contexts: Res<ReplicationContexts>,
+        entities: Query<
+            (Entity, Option<&ReplicationPriority>, Option<&ClientOwned>),
+            With<Replication>,
+        >,
+    ) {
+        if visibility.policy() == VisibilityPolicy::All {
+            return;
+        }
+
+        for client_id in server.clients_id() {
+            let mut visible: Vec<_> = entities
+                .iter()
+                .filter(|&(entity, ..)| {
+                    visibility.is_visible(client_id, entity)
+                        && contexts.is_member(client_id, entity)
+                })
+                .map(|(entity, priority, owner)| {
+                    let priority = priority.copied().unwrap_or_default();
+                    (priority.for_client(client_id, owner), entity)
+                })
+                .collect();
+            if visible.len() <= limit.0 {
+                continue;
+            }
+
+            visible.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+            let dropped: Vec<_> = visible.drain(limit.0..).map(|(_, entity)| entity).collect();
+            debug!(
+                "hiding {} entities over interest limit for client {client_id}",
+                dropped.len()
+            );
+            visibility.set_visibility_many(client_id, dropped, false);
+        }
+    }
+}
+
+/// Maximum number of entities visible for a single client.
+///
+/// When exceeded, entities with the lowest [`ReplicationPriority`] are removed from the client
+/// visibility and the client receives a despawn or hide for them according to
+/// [`LeavePolicy`](super::LeavePolicy). Protects low-end clients and the server from crowding.
+/// Requires [`VisibilityPolicy::Blacklist`] or [`VisibilityPolicy::Whitelist`],
+/// the limit is ignored for [`VisibilityPolicy::All`].
+/// Hidden entities are not restored automatically when the number of visible entities drops,
+/// visibility should be updated by the game logic, like [`ChunkStreaming`](super::ChunkStreaming)
+/// or [`TagVisibility`](super::TagVisibility).
+///
+/// Insert as a resource on server to enable.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Deref, DerefMut, Resource)]
+pub struct InterestLimit(pub usize);
@@ -1,10 +1,12 @@
 use bevy::{
     ecs::{
+        archetype::Archetypes,
         component::{ComponentId, Tick},
+        entity::Entities,
         system::SystemChangeTick,
     },
     prelude::*,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bevy_renet::renet::RenetServer;
 
@@ -14,7 +16,7 @@ use crate::replication_core::{Replication, ReplicationRules};
 /// Stores component removals in [`RemovalTracker`] component to make them persistent across ticks.
 ///
 /// Used only on server and tracks only entities with [`Replication`] component.
-pub(super) struct RemovalTrackerPlugin;
+pub(crate) struct RemovalTrackerPlugin;
 
 impl Plugin for RemovalTrackerPlugin {
     fn build(&self, app: &mut App) {
@@ -22,7 +24,7 @@ impl Plugin for RemovalTrackerPlugin {
             PostUpdate,
             (
                 Self::insertion_system,
-                Self::cleanup_system,
+                (Self::reenabling_system, Self::cleanup_system).chain(),
                 Self::detection_system.run_if(resource_exists::<RenetServer>()),
             )
                 .before(ServerSet::Send)
@@ -42,21 +44,59 @@ impl RemovalTrackerPlugin {
     }
 
     /// Cleanups all acknowledged despawns.
+    ///
+    /// Removals of disabled components are kept to be sent after enabling.
     fn cleanup_system(
         change_tick: SystemChangeTick,
         client_acks: Res<AckedTicks>,
+        replication_rules: Res<ReplicationRules>,
         mut removal_trackers: Query<&mut RemovalTracker>,
     ) {
         for mut removal_tracker in &mut removal_trackers {
-            removal_tracker.retain(|_, tick| {
-                client_acks
-                    .values()
-                    .any(|last_tick| tick.is_newer_than(*last_tick, change_tick.this_run()))
+            removal_tracker.retain(|&component_id, tick| {
+                !replication_rules.is_component_enabled(component_id)
+                    || client_acks
+                        .values()
+                        .any(|last_tick| tick.is_newer_than(*last_tick, change_tick.this_run()))
             });
         }
     }
 
-    fn detection_system(
+    /// Marks removals of components that were enabled again as new to send them to all clients.
+    ///
+    /// Removals are dropped for entities that have the component again, its value will be sent instead.
+    fn reenabling_system(
+        change_tick: SystemChangeTick,
+        mut disabled: Local<HashSet<ComponentId>>,
+        replication_rules: Res<ReplicationRules>,
+        entities: &Entities,
+        archetypes: &Archetypes,
+        mut removal_trackers: Query<(Entity, &mut RemovalTracker)>,
+    ) {
+        if *disabled == replication_rules.disabled {
+            return;
+        }
+
+        for &component_id in disabled.difference(&replication_rules.disabled) {
+            for (entity, mut removal_tracker) in &mut removal_trackers {
+                if !removal_tracker.contains_key(&component_id) {
+                    continue;
+                }
+                let contains = entities
+                    .get(entity)
+                    .and_then(|location| archetypes.get(location.archetype_id))
+                    .is_some_and(|archetype| archetype.contains(component_id));
+                if contains {
+                    removal_tracker.remove(&component_id);
+                } else {
+                    removal_tracker.insert(component_id, change_tick.this_run());
+                }
+            }
+        }
+        disabled.clone_from(&replication_rules.disabled);
+    }
+
+    pub(crate) fn detection_system(
         mut set: ParamSet<(&World, Query<&mut RemovalTracker>)>,
         replication_rules: Res<ReplicationRules>,
     ) {
@@ -0,0 +1,413 @@
+use std::mem;
+
+use bevy::{
+    ecs::{component::Tick, system::SystemChangeTick},
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_renet::renet::RenetServer;
+
+use super::{has_authority, AckedTicks, ServerSet};
+use crate::replication_core::{Replication, ReplicationContext};
+
+/// Tracks contexts of entities, assigns ticks to membership changes and cleanups acknowledged ones.
+///
+/// Used only on server.
+pub(super) struct ReplicationContextsPlugin;
+
+impl Plugin for ReplicationContextsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplicationContexts>().add_systems(
+            PostUpdate,
+            (
+                Self::tracking_system.run_if(has_authority()),
+                (Self::cleanup_system, Self::tick_assignment_system)
+                    .run_if(resource_exists::<RenetServer>()),
+            )
+                .chain()
+                .before(ServerSet::Send),
+        );
+    }
+}
+
+impl ReplicationContextsPlugin {
+    /// Updates contexts of entities and applies membership changes to entities of affected contexts.
+    ///
+    /// Entities that moved to another context are sent or despawned for clients depending on their membership.
+    #[allow(clippy::type_complexity)]
+    fn tracking_system(
+        mut removed_contexts: RemovedComponents<ReplicationContext>,
+        mut removed_replications: RemovedComponents<Replication>,
+        mut contexts: ResMut<ReplicationContexts>,
+        server: Option<Res<RenetServer>>,
+        changed_entities: Query<
+            (Entity, &ReplicationContext, Ref<Replication>),
+            Changed<ReplicationContext>,
+        >,
+        entities: Query<(Entity, Option<&ReplicationContext>), With<Replication>>,
+    ) {
+        let clients = server.map(|server| server.clients_id()).unwrap_or_default();
+        for entity in &mut removed_contexts {
+            if entities.contains(entity) {
+                contexts.move_entity(&clients, entity, ReplicationContext::DEFAULT);
+            }
+        }
+        for (entity, &context, replication) in &changed_entities {
+            if replication.is_added() {
+                // New entities are sent with all components anyway.
+                contexts.insert_entity(entity, context);
+            } else {
+                contexts.move_entity(&clients, entity, context);
+            }
+        }
+        for entity in &mut removed_replications {
+            if !entities.contains(entity) {
+                contexts.remove_entity(entity);
+            }
+        }
+
+        for (client_id, context, joined) in mem::take(&mut contexts.pending) {
+            if !clients.contains(&client_id) {
+                continue;
+            }
+            let client_contexts = contexts.clients.entry(client_id).or_default();
+            for (entity, _) in entities.iter().filter(|(_, entity_context)| {
+                entity_context.copied().unwrap_or_default() == context
+            }) {
+                client_contexts.set_member(entity, joined);
+            }
+        }
+    }
+
+    /// Removes membership changes that were acknowledged by clients.
+    fn cleanup_system(
+        change_tick: SystemChangeTick,
+        mut contexts: ResMut<ReplicationContexts>,
+        acked_ticks: Res<AckedTicks>,
+    ) {
+        for (client_id, client_contexts) in &mut contexts.clients {
+            let Some(&last_tick) = acked_ticks.get(client_id) else {
+                continue;
+            };
+            let is_unacked = |tick: &mut Option<Tick>| {
+                tick.map_or(true, |tick| {
+                    tick.is_newer_than(last_tick, change_tick.this_run())
+                })
+            };
+            client_contexts.entered.retain(|_, tick| is_unacked(tick));
+            client_contexts.left.retain(|_, tick| is_unacked(tick));
+        }
+    }
+
+    /// Assigns the current tick to membership changes that were made since the last run.
+    fn tick_assignment_system(
+        change_tick: SystemChangeTick,
+        mut contexts: ResMut<ReplicationContexts>,
+    ) {
+        for client_contexts in contexts.clients.values_mut() {
+            for tick in client_contexts
+                .entered
+                .values_mut()
+                .chain(client_contexts.left.values_mut())
+                .filter(|tick| tick.is_none())
+            {
+                *tick = Some(change_tick.this_run());
+            }
+        }
+    }
+}
+
+/// Membership of clients in replication contexts and settings of each context.
+///
+/// Clients receive only entities of [`ReplicationContext`]s they joined. After joining a context,
+/// a client receives all its entities with all components, and after leaving, they are despawned
+/// on the client with [`DespawnReason::LeftContext`](crate::world_diff::DespawnReason::LeftContext).
+/// Entities that move to another context are sent or despawned in the same way.
+/// All clients are members of [`ReplicationContext::DEFAULT`] after connection.
+///
+/// Each context could also have its own send interval and rule set, for example, a persistent meta world
+/// with parties and chat presence could be updated at a lower rate than the match world.
+/// Both work together with [`ClientVisibility`](super::ClientVisibility) and other replication settings.
+///
+/// Used only on server.
+#[derive(Default, Resource)]
+pub struct ReplicationContexts {
+    /// Contexts of replicated entities that are not in [`ReplicationContext::DEFAULT`].
+    entities: HashMap<Entity, ReplicationContext>,
+
+    /// Send intervals in ticks for contexts that are not updated on each tick.
+    intervals: HashMap<ReplicationContext, u32>,
+
+    /// Rule sets that are used instead of the active rule set for entities of contexts.
+    rule_sets: HashMap<ReplicationContext, &'static str>,
+
+    clients: HashMap<u64, ClientContexts>,
+
+    /// Joins and leaves that are not yet applied to entities of contexts.
+    pending: Vec<(u64, ReplicationContext, bool)>,
+}
+
+impl ReplicationContexts {
+    /// Adds a client to a context.
+    ///
+    /// Joining an already joined context has no effect.
+    pub fn join(&mut self, client_id: u64, context: ReplicationContext) {
+        self.set_joined(client_id, context, true);
+    }
+
+    /// Removes a client from a context.
+    ///
+    /// Leaving a context that wasn't joined has no effect.
+    pub fn leave(&mut self, client_id: u64, context: ReplicationContext) {
+        self.set_joined(client_id, context, false);
+    }
+
+    fn set_joined(&mut self, client_id: u64, context: ReplicationContext, joined: bool) {
+        let client_contexts = self.clients.entry(client_id).or_default();
+        let changed = if joined {
+            client_contexts.joined.insert(context)
+        } else {
+            client_contexts.joined.remove(&context)
+        };
+        if changed {
+            self.pending.push((client_id, context, joined));
+        }
+    }
+
+    /// Returns `true` if a client is a member of a context.
+    pub fn is_joined(&self, client_id: u64, context: ReplicationContext) -> bool {
+        self.clients
+            .get(&client_id)
+            .map_or(context == ReplicationContext::DEFAULT, |client_contexts| {
+                client_contexts.joined.contains(&context)
+            })
+    }
+
+    /// Returns the context of a replicated entity.
+    pub fn context(&self, entity: Entity) -> ReplicationContext {
+        self.entities.get(&entity).copied().unwrap_or_default()
+    }
+
+    /// Sets how often changes of entities in a context are sent, in server ticks.
+    ///
+    /// For example, with interval 10 changes are sent only on every 10th [`ServerTick`](super::ServerTick).
+    /// Skipped changes are deferred like for congested clients and sent later together with newer changes.
+    /// Spawns, component insertions, removals and despawns are never deferred.
+    /// All contexts are updated on each tick by default, zero is treated as 1.
+    pub fn set_interval(&mut self, context: ReplicationContext, interval: u32) {
+        if interval > 1 {
+            self.intervals.insert(context, interval);
+        } else {
+            self.intervals.remove(&context);
+        }
+    }
+
+    /// Returns the send interval of a context in server ticks.
+    pub fn interval(&self, context: ReplicationContext) -> u32 {
+        self.intervals.get(&context).copied().unwrap_or(1)
+    }
+
+    /// Sets the rule set for entities of a context.
+    ///
+    /// Entities of the context replicate components from the specified rule set instead of the active one
+    /// from [`ReplicationRules::set_active_rule_set`](crate::replication_core::ReplicationRules::set_active_rule_set).
+    /// [`None`] makes the context use the active rule set, which is the default.
+    /// Components that start replicating will be sent for all entities of the context.
+    pub fn set_rule_set(&mut self, context: ReplicationContext, rule_set: Option<&'static str>) {
+        match rule_set {
+            Some(rule_set) => self.rule_sets.insert(context, rule_set),
+            None => self.rule_sets.remove(&context),
+        };
+    }
+
+    /// Returns the rule set of a context if it was set with [`Self::set_rule_set`].
+    pub fn rule_set(&self, context: ReplicationContext) -> Option<&'static str> {
+        self.rule_sets.get(&context).copied()
+    }
+
+    /// Returns rule sets that are set for contexts.
+    pub(super) fn context_rule_sets(&self) -> &HashMap<ReplicationContext, &'static str> {
+        &self.rule_sets
+    }
+
+    /// Returns `true` if a client is a member of the entity context.
+    pub(super) fn is_member(&self, client_id: u64, entity: Entity) -> bool {
+        self.is_joined(client_id, self.context(entity))
+    }
+
+    /// Returns `true` if changes of an entity should be sent on the tick.
+    pub(super) fn is_due(&self, entity: Entity, tick: u32) -> bool {
+        if self.intervals.is_empty() {
+            return true;
+        }
+        tick % self.interval(self.context(entity)) == 0
+    }
+
+    /// Returns `true` if an entity became available for a client after `last_tick`
+    /// by joining its context or by moving to a joined context.
+    pub(super) fn is_entered(
+        &self,
+        client_id: u64,
+        entity: Entity,
+        last_tick: Tick,
+        this_run: Tick,
+    ) -> bool {
+        self.clients
+            .get(&client_id)
+            .and_then(|client_contexts| client_contexts.entered.get(&entity))
+            .is_some_and(|tick| tick.map_or(true, |tick| tick.is_newer_than(last_tick, this_run)))
+    }
+
+    /// Returns all entities that recently became available for any client.
+    pub(super) fn entered_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.clients
+            .values()
+            .flat_map(|client_contexts| client_contexts.entered.keys().copied())
+    }
+
+    /// Returns entities that stopped being available for a client with ticks when it happened.
+    pub(super) fn left_entities(
+        &self,
+        client_id: u64,
+    ) -> impl Iterator<Item = (Entity, Tick)> + '_ {
+        self.clients
+            .get(&client_id)
+            .into_iter()
+            .flat_map(|client_contexts| client_contexts.left.iter())
+            .filter_map(|(&entity, tick)| tick.map(|tick| (entity, tick)))
+    }
+
+    fn insert_entity(&mut self, entity: Entity, context: ReplicationContext) {
+        if context == ReplicationContext::DEFAULT {
+            self.entities.remove(&entity);
+        } else {
+            self.entities.insert(entity, context);
+        }
+    }
+
+    fn move_entity(&mut self, clients: &[u64], entity: Entity, context: ReplicationContext) {
+        let previous = self.context(entity);
+        if previous == context {
+            return;
+        }
+        self.insert_entity(entity, context);
+
+        for &client_id in clients {
+            let was_member = self.is_joined(client_id, previous);
+            let is_member = self.is_joined(client_id, context);
+            if was_member != is_member {
+                self.clients
+                    .entry(client_id)
+                    .or_default()
+                    .set_member(entity, is_member);
+            }
+        }
+    }
+
+    fn remove_entity(&mut self, entity: Entity) {
+        self.entities.remove(&entity);
+        for client_contexts in self.clients.values_mut() {
+            client_contexts.entered.remove(&entity);
+            client_contexts.left.remove(&entity);
+        }
+    }
+
+    /// Returns the estimated memory in bytes used by the context state of a client.
+    pub(crate) fn memory_usage(&self, client_id: u64) -> usize {
+        self.clients.get(&client_id).map_or(0, |client_contexts| {
+            client_contexts.joined.len() * mem::size_of::<ReplicationContext>()
+                + (client_contexts.entered.len() + client_contexts.left.len())
+                    * mem::size_of::<(Entity, Option<Tick>)>()
+        })
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+        self.pending.retain(|&(id, ..)| id != client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.clients.clear();
+        self.pending.clear();
+    }
+}
+
+/// Context state of a single client.
+struct ClientContexts {
+    joined: HashSet<ReplicationContext>,
+
+    /// Entities that became available and ticks when it was detected.
+    ///
+    /// Such entities are sent with all their components until the tick is acknowledged.
+    entered: HashMap<Entity, Option<Tick>>,
+
+    /// Entities that stopped being available and ticks when it was detected.
+    left: HashMap<Entity, Option<Tick>>,
+}
+
+impl ClientContexts {
+    /// Records that an entity became available or unavailable for the client.
+    fn set_member(&mut self, entity: Entity, member: bool) {
+        if member {
+            self.left.remove(&entity);
+            self.entered.insert(entity, None);
+        } else {
+            self.entered.remove(&entity);
+            self.left.insert(entity, None);
+        }
+    }
+}
+
+impl Default for ClientContexts {
+    fn default() -> Self {
+        Self {
+            joined: [ReplicationContext::DEFAULT].into_iter().collect(),
+            entered: Default::default(),
+            left: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn membership() {
+        const CLIENT_ID: u64 = 1;
+        const META: ReplicationContext = ReplicationContext(1);
+        let mut contexts = ReplicationContexts::default();
+        assert!(contexts.is_joined(CLIENT_ID, ReplicationContext::DEFAULT));
+        assert!(!contexts.is_joined(CLIENT_ID, META));
+
+        contexts.join(CLIENT_ID, META);
+        contexts.join(CLIENT_ID, META);
+        assert!(contexts.is_joined(CLIENT_ID, META));
+        assert_eq!(contexts.pending.len(), 1, "joining twice should be ignored");
+
+        contexts.leave(CLIENT_ID, ReplicationContext::DEFAULT);
+        assert!(!contexts.is_joined(CLIENT_ID, ReplicationContext::DEFAULT));
+
+        contexts.remove_client(CLIENT_ID);
+        assert!(contexts.pending.is_empty());
+        assert!(contexts.is_joined(CLIENT_ID, ReplicationContext::DEFAULT));
+    }
+
+    #[test]
+    fn intervals() {
+        const META: ReplicationContext = ReplicationContext(1);
+        let mut contexts = ReplicationContexts::default();
+        let entity = Entity::from_raw(0);
+        contexts.insert_entity(entity, META);
+        assert_eq!(contexts.context(entity), META);
+        assert!(contexts.is_due(entity, 1));
+
+        contexts.set_interval(META, 4);
+        assert!(!contexts.is_due(entity, 1));
+        assert!(contexts.is_due(entity, 4));
+        assert!(contexts.is_due(Entity::from_raw(1), 1));
+
+        contexts.set_interval(META, 0);
+        assert_eq!(contexts.interval(META), 1);
+    }
+}
@@ -0,0 +1,276 @@
+use bevy::{ecs::system::SystemChangeTick, prelude::*, utils::HashMap};
+
+use super::{ClientCongestion, ClientLods, ClientVisibility, DeferralChange, ReplicationContexts};
+use crate::{
+    replication_core::ClientOwned,
+    world_diff::{ComponentDiff, WorldDiff},
+};
+
+/// Priority of entity replication for each client.
+///
+/// Higher-priority entities are placed first in world diffs and changes of lower-priority entities
+/// are deferred for congested clients according to [`CongestionPolicy`](super::CongestionPolicy)
+/// and when a diff exceeds [`MessageEntityLimit`].
+/// Entities without this component
+/// use [`ReplicationPriority::default`], so entities with [`ClientOwned`] are always boosted for their owners.
+///
+/// Used only on server.
+#[derive(Clone, Component, Copy, Debug, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct ReplicationPriority {
+    /// Priority for clients that don't own the entity.
+    pub base: f32,
+    /// Priority for the client from [`ClientOwned`].
+    pub owner: f32,
+}
+
+impl ReplicationPriority {
+    /// Default priority for clients that don't own the entity.
+    pub const BASE: f32 = 1.0;
+
+    /// Default priority for the owner of the entity.
+    pub const OWNER: f32 = 100.0;
+
+    /// Creates priority with the specified base value and the default owner boost.
+    pub fn new(base: f32) -> Self {
+        Self {
+            base,
+            owner: Self::OWNER,
+        }
+    }
+
+    /// Returns priority of the entity for a client.
+    pub fn for_client(&self, client_id: u64, owner: Option<&ClientOwned>) -> f32 {
+        if owner.is_some_and(|owner| owner.0 == client_id) {
+            self.owner
+        } else {
+            self.base
+        }
+    }
+}
+
+/// Returns priority of an entity for a client.
+///
+/// Uses the default priority if the entity doesn't have [`ReplicationPriority`].
+pub(super) fn entity_priority(world: &World, entity: Entity, client_id: u64) -> f32 {
+    let priority = world
+        .get::<ReplicationPriority>(entity)
+        .copied()
+        .unwrap_or_default();
+    priority.for_client(client_id, world.get::<ClientOwned>(entity))
+}
+
+impl Default for ReplicationPriority {
+    fn default() -> Self {
+        Self::new(Self::BASE)
+    }
+}
+
+/// Maximum number of changed entities in a single world diff for each client.
+///
+/// Entities with the highest [`ReplicationPriority`] are included first, changes of the rest
+/// are deferred to the next ticks like for congested clients. Unlike congestion deferral, spawns
+/// and entities that became visible are also limited, which allows to spread a large initial
+/// world over multiple ticks. Entities with component removals are always included.
+/// Despawns are not limited.
+///
+/// Insert as a resource on server to enable.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Deref, DerefMut, Resource)]
+pub struct MessageEntityLimit(pub usize);
+
+impl MessageEntityLimit {
+    /// Removes entities over the limit from prioritized diffs and defers their changes.
+    pub(super) fn apply(
+        self,
+        client_diffs: &mut HashMap<u64, WorldDiff>,
+        world: &World,
+        change_tick: &SystemChangeTick,
+        visibility: &ClientVisibility,
+        deferral_changes: &mut Vec<DeferralChange>,
+    ) {
+        let congestion = world.resource::<ClientCongestion>();
+        let lods = world.resource::<ClientLods>();
+        let contexts = world.resource::<ReplicationContexts>();
+        for (&client_id, world_diff) in client_diffs.iter_mut() {
+            if world_diff.entities_count() <= self.0 {
+                continue;
+            }
+
+            let mut candidates = Vec::new();
+            let mut included = 0;
+            for (archetype_index, archetype_diff) in world_diff.archetypes.iter().enumerate() {
+                let has_removals = archetype_diff
+                    .components
+                    .iter()
+                    .any(|component_diff| matches!(component_diff, ComponentDiff::Removed(_)));
+                if has_removals {
+                    included += archetype_diff.entities.len();
+                    continue;
+                }
+                for (entity_index, entity_diff) in archetype_diff.entities.iter().enumerate() {
+                    let priority = entity_priority(world, entity_diff.entity, client_id);
+                    candidates.push((priority, archetype_index, entity_index));
+                }
+            }
+            candidates.sort_by(|(a, ..), (b, ..)| b.total_cmp(a));
+
+            let mut kept: Vec<_> = world_diff
+                .archetypes
+                .iter()
+                .map(|archetype_diff| vec![true; archetype_diff.entities.len()])
+                .collect();
+            let available = self.0.saturating_sub(included);
+            for &(_, archetype_index, entity_index) in candidates.iter().skip(available) {
+                kept[archetype_index][entity_index] = false;
+            }
+
+            let last_tick = world_diff.tick;
+            for (archetype_diff, kept) in world_diff.archetypes.iter_mut().zip(kept) {
+                let mut kept = kept.into_iter();
+                archetype_diff.entities.retain(|entity_diff| {
+                    let keep = kept.next().expect("flags should match entities");
+                    if !keep {
+                        let entity = entity_diff.entity;
+                        let since = congestion
+                            .deferred_tick(client_id, entity)
+                            .unwrap_or(last_tick);
+                        let entered =
+                            lods.is_changed(client_id, entity, last_tick, change_tick.this_run())
+                                || visibility.is_entered(
+                                    client_id,
+                                    entity,
+                                    last_tick,
+                                    change_tick.this_run(),
+                                )
+                                || contexts.is_entered(
+                                    client_id,
+                                    entity,
+                                    last_tick,
+                                    change_tick.this_run(),
+                                )
+                                || congestion.is_deferred_entry(client_id, entity);
+                        deferral_changes.push(DeferralChange::Deferred {
+                            client_id,
+                            entity,
+                            since,
+                            entered,
+                        });
+                    }
+                    keep
+                });
+            }
+            world_diff
+                .archetypes
+                .retain(|archetype_diff| !archetype_diff.entities.is_empty());
+        }
+    }
+}
+
+/// Reduces update rate of distant entities for each client.
+///
+/// Distances are measured from client viewpoints set with [`Self::set_viewpoint`] to [`GlobalTransform`]
+/// of entities. Changes of an entity that is farther than a band distance are sent only on every
+/// `interval`-th [`ServerTick`](super::ServerTick), staggered by entity to spread them across ticks.
+/// Skipped changes are deferred like for congested clients and sent later together with newer changes.
+/// Spawns, component insertions, removals and despawns are never deferred.
+/// Clients without a viewpoint and entities without [`GlobalTransform`] are updated at full rate.
+///
+/// Used only on server.
+#[derive(Clone, Debug, Default, Resource)]
+pub struct DistanceFalloff {
+    /// Distances with send intervals in ticks, sorted by distance.
+    bands: Vec<(f32, u32)>,
+    viewpoints: HashMap<u64, Vec3>,
+}
+
+impl DistanceFalloff {
+    /// Creates falloff from distance bands with send intervals in ticks.
+    ///
+    /// For example, `[(50.0, 2), (100.0, 4)]` sends changes of entities farther than 50 units
+    /// at 1/2 rate and farther than 100 units at 1/4 rate.
+    pub fn new(bands: impl IntoIterator<Item = (f32, u32)>) -> Self {
+        let mut bands: Vec<_> = bands.into_iter().collect();
+        bands.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Self {
+            bands,
+            viewpoints: Default::default(),
+        }
+    }
+
+    /// Sets position from which distances are measured for a client, usually its camera or player.
+    pub fn set_viewpoint(&mut self, client_id: u64, viewpoint: Vec3) {
+        self.viewpoints.insert(client_id, viewpoint);
+    }
+
+    /// Returns the viewpoint of a client if it was set.
+    pub fn viewpoint(&self, client_id: u64) -> Option<Vec3> {
+        self.viewpoints.get(&client_id).copied()
+    }
+
+    /// Returns the send interval in ticks for a position and a client.
+    pub fn interval(&self, client_id: u64, position: Vec3) -> u32 {
+        let Some(viewpoint) = self.viewpoint(client_id) else {
+            return 1;
+        };
+        let distance = viewpoint.distance(position);
+        self.bands
+            .iter()
+            .rev()
+            .find(|&&(band_distance, _)| distance > band_distance)
+            .map_or(1, |&(_, interval)| interval.max(1))
+    }
+
+    /// Returns `true` if changes of an entity should be sent to a client on the tick.
+    pub(super) fn is_due(&self, world: &World, entity: Entity, client_id: u64, tick: u32) -> bool {
+        if self.bands.is_empty() {
+            return true;
+        }
+        let Some(transform) = world.get::<GlobalTransform>(entity) else {
+            return true;
+        };
+        let interval = self.interval(client_id, transform.translation());
+        tick.wrapping_add(entity.index()) % interval == 0
+    }
+
+    pub(super) fn remove_client(&mut self, client_id: u64) {
+        self.viewpoints.remove(&client_id);
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.viewpoints.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn owner_boost() {
+        const OWNER_ID: u64 = 1;
+        const OTHER_ID: u64 = 2;
+        let owner = ClientOwned(OWNER_ID);
+        let priority = ReplicationPriority::new(0.5);
+
+        assert_eq!(
+            priority.for_client(OWNER_ID, Some(&owner)),
+            ReplicationPriority::OWNER
+        );
+        assert_eq!(priority.for_client(OTHER_ID, Some(&owner)), 0.5);
+        assert_eq!(priority.for_client(OWNER_ID, None), 0.5);
+    }
+
+    #[test]
+    fn distance_bands() {
+        const CLIENT_ID: u64 = 1;
+        let mut falloff = DistanceFalloff::new([(100.0, 4), (50.0, 2)]);
+        assert_eq!(falloff.interval(CLIENT_ID, Vec3::X * 200.0), 1);
+
+        falloff.set_viewpoint(CLIENT_ID, Vec3::ZERO);
+        assert_eq!(falloff.interval(CLIENT_ID, Vec3::X * 10.0), 1);
+        assert_eq!(falloff.interval(CLIENT_ID, Vec3::X * 60.0), 2);
+        assert_eq!(falloff.interval(CLIENT_ID, Vec3::X * 200.0), 4);
+    }
+}
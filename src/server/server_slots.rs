@@ -0,0 +1,179 @@
+use bevy::{prelude::*, utils::HashSet};
+use bevy_renet::renet::{RenetServer, ServerEvent};
+
+use super::ServerSet;
+
+/// Rejects connections over [`ServerSlots`] with [`ClientRejected`].
+///
+/// Used only on server.
+pub(super) struct ServerSlotsPlugin;
+
+impl Plugin for ServerSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClientRejected>()
+            .add_systems(
+                PreUpdate,
+                Self::admission_system
+                    .in_set(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>())
+                    .run_if(resource_exists::<ServerSlots>()),
+            )
+            .add_systems(
+                PostUpdate,
+                Self::reset_system
+                    .run_if(resource_removed::<RenetServer>())
+                    .run_if(resource_exists::<ServerSlots>()),
+            );
+    }
+}
+
+impl ServerSlotsPlugin {
+    fn admission_system(
+        mut server_events: EventReader<ServerEvent>,
+        mut rejected_events: EventWriter<ClientRejected>,
+        mut slots: ResMut<ServerSlots>,
+    ) {
+        for event in &mut server_events {
+            match *event {
+                ServerEvent::ClientConnected { client_id } => {
+                    if !slots.admit(client_id) {
+                        debug!("rejecting client {client_id} because the server is full");
+                        rejected_events.send(ClientRejected { client_id });
+                    }
+                }
+                ServerEvent::ClientDisconnected { client_id, .. } => {
+                    slots.admitted.remove(&client_id);
+                }
+            }
+        }
+    }
+
+    fn reset_system(mut slots: ResMut<ServerSlots>) {
+        slots.admitted.clear();
+    }
+}
+
+/// Maximum number of players on server with slots reserved for specific clients.
+///
+/// Regular clients are admitted while less than [`Self::max_clients`] minus [`Self::reserved_slots`]
+/// clients are connected, while clients marked with [`Self::reserve`], like admins or players
+/// that are rejoining, are admitted up to [`Self::max_clients`].
+/// Connections over the limit are disconnected by server
+/// with [`ClientDisconnectReason::ServerFull`](crate::client::ClientDisconnectReason::ServerFull)
+/// and [`ClientRejected`] is emitted for them, so game logic could ignore these clients until
+/// they are disconnected.
+///
+/// The transport should accept more clients than [`Self::max_clients`], otherwise it refuses
+/// connections itself and clients receive only
+/// [`ClientDisconnectReason::Denied`](crate::client::ClientDisconnectReason::Denied).
+///
+/// Insert as a resource on server before starting it to enable.
+///
+/// Used only on server.
+#[derive(Clone, Debug, Resource)]
+pub struct ServerSlots {
+    /// Maximum number of connected clients, including reserved slots.
+    pub max_clients: usize,
+    /// Number of slots available only for reserved clients.
+    pub reserved_slots: usize,
+    reserved_clients: HashSet<u64>,
+    admitted: HashSet<u64>,
+}
+
+impl ServerSlots {
+    /// Creates slots for `max_clients` without reservations.
+    pub fn new(max_clients: usize) -> Self {
+        Self {
+            max_clients,
+            reserved_slots: 0,
+            reserved_clients: Default::default(),
+            admitted: Default::default(),
+        }
+    }
+
+    /// Sets the number of slots available only for reserved clients.
+    #[must_use]
+    pub fn with_reserved_slots(mut self, reserved_slots: usize) -> Self {
+        self.reserved_slots = reserved_slots;
+        self
+    }
+
+    /// Allows the client to take reserved slots.
+    pub fn reserve(&mut self, client_id: u64) {
+        self.reserved_clients.insert(client_id);
+    }
+
+    /// Removes the reservation for the client.
+    ///
+    /// Doesn't disconnect the client if it's already connected.
+    pub fn unreserve(&mut self, client_id: u64) {
+        self.reserved_clients.remove(&client_id);
+    }
+
+    /// Returns `true` if the client could take reserved slots.
+    pub fn is_reserved(&self, client_id: u64) -> bool {
+        self.reserved_clients.contains(&client_id)
+    }
+
+    /// Returns the number of admitted clients.
+    pub fn occupied(&self) -> usize {
+        self.admitted.len()
+    }
+
+    /// Returns `true` if the client could be admitted.
+    pub fn has_slot_for(&self, client_id: u64) -> bool {
+        let limit = if self.is_reserved(client_id) {
+            self.max_clients
+        } else {
+            self.max_clients.saturating_sub(self.reserved_slots)
+        };
+
+        self.admitted.len() < limit
+    }
+
+    /// Occupies a slot for the client if there is one available.
+    fn admit(&mut self, client_id: u64) -> bool {
+        if !self.has_slot_for(client_id) {
+            return false;
+        }
+
+        self.admitted.insert(client_id);
+        true
+    }
+}
+
+/// An event that emitted on server when a connected client is rejected because of [`ServerSlots`].
+///
+/// The client will be disconnected by server, but it will appear connected in
+/// [`RenetServer`] until then.
+///
+/// Used only on server.
+#[derive(Clone, Copy, Debug, Event)]
+pub struct ClientRejected {
+    pub client_id: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admission() {
+        const RESERVED_CLIENT: u64 = 3;
+
+        let mut slots = ServerSlots::new(2).with_reserved_slots(1);
+        slots.reserve(RESERVED_CLIENT);
+
+        assert!(slots.admit(1));
+        assert!(!slots.admit(2), "only one slot should be public");
+        assert!(slots.admit(RESERVED_CLIENT));
+        assert!(!slots.admit(4));
+        assert_eq!(slots.occupied(), 2);
+
+        slots.admitted.remove(&RESERVED_CLIENT);
+        assert!(!slots.admit(2), "reserved slot should stay free");
+
+        slots.admitted.remove(&1);
+        assert!(slots.admit(2));
+    }
+}
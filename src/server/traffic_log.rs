@@ -0,0 +1,430 @@
+use std::{
+    any,
+    fmt::{self, Debug, Display, Formatter},
+    fs::File,
+    io::{self, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    utils::{get_short_name, HashSet},
+};
+use bincode::{DefaultOptions, Options};
+use serde::{Deserialize, Serialize};
+
+use super::{ServerPlugin, ServerSet, ServerTick};
+use crate::{
+    client::ReplicationAck,
+    world_diff::{ComponentDiff, WorldDiff},
+};
+
+/// Keeps the current tick of [`TrafficLog`] for captured messages.
+///
+/// Used only on server.
+pub(super) struct TrafficLogPlugin;
+
+impl Plugin for TrafficLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrafficLog>().add_systems(
+            PostUpdate,
+            Self::tick_system
+                .in_set(ServerSet::Send)
+                .after(ServerPlugin::tick_increment_system)
+                .run_if(resource_changed::<ServerTick>()),
+        );
+    }
+}
+
+impl TrafficLogPlugin {
+    fn tick_system(server_tick: Res<ServerTick>, traffic_log: Res<TrafficLog>) {
+        traffic_log.tick.store(server_tick.get(), Ordering::Relaxed);
+    }
+}
+
+/// Logs messages sent to and received from selected clients.
+///
+/// Useful to investigate desync of a single player without enabling logs for all traffic.
+/// Each message is summarized: world diffs with the number of entities and names of components,
+/// events with their type names and values.
+/// Records are logged with `trace` level under `bevy_replicon::traffic` target
+/// or passed to a sink set with [`Self::set_sink`].
+///
+/// Traffic of all clients could also be written into a capture file with [`Self::start_capture`]
+/// for offline analysis. Captured messages are stored as is and could be read back with
+/// [`CapturedMessage::read_all`].
+///
+/// Used only on server.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_replicon::prelude::*;
+/// fn watch_system(mut traffic_log: ResMut<TrafficLog>) {
+///     traffic_log.watch(42);
+///     traffic_log.set_sink(|record| println!("{record}"));
+/// }
+/// ```
+#[derive(Default, Resource)]
+pub struct TrafficLog {
+    clients: HashSet<u64>,
+    sink: Option<TrafficSink>,
+    capture: Mutex<Option<BufWriter<File>>>,
+    tick: AtomicU32,
+}
+
+type TrafficSink = Box<dyn Fn(&TrafficRecord) + Send + Sync>;
+
+impl TrafficLog {
+    /// Starts logging traffic of a client.
+    pub fn watch(&mut self, client_id: u64) {
+        self.clients.insert(client_id);
+    }
+
+    /// Stops logging traffic of a client.
+    pub fn unwatch(&mut self, client_id: u64) {
+        self.clients.remove(&client_id);
+    }
+
+    /// Returns `true` if traffic of a client is logged.
+    pub fn is_watched(&self, client_id: u64) -> bool {
+        self.clients.contains(&client_id)
+    }
+
+    /// Returns all watched clients.
+    pub fn clients(&self) -> impl Iterator<Item = u64> + '_ {
+        self.clients.iter().copied()
+    }
+
+    /// Passes records to `sink` instead of logging them.
+    pub fn set_sink(&mut self, sink: impl Fn(&TrafficRecord) + Send + Sync + 'static) {
+        self.sink = Some(Box::new(sink));
+    }
+
+    /// Removes the sink set with [`Self::set_sink`], so records will be logged again.
+    pub fn remove_sink(&mut self) {
+        self.sink = None;
+    }
+
+    /// Starts writing traffic of all clients into a file at `path`.
+    ///
+    /// Replaces the file if it exists and finishes the previous capture.
+    pub fn start_capture(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = File::create(path)?;
+        self.stop_capture();
+        *self.capture_mut() = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    /// Finishes writing of the capture file started with [`Self::start_capture`].
+    pub fn stop_capture(&mut self) {
+        if let Some(mut capture) = self.capture_mut().take() {
+            if let Err(e) = capture.flush() {
+                error!("unable to flush traffic capture: {e}");
+            }
+        }
+    }
+
+    /// Returns `true` if traffic is written into a capture file.
+    pub fn is_capturing(&self) -> bool {
+        self.capture
+            .lock()
+            .expect("traffic capture should not be poisoned")
+            .is_some()
+    }
+
+    /// Returns `true` if messages should be passed to [`Self::sent`] and [`Self::received`].
+    ///
+    /// Allows to skip iteration over recipients when nothing is recorded.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.clients.is_empty() || self.is_capturing()
+    }
+
+    /// Records a message sent to a client.
+    ///
+    /// `summary` is called only for watched clients.
+    pub(crate) fn sent(
+        &self,
+        client_id: u64,
+        channel_id: u8,
+        message: &[u8],
+        summary: impl FnOnce() -> String,
+    ) {
+        self.record(
+            TrafficDirection::Sent,
+            client_id,
+            channel_id,
+            message,
+            summary,
+        );
+    }
+
+    /// Records a message received from a client.
+    ///
+    /// `summary` is called only for watched clients.
+    pub(crate) fn received(
+        &self,
+        client_id: u64,
+        channel_id: u8,
+        message: &[u8],
+        summary: impl FnOnce() -> String,
+    ) {
+        self.record(
+            TrafficDirection::Received,
+            client_id,
+            channel_id,
+            message,
+            summary,
+        );
+    }
+
+    fn record(
+        &self,
+        direction: TrafficDirection,
+        client_id: u64,
+        channel_id: u8,
+        message: &[u8],
+        summary: impl FnOnce() -> String,
+    ) {
+        self.capture(direction, client_id, channel_id, message);
+
+        if !self.is_watched(client_id) {
+            return;
+        }
+
+        let record = TrafficRecord {
+            direction,
+            client_id,
+            channel_id,
+            bytes: message.len(),
+            summary: summary(),
+        };
+        match &self.sink {
+            Some(sink) => sink(&record),
+            None => trace!(target: "bevy_replicon::traffic", "{record}"),
+        }
+    }
+
+    /// Writes a message into the capture file if capturing.
+    ///
+    /// Stops capturing on write error.
+    fn capture(&self, direction: TrafficDirection, client_id: u64, channel_id: u8, message: &[u8]) {
+        let mut capture = self
+            .capture
+            .lock()
+            .expect("traffic capture should not be poisoned");
+        let Some(writer) = &mut *capture else {
+            return;
+        };
+
+        let tick = self.tick.load(Ordering::Relaxed);
+        if let Err(e) = DefaultOptions::new()
+            .serialize_into(writer, &(direction, client_id, channel_id, tick, message))
+        {
+            error!("unable to write traffic capture, stopping it: {e}");
+            *capture = None;
+        }
+    }
+
+    fn capture_mut(&mut self) -> &mut Option<BufWriter<File>> {
+        self.capture
+            .get_mut()
+            .expect("traffic capture should not be poisoned")
+    }
+}
+
+impl Drop for TrafficLog {
+    fn drop(&mut self) {
+        self.stop_capture();
+    }
+}
+
+/// A message read from a capture file written by [`TrafficLog`].
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct CapturedMessage {
+    pub direction: TrafficDirection,
+    pub client_id: u64,
+    pub channel_id: u8,
+    /// Value of [`ServerTick`] when the message was sent or received.
+    pub tick: u32,
+    /// Message content as sent over the network.
+    pub message: Vec<u8>,
+}
+
+impl CapturedMessage {
+    /// Reads all messages from a capture file.
+    pub fn read_all(mut reader: impl Read) -> bincode::Result<Vec<Self>> {
+        let mut messages = Vec::new();
+        loop {
+            match DefaultOptions::new().deserialize_from(&mut reader) {
+                Ok(message) => messages.push(message),
+                Err(e) => match *e {
+                    bincode::ErrorKind::Io(io_error)
+                        if io_error.kind() == ErrorKind::UnexpectedEof =>
+                    {
+                        return Ok(messages);
+                    }
+                    _ => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+/// A single message logged by [`TrafficLog`].
+#[derive(Clone, Debug)]
+pub struct TrafficRecord {
+    pub direction: TrafficDirection,
+    pub client_id: u64,
+    pub channel_id: u8,
+    /// Size of the message.
+    pub bytes: usize,
+    /// Decoded content of the message.
+    pub summary: String,
+}
+
+impl Display for TrafficRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let preposition = match self.direction {
+            TrafficDirection::Sent => "to",
+            TrafficDirection::Received => "from",
+        };
+        write!(
+            f,
+            "{:?} {} bytes on channel {} {preposition} client {}: {}",
+            self.direction, self.bytes, self.channel_id, self.client_id, self.summary
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Hash, PartialEq, Eq, Serialize)]
+pub enum TrafficDirection {
+    Sent,
+    Received,
+}
+
+/// Returns the type name and the value of an event.
+pub(crate) fn event_summary<T: Debug>(event: &T) -> String {
+    format!("{} {event:?}", get_short_name(any::type_name::<T>()))
+}
+
+/// Returns the acknowledged tick and the number of unconfirmed entities in an ack.
+pub(crate) fn ack_summary(message: &[u8]) -> String {
+    match bincode::deserialize::<ReplicationAck>(message) {
+        Ok(ack) => format!(
+            "ack of tick {} with {} unconfirmed entities",
+            ack.tick.get().get(),
+            ack.unconfirmed.len()
+        ),
+        Err(e) => format!("invalid ack: {e}"),
+    }
+}
+
+/// Returns counts of entities, names of components and despawns in a world diff.
+pub(super) fn diff_summary(world_diff: &WorldDiff) -> String {
+    if world_diff.is_empty() {
+        return format!("keepalive for tick {}", world_diff.tick.get());
+    }
+
+    let mut components = Vec::new();
+    for archetype_diff in &world_diff.archetypes {
+        for component_diff in &archetype_diff.components {
+            let name = match component_diff {
+                ComponentDiff::Changed(registration) => registration.short_name().to_string(),
+                ComponentDiff::Removed(registration) => format!("-{}", registration.short_name()),
+            };
+            if !components.contains(&name) {
+                components.push(name);
+            }
+        }
+    }
+
+    format!(
+        "world diff for tick {} with {} entities [{}], {} despawns and {} hidden",
+        world_diff.tick.get(),
+        world_diff.entities_count(),
+        components.join(", "),
+        world_diff.despawns.len(),
+        world_diff.hidden.len(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn watched_clients() {
+        const WATCHED_ID: u64 = 1;
+        const OTHER_ID: u64 = 2;
+
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink_records = records.clone();
+        let mut traffic_log = TrafficLog::default();
+        traffic_log.watch(WATCHED_ID);
+        traffic_log.set_sink(move |record| sink_records.lock().unwrap().push(record.clone()));
+
+        traffic_log.sent(WATCHED_ID, 0, &[0; 10], || "sent".to_string());
+        traffic_log.received(WATCHED_ID, 1, &[0; 20], || "received".to_string());
+        traffic_log.sent(OTHER_ID, 0, &[0; 10], || {
+            panic!("summary shouldn't be built")
+        });
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, TrafficDirection::Sent);
+        assert_eq!(records[1].direction, TrafficDirection::Received);
+        assert_eq!(records[1].bytes, 20);
+        assert_eq!(records[1].summary, "received");
+    }
+
+    #[test]
+    fn capture() {
+        const CLIENT_ID: u64 = 1;
+
+        let path = std::env::temp_dir().join(format!(
+            "bevy_replicon_traffic_capture_{}",
+            std::process::id()
+        ));
+        let mut traffic_log = TrafficLog::default();
+        traffic_log.start_capture(&path).unwrap();
+        assert!(traffic_log.is_capturing());
+        assert!(traffic_log.is_enabled());
+
+        traffic_log.sent(CLIENT_ID, 0, &[1, 2], || {
+            panic!("summary shouldn't be built")
+        });
+        traffic_log.tick.store(5, Ordering::Relaxed);
+        traffic_log.received(CLIENT_ID, 1, &[3], || panic!("summary shouldn't be built"));
+        traffic_log.stop_capture();
+        assert!(!traffic_log.is_capturing());
+        traffic_log.sent(CLIENT_ID, 0, &[4], || panic!("summary shouldn't be built"));
+
+        let messages = CapturedMessage::read_all(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            messages,
+            [
+                CapturedMessage {
+                    direction: TrafficDirection::Sent,
+                    client_id: CLIENT_ID,
+                    channel_id: 0,
+                    tick: 0,
+                    message: vec![1, 2],
+                },
+                CapturedMessage {
+                    direction: TrafficDirection::Received,
+                    client_id: CLIENT_ID,
+                    channel_id: 1,
+                    tick: 5,
+                    message: vec![3],
+                },
+            ]
+        );
+    }
+}
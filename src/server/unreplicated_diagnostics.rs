@@ -0,0 +1,137 @@
+use std::any::TypeId;
+
+use bevy::{ecs::component::ComponentId, prelude::*, utils::HashSet};
+#[cfg(debug_assertions)]
+use bevy_renet::renet::RenetServer;
+
+use super::ReplicationPriority;
+#[cfg(debug_assertions)]
+use super::ServerSet;
+#[cfg(debug_assertions)]
+use crate::replication_core::ReplicationRules;
+use crate::replication_core::{ClientOwned, Replication, ReplicationRecursive};
+
+/// Warns about reflected components on replicated entities that are not marked for replication.
+///
+/// The check runs only in debug builds.
+/// Used only on server.
+pub(super) struct UnreplicatedDiagnosticsPlugin;
+
+impl Plugin for UnreplicatedDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UnreplicatedDiagnostics>();
+
+        #[cfg(debug_assertions)]
+        app.add_systems(
+            PostUpdate,
+            Self::detection_system
+                .before(ServerSet::Send)
+                .run_if(resource_exists::<RenetServer>()),
+        );
+    }
+}
+
+#[cfg(debug_assertions)]
+impl UnreplicatedDiagnosticsPlugin {
+    /// Checks components of new archetypes with [`Replication`].
+    ///
+    /// Each component is checked only once, so each type is reported at most once.
+    fn detection_system(world: &mut World) {
+        world.resource_scope(|world, mut diagnostics: Mut<UnreplicatedDiagnostics>| {
+            let archetypes = world.archetypes();
+            if diagnostics.generation == archetypes.len() {
+                return;
+            }
+
+            let Some(replication_id) = world.component_id::<Replication>() else {
+                return;
+            };
+            let replication_rules = world.resource::<ReplicationRules>();
+            let registry = world.resource::<AppTypeRegistry>().read();
+            for archetype in archetypes
+                .iter()
+                .skip(diagnostics.generation)
+                .filter(|archetype| archetype.contains(replication_id))
+            {
+                for component_id in archetype.components() {
+                    if !diagnostics.checked.insert(component_id)
+                        || replication_rules.is_marked(component_id)
+                    {
+                        continue;
+                    }
+                    let component_info = world
+                        .components()
+                        .get_info(component_id)
+                        .expect("archetype component should be initialized");
+                    let Some(type_id) = component_info.type_id() else {
+                        continue;
+                    };
+                    if diagnostics.ignored.contains(&type_id) {
+                        continue;
+                    }
+                    if registry
+                        .get_type_data::<ReflectComponent>(type_id)
+                        .is_some()
+                    {
+                        let name = component_info.name();
+                        warn!("{name} is reflected and present on a replicated entity, but not marked for replication");
+                        diagnostics.reported.push(name.to_string());
+                    }
+                }
+            }
+            diagnostics.generation = archetypes.len();
+        });
+    }
+}
+
+/// Configures warnings about reflected components on entities with [`Replication`]
+/// that are not marked for replication.
+///
+/// In debug builds server warns once per component type, which helps to find components that were
+/// forgotten to be registered with [`AppReplicationExt::replicate`](crate::replication_core::AppReplicationExt::replicate).
+/// Components that are intentionally local to server could be excluded with [`Self::ignore`].
+#[derive(Resource)]
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+pub struct UnreplicatedDiagnostics {
+    /// Components that are never reported.
+    ignored: HashSet<TypeId>,
+    /// Components that were already checked.
+    checked: HashSet<ComponentId>,
+    /// Names of reported components.
+    reported: Vec<String>,
+    /// Number of processed archetypes.
+    generation: usize,
+}
+
+impl UnreplicatedDiagnostics {
+    /// Disables warnings for component `C`.
+    pub fn ignore<C: Component>(&mut self) {
+        self.ignored.insert(TypeId::of::<C>());
+    }
+
+    /// Returns type names of components that were reported.
+    ///
+    /// Always empty in release builds.
+    pub fn reported(&self) -> &[String] {
+        &self.reported
+    }
+}
+
+impl Default for UnreplicatedDiagnostics {
+    fn default() -> Self {
+        let mut diagnostics = Self {
+            ignored: Default::default(),
+            checked: Default::default(),
+            reported: Default::default(),
+            generation: 0,
+        };
+        diagnostics.ignore::<Replication>();
+        diagnostics.ignore::<ReplicationRecursive>();
+        diagnostics.ignore::<ClientOwned>();
+        diagnostics.ignore::<ReplicationPriority>();
+        diagnostics.ignore::<GlobalTransform>();
+        diagnostics.ignore::<Children>();
+        diagnostics.ignore::<Parent>();
+        diagnostics
+    }
+}
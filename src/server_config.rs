@@ -0,0 +1,155 @@
+use std::{
+    any::{self, TypeId},
+    fmt::Debug,
+    marker::PhantomData,
+    time::Duration,
+};
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_renet::{
+    renet::{RenetClient, RenetServer, SendType, ServerEvent},
+    transport::client_connected,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    client::ClientSet,
+    replication_core::{NetworkChannels, ReplicationSet},
+    server::{traffic_log::event_summary, ServerSet, TrafficDirection, TrafficLog},
+    traffic_decoder::{decode_event, EventDecoders},
+    wire_format::{deserialize_message, serialize_message},
+};
+
+/// An extension trait for [`App`] for sending server configuration to clients on connection.
+pub trait ServerConfigAppExt {
+    /// Sends resource `C` from server to each client right after connection.
+    ///
+    /// Useful for settings that clients need before gameplay, like tick rate, world seed or game rules.
+    /// The resource is inserted on client before the first world diff is applied, so systems that react
+    /// to replicated entities can already read it. Client doesn't apply world diffs until all registered
+    /// configs are received. `C` should be inserted on server before clients connect,
+    /// later changes are not sent. The resource is removed from client on disconnect.
+    /// Should be called on both server and clients.
+    fn sync_server_config<C: Resource + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self;
+}
+
+impl ServerConfigAppExt for App {
+    fn sync_server_config<C: Resource + Serialize + DeserializeOwned + Debug>(
+        &mut self,
+    ) -> &mut Self {
+        let channel_id = self
+            .world
+            .resource_mut::<NetworkChannels>()
+            .create_server_channel::<C>(SendType::ReliableOrdered {
+                resend_time: Duration::from_millis(300),
+            });
+
+        self.init_resource::<ServerConfigs>()
+            .init_resource::<TrafficLog>()
+            .init_resource::<EventDecoders>();
+        self.world.resource_mut::<ServerConfigs>().registered += 1;
+        self.world.resource_mut::<EventDecoders>().insert::<C>(
+            TrafficDirection::Sent,
+            channel_id,
+            None,
+            Some(decode_event::<C>),
+        );
+
+        self.insert_resource(ConfigChannel::<C> {
+            id: channel_id,
+            marker: PhantomData,
+        })
+        .add_systems(
+            PreUpdate,
+            (
+                receiving_system::<C>
+                    .in_set(ClientSet::Receive)
+                    .before(ReplicationSet)
+                    .run_if(client_connected()),
+                sending_system::<C>
+                    .after(ServerSet::Receive)
+                    .run_if(resource_exists::<RenetServer>()),
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            reset_system::<C>.run_if(resource_removed::<RenetClient>()),
+        )
+    }
+}
+
+/// Sends the config to connected clients.
+fn sending_system<C: Resource + Serialize + Debug>(
+    mut server_events: EventReader<ServerEvent>,
+    mut server: ResMut<RenetServer>,
+    channel: Res<ConfigChannel<C>>,
+    config: Option<Res<C>>,
+    traffic_log: Res<TrafficLog>,
+) {
+    for event in &mut server_events {
+        if let ServerEvent::ClientConnected { client_id } = *event {
+            let config = config.as_ref().unwrap_or_else(|| {
+                panic!(
+                    "{} should be inserted on server before clients connect",
+                    any::type_name::<C>()
+                )
+            });
+            let message = serialize_message(&**config).expect("config should be serializable");
+            traffic_log.sent(client_id, channel.id, &message, || event_summary(&**config));
+            server.send_message(client_id, channel.id, message);
+            debug!("sent config {config:?} to client {client_id}");
+        }
+    }
+}
+
+/// Inserts the received config.
+///
+/// Exclusive to make the resource available to the diff receiving system in the same frame.
+fn receiving_system<C: Resource + DeserializeOwned + Debug>(world: &mut World) {
+    let channel_id = world.resource::<ConfigChannel<C>>().id;
+    while let Some(message) = world
+        .resource_mut::<RenetClient>()
+        .receive_message(channel_id)
+    {
+        match deserialize_message::<C>(&message) {
+            Ok(config) => {
+                debug!("received config {config:?}");
+                world.insert_resource(config);
+                world
+                    .resource_mut::<ServerConfigs>()
+                    .received
+                    .insert(TypeId::of::<C>());
+            }
+            Err(e) => error!("unable to deserialize config from server: {e}"),
+        }
+    }
+}
+
+fn reset_system<C: Resource>(mut commands: Commands, mut configs: ResMut<ServerConfigs>) {
+    commands.remove_resource::<C>();
+    configs.received.remove(&TypeId::of::<C>());
+}
+
+/// Condition that returns `true` if all configs registered with
+/// [`ServerConfigAppExt::sync_server_config`] are received.
+pub(crate) fn configs_received() -> impl FnMut(Option<Res<ServerConfigs>>) -> bool + Clone {
+    |configs| configs.map_or(true, |configs| configs.received.len() == configs.registered)
+}
+
+/// Tracks configs received by client.
+#[derive(Default, Resource)]
+pub(crate) struct ServerConfigs {
+    /// Number of registered configs.
+    registered: usize,
+    /// Configs received after connection.
+    received: HashSet<TypeId>,
+}
+
+/// Holds a channel ID for config `C`.
+#[derive(Resource)]
+struct ConfigChannel<C> {
+    id: u8,
+    marker: PhantomData<C>,
+}
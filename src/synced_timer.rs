@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_renet::renet::RenetClient;
+use serde::{Deserialize, Serialize};
+
+use crate::{client::ServerTickEstimate, replication_core::AppReplicationExt, server::ServerTick};
+
+/// Replicates [`SyncedTimer`].
+pub struct SyncedTimerPlugin;
+
+impl Plugin for SyncedTimerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SyncedTimer>()
+            .replicate::<SyncedTimer>();
+    }
+}
+
+/// A timer defined by server ticks instead of local elapsed time.
+///
+/// Start it on server with the current [`ServerTick`] and read it with [`SyncedClock::tick`],
+/// so round timers and respawn countdowns show the same values on all machines without sending
+/// the remaining time every frame. Replicated by [`SyncedTimerPlugin`] when used as a component.
+/// Could also be inserted as a resource, for example after receiving a server event.
+#[derive(
+    Clone, Component, Copy, Debug, Default, Deserialize, Eq, PartialEq, Reflect, Resource, Serialize,
+)]
+#[reflect(Component)]
+pub struct SyncedTimer {
+    /// Tick at which the timer was started.
+    pub start_tick: u32,
+    /// Tick at which the timer finishes.
+    pub end_tick: u32,
+}
+
+impl SyncedTimer {
+    /// Creates a timer that finishes after `duration_ticks` since `start_tick`.
+    pub fn new(start_tick: u32, duration_ticks: u32) -> Self {
+        Self {
+            start_tick,
+            end_tick: start_tick.wrapping_add(duration_ticks),
+        }
+    }
+
+    /// Returns the number of ticks between start and end.
+    pub fn duration_ticks(&self) -> u32 {
+        self.end_tick.wrapping_sub(self.start_tick)
+    }
+
+    /// Returns the number of ticks passed since start at `tick`, clamped to the duration.
+    pub fn elapsed_ticks(&self, tick: f64) -> f64 {
+        let whole = tick.floor();
+        let elapsed = (whole as u32).wrapping_sub(self.start_tick) as i32 as f64 + (tick - whole);
+        elapsed.clamp(0.0, self.duration_ticks() as f64)
+    }
+
+    /// Returns the number of ticks left until end at `tick`.
+    pub fn remaining_ticks(&self, tick: f64) -> f64 {
+        self.duration_ticks() as f64 - self.elapsed_ticks(tick)
+    }
+
+    /// Returns the time left until end at `tick` for ticks of the specified duration.
+    ///
+    /// Tick duration is defined by [`TickPolicy`](crate::server::TickPolicy) and could be estimated
+    /// on clients with [`SyncedClock::tick_duration`].
+    pub fn remaining(&self, tick: f64, tick_duration: Duration) -> Duration {
+        tick_duration.mul_f64(self.remaining_ticks(tick))
+    }
+
+    /// Returns the passed fraction of the timer at `tick`, in range `[0, 1]`.
+    pub fn fraction(&self, tick: f64) -> f32 {
+        let duration = self.duration_ticks();
+        if duration == 0 {
+            return 1.0;
+        }
+        (self.elapsed_ticks(tick) / duration as f64) as f32
+    }
+
+    /// Returns `true` if the timer reached its end at `tick`.
+    pub fn finished(&self, tick: f64) -> bool {
+        self.remaining_ticks(tick) <= 0.0
+    }
+}
+
+/// A system parameter to read the current server tick for [`SyncedTimer`].
+///
+/// On clients it's estimated with [`ServerTickEstimate`], on server it's [`ServerTick`].
+#[derive(SystemParam)]
+pub struct SyncedClock<'w> {
+    client: Option<Res<'w, RenetClient>>,
+    estimate: Option<Res<'w, ServerTickEstimate>>,
+    server_tick: Res<'w, ServerTick>,
+}
+
+impl SyncedClock<'_> {
+    /// Returns the current server tick with the passed fraction of the next tick.
+    pub fn tick(&self) -> f64 {
+        match (&self.client, &self.estimate) {
+            (Some(_), Some(estimate)) => estimate.tick() as f64 + estimate.overstep(),
+            _ => self.server_tick.get() as f64,
+        }
+    }
+
+    /// Returns the measured duration of a single server tick.
+    ///
+    /// Available only on clients after receiving two ticks.
+    pub fn tick_duration(&self) -> Option<Duration> {
+        self.client
+            .as_ref()
+            .and(self.estimate.as_ref())
+            .and_then(|estimate| estimate.tick_duration())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress() {
+        let timer = SyncedTimer::new(10, 20);
+        assert_eq!(timer.duration_ticks(), 20);
+        assert_eq!(timer.elapsed_ticks(5.0), 0.0);
+        assert_eq!(timer.remaining_ticks(15.5), 14.5);
+        assert_eq!(timer.fraction(20.0), 0.5);
+        assert_eq!(
+            timer.remaining(25.0, Duration::from_millis(100)),
+            Duration::from_millis(500)
+        );
+        assert!(!timer.finished(29.0));
+        assert!(timer.finished(30.0));
+        assert_eq!(timer.remaining_ticks(40.0), 0.0);
+    }
+
+    #[test]
+    fn wrapping() {
+        let timer = SyncedTimer::new(u32::MAX - 4, 10);
+        assert_eq!(timer.end_tick, 5);
+        assert_eq!(timer.elapsed_ticks((u32::MAX - 2) as f64), 2.0);
+        assert_eq!(timer.remaining_ticks(3.0), 2.0);
+        assert!(timer.finished(5.0));
+    }
+}
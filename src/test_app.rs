@@ -0,0 +1,143 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::renet::{
+    transport::{NetcodeClientTransport, NetcodeServerTransport},
+    RenetServer,
+};
+
+use crate::netcode::NetcodeWorldExt;
+
+/// A server app and client apps updated in lockstep for integration tests.
+///
+/// Apps are connected over localhost and [`Time`] of each app advances by [`Self::TIME_STEP`]
+/// on each update instead of real time, so tests don't depend on the machine speed.
+/// Apps should use [`TickPolicy::Manual`](crate::server::TickPolicy::Manual) to increment
+/// [`ServerTick`](crate::server::ServerTick) on each server update.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_replicon::{prelude::*, test_app::LockstepApps};
+/// let mut apps = LockstepApps::new(2, |app| {
+///     app.add_plugins((
+///         MinimalPlugins,
+///         ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+///     ));
+/// });
+///
+/// apps.server.world.spawn((Replication, Transform::default()));
+/// apps.exchange_with_server();
+/// ```
+pub struct LockstepApps {
+    pub server: App,
+    pub clients: Vec<App>,
+}
+
+impl LockstepApps {
+    /// Duration by which [`Time`] of each app advances on each update.
+    pub const TIME_STEP: Duration = Duration::from_millis(16);
+
+    /// Creates a server app and `clients_count` client apps, calls `setup` for each of them and connects clients.
+    pub fn new(clients_count: usize, mut setup: impl FnMut(&mut App)) -> Self {
+        let mut server = App::new();
+        let mut clients: Vec<_> = (0..clients_count).map(|_| App::new()).collect();
+        for app in [&mut server].into_iter().chain(&mut clients) {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(Self::TIME_STEP));
+            setup(app);
+        }
+
+        create_server(&mut server, clients_count);
+        connect(&mut server, clients.iter_mut());
+
+        Self { server, clients }
+    }
+
+    /// Updates server and then each client, so each app receives messages sent by the other side on its previous update.
+    pub fn exchange_with_server(&mut self) {
+        self.server.update();
+        for client in &mut self.clients {
+            client.update();
+        }
+    }
+
+    /// Calls [`Self::exchange_with_server`] `ticks` times.
+    pub fn advance_ticks(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.exchange_with_server();
+        }
+    }
+}
+
+/// An extension trait for [`App`] for connecting clients in integration tests.
+pub trait ServerTestAppExt {
+    /// Connects `client_app` to this app over localhost.
+    ///
+    /// Creates server for a single client if it doesn't exist.
+    /// Updates both apps until the client is connected.
+    fn connect_client(&mut self, client_app: &mut App);
+}
+
+impl ServerTestAppExt for App {
+    fn connect_client(&mut self, client_app: &mut App) {
+        if !self.world.contains_resource::<RenetServer>() {
+            create_server(self, 1);
+        }
+        connect(self, [client_app]);
+    }
+}
+
+fn create_server(server_app: &mut App, max_clients: usize) {
+    let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    server_app
+        .world
+        .create_netcode_server(addr, max_clients, PROTOCOL_ID, None)
+        .expect("server should be created on localhost");
+}
+
+/// Creates netcode clients and updates all apps until they are connected.
+fn connect<'a>(server_app: &mut App, client_apps: impl IntoIterator<Item = &'a mut App>) {
+    let port = server_app
+        .world
+        .resource::<NetcodeServerTransport>()
+        .addr()
+        .port();
+    let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+
+    let mut client_apps: Vec<_> = client_apps.into_iter().collect();
+    for client_app in &mut client_apps {
+        let client_id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
+        client_app
+            .world
+            .create_netcode_client(server_addr, client_id, PROTOCOL_ID)
+            .expect("client should be created on localhost");
+    }
+
+    let started_at = Instant::now();
+    while started_at.elapsed() < CONNECTION_TIMEOUT {
+        for client_app in &mut client_apps {
+            client_app.update();
+        }
+        server_app.update();
+        if client_apps.iter().all(|client_app| {
+            client_app
+                .world
+                .resource::<NetcodeClientTransport>()
+                .is_connected()
+        }) {
+            return;
+        }
+    }
+
+    panic!("clients should connect within {CONNECTION_TIMEOUT:?}");
+}
+
+const PROTOCOL_ID: u64 = 0;
+
+/// Real time after which connection fails a test instead of hanging it.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
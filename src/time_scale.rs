@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_renet::{
+    renet::{RenetClient, RenetServer, ServerEvent},
+    transport::client_connected,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::ClientSet,
+    network_event::{
+        server_event::{SendMode, ServerEventAppExt, ToClients},
+        SendPolicy,
+    },
+    server::ServerSet,
+};
+
+/// Replicates [`ServerTimeScale`] from server to clients.
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ServerTimeScale>()
+            .add_server_event::<TimeScaleChanged>(SendPolicy::reliable().ordered())
+            .add_systems(
+                PreUpdate,
+                Self::receiving_system
+                    .after(ClientSet::Receive)
+                    .run_if(client_connected()),
+            )
+            .add_systems(
+                PostUpdate,
+                (
+                    Self::sending_system
+                        .before(ServerSet::Send)
+                        .run_if(resource_exists::<RenetServer>()),
+                    Self::reset_system.run_if(resource_removed::<RenetClient>()),
+                ),
+            );
+    }
+}
+
+impl TimeScalePlugin {
+    /// Sends changes of the time scale to all clients and the current scale to newly connected clients.
+    fn sending_system(
+        mut server_events: EventReader<ServerEvent>,
+        mut scale_events: EventWriter<ToClients<TimeScaleChanged>>,
+        time_scale: Res<ServerTimeScale>,
+    ) {
+        if time_scale.is_changed() {
+            // Newly connected clients will receive the broadcast.
+            server_events.clear();
+            scale_events.send(ToClients {
+                mode: SendMode::Broadcast,
+                event: TimeScaleChanged(*time_scale),
+            });
+        } else {
+            for event in &mut server_events {
+                if let ServerEvent::ClientConnected { client_id } = *event {
+                    scale_events.send(ToClients {
+                        mode: SendMode::Direct(client_id),
+                        event: TimeScaleChanged(*time_scale),
+                    });
+                }
+            }
+        }
+    }
+
+    fn receiving_system(
+        mut scale_events: EventReader<TimeScaleChanged>,
+        mut time_scale: ResMut<ServerTimeScale>,
+    ) {
+        if let Some(TimeScaleChanged(received)) = scale_events.iter().last() {
+            debug!("received time scale {received:?}");
+            *time_scale = *received;
+        }
+    }
+
+    fn reset_system(mut time_scale: ResMut<ServerTimeScale>) {
+        *time_scale = Default::default();
+    }
+}
+
+/// Pause and speed of the simulation controlled by server.
+///
+/// Modify it on server to pause or scale gameplay on all peers, for example when the host opens a menu
+/// in a co-op game. Clients receive the current value on connection and every change after it.
+/// Reset to default on client disconnect.
+///
+/// [`Time`] is not modified because it also drives the transport, so scale gameplay deltas with
+/// [`Self::delta`] and skip systems with the [`simulation_running`] condition instead.
+/// Transform extrapolation and correction smoothing take it into account automatically.
+/// [`ServerTick`](crate::server::ServerTick) keeps advancing during pause,
+/// so tick-based timers should be extended by server on resume.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Resource, Serialize)]
+pub struct ServerTimeScale {
+    /// Stops the simulation when `true`.
+    pub paused: bool,
+    /// Multiplier of the simulation speed.
+    speed: f32,
+}
+
+impl ServerTimeScale {
+    /// Returns the multiplier of the simulation speed set with [`Self::set_speed`].
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets the multiplier of the simulation speed.
+    ///
+    /// Negative and NaN values are clamped to zero.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// Returns the multiplier of the simulation speed, which is zero during pause.
+    pub fn relative_speed(&self) -> f32 {
+        if self.paused {
+            0.0
+        } else {
+            // Received values are not checked by the setter.
+            self.speed.max(0.0)
+        }
+    }
+
+    /// Returns [`Time::delta`] scaled by [`Self::relative_speed`].
+    ///
+    /// Saturates at [`Duration::MAX`].
+    pub fn delta(&self, time: &Time) -> Duration {
+        Duration::try_from_secs_f64(time.delta().as_secs_f64() * self.relative_speed() as f64)
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Returns [`Time::delta_seconds`] scaled by [`Self::relative_speed`].
+    pub fn delta_seconds(&self, time: &Time) -> f32 {
+        time.delta_seconds() * self.relative_speed()
+    }
+}
+
+impl Default for ServerTimeScale {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Condition that returns `true` if the simulation is not paused by [`ServerTimeScale`].
+pub fn simulation_running() -> impl FnMut(Res<ServerTimeScale>) -> bool + Clone {
+    |time_scale| !time_scale.paused
+}
+
+#[derive(Debug, Deserialize, Event, Serialize)]
+struct TimeScaleChanged(ServerTimeScale);
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::Instant;
+
+    use super::*;
+
+    #[test]
+    fn speed_clamping() {
+        let mut time = Time::default();
+        let now = Instant::now();
+        time.update_with_instant(now);
+        time.update_with_instant(now + Duration::from_secs(1));
+
+        let mut time_scale = ServerTimeScale::default();
+        time_scale.set_speed(-1.0);
+        assert_eq!(time_scale.speed(), 0.0);
+        assert_eq!(time_scale.delta(&time), Duration::ZERO);
+
+        time_scale.set_speed(f32::NAN);
+        assert_eq!(time_scale.speed(), 0.0);
+        assert_eq!(time_scale.delta(&time), Duration::ZERO);
+
+        time_scale.set_speed(f32::INFINITY);
+        assert_eq!(time_scale.delta(&time), Duration::MAX);
+
+        time_scale.set_speed(2.0);
+        assert_eq!(time_scale.delta(&time), Duration::from_secs(2));
+    }
+}
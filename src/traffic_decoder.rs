@@ -0,0 +1,244 @@
+use std::{
+    any,
+    fmt::{Debug, Write},
+};
+
+use bevy::{
+    ecs::event::Event,
+    prelude::*,
+    reflect::TypeRegistryInternal,
+    utils::{get_short_name, HashMap},
+};
+use serde::de::{DeserializeOwned, DeserializeSeed};
+
+use crate::{
+    client::deserialize_world_diff,
+    network_event::{shared_event::read_tag, BuildEventDeserializer},
+    replication_core::{
+        ENTITY_MAPPING_CHANNEL_ID, RELIABLE_REPLICATION_CHANNEL_ID, REPLICATION_CHANNEL_ID,
+    },
+    server::{
+        traffic_log::{ack_summary, event_summary},
+        CapturedMessage, TrafficDirection, TrafficRecord,
+    },
+    wire_format::{deserialize_message, deserialize_message_seed},
+    world_diff::ComponentDiff,
+};
+
+/// Decodes messages captured by [`TrafficLog`](crate::server::TrafficLog) into human-readable descriptions.
+///
+/// World diffs are described with values of each changed component per entity, events with their
+/// type names and values. Decoding requires the same components and events registered as in the app
+/// that captured the traffic, so the decoder should be created from its world.
+/// Messages that can't be decoded are described with the error.
+///
+/// ```no_run
+/// # use std::fs::File;
+/// # use bevy::prelude::*;
+/// # use bevy_replicon::prelude::*;
+/// # let mut app = App::new();
+/// # app.add_plugins(ReplicationPlugins);
+/// let decoder = TrafficDecoder::new(&app.world);
+/// let messages = CapturedMessage::read_all(File::open("traffic.capture")?)?;
+/// for message in &messages {
+///     println!("{}", decoder.decode(message));
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct TrafficDecoder {
+    registry: AppTypeRegistry,
+    decoders: EventDecoders,
+}
+
+impl TrafficDecoder {
+    /// Creates a decoder for messages of the app with `world`.
+    pub fn new(world: &World) -> Self {
+        Self {
+            registry: world.resource::<AppTypeRegistry>().clone(),
+            decoders: world
+                .get_resource::<EventDecoders>()
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns a record with the description of the message as summary.
+    pub fn decode(&self, captured: &CapturedMessage) -> TrafficRecord {
+        let registry = self.registry.read();
+        let message = &captured.message;
+        let summary = match (captured.direction, captured.channel_id) {
+            (TrafficDirection::Sent, REPLICATION_CHANNEL_ID | RELIABLE_REPLICATION_CHANNEL_ID) => {
+                describe_diff(&registry, message)
+                    .unwrap_or_else(|e| format!("invalid world diff: {e}"))
+            }
+            (TrafficDirection::Received, REPLICATION_CHANNEL_ID) => ack_summary(message),
+            (TrafficDirection::Received, ENTITY_MAPPING_CHANNEL_ID) => describe_mappings(message)
+                .unwrap_or_else(|e| format!("invalid entity mappings: {e}")),
+            (direction, channel_id) => match self.decoders.get(direction, channel_id, message) {
+                Some(decoder) => decoder.describe(&registry, message),
+                None => format!("message on unknown channel {channel_id}"),
+            },
+        };
+
+        TrafficRecord {
+            direction: captured.direction,
+            client_id: captured.client_id,
+            channel_id: captured.channel_id,
+            bytes: message.len(),
+            summary,
+        }
+    }
+}
+
+/// Describes each changed entity with component values, despawns and hidden entities.
+fn describe_diff(registry: &TypeRegistryInternal, message: &[u8]) -> bincode::Result<String> {
+    let (server_tick, world_diff) = deserialize_world_diff(registry, message)?;
+    if world_diff.is_empty() {
+        return Ok(format!(
+            "keepalive for server tick {} and change tick {}",
+            server_tick.get(),
+            world_diff.tick.get()
+        ));
+    }
+
+    let mut description = format!(
+        "world diff for server tick {} and change tick {}",
+        server_tick.get(),
+        world_diff.tick.get()
+    );
+    for archetype_diff in &world_diff.archetypes {
+        for entity_diff in &archetype_diff.entities {
+            write!(description, "\n  entity {:?}:", entity_diff.entity)
+                .expect("string should be writable");
+            let mut values = entity_diff.components.iter();
+            for component_diff in &archetype_diff.components {
+                match component_diff {
+                    ComponentDiff::Changed(_) => {
+                        let value = values
+                            .next()
+                            .expect("each changed component should have a value");
+                        write!(description, " {:?}", &**value)
+                    }
+                    ComponentDiff::Removed(registration) => {
+                        write!(description, " -{}", registration.short_name())
+                    }
+                }
+                .expect("string should be writable");
+            }
+        }
+    }
+    for (entity, reason) in &world_diff.despawns {
+        write!(description, "\n  despawn {entity:?} ({reason:?})")
+            .expect("string should be writable");
+    }
+    for entity in &world_diff.hidden {
+        write!(description, "\n  hide {entity:?}").expect("string should be writable");
+    }
+
+    Ok(description)
+}
+
+/// Lists pairs of server and client entities.
+fn describe_mappings(message: &[u8]) -> bincode::Result<String> {
+    let mappings: Vec<(Entity, Entity)> = bincode::deserialize(message)?;
+    let mappings: Vec<_> = mappings
+        .iter()
+        .map(|(server_entity, client_entity)| format!("{server_entity:?} -> {client_entity:?}"))
+        .collect();
+
+    Ok(format!("entity mappings [{}]", mappings.join(", ")))
+}
+
+/// Decoders of registered events and server configs by their direction, channel and tag for shared events.
+///
+/// Filled on event registration and used by [`TrafficDecoder`].
+#[derive(Clone, Default, Resource)]
+pub(crate) struct EventDecoders(HashMap<(TrafficDirection, u8, Option<u16>), EventDecoder>);
+
+impl EventDecoders {
+    /// Registers `T` for the channel, replacing the previous registration.
+    ///
+    /// Messages without `decode` are described only by the event name.
+    pub(crate) fn insert<T>(
+        &mut self,
+        direction: TrafficDirection,
+        channel_id: u8,
+        tag: Option<u16>,
+        decode: Option<DecodeFn>,
+    ) {
+        let decoder = EventDecoder {
+            name: get_short_name(any::type_name::<T>()),
+            decode,
+        };
+        self.0.insert((direction, channel_id, tag), decoder);
+    }
+
+    fn get(
+        &self,
+        direction: TrafficDirection,
+        channel_id: u8,
+        message: &[u8],
+    ) -> Option<&EventDecoder> {
+        self.0.get(&(direction, channel_id, None)).or_else(|| {
+            let tag = read_tag(message).ok()?;
+            self.0.get(&(direction, channel_id, Some(tag)))
+        })
+    }
+}
+
+#[derive(Clone)]
+struct EventDecoder {
+    name: String,
+    decode: Option<DecodeFn>,
+}
+
+impl EventDecoder {
+    fn describe(&self, registry: &TypeRegistryInternal, message: &[u8]) -> String {
+        let Some(decode) = self.decode else {
+            return format!("{} of {} bytes", self.name, message.len());
+        };
+
+        decode(registry, message).unwrap_or_else(|e| format!("invalid {}: {e}", self.name))
+    }
+}
+
+/// Deserializes a message and returns its description.
+pub(crate) type DecodeFn = fn(&TypeRegistryInternal, &[u8]) -> bincode::Result<String>;
+
+/// Decodes events and configs registered with [`serde`].
+pub(crate) fn decode_event<T: DeserializeOwned + Debug>(
+    _registry: &TypeRegistryInternal,
+    message: &[u8],
+) -> bincode::Result<String> {
+    deserialize_message::<T>(message).map(|event| event_summary(&event))
+}
+
+/// Decodes events stamped with the client tick.
+pub(crate) fn decode_ticked_event<T: DeserializeOwned + Debug>(
+    _registry: &TypeRegistryInternal,
+    message: &[u8],
+) -> bincode::Result<String> {
+    deserialize_message::<(u32, T)>(message)
+        .map(|(tick, event)| format!("{} on tick {tick}", event_summary(&event)))
+}
+
+/// Decodes events prefixed with their tag.
+pub(crate) fn decode_shared_event<T: DeserializeOwned + Debug>(
+    _registry: &TypeRegistryInternal,
+    message: &[u8],
+) -> bincode::Result<String> {
+    deserialize_message::<(u16, T)>(message).map(|(_, event)| event_summary(&event))
+}
+
+/// Decodes events registered with a deserializer that uses [`TypeRegistryInternal`].
+pub(crate) fn decode_reflect_event<T, D>(
+    registry: &TypeRegistryInternal,
+    message: &[u8],
+) -> bincode::Result<String>
+where
+    T: Event + Debug,
+    D: BuildEventDeserializer,
+    for<'a, 'de> D::EventDeserializer<'a>: DeserializeSeed<'de, Value = T>,
+{
+    deserialize_message_seed(message, D::new(registry)).map(|event| event_summary(&event))
+}
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{
+    transport::{NetcodeClientTransport, NetcodeServerTransport},
+    RenetClient, RenetServer,
+};
+
+/// Emits events about transport switches started with [`TransportSwitchWorldExt::start_transport_switch`].
+pub struct TransportSwitchPlugin;
+
+impl Plugin for TransportSwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TransportSwitchStarted>()
+            .add_event::<TransportSwitched>()
+            .add_systems(
+                Last,
+                Self::finishing_system.run_if(resource_exists::<TransportSwitch>()),
+            );
+    }
+}
+
+impl TransportSwitchPlugin {
+    fn finishing_system(
+        mut commands: Commands,
+        mut switched_events: EventWriter<TransportSwitched>,
+        switch: Res<TransportSwitch>,
+        client: Option<Res<RenetClient>>,
+        server: Option<Res<RenetServer>>,
+    ) {
+        let recreated = if switch.client {
+            client.is_some()
+        } else {
+            server.is_some()
+        };
+        if recreated {
+            commands.remove_resource::<TransportSwitch>();
+            switched_events.send(TransportSwitched);
+        }
+    }
+}
+
+/// An extension trait for [`World`] to replace Renet resources at runtime.
+pub trait TransportSwitchWorldExt {
+    /// Disconnects and removes Renet client or server resources with netcode transport
+    /// while keeping the replication state.
+    ///
+    /// Emits [`TransportSwitchStarted`] and inserts [`TransportSwitch`] until Renet resources
+    /// of the same kind are inserted again, for example with a different authentication.
+    /// Then [`TransportSwitched`] is emitted.
+    ///
+    /// During the switch client keeps its [`NetworkEntityMap`](crate::client::NetworkEntityMap),
+    /// so entities received after reconnection are mapped to the already replicated ones,
+    /// and [`has_authority`](crate::server::has_authority) continues to return `false`.
+    /// Server keeps its [`ServerTick`](crate::server::ServerTick) and resends the whole
+    /// world to reconnected clients. But entities that were despawned on server while the client
+    /// was disconnected will remain on client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no Renet resources or a switch is already in progress.
+    fn start_transport_switch(&mut self);
+}
+
+impl TransportSwitchWorldExt for World {
+    fn start_transport_switch(&mut self) {
+        assert!(
+            !self.contains_resource::<TransportSwitch>(),
+            "only one transport switch should be in progress"
+        );
+
+        let client = if let Some(mut transport) = self.remove_resource::<NetcodeClientTransport>() {
+            transport.disconnect();
+            self.remove_resource::<RenetClient>();
+            true
+        } else if let Some(mut transport) = self.remove_resource::<NetcodeServerTransport>() {
+            let mut server = self
+                .remove_resource::<RenetServer>()
+                .expect("server transport should be created with server");
+            transport.disconnect_all(&mut server);
+            false
+        } else {
+            panic!("client or server transport should exist to switch it");
+        };
+
+        self.insert_resource(TransportSwitch { client });
+        self.send_event(TransportSwitchStarted);
+    }
+}
+
+/// Exists while Renet resources are being replaced.
+///
+/// Inserted by [`TransportSwitchWorldExt::start_transport_switch`].
+#[derive(Debug, Resource)]
+pub struct TransportSwitch {
+    client: bool,
+}
+
+impl TransportSwitch {
+    /// Returns `true` if client resources are being replaced and `false` for server.
+    pub fn is_client(&self) -> bool {
+        self.client
+    }
+}
+
+/// An event that emitted when Renet resources are removed by [`TransportSwitchWorldExt::start_transport_switch`].
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct TransportSwitchStarted;
+
+/// An event that emitted when Renet resources are inserted again after [`TransportSwitchStarted`].
+#[derive(Clone, Copy, Debug, Default, Event)]
+pub struct TransportSwitched;
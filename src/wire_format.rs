@@ -0,0 +1,118 @@
+use bincode::{de::read::SliceReader, DefaultOptions, ErrorKind, Options};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed},
+    Serialize,
+};
+
+/// Version of the format of world diff and network event messages.
+///
+/// Every message starts with this byte, followed by the payload encoded with
+/// [`bincode::serialize`]: fixed-size little-endian integers, `u64` lengths for sequences and strings
+/// and `u32` indices for enum variants.
+///
+/// World diff payload is a [`ServerTick`](crate::server::ServerTick) as `u32`, followed by
+/// [`WorldDiff`](crate::world_diff::WorldDiff) serialized with
+/// [`WorldDiffSerializer`](crate::world_diff::WorldDiffSerializer):
+///
+/// - Change tick as `u32`.
+/// - Sequence of archetypes. Each archetype is a sequence of component diffs, where each diff is
+///   an enum with `Changed` (0) or `Removed` (1) variant and the component type name,
+///   followed by a sequence of entities. Each entity is its bits as `u64`, followed by a value
+///   for each changed component in reflection format without a length prefix.
+/// - Sequence of despawns, where each despawn is an entity and a
+///   [`DespawnReason`](crate::world_diff::DespawnReason) variant.
+/// - Sequence of hidden entities.
+///
+/// Diffs without changes are sent as keepalives with only the change tick after the server tick.
+///
+/// Network event payload is the event serialized with [`serde`] or with the event serializer for
+/// reflect events. Payload of shared events is prefixed with their tag as `u16`.
+///
+/// The version is incremented on any change of the layout described above, patch releases of the crate
+/// never change it. Messages with a different version are rejected instead of being misinterpreted,
+/// so replays and external tools should store the version together with recorded messages.
+/// Layouts of component values and events are defined by the user and should be versioned separately,
+/// see [`ProtocolVersion`](crate::protocol_version::ProtocolVersion).
+pub const WIRE_FORMAT_VERSION: u8 = 3;
+
+/// Serializes `value` into a message prefixed with [`WIRE_FORMAT_VERSION`].
+pub fn serialize_message<T: Serialize + ?Sized>(value: &T) -> bincode::Result<Vec<u8>> {
+    let mut message = vec![WIRE_FORMAT_VERSION];
+    bincode::serialize_into(&mut message, value)?;
+    Ok(message)
+}
+
+/// Returns the size of the message that [`serialize_message`] will produce.
+pub fn serialized_message_size<T: Serialize + ?Sized>(value: &T) -> bincode::Result<u64> {
+    let size = bincode::serialized_size(value)?;
+    Ok(size + 1)
+}
+
+/// Returns the payload of a message after checking its [`WIRE_FORMAT_VERSION`].
+pub fn message_payload(message: &[u8]) -> bincode::Result<&[u8]> {
+    let Some((&version, payload)) = message.split_first() else {
+        return Err(Box::new(ErrorKind::Custom(
+            "message should contain wire format version".into(),
+        )));
+    };
+    if version != WIRE_FORMAT_VERSION {
+        return Err(Box::new(ErrorKind::Custom(format!(
+            "message wire format version {version} should be {WIRE_FORMAT_VERSION}"
+        ))));
+    }
+
+    Ok(payload)
+}
+
+/// Deserializes a message produced by [`serialize_message`].
+pub fn deserialize_message<T: DeserializeOwned>(message: &[u8]) -> bincode::Result<T> {
+    bincode::deserialize(message_payload(message)?)
+}
+
+/// Like [`deserialize_message`], but uses `seed` for deserialization.
+pub fn deserialize_message_seed<'de, S: DeserializeSeed<'de>>(
+    message: &'de [u8],
+    seed: S,
+) -> bincode::Result<S::Value> {
+    let mut deserializer = message_deserializer(message)?;
+    seed.deserialize(&mut deserializer)
+}
+
+/// Returns a deserializer for the payload of a message produced by [`serialize_message`].
+///
+/// Useful to deserialize multiple values from a single message.
+pub(crate) fn message_deserializer(
+    message: &[u8],
+) -> bincode::Result<bincode::Deserializer<SliceReader<'_>, impl Options>> {
+    // Set options to match `bincode::serialize`.
+    // https://docs.rs/bincode/latest/bincode/config/index.html#options-struct-vs-bincode-functions
+    let options = DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes();
+    Ok(bincode::Deserializer::from_slice(
+        message_payload(message)?,
+        options,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_check() {
+        let message = serialize_message(&42u32).unwrap();
+        assert_eq!(message[0], WIRE_FORMAT_VERSION);
+        assert_eq!(
+            serialized_message_size(&42u32).unwrap(),
+            message.len() as u64
+        );
+
+        assert_eq!(deserialize_message::<u32>(&message).unwrap(), 42);
+
+        let mut message = message;
+        message[0] = WIRE_FORMAT_VERSION.wrapping_add(1);
+        assert!(message_payload(&message).is_err());
+        assert!(message_payload(&[]).is_err());
+    }
+}
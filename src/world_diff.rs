@@ -1,43 +1,167 @@
 use std::{
     any,
     fmt::{self, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
 };
 
 use bevy::{
-    ecs::component::Tick,
+    ecs::{component::Tick, reflect::ReflectMapEntities},
     prelude::*,
     reflect::{
-        serde::{ReflectSerializer, UntypedReflectDeserializer},
-        TypeRegistryInternal,
+        serde::{TypedReflectDeserializer, TypedReflectSerializer},
+        TypeRegistration, TypeRegistryInternal,
     },
-    utils::HashMap,
 };
 use derive_more::Constructor;
 use serde::{
-    de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor},
-    ser::{SerializeMap, SerializeSeq, SerializeStruct},
+    de::{self, DeserializeSeed, EnumAccess, SeqAccess, VariantAccess, Visitor},
+    ser::{SerializeSeq, SerializeStruct, SerializeTuple},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 use strum::{EnumDiscriminants, EnumVariantNames, IntoStaticStr, VariantNames};
 
+use crate::client::{
+    despawn_replicated, DespawnBudget, EntityMappingPolicy, NetworkEntityMap, ReplicatedChange,
+    ReplicationHidden, ReplicationNotifiers,
+};
+
 /// Changed world data and current tick from server.
 ///
-/// Sent from server to clients.
-pub(super) struct WorldDiff {
-    pub(super) tick: Tick,
-    pub(super) entities: HashMap<Entity, Vec<ComponentDiff>>,
-    pub(super) despawns: Vec<Entity>,
+/// Sent from server to clients using [`WorldDiffSerializer`] and [`WorldDiffDeserializer`].
+/// Can also be used to build custom pipelines on top of the replication, like saves, replays or relays.
+///
+/// Type registrations and component values are borrowed with lifetime `'a`. It's usually the lifetime
+/// of [`TypeRegistryInternal`] and the world from which values were taken.
+pub struct WorldDiff<'a> {
+    /// Tick on which the diff was created.
+    pub tick: Tick,
+    /// Changed entities grouped by their sets of component changes.
+    pub archetypes: Vec<ArchetypeDiff<'a>>,
+    /// Despawned entities with reasons of their despawns.
+    pub despawns: Vec<(Entity, DespawnReason)>,
+    /// Entities that are no longer visible, but should be kept on client.
+    pub hidden: Vec<Entity>,
 }
 
-impl WorldDiff {
+impl<'a> WorldDiff<'a> {
     /// Creates a new [`WorldDiff`] with a tick and empty entities.
-    pub(super) fn new(tick: Tick) -> Self {
+    pub fn new(tick: Tick) -> Self {
         Self {
             tick,
-            entities: Default::default(),
+            archetypes: Default::default(),
             despawns: Default::default(),
+            hidden: Default::default(),
         }
     }
+
+    /// Returns the total number of changed entities in all groups.
+    pub fn entities_count(&self) -> usize {
+        self.archetypes
+            .iter()
+            .map(|archetype_diff| archetype_diff.entities.len())
+            .sum()
+    }
+
+    /// Returns `true` if the diff contains no changes, despawns and hidden entities.
+    pub fn is_empty(&self) -> bool {
+        self.despawns.is_empty()
+            && self.hidden.is_empty()
+            && self
+                .archetypes
+                .iter()
+                .all(|archetype_diff| archetype_diff.entities.is_empty())
+    }
+
+    /// Applies the diff to the world.
+    ///
+    /// Entities from the diff are treated as server entities and mapped using `entity_map`.
+    /// Missing entities will be spawned with [`Replication`](crate::replication_core::Replication) and inserted into the map.
+    /// All missing entities are spawned before any components are applied, so components that reference
+    /// other entities from the same diff, like [`ParentSync`](crate::parent_sync::ParentSync), never point to
+    /// entities that don't exist yet.
+    /// Components that reference unknown entities are handled according to [`EntityMappingPolicy`].
+    /// Despawned entities will be removed from the map and despawned according to [`DespawnPolicy`](crate::client::DespawnPolicy),
+    /// [`ReplicationDespawned`](crate::client::ReplicationDespawned) will be emitted for each of them if the event is registered.
+    /// If [`DespawnBudget`] exists, despawns are queued into it instead.
+    /// Hidden entities will be marked with [`ReplicationHidden`] until they receive changes again.
+    ///
+    /// All components should be registered with [`ReflectComponent`].
+    pub fn apply(self, world: &mut World, entity_map: &mut NetworkEntityMap) {
+        // Map entities non-lazily in order to correctly map components that reference server entities.
+        let client_entities: Vec<Vec<_>> = self
+            .archetypes
+            .iter()
+            .map(|archetype_diff| {
+                archetype_diff
+                    .entities
+                    .iter()
+                    .map(|entity_diff| entity_map.get_by_server_or_spawn(world, entity_diff.entity))
+                    .collect()
+            })
+            .collect();
+
+        let mapping_policy = world
+            .get_resource::<EntityMappingPolicy>()
+            .copied()
+            .unwrap_or_default();
+        for (archetype_diff, client_entities) in self.archetypes.into_iter().zip(client_entities) {
+            for &client_entity in &client_entities {
+                let mut entity = world.entity_mut(client_entity);
+                if entity.contains::<ReplicationHidden>() {
+                    entity.remove::<ReplicationHidden>();
+                }
+            }
+            archetype_diff.apply(world, entity_map, &client_entities, mapping_policy);
+        }
+        entity_map.apply_deferred(world);
+
+        for server_entity in self.hidden {
+            if let Some(client_entity) = entity_map.to_client().get(server_entity) {
+                world.entity_mut(client_entity).insert(ReplicationHidden);
+            }
+        }
+
+        for (server_entity, reason) in self.despawns {
+            // The entity might have already been deleted with the last diff,
+            // but the server might not yet have received confirmation from the
+            // client and could include the deletion in the latest diff.
+            if let Some(client_entity) = entity_map.remove_by_server(server_entity) {
+                if let Some(mut budget) = world.get_resource_mut::<DespawnBudget>() {
+                    budget.push(client_entity, reason);
+                } else {
+                    despawn_replicated(world, client_entity, reason);
+                }
+            }
+        }
+    }
+}
+
+/// Why an entity was despawned on client.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum DespawnReason {
+    /// The entity was despawned on server.
+    Despawned,
+    /// The entity still exists on server, but is no longer visible for the client.
+    ///
+    /// See [`ClientVisibility`](crate::server::ClientVisibility) and [`LeavePolicy`](crate::server::LeavePolicy).
+    LeftVisibility,
+    /// The entity was despawned on server with an application-defined code.
+    ///
+    /// See [`DespawnWithReasonExt`](crate::server::DespawnWithReasonExt).
+    Custom(u16),
+    /// The entity still exists on server, but the client left its context or the entity moved
+    /// to a context the client isn't a member of.
+    ///
+    /// See [`ReplicationContexts`](crate::server::ReplicationContexts).
+    LeftContext,
+}
+
+impl DespawnReason {
+    /// Returns `true` if the entity was despawned on server, with or without a code.
+    pub fn is_despawned(self) -> bool {
+        matches!(self, DespawnReason::Despawned | DespawnReason::Custom(_))
+    }
 }
 
 /// Fields of [`WorldDiff`] for manual deserialization.
@@ -45,37 +169,257 @@ impl WorldDiff {
 #[strum(serialize_all = "snake_case")]
 enum WorldDiffField {
     Tick,
-    Entities,
+    Archetypes,
     Despawned,
+    Hidden,
+}
+
+/// Entities that share the same set of component changes.
+///
+/// The set is serialized only once for the whole group, followed by the data of each entity.
+/// This avoids repeating type names for every entity and resolving them on client.
+pub struct ArchetypeDiff<'a> {
+    /// Changes shared by all entities of the group.
+    ///
+    /// Removals are applied before changes.
+    pub components: Vec<ComponentDiff<'a>>,
+    /// Entities with values for each [`ComponentDiff::Changed`] from [`Self::components`].
+    pub entities: Vec<EntityDiff<'a>>,
+}
+
+impl<'a> ArchetypeDiff<'a> {
+    /// Creates a new [`ArchetypeDiff`] with a set of component changes and no entities.
+    pub fn new(components: Vec<ComponentDiff<'a>>) -> Self {
+        Self {
+            components,
+            entities: Default::default(),
+        }
+    }
+
+    /// Adds an entity with values of changed components to the group.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values doesn't match the number of [`ComponentDiff::Changed`] in the set.
+    pub fn push(&mut self, entity_diff: EntityDiff<'a>) {
+        assert_eq!(
+            entity_diff.components.len(),
+            self.changed_count(),
+            "entity should have a value for each changed component"
+        );
+        self.entities.push(entity_diff);
+    }
+
+    /// Returns the number of [`ComponentDiff::Changed`] in the set.
+    ///
+    /// Each [`EntityDiff`] of the group contains exactly this number of values.
+    pub fn changed_count(&self) -> usize {
+        changed_count(&self.components)
+    }
+
+    /// Applies changes of the group to already mapped entities.
+    ///
+    /// Reflection data resolved only once for the whole group.
+    fn apply(
+        self,
+        world: &mut World,
+        entity_map: &mut NetworkEntityMap,
+        client_entities: &[Entity],
+        mapping_policy: EntityMappingPolicy,
+    ) {
+        let components: Vec<_> = self
+            .components
+            .iter()
+            .map(|component_diff| {
+                let registration = component_diff.registration();
+                let reflect_component =
+                    registration.data::<ReflectComponent>().unwrap_or_else(|| {
+                        panic!(
+                            "{} should have reflect(Component)",
+                            registration.type_name()
+                        )
+                    });
+                let reflect_map_entities = registration.data::<ReflectMapEntities>();
+                let notifier = ReplicationNotifiers::get(world, registration.type_id());
+                (
+                    component_diff,
+                    reflect_component,
+                    reflect_map_entities,
+                    notifier,
+                )
+            })
+            .collect();
+
+        // Used to inspect referenced entities before applying.
+        let scratch_entity = (mapping_policy == EntityMappingPolicy::Defer
+            && components
+                .iter()
+                .any(|(component_diff, _, reflect_map_entities, _)| {
+                    matches!(component_diff, ComponentDiff::Changed(_))
+                        && reflect_map_entities.is_some()
+                }))
+        .then(|| world.spawn_empty().id());
+
+        // Entities to map for each component from the group.
+        let mut applied_entities = vec![Vec::new(); components.len()];
+        for (entity_diff, &client_entity) in self.entities.into_iter().zip(client_entities) {
+            for (component_diff, reflect_component, _, notifier) in &components {
+                if let ComponentDiff::Removed(registration) = component_diff {
+                    entity_map.discard_deferred(client_entity, registration.type_id());
+                    let mut entity = world.entity_mut(client_entity);
+                    let contains = entity.contains_type_id(registration.type_id());
+                    reflect_component.remove(&mut entity);
+                    if let (true, Some(notifier)) = (contains, notifier) {
+                        notifier(world, client_entity, ReplicatedChange::Removed);
+                    }
+                }
+            }
+
+            let changed_components =
+                components
+                    .iter()
+                    .zip(&mut applied_entities)
+                    .filter(|((component_diff, ..), _)| {
+                        matches!(component_diff, ComponentDiff::Changed(_))
+                    });
+            for (
+                ((component_diff, reflect_component, reflect_map_entities, notifier), applied),
+                component,
+            ) in changed_components.zip(entity_diff.components)
+            {
+                let type_id = component_diff.registration().type_id();
+                entity_map.discard_deferred(client_entity, type_id);
+                if let (Some(scratch_entity), Some(reflect_map_entities)) =
+                    (scratch_entity, reflect_map_entities)
+                {
+                    let unmapped = entity_map.unmapped_entities(
+                        world,
+                        scratch_entity,
+                        reflect_component,
+                        reflect_map_entities,
+                        &*component,
+                    );
+                    if !unmapped.is_empty() {
+                        entity_map.defer(client_entity, type_id, component.clone_value(), unmapped);
+                        continue;
+                    }
+                }
+                let mut entity = world.entity_mut(client_entity);
+                let change = if entity.contains_type_id(type_id) {
+                    ReplicatedChange::Updated
+                } else {
+                    ReplicatedChange::Inserted
+                };
+                reflect_component.apply_or_insert(&mut entity, &*component);
+                if let Some(notifier) = notifier {
+                    notifier(world, client_entity, change);
+                }
+                applied.push(client_entity);
+            }
+        }
+
+        if let Some(scratch_entity) = scratch_entity {
+            world.despawn(scratch_entity);
+        }
+
+        for ((_, _, reflect_map_entities, _), applied) in components.iter().zip(applied_entities) {
+            if let Some(reflect_map_entities) = reflect_map_entities {
+                entity_map.map_entities(world, reflect_map_entities, &applied);
+            }
+        }
+    }
+}
+
+fn changed_count(components: &[ComponentDiff]) -> usize {
+    components
+        .iter()
+        .filter(|component_diff| matches!(component_diff, ComponentDiff::Changed(_)))
+        .count()
+}
+
+/// Fields of [`ArchetypeDiff`] for manual deserialization.
+#[derive(IntoStaticStr, EnumVariantNames)]
+#[strum(serialize_all = "snake_case")]
+enum ArchetypeDiffField {
+    Components,
+    Entities,
 }
 
-/// Type of component or resource change.
-#[derive(EnumDiscriminants)]
+/// Entity and values of its changed components.
+///
+/// Values are stored in the order of [`ComponentDiff::Changed`] from the [`ArchetypeDiff`].
+#[derive(Constructor)]
+pub struct EntityDiff<'a> {
+    pub entity: Entity,
+    pub components: Vec<ComponentValue<'a>>,
+}
+
+/// Value of a changed component.
+///
+/// On server it's borrowed directly from the ECS storage to avoid cloning,
+/// on client it's owned after deserialization.
+pub enum ComponentValue<'a> {
+    Borrowed(&'a dyn Reflect),
+    Owned(Box<dyn Reflect>),
+}
+
+impl Deref for ComponentValue<'_> {
+    type Target = dyn Reflect;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ComponentValue::Borrowed(component) => *component,
+            ComponentValue::Owned(component) => &**component,
+        }
+    }
+}
+
+/// Type of component change.
+///
+/// Compared and hashed by kind and type ID.
+#[derive(Clone, Copy, EnumDiscriminants)]
 #[strum_discriminants(
     name(ComponentDiffField),
-    derive(Deserialize, EnumVariantNames, IntoStaticStr),
+    derive(Deserialize, EnumVariantNames, Hash, IntoStaticStr),
     strum(serialize_all = "snake_case")
 )]
-pub(super) enum ComponentDiff {
-    /// Indicates that a component was added or changed, contains serialized [`Reflect`].
-    Changed(Box<dyn Reflect>),
-    /// Indicates that a component was removed, contains component name.
-    Removed(String),
+pub enum ComponentDiff<'a> {
+    /// Indicates that a component was added or changed, its value is stored in [`EntityDiff`].
+    Changed(&'a TypeRegistration),
+    /// Indicates that a component was removed.
+    Removed(&'a TypeRegistration),
 }
 
-impl ComponentDiff {
-    /// Returns changed component type name.
-    pub(super) fn type_name(&self) -> &str {
-        match self {
-            ComponentDiff::Changed(component) => component.type_name(),
-            ComponentDiff::Removed(type_name) => type_name,
+impl<'a> ComponentDiff<'a> {
+    /// Returns registration of the changed component.
+    pub fn registration(&self) -> &'a TypeRegistration {
+        match *self {
+            ComponentDiff::Changed(registration) => registration,
+            ComponentDiff::Removed(registration) => registration,
         }
     }
 }
 
+impl PartialEq for ComponentDiff<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        ComponentDiffField::from(self) == ComponentDiffField::from(other)
+            && self.registration().type_id() == other.registration().type_id()
+    }
+}
+
+impl Eq for ComponentDiff<'_> {}
+
+impl Hash for ComponentDiff<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ComponentDiffField::from(self).hash(state);
+        self.registration().type_id().hash(state);
+    }
+}
+
+/// Serializes [`WorldDiff`] using [`TypeRegistryInternal`].
 #[derive(Constructor)]
-pub(super) struct WorldDiffSerializer<'a> {
-    world_diff: &'a WorldDiff,
+pub struct WorldDiffSerializer<'a> {
+    world_diff: &'a WorldDiff<'a>,
     registry: &'a TypeRegistryInternal,
 }
 
@@ -87,81 +431,131 @@ impl Serialize for WorldDiffSerializer<'_> {
         )?;
         state.serialize_field(WorldDiffField::Tick.into(), &self.world_diff.tick.get())?;
         state.serialize_field(
-            WorldDiffField::Entities.into(),
-            &EntitiesSerializer::new(&self.world_diff.entities, self.registry),
+            WorldDiffField::Archetypes.into(),
+            &ArchetypesSerializer::new(&self.world_diff.archetypes, self.registry),
         )?;
         state.serialize_field(WorldDiffField::Despawned.into(), &self.world_diff.despawns)?;
+        state.serialize_field(WorldDiffField::Hidden.into(), &self.world_diff.hidden)?;
         state.end()
     }
 }
 
 #[derive(Constructor)]
-struct EntitiesSerializer<'a> {
-    entities: &'a HashMap<Entity, Vec<ComponentDiff>>,
+struct ArchetypesSerializer<'a> {
+    archetypes: &'a [ArchetypeDiff<'a>],
     registry: &'a TypeRegistryInternal,
 }
 
-impl Serialize for EntitiesSerializer<'_> {
+impl Serialize for ArchetypesSerializer<'_> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut map = serializer.serialize_map(Some(self.entities.len()))?;
-        for (entity, components) in self.entities {
-            map.serialize_entry(
-                entity,
-                &ComponentsSerializer::new(components, self.registry),
-            )?;
+        let mut seq = serializer.serialize_seq(Some(self.archetypes.len()))?;
+        for archetype_diff in self.archetypes {
+            seq.serialize_element(&ArchetypeDiffSerializer::new(archetype_diff, self.registry))?;
         }
-        map.end()
+        seq.end()
     }
 }
 
 #[derive(Constructor)]
-struct ComponentsSerializer<'a> {
-    components: &'a [ComponentDiff],
+struct ArchetypeDiffSerializer<'a> {
+    archetype_diff: &'a ArchetypeDiff<'a>,
     registry: &'a TypeRegistryInternal,
 }
 
+impl Serialize for ArchetypeDiffSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct(
+            any::type_name::<ArchetypeDiff>(),
+            ArchetypeDiffField::VARIANTS.len(),
+        )?;
+        state.serialize_field(
+            ArchetypeDiffField::Components.into(),
+            &ComponentsSerializer(&self.archetype_diff.components),
+        )?;
+        state.serialize_field(
+            ArchetypeDiffField::Entities.into(),
+            &EntitiesSerializer::new(
+                &self.archetype_diff.entities,
+                self.archetype_diff.changed_count(),
+                self.registry,
+            ),
+        )?;
+        state.end()
+    }
+}
+
+struct ComponentsSerializer<'a>(&'a [ComponentDiff<'a>]);
+
 impl Serialize for ComponentsSerializer<'_> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut seq = serializer.serialize_seq(Some(self.components.len()))?;
-        for component_diff in self.components {
-            seq.serialize_element(&ComponentDiffSerializer::new(component_diff, self.registry))?;
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for component_diff in self.0 {
+            seq.serialize_element(&ComponentDiffSerializer(component_diff))?;
         }
         seq.end()
     }
 }
 
+struct ComponentDiffSerializer<'a>(&'a ComponentDiff<'a>);
+
+impl Serialize for ComponentDiffSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let field = ComponentDiffField::from(self.0);
+        serializer.serialize_newtype_variant(
+            any::type_name::<ComponentDiff>(),
+            field as u32,
+            field.into(),
+            self.0.registration().type_name(),
+        )
+    }
+}
+
 #[derive(Constructor)]
-struct ComponentDiffSerializer<'a> {
-    component_diff: &'a ComponentDiff,
+struct EntitiesSerializer<'a> {
+    entities: &'a [EntityDiff<'a>],
+    changed_count: usize,
     registry: &'a TypeRegistryInternal,
 }
 
-impl Serialize for ComponentDiffSerializer<'_> {
+impl Serialize for EntitiesSerializer<'_> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        match self.component_diff {
-            ComponentDiff::Changed(component) => serializer.serialize_newtype_variant(
-                any::type_name::<ComponentDiff>(),
-                ComponentDiffField::Changed as u32,
-                ComponentDiffField::Changed.into(),
-                &ReflectSerializer::new(&**component, self.registry),
-            ),
-            ComponentDiff::Removed(type_name) => serializer.serialize_newtype_variant(
-                any::type_name::<ComponentDiff>(),
-                ComponentDiffField::Removed as u32,
-                ComponentDiffField::Removed.into(),
-                type_name,
-            ),
+        let mut seq = serializer.serialize_seq(Some(self.entities.len()))?;
+        for entity_diff in self.entities {
+            debug_assert_eq!(entity_diff.components.len(), self.changed_count);
+            seq.serialize_element(&EntityDiffSerializer::new(entity_diff, self.registry))?;
         }
+        seq.end()
     }
 }
 
 #[derive(Constructor)]
-pub(super) struct WorldDiffDeserializer<'a> {
+struct EntityDiffSerializer<'a> {
+    entity_diff: &'a EntityDiff<'a>,
     registry: &'a TypeRegistryInternal,
 }
 
-impl<'de> DeserializeSeed<'de> for WorldDiffDeserializer<'_> {
-    type Value = WorldDiff;
+impl Serialize for EntityDiffSerializer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Serialize as a tuple since the number of values is known from the group.
+        let mut tuple = serializer.serialize_tuple(self.entity_diff.components.len() + 1)?;
+        tuple.serialize_element(&self.entity_diff.entity)?;
+        for component in &self.entity_diff.components {
+            tuple.serialize_element(&TypedReflectSerializer::new(&**component, self.registry))?;
+        }
+        tuple.end()
+    }
+}
+
+/// Deserializes [`WorldDiff`] with owned component values.
+///
+/// All component types should be registered in [`TypeRegistryInternal`].
+#[derive(Constructor)]
+pub struct WorldDiffDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for WorldDiffDeserializer<'a> {
+    type Value = WorldDiff<'a>;
 
     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
         deserializer.deserialize_struct(
@@ -172,8 +566,8 @@ impl<'de> DeserializeSeed<'de> for WorldDiffDeserializer<'_> {
     }
 }
 
-impl<'de> Visitor<'de> for WorldDiffDeserializer<'_> {
-    type Value = WorldDiff;
+impl<'a, 'de> Visitor<'de> for WorldDiffDeserializer<'a> {
+    type Value = WorldDiff<'a>;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
         formatter.write_str(any::type_name::<Self::Value>())
@@ -183,48 +577,95 @@ impl<'de> Visitor<'de> for WorldDiffDeserializer<'_> {
         let tick = seq
             .next_element()?
             .ok_or_else(|| de::Error::invalid_length(WorldDiffField::Tick as usize, &self))?;
-        let entities = seq
-            .next_element_seed(EntitiesDeserializer::new(self.registry))?
-            .ok_or_else(|| de::Error::invalid_length(WorldDiffField::Entities as usize, &self))?;
+        let archetypes = seq
+            .next_element_seed(ArchetypesDeserializer::new(self.registry))?
+            .ok_or_else(|| de::Error::invalid_length(WorldDiffField::Archetypes as usize, &self))?;
         let despawns = seq
             .next_element()?
             .ok_or_else(|| de::Error::invalid_length(WorldDiffField::Despawned as usize, &self))?;
+        let hidden = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(WorldDiffField::Hidden as usize, &self))?;
         Ok(WorldDiff {
             tick: Tick::new(tick),
-            entities,
+            archetypes,
             despawns,
+            hidden,
         })
     }
 }
 
 #[derive(Constructor)]
-struct EntitiesDeserializer<'a> {
+struct ArchetypesDeserializer<'a> {
     registry: &'a TypeRegistryInternal,
 }
 
-impl<'de> DeserializeSeed<'de> for EntitiesDeserializer<'_> {
-    type Value = HashMap<Entity, Vec<ComponentDiff>>;
+impl<'a, 'de> DeserializeSeed<'de> for ArchetypesDeserializer<'a> {
+    type Value = Vec<ArchetypeDiff<'a>>;
 
     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
-        deserializer.deserialize_map(self)
+        deserializer.deserialize_seq(self)
     }
 }
 
-impl<'de> Visitor<'de> for EntitiesDeserializer<'_> {
-    type Value = HashMap<Entity, Vec<ComponentDiff>>;
+impl<'a, 'de> Visitor<'de> for ArchetypesDeserializer<'a> {
+    type Value = Vec<ArchetypeDiff<'a>>;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
         formatter.write_str(any::type_name::<Self::Value>())
     }
 
-    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-        let mut entities = HashMap::with_capacity(map.size_hint().unwrap_or_default());
-        while let Some(key) = map.next_key()? {
-            let value = map.next_value_seed(ComponentsDeserializer::new(self.registry))?;
-            entities.insert(key, value);
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut archetypes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(archetype_diff) =
+            seq.next_element_seed(ArchetypeDiffDeserializer::new(self.registry))?
+        {
+            archetypes.push(archetype_diff);
         }
 
-        Ok(entities)
+        Ok(archetypes)
+    }
+}
+
+#[derive(Constructor)]
+struct ArchetypeDiffDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ArchetypeDiffDeserializer<'a> {
+    type Value = ArchetypeDiff<'a>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct(
+            any::type_name::<Self::Value>(),
+            ArchetypeDiffField::VARIANTS,
+            self,
+        )
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ArchetypeDiffDeserializer<'a> {
+    type Value = ArchetypeDiff<'a>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(any::type_name::<Self::Value>())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let components = seq
+            .next_element_seed(ComponentsDeserializer::new(self.registry))?
+            .ok_or_else(|| {
+                de::Error::invalid_length(ArchetypeDiffField::Components as usize, &self)
+            })?;
+        let entities = seq
+            .next_element_seed(EntitiesDeserializer::new(&components, self.registry))?
+            .ok_or_else(|| {
+                de::Error::invalid_length(ArchetypeDiffField::Entities as usize, &self)
+            })?;
+        Ok(ArchetypeDiff {
+            components,
+            entities,
+        })
     }
 }
 
@@ -233,16 +674,16 @@ struct ComponentsDeserializer<'a> {
     registry: &'a TypeRegistryInternal,
 }
 
-impl<'de> DeserializeSeed<'de> for ComponentsDeserializer<'_> {
-    type Value = Vec<ComponentDiff>;
+impl<'a, 'de> DeserializeSeed<'de> for ComponentsDeserializer<'a> {
+    type Value = Vec<ComponentDiff<'a>>;
 
     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
         deserializer.deserialize_seq(self)
     }
 }
 
-impl<'de> Visitor<'de> for ComponentsDeserializer<'_> {
-    type Value = Vec<ComponentDiff>;
+impl<'a, 'de> Visitor<'de> for ComponentsDeserializer<'a> {
+    type Value = Vec<ComponentDiff<'a>>;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
         formatter.write_str(any::type_name::<Self::Value>())
@@ -265,8 +706,8 @@ struct ComponentDiffDeserializer<'a> {
     registry: &'a TypeRegistryInternal,
 }
 
-impl<'de> DeserializeSeed<'de> for ComponentDiffDeserializer<'_> {
-    type Value = ComponentDiff;
+impl<'a, 'de> DeserializeSeed<'de> for ComponentDiffDeserializer<'a> {
+    type Value = ComponentDiff<'a>;
 
     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
         deserializer.deserialize_enum(
@@ -277,8 +718,8 @@ impl<'de> DeserializeSeed<'de> for ComponentDiffDeserializer<'_> {
     }
 }
 
-impl<'de> Visitor<'de> for ComponentDiffDeserializer<'_> {
-    type Value = ComponentDiff;
+impl<'a, 'de> Visitor<'de> for ComponentDiffDeserializer<'a> {
+    type Value = ComponentDiff<'a>;
 
     fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
         formatter.write_str(any::type_name::<Self::Value>())
@@ -286,83 +727,150 @@ impl<'de> Visitor<'de> for ComponentDiffDeserializer<'_> {
 
     fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
         let (field, variant) = data.variant::<ComponentDiffField>()?;
+        let type_name: String = variant.newtype_variant()?;
+        let registration = self
+            .registry
+            .get_with_name(&type_name)
+            .ok_or_else(|| de::Error::custom(format_args!("{type_name} should be registered")))?;
         let component_diff = match field {
-            ComponentDiffField::Changed => ComponentDiff::Changed(
-                variant.newtype_variant_seed(UntypedReflectDeserializer::new(self.registry))?,
-            ),
-            ComponentDiffField::Removed => ComponentDiff::Removed(variant.newtype_variant()?),
+            ComponentDiffField::Changed => ComponentDiff::Changed(registration),
+            ComponentDiffField::Removed => ComponentDiff::Removed(registration),
         };
 
         Ok(component_diff)
     }
 }
 
+#[derive(Constructor)]
+struct EntitiesDeserializer<'a, 'c> {
+    components: &'c [ComponentDiff<'a>],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for EntitiesDeserializer<'a, '_> {
+    type Value = Vec<EntityDiff<'a>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for EntitiesDeserializer<'a, '_> {
+    type Value = Vec<EntityDiff<'a>>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(any::type_name::<Self::Value>())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut entities = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(entity_diff) =
+            seq.next_element_seed(EntityDiffDeserializer::new(self.components, self.registry))?
+        {
+            entities.push(entity_diff);
+        }
+
+        Ok(entities)
+    }
+}
+
+#[derive(Constructor)]
+struct EntityDiffDeserializer<'a, 'c> {
+    components: &'c [ComponentDiff<'a>],
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for EntityDiffDeserializer<'a, '_> {
+    type Value = EntityDiff<'a>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_tuple(changed_count(self.components) + 1, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for EntityDiffDeserializer<'a, '_> {
+    type Value = EntityDiff<'a>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str(any::type_name::<Self::Value>())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let entity = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let mut components = Vec::with_capacity(changed_count(self.components));
+        for component_diff in self.components {
+            if let ComponentDiff::Changed(registration) = *component_diff {
+                let component = seq
+                    .next_element_seed(TypedReflectDeserializer::new(registration, self.registry))?
+                    .ok_or_else(|| de::Error::invalid_length(components.len() + 1, &self))?;
+                components.push(ComponentValue::Owned(component));
+            }
+        }
+
+        Ok(EntityDiff { entity, components })
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use bincode::{DefaultOptions, Options};
     use serde_test::Token;
 
     use super::*;
+    use crate::client::ReplicationDespawned;
 
-    const COMPONENT_NAME: &str = "My component";
-
-    #[derive(Component, Reflect, Default)]
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
     #[reflect(Component)]
-    struct DummyComponent;
-
-    #[test]
-    fn component_diff_removed_ser() {
-        let registry = TypeRegistryInternal::new();
-        let component_diff = ComponentDiff::Removed(COMPONENT_NAME.to_string());
-        let serializer = ComponentDiffSerializer::new(&component_diff, &registry);
-
-        serde_test::assert_ser_tokens(
-            &serializer,
-            &[
-                Token::NewtypeVariant {
-                    name: any::type_name::<ComponentDiff>(),
-                    variant: ComponentDiffField::Removed.into(),
-                },
-                Token::Str(COMPONENT_NAME),
-            ],
-        );
-    }
+    struct DummyComponent(u32);
 
     #[test]
-    fn component_diff_changed_ser() {
+    fn component_diff_ser() {
         let mut registry = TypeRegistryInternal::new();
         registry.register::<DummyComponent>();
-        let component_diff = ComponentDiff::Changed(DummyComponent.clone_value());
-        let serializer = ComponentDiffSerializer::new(&component_diff, &registry);
+        let registration = registry.get(any::TypeId::of::<DummyComponent>()).unwrap();
 
-        serde_test::assert_ser_tokens(
-            &serializer,
-            &[
-                Token::NewtypeVariant {
-                    name: any::type_name::<ComponentDiff>(),
-                    variant: ComponentDiffField::Changed.into(),
-                },
-                Token::Map { len: Some(1) },
-                Token::Str(any::type_name::<DummyComponent>()),
-                Token::Struct {
-                    name: "DummyComponent",
-                    len: 0,
-                },
-                Token::StructEnd,
-                Token::MapEnd,
-            ],
-        );
+        for (component_diff, field) in [
+            (
+                ComponentDiff::Changed(registration),
+                ComponentDiffField::Changed,
+            ),
+            (
+                ComponentDiff::Removed(registration),
+                ComponentDiffField::Removed,
+            ),
+        ] {
+            serde_test::assert_ser_tokens(
+                &ComponentDiffSerializer(&component_diff),
+                &[
+                    Token::NewtypeVariant {
+                        name: any::type_name::<ComponentDiff>(),
+                        variant: field.into(),
+                    },
+                    Token::Str(any::type_name::<DummyComponent>()),
+                ],
+            );
+        }
     }
 
     #[test]
     fn world_diff_ser() {
-        let registry = TypeRegistryInternal::default();
+        let mut registry = TypeRegistryInternal::new();
+        registry.register::<DummyComponent>();
+        let registration = registry.get(any::TypeId::of::<DummyComponent>()).unwrap();
+
         let world_diff = WorldDiff {
             tick: Tick::new(0),
-            entities: HashMap::from([(
-                Entity::PLACEHOLDER,
-                Vec::from([ComponentDiff::Removed(COMPONENT_NAME.to_string())]),
-            )]),
-            despawns: Vec::from([Entity::PLACEHOLDER]),
+            archetypes: Vec::from([ArchetypeDiff {
+                components: Vec::from([ComponentDiff::Changed(registration)]),
+                entities: Vec::from([EntityDiff {
+                    entity: Entity::PLACEHOLDER,
+                    components: Vec::from([ComponentValue::Borrowed(&DummyComponent(1))]),
+                }]),
+            }]),
+            despawns: Vec::from([(Entity::PLACEHOLDER, DespawnReason::Despawned)]),
+            hidden: Vec::from([Entity::PLACEHOLDER]),
         };
         let serializer = WorldDiffSerializer::new(&world_diff, &registry);
 
@@ -375,23 +883,163 @@ mod tests {
                 },
                 Token::Str(WorldDiffField::Tick.into()),
                 Token::U32(world_diff.tick.get()),
-                Token::Str(WorldDiffField::Entities.into()),
-                Token::Map { len: Some(1) },
-                Token::U64(Entity::PLACEHOLDER.to_bits()),
+                Token::Str(WorldDiffField::Archetypes.into()),
+                Token::Seq { len: Some(1) },
+                Token::Struct {
+                    name: any::type_name::<ArchetypeDiff>(),
+                    len: ArchetypeDiffField::VARIANTS.len(),
+                },
+                Token::Str(ArchetypeDiffField::Components.into()),
                 Token::Seq { len: Some(1) },
                 Token::NewtypeVariant {
                     name: any::type_name::<ComponentDiff>(),
-                    variant: ComponentDiffField::Removed.into(),
+                    variant: ComponentDiffField::Changed.into(),
                 },
-                Token::Str(COMPONENT_NAME),
+                Token::Str(any::type_name::<DummyComponent>()),
+                Token::SeqEnd,
+                Token::Str(ArchetypeDiffField::Entities.into()),
+                Token::Seq { len: Some(1) },
+                Token::Tuple { len: 2 },
+                Token::U64(Entity::PLACEHOLDER.to_bits()),
+                Token::TupleStruct {
+                    name: "DummyComponent",
+                    len: 1,
+                },
+                Token::U32(1),
+                Token::TupleStructEnd,
+                Token::TupleEnd,
+                Token::SeqEnd,
+                Token::StructEnd,
                 Token::SeqEnd,
-                Token::MapEnd,
                 Token::Str(WorldDiffField::Despawned.into()),
                 Token::Seq { len: Some(1) },
+                Token::Tuple { len: 2 },
+                Token::U64(Entity::PLACEHOLDER.to_bits()),
+                Token::UnitVariant {
+                    name: "DespawnReason",
+                    variant: "Despawned",
+                },
+                Token::TupleEnd,
+                Token::SeqEnd,
+                Token::Str(WorldDiffField::Hidden.into()),
+                Token::Seq { len: Some(1) },
                 Token::U64(Entity::PLACEHOLDER.to_bits()),
                 Token::SeqEnd,
                 Token::StructEnd,
             ],
         );
     }
+
+    #[test]
+    fn world_diff_bincode() {
+        let mut registry = TypeRegistryInternal::new();
+        registry.register::<DummyComponent>();
+        let registration = registry.get(any::TypeId::of::<DummyComponent>()).unwrap();
+
+        let world_diff = WorldDiff {
+            tick: Tick::new(1),
+            archetypes: Vec::from([ArchetypeDiff {
+                components: Vec::from([
+                    ComponentDiff::Removed(registration),
+                    ComponentDiff::Changed(registration),
+                ]),
+                entities: Vec::from([
+                    EntityDiff {
+                        entity: Entity::from_raw(0),
+                        components: Vec::from([ComponentValue::Borrowed(&DummyComponent(0))]),
+                    },
+                    EntityDiff {
+                        entity: Entity::from_raw(1),
+                        components: Vec::from([ComponentValue::Borrowed(&DummyComponent(1))]),
+                    },
+                ]),
+            }]),
+            despawns: Vec::from([(Entity::PLACEHOLDER, DespawnReason::Despawned)]),
+            hidden: Vec::from([Entity::PLACEHOLDER]),
+        };
+        let message =
+            bincode::serialize(&WorldDiffSerializer::new(&world_diff, &registry)).unwrap();
+
+        let options = DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes();
+        let mut deserializer = bincode::Deserializer::from_slice(&message, options);
+        let deserialized = WorldDiffDeserializer::new(&registry)
+            .deserialize(&mut deserializer)
+            .unwrap();
+
+        assert_eq!(deserialized.tick, world_diff.tick);
+        assert_eq!(deserialized.despawns, world_diff.despawns);
+        assert_eq!(deserialized.hidden, world_diff.hidden);
+        let [archetype_diff] = deserialized.archetypes.as_slice() else {
+            panic!("diff should contain a single archetype");
+        };
+        assert!(archetype_diff.components == world_diff.archetypes[0].components);
+        for (index, entity_diff) in archetype_diff.entities.iter().enumerate() {
+            assert_eq!(entity_diff.entity, Entity::from_raw(index as u32));
+            let [component] = entity_diff.components.as_slice() else {
+                panic!("entity should contain a single component");
+            };
+            let component = DummyComponent::from_reflect(&**component).unwrap();
+            assert_eq!(component, DummyComponent(index as u32));
+        }
+    }
+
+    #[test]
+    fn world_diff_apply() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world.init_resource::<Events<ReplicationDespawned>>();
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        registry.write().register::<DummyComponent>();
+        let registry = registry.read();
+        let registration = registry.get(any::TypeId::of::<DummyComponent>()).unwrap();
+
+        let server_entity = Entity::from_raw(0);
+        let mut entity_map = NetworkEntityMap::default();
+        let mut archetype_diff =
+            ArchetypeDiff::new(Vec::from([ComponentDiff::Changed(registration)]));
+        archetype_diff.push(EntityDiff::new(
+            server_entity,
+            Vec::from([ComponentValue::Borrowed(&DummyComponent(1))]),
+        ));
+        let mut world_diff = WorldDiff::new(Tick::new(1));
+        world_diff.archetypes.push(archetype_diff);
+        world_diff.apply(&mut world, &mut entity_map);
+
+        let client_entity = entity_map
+            .to_client()
+            .get(server_entity)
+            .expect("server entity should be mapped");
+        assert_eq!(
+            world.get::<DummyComponent>(client_entity),
+            Some(&DummyComponent(1))
+        );
+
+        let mut archetype_diff =
+            ArchetypeDiff::new(Vec::from([ComponentDiff::Removed(registration)]));
+        archetype_diff.push(EntityDiff::new(server_entity, Vec::new()));
+        let mut world_diff = WorldDiff::new(Tick::new(2));
+        world_diff.archetypes.push(archetype_diff);
+        world_diff.apply(&mut world, &mut entity_map);
+
+        assert!(!world.entity(client_entity).contains::<DummyComponent>());
+
+        let mut world_diff = WorldDiff::new(Tick::new(3));
+        world_diff
+            .despawns
+            .push((server_entity, DespawnReason::LeftVisibility));
+        world_diff.apply(&mut world, &mut entity_map);
+
+        assert!(world.get_entity(client_entity).is_none());
+        assert!(entity_map.to_client().is_empty());
+
+        let despawned_events = world.resource::<Events<ReplicationDespawned>>();
+        let mut reader = despawned_events.get_reader();
+        let [event] = reader.iter(despawned_events).collect::<Vec<_>>()[..] else {
+            panic!("despawn should emit a single event");
+        };
+        assert_eq!(event.entity, client_entity);
+        assert_eq!(event.reason, DespawnReason::LeftVisibility);
+    }
 }
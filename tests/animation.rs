@@ -0,0 +1,63 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+#[test]
+fn playback_insertion() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+            AnimationSyncPlugin,
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_tick = *server_app.world.resource::<ServerTick>();
+    let server_entity = server_app
+        .world
+        .spawn((AnimationState::start(1, server_tick), Replication))
+        .id();
+
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated");
+
+    let state = client_app
+        .world
+        .get::<AnimationState>(client_entity)
+        .unwrap();
+    assert_eq!(state.clip_id, 1);
+    assert!(
+        client_app
+            .world
+            .get::<AnimationPlayback>(client_entity)
+            .is_some(),
+        "playback should be inserted after measuring tick duration"
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<AnimationState>();
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app
+        .world
+        .get::<AnimationPlayback>(client_entity)
+        .is_none());
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_renet::renet::transport::NetcodeClientTransport;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn authority_over() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+    let owned_entity = server_app
+        .world
+        .spawn((Replication, Authority::Client(client_id)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_server_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be replicated");
+    let client_owned_entity = entity_map
+        .to_client()
+        .get(owned_entity)
+        .expect("owned entity should be replicated");
+
+    let mut server_state = SystemState::<LocalAuthority>::new(&mut server_app.world);
+    let server_authority = server_state.get(&server_app.world);
+    assert!(server_authority.has_authority_over(server_entity));
+    assert!(!server_authority.has_authority_over(owned_entity));
+
+    let mut client_state = SystemState::<LocalAuthority>::new(&mut client_app.world);
+    let client_authority = client_state.get(&client_app.world);
+    assert_eq!(client_authority.local_id(), Some(client_id));
+    assert!(!client_authority.has_authority_over(client_server_entity));
+    assert!(client_authority.has_authority_over(client_owned_entity));
+}
+
+#[test]
+fn client_authoritative() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Cursor>()
+        .client_authoritative_with(|_, _, received: &Cursor| received.0 >= 0.0);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Cursor(0.0), Authority::Client(client_id)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("owned entity should be replicated");
+
+    client_app.world.get_mut::<Cursor>(client_entity).unwrap().0 = 1.0;
+
+    client_app.update();
+    server_app.update();
+
+    let cursor = server_app.world.get::<Cursor>(server_entity).unwrap();
+    assert_eq!(cursor.0, 1.0, "change from the owner should be applied");
+
+    client_app.world.get_mut::<Cursor>(client_entity).unwrap().0 = -1.0;
+
+    client_app.update();
+    server_app.update();
+    client_app.update();
+
+    let cursor = server_app.world.get::<Cursor>(server_entity).unwrap();
+    assert_eq!(cursor.0, 1.0, "invalid change should be rejected");
+    let cursor = client_app.world.get::<Cursor>(client_entity).unwrap();
+    assert_eq!(
+        cursor.0, 1.0,
+        "rejected change should be corrected on the owner"
+    );
+
+    server_app.world.get_mut::<Cursor>(server_entity).unwrap().0 = 2.0;
+
+    server_app.update();
+
+    let cursor = server_app.world.get::<Cursor>(server_entity).unwrap();
+    assert_eq!(
+        cursor.0, 2.0,
+        "correction shouldn't be sent back to server as a change"
+    );
+}
+
+#[derive(Clone, Component, Debug, Default, Deserialize, Reflect, Serialize)]
+#[reflect(Component)]
+struct Cursor(f32);
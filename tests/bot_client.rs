@@ -0,0 +1,50 @@
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::{bot_client::BotClient, prelude::*, test_app::ServerTestAppExt};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn scripted_events() {
+    let mut server_app = App::new();
+    server_app
+        .add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .add_client_event::<BotEvent>(SendPolicy::reliable().ordered());
+
+    let mut bot = BotClient::new(|app| {
+        app.replicate::<Transform>()
+            .add_client_event::<BotEvent>(SendPolicy::reliable().ordered());
+    });
+    server_app.connect_client(&mut bot.app);
+    assert!(bot.is_connected());
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+    bot.send_event_after(1, BotEvent(1));
+    bot.send_event(BotEvent(0));
+    assert!(!bot.is_script_finished());
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        server_app.update();
+        bot.update();
+        let mut client_events = server_app
+            .world
+            .resource_mut::<Events<FromClient<BotEvent>>>();
+        received.extend(client_events.drain().map(|FromClient { event, .. }| event));
+    }
+
+    assert!(bot.is_script_finished());
+    assert_eq!(received, [BotEvent(0), BotEvent(1)]);
+    assert!(bot.entity_map().to_client().get(server_entity).is_some());
+
+    bot.disconnect();
+    assert!(!bot.is_connected());
+}
+
+#[derive(Debug, Deserialize, Event, PartialEq, Serialize)]
+struct BotEvent(u8);
@@ -1,8 +1,14 @@
 mod common;
 
+use std::time::Duration;
+
 use bevy::prelude::*;
-use bevy::{ecs::event::Events, time::TimePlugin};
-use bevy_replicon::prelude::*;
+use bevy::{
+    ecs::{event::Events, system::SystemState},
+    time::TimePlugin,
+};
+use bevy_replicon::{network_event::EventChannel, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use common::{DummyEvent, ReflectEvent, ReflectEventDeserializer, ReflectEventSerializer};
 
@@ -13,7 +19,7 @@ fn without_server_plugin() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins)
         .add_plugins(ReplicationPlugins.build().disable::<ServerPlugin>())
-        .add_client_event_with::<DummyEvent, _, _>(SendPolicy::Ordered, || {}, || {})
+        .add_client_event_with::<DummyEvent, _, _>(SendPolicy::reliable().ordered(), || {}, || {})
         .update();
 }
 
@@ -22,7 +28,7 @@ fn without_client_plugin() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins)
         .add_plugins(ReplicationPlugins.build().disable::<ClientPlugin>())
-        .add_client_event_with::<DummyEvent, _, _>(SendPolicy::Ordered, || {}, || {})
+        .add_client_event_with::<DummyEvent, _, _>(SendPolicy::reliable().ordered(), || {}, || {})
         .update();
 }
 
@@ -32,7 +38,7 @@ fn sending_receiving() {
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
         app.add_plugins((MinimalPlugins, ReplicationPlugins))
-            .add_client_event::<DummyEvent>(SendPolicy::Ordered);
+            .add_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -51,13 +57,116 @@ fn sending_receiving() {
     assert_eq!(client_events.len(), 1);
 }
 
+#[test]
+fn receive_stamping() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .send(DummyEvent(Entity::PLACEHOLDER));
+
+    client_app.update();
+    let server_tick = *server_app.world.resource::<ServerTick>();
+    server_app.update();
+
+    let channel_id = server_app.world.resource::<EventChannel<DummyEvent>>().id;
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<DummyEvent>>>();
+    let event = client_events
+        .iter_current_update_events()
+        .next()
+        .expect("server should receive event");
+    assert_eq!(event.received_tick, server_tick.get());
+    assert_eq!(event.channel_id, channel_id);
+}
+
+#[test]
+fn event_stats() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .send(DummyEvent(Entity::PLACEHOLDER));
+
+    client_app.update();
+    server_app.update();
+
+    let client_stats = *client_app
+        .world
+        .resource::<NetworkEventStats>()
+        .get::<DummyEvent>()
+        .expect("client should record sent event");
+    assert_eq!(client_stats.sent_messages, 1);
+    assert_eq!(client_stats.received_messages, 0);
+
+    let server_stats = *server_app
+        .world
+        .resource::<NetworkEventStats>()
+        .get::<DummyEvent>()
+        .expect("server should record received event");
+    assert_eq!(server_stats.received_messages, 1);
+    assert_eq!(server_stats.received_bytes, client_stats.sent_bytes);
+}
+
+#[test]
+fn ticked_sending_receiving() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((MinimalPlugins, ReplicationPlugins))
+            .add_ticked_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .send(DummyEvent(Entity::PLACEHOLDER));
+
+    client_app.update();
+    server_app.update();
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<DummyEvent>>>();
+    let event = client_events
+        .iter_current_update_events()
+        .next()
+        .expect("server should receive the event");
+    assert!(event.tick.is_some());
+}
+
 #[test]
 fn mapping_and_sending_receiving() {
     let mut server_app = App::new();
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
         app.add_plugins((MinimalPlugins, ReplicationPlugins))
-            .add_mapped_client_event::<DummyEvent>(SendPolicy::Ordered);
+            .add_mapped_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -86,6 +195,143 @@ fn mapping_and_sending_receiving() {
     assert_eq!(mapped_entities, [server_entity]);
 }
 
+#[test]
+fn receiving_and_mapping_replicated() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .add_mapped_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be replicated");
+    client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .send(DummyEvent(client_entity));
+
+    client_app.update();
+    server_app.update();
+
+    let mapped_entities: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<FromClient<DummyEvent>>>()
+        .drain()
+        .map(|event| event.event.0)
+        .collect();
+    assert_eq!(mapped_entities, [server_entity]);
+}
+
+#[test]
+fn receiving_unmapped() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((MinimalPlugins, ReplicationPlugins))
+            .add_mapped_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = Entity::from_raw(0);
+    client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .send(DummyEvent(client_entity));
+
+    client_app.update();
+    server_app.update();
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<DummyEvent>>>();
+    assert!(client_events.is_empty(), "unmapped event should be dropped");
+
+    let failures: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<EventMappingFailed>>()
+        .drain()
+        .map(|failure| (failure.entity, failure.delivered))
+        .collect();
+    assert_eq!(failures, [(client_entity, false)]);
+}
+
+#[test]
+fn entity_event_mapping() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((MinimalPlugins, ReplicationPlugins))
+            .add_client_entity_event::<DummyRequest>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn_empty().id();
+    let despawned_entity = server_app.world.spawn_empty().id();
+    server_app.world.despawn(despawned_entity);
+
+    let client_entity = Entity::from_raw(10);
+    let stale_entity = Entity::from_raw(11);
+    let mut entity_map = client_app.world.resource_mut::<NetworkEntityMap>();
+    entity_map.insert(server_entity, client_entity);
+    entity_map.insert(despawned_entity, stale_entity);
+
+    let mut events = client_app
+        .world
+        .resource_mut::<Events<ToEntity<DummyRequest>>>();
+    events.send(ToEntity {
+        entity: client_entity,
+        event: DummyRequest(0),
+    });
+    events.send(ToEntity {
+        entity: stale_entity,
+        event: DummyRequest(1),
+    });
+
+    client_app.update();
+    server_app.update();
+
+    let received: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<FromClient<ToEntity<DummyRequest>>>>()
+        .drain()
+        .map(|event| (event.event.entity, event.event.event.0))
+        .collect();
+    assert_eq!(
+        received,
+        [(server_entity, 0)],
+        "event for despawned entity should be dropped"
+    );
+
+    let failures: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<EventMappingFailed>>()
+        .drain()
+        .map(|failure| failure.entity)
+        .collect();
+    assert_eq!(failures, [stale_entity]);
+}
+
 #[test]
 fn sending_receiving_reflect() {
     let mut server_app = App::new();
@@ -94,7 +340,7 @@ fn sending_receiving_reflect() {
         app.add_plugins((MinimalPlugins, ReplicationPlugins))
             .register_type::<ReflectedValue>()
             .add_client_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(
-                SendPolicy::Ordered,
+                SendPolicy::reliable().ordered(),
                 );
     }
 
@@ -124,7 +370,7 @@ fn mapping_and_sending_receiving_reflect() {
     for app in [&mut server_app, &mut client_app] {
         app.add_plugins((MinimalPlugins, ReplicationPlugins))
             .register_type::<ReflectedValue>()
-            .add_mapped_client_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(SendPolicy::Ordered);
+            .add_mapped_client_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -160,7 +406,7 @@ fn mapping_and_sending_receiving_reflect() {
 fn local_resending() {
     let mut app = App::new();
     app.add_plugins((TimePlugin, ReplicationPlugins))
-        .add_client_event::<DummyEvent>(SendPolicy::Ordered);
+        .add_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
 
     app.world
         .resource_mut::<Events<DummyEvent>>()
@@ -174,3 +420,205 @@ fn local_resending() {
     let client_events = app.world.resource::<Events<FromClient<DummyEvent>>>();
     assert_eq!(client_events.len(), 1);
 }
+
+#[test]
+fn optimistic_rejection() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .init_resource::<AppliedCount>()
+        .add_optimistic_client_event::<OptimisticEvent>(
+            SendPolicy::reliable().ordered(),
+            Duration::from_secs(10),
+            |world, _| world.resource_mut::<AppliedCount>().0 += 1,
+            |world, _| world.resource_mut::<AppliedCount>().0 -= 1,
+        );
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<OptimisticEvent>>()
+        .send(OptimisticEvent);
+
+    client_app.update();
+    assert_eq!(
+        client_app.world.resource::<AppliedCount>().0,
+        1,
+        "event should be applied immediately"
+    );
+
+    server_app.update();
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<Optimistic<OptimisticEvent>>>>();
+    let mut reader = client_events.get_reader();
+    let FromClient {
+        client_id, event, ..
+    } = reader
+        .iter(client_events)
+        .next()
+        .expect("server should receive optimistic event");
+    let rejection = ToClients {
+        mode: SendMode::Direct(*client_id),
+        event: event.rejection(),
+    };
+    server_app.world.send_event(rejection);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        client_app.world.resource::<AppliedCount>().0,
+        0,
+        "event should be reverted after rejection"
+    );
+}
+
+#[test]
+fn request_response() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_client_request::<DummyRequest, u32>(
+            SendPolicy::reliable().ordered(),
+            Duration::from_secs(10),
+        );
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyRequest>>()
+        .send(DummyRequest(2));
+
+    client_app.update();
+    server_app.update();
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<Request<DummyRequest>>>>();
+    let mut reader = client_events.get_reader();
+    let FromClient {
+        client_id, event, ..
+    } = reader
+        .iter(client_events)
+        .next()
+        .expect("server should receive request");
+    let response = event.respond(*client_id, event.request.0 * 2);
+    server_app.world.send_event(response);
+
+    server_app.update();
+    client_app.update();
+
+    let results = client_app
+        .world
+        .resource::<Events<RequestResult<DummyRequest, u32>>>();
+    let mut reader = results.get_reader();
+    let result = reader
+        .iter(results)
+        .next()
+        .expect("client should receive response");
+    assert_eq!(result.request.0, 2);
+    assert_eq!(result.result, Ok(4));
+}
+
+#[test]
+fn request_timeout() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .add_client_request::<DummyRequest, u32>(SendPolicy::reliable().ordered(), Duration::ZERO);
+
+    app.world
+        .resource_mut::<Events<DummyRequest>>()
+        .send(DummyRequest(0));
+
+    app.update();
+    // Advance time to exceed the timeout.
+    std::thread::sleep(Duration::from_millis(1));
+    app.update();
+
+    let results = app
+        .world
+        .resource::<Events<RequestResult<DummyRequest, u32>>>();
+    let mut reader = results.get_reader();
+    let result = reader
+        .iter(results)
+        .next()
+        .expect("request should time out");
+    assert_eq!(result.result, Err(RequestTimeout));
+}
+
+#[test]
+fn event_receipts() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_receipted_client_event_with::<DummyRequest>(
+            SendPolicy::reliable().ordered(),
+            |_, request| request.0 != 0,
+        );
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let mut system_state: SystemState<ReceiptSender<DummyRequest>> =
+        SystemState::new(&mut client_app.world);
+    let mut sender = system_state.get_mut(&mut client_app.world);
+    let rejected_id = sender.send(DummyRequest(0));
+    let accepted_id = sender.send(DummyRequest(1));
+    assert!(sender.is_pending(rejected_id));
+    assert!(sender.is_pending(accepted_id));
+    system_state.apply(&mut client_app.world);
+
+    client_app.update();
+    server_app.update();
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<DummyRequest>>>();
+    let requests: Vec<_> = client_events
+        .iter_current_update_events()
+        .map(|from_client| from_client.event.0)
+        .collect();
+    assert_eq!(requests, [1], "only accepted events should be emitted");
+
+    server_app.update();
+    client_app.update();
+
+    let receipts: Vec<_> = client_app
+        .world
+        .resource::<Events<EventReceipt<DummyRequest>>>()
+        .iter_current_update_events()
+        .map(|receipt| (receipt.id, receipt.accepted))
+        .collect();
+    assert_eq!(receipts, [(rejected_id, false), (accepted_id, true)]);
+
+    let sender = system_state.get_mut(&mut client_app.world);
+    assert!(!sender.is_pending(rejected_id));
+    assert!(!sender.is_pending(accepted_id));
+}
+
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+struct OptimisticEvent;
+
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+struct DummyRequest(u32);
+
+#[derive(Default, Resource)]
+struct AppliedCount(i32);
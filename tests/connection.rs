@@ -0,0 +1,390 @@
+mod common;
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    thread,
+    time::Duration,
+};
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::{
+    prelude::*,
+    renet::transport::{NetcodeClientTransport, NetcodeServerTransport},
+};
+use common::DummyEvent;
+
+#[test]
+fn kick() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    server_app.world.send_event(KickClient {
+        client_id,
+        code: 42,
+    });
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .is_disconnected());
+    assert_eq!(
+        disconnect_reasons(&client_app),
+        [ClientDisconnectReason::Kicked(42)]
+    );
+
+    client_app.update();
+    assert!(
+        disconnect_reasons(&client_app).len() <= 1,
+        "disconnection should be reported once"
+    );
+}
+
+#[test]
+fn client_disconnect() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<NetcodeClientTransport>()
+        .disconnect();
+
+    client_app.update();
+
+    assert_eq!(
+        disconnect_reasons(&client_app),
+        [ClientDisconnectReason::DisconnectedByClient]
+    );
+}
+
+#[test]
+fn server_disconnect() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    server_app
+        .world
+        .resource_mut::<RenetServer>()
+        .disconnect(client_id);
+
+    server_app.update();
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        disconnect_reasons(&client_app),
+        [ClientDisconnectReason::DisconnectedByServer]
+    );
+}
+
+#[test]
+fn timeout() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    client_app.insert_resource(NetcodeSettings {
+        timeout: Some(Duration::from_millis(500)),
+        ..Default::default()
+    });
+
+    common::connect(&mut server_app, &mut client_app);
+
+    // Stop updating server and wait for the timeout.
+    // The first update receives packets that server sent before.
+    client_app.update();
+    thread::sleep(Duration::from_millis(600));
+    client_app.update();
+
+    assert_eq!(
+        disconnect_reasons(&client_app),
+        [ClientDisconnectReason::TimedOut]
+    );
+}
+
+#[test]
+fn network_quality() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.update();
+    client_app.update();
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let client_quality = client_app.world.resource::<NetworkQuality>();
+    assert!(client_quality.bytes_received_per_second > 0.0);
+    let server_quality = server_app.world.resource::<ClientNetworkQuality>();
+    assert!(server_quality.contains_key(&client_id));
+
+    client_app.world.remove_resource::<NetcodeClientTransport>();
+    client_app.world.remove_resource::<RenetClient>();
+    client_app.update();
+
+    assert!(!client_app.world.contains_resource::<NetworkQuality>());
+}
+
+#[test]
+fn server_slots() {
+    const RESERVED_CLIENT_ID: u64 = 3;
+
+    let mut server_app = App::new();
+    let mut regular_app = App::new();
+    let mut rejected_app = App::new();
+    let mut reserved_app = App::new();
+    for app in [
+        &mut server_app,
+        &mut regular_app,
+        &mut rejected_app,
+        &mut reserved_app,
+    ] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    let mut slots = ServerSlots::new(2).with_reserved_slots(1);
+    slots.reserve(RESERVED_CLIENT_ID);
+    server_app.insert_resource(slots);
+
+    let server_addr = create_server(&mut server_app, 3);
+
+    for (client_id, admitted, client_app) in [
+        (1, true, &mut regular_app),
+        (2, false, &mut rejected_app),
+        (RESERVED_CLIENT_ID, true, &mut reserved_app),
+    ] {
+        connect_client(&mut server_app, client_app, server_addr, client_id);
+        assert_eq!(
+            client_app
+                .world
+                .resource::<NetcodeClientTransport>()
+                .is_connected(),
+            admitted,
+            "client {client_id} should be connected only if admitted"
+        );
+    }
+
+    assert_eq!(
+        disconnect_reasons(&rejected_app),
+        [ClientDisconnectReason::ServerFull]
+    );
+    assert_eq!(server_app.world.resource::<ServerSlots>().occupied(), 2);
+}
+
+#[test]
+fn auth() {
+    let mut server_app = App::new();
+    let mut accepted_app = App::new();
+    let mut rejected_app = App::new();
+    for app in [&mut server_app, &mut accepted_app, &mut rejected_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+    let secret = b"secret".to_vec();
+    server_app.insert_resource(ServerAuth::new(move |_, credentials| {
+        if credentials == secret {
+            Ok(())
+        } else {
+            Err(42)
+        }
+    }));
+    accepted_app.insert_resource(AuthCredentials(b"secret".to_vec()));
+    rejected_app.insert_resource(AuthCredentials(b"guess".to_vec()));
+
+    server_app.world.spawn((Replication, Transform::default()));
+
+    let server_addr = create_server(&mut server_app, 2);
+    connect_client(&mut server_app, &mut rejected_app, server_addr, 1);
+
+    assert_eq!(
+        disconnect_reasons(&rejected_app),
+        [ClientDisconnectReason::AuthRejected(42)]
+    );
+    assert!(
+        rejected_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .to_client()
+            .is_empty(),
+        "rejected client shouldn't receive replication"
+    );
+
+    connect_client(&mut server_app, &mut accepted_app, server_addr, 2);
+
+    assert!(accepted_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .is_connected());
+    assert_eq!(
+        accepted_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .to_client()
+            .len(),
+        1
+    );
+    let auth = server_app.world.resource::<ServerAuth>();
+    assert!(auth.is_authenticated(2));
+    assert!(!auth.is_authenticated(1));
+}
+
+#[test]
+fn auth_timeout() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    server_app.insert_resource(ServerAuth::new(|_, _| Ok(())).with_timeout(Duration::ZERO));
+
+    let server_addr = create_server(&mut server_app, 1);
+    connect_client(&mut server_app, &mut client_app, server_addr, 1);
+
+    assert_eq!(
+        disconnect_reasons(&client_app),
+        [ClientDisconnectReason::AuthRejected(
+            ServerAuth::TIMEOUT_CODE
+        )],
+        "client without credentials should be rejected after timeout"
+    );
+}
+
+fn disconnect_reasons(app: &App) -> Vec<ClientDisconnectReason> {
+    let events = app.world.resource::<Events<ClientDisconnected>>();
+    let mut reader = events.get_reader();
+    reader.iter(events).map(|event| event.reason).collect()
+}
+
+#[test]
+fn listen_server() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+    ))
+    .add_client_event::<DummyEvent>(SendPolicy::reliable().ordered());
+
+    let localhost = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    app.world
+        .create_netcode_client(localhost, 1, 0)
+        .expect("client should be created");
+    app.world
+        .create_listen_server(localhost, 1, 0, None)
+        .expect("server should be created");
+
+    assert!(app.world.get_resource::<RenetClient>().is_none());
+    assert!(app.world.get_resource::<NetcodeClientTransport>().is_none());
+    assert!(app.world.contains_resource::<RenetServer>());
+
+    app.world.send_event(DummyEvent(Entity::PLACEHOLDER));
+    app.update();
+
+    let client_events = app.world.resource::<Events<FromClient<DummyEvent>>>();
+    let from_client = client_events
+        .iter_current_update_events()
+        .next()
+        .expect("host events should be received locally");
+    assert_eq!(from_client.client_id, SERVER_ID);
+}
+
+/// Creates server on localhost and returns its address.
+fn create_server(server_app: &mut App, max_clients: usize) -> SocketAddr {
+    let localhost = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    server_app
+        .world
+        .create_netcode_server(localhost, max_clients, 0, None)
+        .expect("server should be created");
+    let port = server_app
+        .world
+        .resource::<NetcodeServerTransport>()
+        .addr()
+        .port();
+
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port)
+}
+
+/// Connects the client and updates both apps until it's disconnected or stays connected for several updates.
+fn connect_client(
+    server_app: &mut App,
+    client_app: &mut App,
+    server_addr: SocketAddr,
+    client_id: u64,
+) {
+    client_app
+        .world
+        .create_netcode_client(server_addr, client_id, 0)
+        .expect("client should be created");
+
+    let mut connected_updates = 0;
+    for _ in 0..100 {
+        client_app.update();
+        server_app.update();
+        let transport = client_app.world.resource::<NetcodeClientTransport>();
+        if transport.is_disconnected() {
+            return;
+        }
+        if transport.is_connected() {
+            connected_updates += 1;
+            if connected_updates == 5 {
+                return;
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
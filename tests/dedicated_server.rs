@@ -0,0 +1,59 @@
+mod common;
+
+use bevy::{app::AppExit, ecs::event::Events, prelude::*};
+use bevy_renet::renet::transport::NetcodeClientTransport;
+use bevy_replicon::prelude::*;
+
+#[test]
+fn shutdown() {
+    let mut server_app = App::new();
+    server_app.add_plugins(
+        DedicatedServerPlugins { tick_rate: 60 }.set(DedicatedServerPlugin {
+            handle_sigint: false,
+            read_stdin: false,
+        }),
+    );
+    let mut client_app = App::new();
+    client_app.add_plugins((
+        MinimalPlugins,
+        ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+    ));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app
+        .world
+        .send_event(AdminCommand::parse("stop").unwrap());
+    server_app.update();
+
+    let shutdown_events = server_app.world.resource::<Events<ShutdownRequested>>();
+    assert_eq!(shutdown_events.len(), 1);
+    assert!(server_app.world.resource::<Events<AppExit>>().is_empty());
+
+    server_app.update();
+    client_app.update();
+
+    assert!(!server_app.world.resource::<Events<AppExit>>().is_empty());
+    assert!(client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .is_disconnected());
+}
+
+#[test]
+#[should_panic(expected = "tick rate should be greater than zero")]
+fn zero_tick_rate() {
+    App::new().add_plugins(DedicatedServerPlugins { tick_rate: 0 });
+}
+
+#[test]
+fn command_parsing() {
+    assert_eq!(AdminCommand::parse("  "), None);
+    assert_eq!(
+        AdminCommand::parse("kick 42  cheating"),
+        Some(AdminCommand {
+            name: "kick".to_string(),
+            args: vec!["42".to_string(), "cheating".to_string()],
+        })
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn stamping_and_sending() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((MinimalPlugins, ReplicationPlugins))
+            .add_client_input::<DummyInput>(SendPolicy::reliable().ordered());
+    }
+    const DELAY: u32 = 2;
+    client_app.insert_resource(InputConfig {
+        delay: DELAY,
+        ..Default::default()
+    });
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyInput>>()
+        .send(DummyInput(1));
+
+    client_app.update();
+    server_app.update();
+
+    let tick = client_app
+        .world
+        .resource::<ServerTickEstimate>()
+        .tick()
+        .wrapping_add(DELAY);
+    let buffer = client_app.world.resource::<InputBuffer<DummyInput>>();
+    assert_eq!(buffer.get(tick), Some(&DummyInput(1)));
+
+    let client_events = server_app
+        .world
+        .resource::<Events<FromClient<TickedInput<DummyInput>>>>();
+    let mut reader = client_events.get_reader();
+    let event = reader
+        .iter(client_events)
+        .next()
+        .expect("server should receive the input");
+    assert_eq!(event.event.tick, tick);
+    assert_eq!(event.event.input, DummyInput(1));
+}
+
+#[test]
+fn local_stamping() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .add_client_input::<DummyInput>(SendPolicy::reliable().ordered());
+
+    app.world
+        .resource_mut::<Events<DummyInput>>()
+        .send(DummyInput(1));
+
+    app.update();
+
+    let tick = app.world.resource::<ServerTick>().get();
+    let client_events = app
+        .world
+        .resource::<Events<FromClient<TickedInput<DummyInput>>>>();
+    let mut reader = client_events.get_reader();
+    let event = reader
+        .iter(client_events)
+        .next()
+        .expect("input should be resent locally");
+    assert_eq!(event.client_id, SERVER_ID);
+    assert_eq!(event.event.tick, tick);
+}
+
+#[test]
+fn jitter_buffering() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_client_input::<DummyInput>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<DummyInput>>()
+        .send(DummyInput(1));
+
+    client_app.update();
+    server_app.update();
+
+    let inputs = server_app
+        .world
+        .resource::<Events<FromClient<DummyInput>>>();
+    let mut reader = inputs.get_reader();
+    let event = reader
+        .iter(inputs)
+        .next()
+        .expect("buffered input should be consumed on server tick");
+    assert_eq!(event.event, DummyInput(1));
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    let health = server_app
+        .world
+        .resource::<InputJitterBuffer<DummyInput>>()
+        .health(client_id)
+        .expect("client should have buffer after sending inputs");
+    assert_eq!(health.buffered, 0);
+    assert_eq!(health.underruns, 0);
+}
+
+#[derive(Clone, Debug, Deserialize, Event, PartialEq, Serialize)]
+struct DummyInput(u32);
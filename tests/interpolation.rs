@@ -0,0 +1,60 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+#[test]
+fn snapshots_recording() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+            TransformInterpolationPlugin::default(),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Interpolated, Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated");
+
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 1.0;
+
+    server_app.update();
+    client_app.update();
+    client_app.update();
+
+    let snapshots = client_app
+        .world
+        .get::<TransformSnapshots>(client_entity)
+        .expect("snapshots should be inserted for interpolated entities");
+    let translations: Vec<_> = snapshots
+        .iter()
+        .map(|(_, transform)| transform.translation.x)
+        .collect();
+    assert_eq!(
+        translations,
+        [0.0, 1.0],
+        "only replicated transforms should be recorded"
+    );
+}
@@ -0,0 +1,139 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_replicon::prelude::*;
+
+#[test]
+fn server_discovery() {
+    // Pick a free port for discovery.
+    let port = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|socket| socket.local_addr())
+        .unwrap()
+        .port();
+    let mut config = LanDiscoveryConfig::new(0);
+    config.port = port;
+    config.target_ip = Ipv4Addr::LOCALHOST.into();
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins,
+            LanDiscoveryPlugin { config },
+        ));
+    }
+
+    server_app
+        .world
+        .create_netcode_server(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 4, 0, None)
+        .unwrap();
+    server_app.insert_resource(LanServerInfo {
+        name: "Dummy".to_string(),
+        max_players: 4,
+    });
+    client_app.init_resource::<DiscoveredServers>();
+
+    server_app.update();
+    client_app.update();
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(1));
+        server_app.update();
+        client_app.update();
+        if !client_app.world.resource::<DiscoveredServers>().is_empty() {
+            break;
+        }
+    }
+
+    let server_port = server_app
+        .world
+        .resource::<bevy_replicon::renet::transport::NetcodeServerTransport>()
+        .addr()
+        .port();
+    let discovered_servers = client_app.world.resource::<DiscoveredServers>();
+    let server = discovered_servers
+        .iter()
+        .next()
+        .expect("client should discover server");
+    assert_eq!(server.name, "Dummy");
+    assert_eq!(server.players, 0);
+    assert_eq!(server.max_players, 4);
+    assert_eq!(server.addr.port(), server_port);
+
+    client_app
+        .world
+        .remove_resource::<DiscoveredServers>()
+        .unwrap();
+    client_app.update();
+}
+
+#[test]
+fn occupied_port() {
+    // Occupy a free port to make server binding fail.
+    let occupying_socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+    let mut config = LanDiscoveryConfig::new(0);
+    config.port = occupying_socket.local_addr().unwrap().port();
+    config.target_ip = Ipv4Addr::LOCALHOST.into();
+
+    let mut server_app = App::new();
+    server_app.add_plugins((
+        MinimalPlugins,
+        ReplicationPlugins,
+        LanDiscoveryPlugin { config },
+    ));
+
+    // Request often to detect the server as soon as it binds.
+    let mut client_config = config;
+    client_config.interval = Duration::from_millis(1);
+    let mut client_app = App::new();
+    client_app.add_plugins((
+        MinimalPlugins,
+        ReplicationPlugins,
+        LanDiscoveryPlugin {
+            config: client_config,
+        },
+    ));
+
+    server_app
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO))
+        .insert_resource(LanServerInfo {
+            name: "Dummy".to_string(),
+            max_players: 4,
+        })
+        .world
+        .create_netcode_server(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 4, 0, None)
+        .unwrap();
+    client_app.init_resource::<DiscoveredServers>();
+
+    server_app.update();
+    drop(occupying_socket);
+
+    for _ in 0..20 {
+        thread::sleep(Duration::from_millis(1));
+        server_app.update();
+        client_app.update();
+    }
+    assert!(
+        client_app.world.resource::<DiscoveredServers>().is_empty(),
+        "server shouldn't retry binding before the interval elapses"
+    );
+
+    server_app.insert_resource(TimeUpdateStrategy::ManualDuration(config.interval));
+    for _ in 0..100 {
+        thread::sleep(Duration::from_millis(1));
+        server_app.update();
+        client_app.update();
+        if !client_app.world.resource::<DiscoveredServers>().is_empty() {
+            break;
+        }
+    }
+    assert_eq!(
+        client_app.world.resource::<DiscoveredServers>().len(),
+        1,
+        "server should bind after the interval"
+    );
+}
@@ -0,0 +1,72 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_renet::renet::transport::NetcodeServerTransport;
+use bevy_replicon::{
+    load_test::{LoadTest, LoadTestServerPlugin, ServerUpdateTime},
+    netcode::NetcodeWorldExt,
+    prelude::*,
+};
+
+#[test]
+fn simulated_clients() {
+    const CLIENTS_COUNT: usize = 3;
+
+    let mut server_app = App::new();
+    server_app
+        .add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+            LoadTestServerPlugin,
+        ))
+        .replicate::<Transform>();
+    let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    server_app
+        .world
+        .create_netcode_server(addr, CLIENTS_COUNT, 0, None)
+        .unwrap();
+    let port = server_app
+        .world
+        .resource::<NetcodeServerTransport>()
+        .addr()
+        .port();
+
+    let mut load_test = LoadTest::new(CLIENTS_COUNT, |app| {
+        app.replicate::<Transform>();
+    });
+    let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
+    load_test.connect(server_addr, 1, 0).unwrap();
+    assert_eq!(load_test.metrics().connected, 0);
+
+    let started_at = Instant::now();
+    while load_test.metrics().connected != CLIENTS_COUNT {
+        assert!(
+            started_at.elapsed() < Duration::from_secs(10),
+            "all clients should connect"
+        );
+        server_app.update();
+        load_test.update();
+    }
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+    server_app.update();
+    load_test.update();
+
+    for bot in load_test.bots() {
+        assert!(bot.entity_map().to_client().get(server_entity).is_some());
+    }
+
+    let metrics = load_test.metrics();
+    assert_eq!(metrics.clients, CLIENTS_COUNT);
+    assert_eq!(metrics.failed, 0);
+
+    let update_time = server_app.world.resource::<ServerUpdateTime>();
+    assert!(update_time.updates() > 0);
+    assert!(update_time.max() >= update_time.average());
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::prelude::*;
+
+#[test]
+fn match_start() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate_in_rule_set::<Transform>("match")
+        .add_plugins(LobbyPlugin::new(Some("match")));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let mut client_events = client_app.world.resource_mut::<Events<LobbyCommand>>();
+    client_events.send(LobbyCommand::SetName("Dummy".to_string()));
+    client_events.send(LobbyCommand::SetReady(true));
+
+    client_app.update();
+    server_app.update();
+    client_app.update();
+
+    let player = client_app
+        .world
+        .query::<&LobbyPlayer>()
+        .single(&client_app.world);
+    assert_eq!(player.name, "Dummy");
+    assert!(player.ready);
+
+    server_app.world.send_event(StartMatch);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        server_app
+            .world
+            .resource::<ReplicationRules>()
+            .active_rule_set(),
+        Some("match")
+    );
+    assert_eq!(
+        client_app.world.resource::<Events<MatchStarted>>().len(),
+        1,
+        "client should receive match start"
+    );
+}
+
+#[test]
+fn not_ready_start() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_plugins(LobbyPlugin::default());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.send_event(StartMatch);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app
+            .world
+            .resource::<Events<MatchStarted>>()
+            .is_empty(),
+        "match shouldn't start with not ready players"
+    );
+}
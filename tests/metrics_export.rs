@@ -0,0 +1,69 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use metrics_util::{
+    debugging::{DebugValue, DebuggingRecorder},
+    MetricKind,
+};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn exported_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    recorder
+        .install()
+        .expect("recorder should be installed only once");
+
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+            MetricsExportPlugin,
+        ))
+        .replicate::<Transform>()
+        .add_server_event::<MetricsEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, Transform::default()));
+    server_app.world.send_event(ToClients {
+        mode: SendMode::Broadcast,
+        event: MetricsEvent,
+    });
+
+    server_app.update();
+    client_app.update();
+
+    let snapshot = snapshotter.snapshot().into_vec();
+    let value = |kind, name: &str| {
+        snapshot
+            .iter()
+            .find(|(key, ..)| key.kind() == kind && key.key().name() == name)
+            .map(|(_, _, _, value)| value)
+    };
+
+    assert_eq!(
+        value(MetricKind::Gauge, "replicon_connected_clients"),
+        Some(&DebugValue::Gauge(1.0.into()))
+    );
+    assert_eq!(
+        value(MetricKind::Gauge, "replicon_replicated_entities"),
+        Some(&DebugValue::Gauge(1.0.into()))
+    );
+    assert_eq!(
+        value(MetricKind::Gauge, "replicon_mapped_entities"),
+        Some(&DebugValue::Gauge(1.0.into()))
+    );
+    assert!(value(MetricKind::Gauge, "replicon_client_rtt_seconds").is_some());
+    assert!(value(MetricKind::Histogram, "replicon_update_seconds").is_some());
+    assert!(value(MetricKind::Counter, "replicon_event_sent_messages").is_some());
+    assert!(value(MetricKind::Counter, "replicon_event_received_messages").is_some());
+}
+
+#[derive(Debug, Deserialize, Event, Serialize)]
+struct MetricsEvent;
@@ -0,0 +1,122 @@
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+use bevy_replicon::{prelude::*, renet::transport::NetcodeClientTransport};
+
+#[test]
+fn punch_through() {
+    let start = Instant::now();
+    let mut rendezvous = RendezvousServer::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+    let rendezvous_addr = rendezvous.local_addr().unwrap();
+
+    let mut host_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut host_app, &mut client_app] {
+        app.add_plugins((MinimalPlugins, ReplicationPlugins, NatPunchPlugin));
+    }
+
+    let mut config = PunchConfig::new(rendezvous_addr, "dummy");
+    config.interval = Duration::ZERO;
+    let host = PunchHost::bind(
+        SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0),
+        config.clone(),
+        1,
+        0,
+    )
+    .unwrap();
+    host_app.insert_resource(host);
+
+    for _ in 0..100 {
+        host_app.update();
+        rendezvous.update(start.elapsed());
+        thread::sleep(Duration::from_millis(1));
+        if host_app
+            .world
+            .resource::<PunchHost>()
+            .public_addr()
+            .is_some()
+        {
+            break;
+        }
+    }
+    let public_addr = host_app
+        .world
+        .resource::<PunchHost>()
+        .public_addr()
+        .expect("host should be registered");
+    let host_events = host_app.world.resource::<Events<PunchEvent>>();
+    let mut host_reader = host_events.get_reader();
+    assert_eq!(
+        host_reader.iter(host_events).copied().collect::<Vec<_>>(),
+        [PunchEvent::Registered(public_addr)]
+    );
+    assert!(host_app.world.contains_resource::<RenetServer>());
+
+    client_app.insert_resource(PunchClient::bind(config, 0, 0).unwrap());
+    let mut client_reader = ManualEventReader::<PunchEvent>::default();
+    let mut client_events = Vec::new();
+    for _ in 0..100 {
+        client_app.update();
+        rendezvous.update(start.elapsed());
+        host_app.update();
+        thread::sleep(Duration::from_millis(1));
+        client_events.extend(
+            client_reader
+                .iter(client_app.world.resource::<Events<PunchEvent>>())
+                .copied(),
+        );
+        if client_app
+            .world
+            .get_resource::<NetcodeClientTransport>()
+            .is_some_and(|transport| transport.is_connected())
+        {
+            break;
+        }
+    }
+
+    assert!(
+        client_app
+            .world
+            .resource::<NetcodeClientTransport>()
+            .is_connected(),
+        "client should be connected to host"
+    );
+    assert!(!client_app.world.contains_resource::<PunchClient>());
+    assert_eq!(client_events, [PunchEvent::Connecting(public_addr)]);
+}
+
+#[test]
+fn unknown_session() {
+    let start = Instant::now();
+    let mut rendezvous = RendezvousServer::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+    let config = PunchConfig::new(rendezvous.local_addr().unwrap(), "dummy");
+
+    let mut client_app = App::new();
+    client_app
+        .add_plugins((MinimalPlugins, ReplicationPlugins, NatPunchPlugin))
+        .insert_resource(PunchClient::bind(config, 0, 0).unwrap());
+
+    for _ in 0..100 {
+        client_app.update();
+        rendezvous.update(start.elapsed());
+        thread::sleep(Duration::from_millis(1));
+        if !client_app.world.contains_resource::<PunchClient>() {
+            break;
+        }
+    }
+
+    assert!(!client_app.world.contains_resource::<PunchClient>());
+    let client_events = client_app.world.resource::<Events<PunchEvent>>();
+    let mut client_reader = client_events.get_reader();
+    assert_eq!(
+        client_reader
+            .iter(client_events)
+            .copied()
+            .collect::<Vec<_>>(),
+        [PunchEvent::Failed(PunchError::UnknownSession)]
+    );
+}
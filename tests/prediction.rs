@@ -0,0 +1,128 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+
+#[test]
+fn misprediction_recording() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .add_misprediction_metrics::<Transform>(0.5);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated");
+    client_app.world.entity_mut(client_entity).insert(Predicted);
+
+    // Predict movement that the server confirms only partially.
+    client_app
+        .world
+        .get_mut::<Transform>(client_entity)
+        .unwrap()
+        .translation
+        .x = 2.0;
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 0.75;
+
+    server_app.update();
+    client_app.update();
+
+    let stats = client_app
+        .world
+        .resource::<MispredictionDiagnostics>()
+        .get::<Transform>()
+        .expect("stats should be created for registered component");
+    assert_eq!(stats.total_count(), 1);
+    assert_eq!(stats.max_error(), 1.25);
+
+    client_app
+        .world
+        .get_mut::<Transform>(client_entity)
+        .unwrap()
+        .translation
+        .x = 1.0;
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 1.25;
+
+    server_app.update();
+    client_app.update();
+
+    let stats = client_app
+        .world
+        .resource::<MispredictionDiagnostics>()
+        .get::<Transform>()
+        .unwrap();
+    assert_eq!(
+        stats.total_count(),
+        1,
+        "errors within tolerance shouldn't be counted"
+    );
+}
+
+#[test]
+fn correction_smoothing() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, CorrectionSmoothingPlugin));
+
+    let mut smoothing = CorrectionSmoothing::default();
+    smoothing.add_correction(Vec3::X, Vec3::ZERO);
+    let parent = app
+        .world
+        .spawn((TransformBundle::default(), smoothing))
+        .id();
+    let child = app
+        .world
+        .spawn(TransformBundle::from_transform(Transform::from_xyz(
+            0.0, 1.0, 0.0,
+        )))
+        .id();
+    app.world.entity_mut(parent).add_child(child);
+
+    app.update();
+
+    let parent_transform = app.world.get::<GlobalTransform>(parent).unwrap();
+    assert_eq!(parent_transform.translation(), Vec3::X);
+    let child_transform = app.world.get::<GlobalTransform>(child).unwrap();
+    assert_eq!(child_transform.translation(), Vec3::new(1.0, 1.0, 0.0));
+    assert_eq!(
+        *app.world.get::<Transform>(parent).unwrap(),
+        Transform::IDENTITY,
+        "only rendered transform should be offset"
+    );
+
+    app.update();
+
+    let parent_transform = app.world.get::<GlobalTransform>(parent).unwrap();
+    assert!(
+        parent_transform.translation().x <= 1.0,
+        "offset shouldn't accumulate between frames"
+    );
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_replicon::{prelude::*, renet::transport::NetcodeClientTransport};
+
+#[test]
+fn older_version() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    server_app.insert_resource(ProtocolVersion::new(2).with_min_supported(1));
+    client_app.insert_resource(ProtocolVersion::new(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_versions = server_app.world.resource::<ClientProtocolVersions>();
+    assert!(
+        client_versions.values().eq(&[1]),
+        "supported client version should be recorded"
+    );
+}
+
+#[test]
+fn unsupported_version() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    server_app.insert_resource(ProtocolVersion::new(2).with_min_supported(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    assert!(server_app
+        .world
+        .resource::<ClientProtocolVersions>()
+        .is_empty());
+    assert!(client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .is_disconnected());
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use bevy::{
+    ecs::{event::Events, system::SystemState},
+    prelude::*,
+};
+use bevy_renet::renet::transport::NetcodeClientTransport;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn relayed_event() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_relayed_event_with::<Emote>(SendPolicy::reliable().ordered(), |_, emote| emote.0 != 0);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let mut client_events = client_app.world.resource_mut::<Events<Emote>>();
+    client_events.send(Emote(0));
+    client_events.send(Emote(1));
+
+    client_app.update();
+    server_app.update();
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let peer_events: Vec<_> = server_app
+        .world
+        .resource::<Events<FromPeer<Emote>>>()
+        .iter_current_update_events()
+        .map(|from_peer| (from_peer.peer_id, from_peer.event.0))
+        .collect();
+    assert_eq!(
+        peer_events,
+        [(client_id, 1)],
+        "only valid events should be relayed"
+    );
+}
+
+#[test]
+fn relay_authority() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .init_resource::<RelayServer>();
+
+    let entity = app.world.spawn(Authority::Server).id();
+
+    let mut state = SystemState::<LocalAuthority>::new(&mut app.world);
+    let authority = state.get(&app.world);
+    assert!(!authority.has_authority_over(entity));
+}
+
+#[derive(Clone, Debug, Deserialize, Event, Serialize)]
+struct Emote(u32);
@@ -1,6 +1,17 @@
 mod common;
 
-use bevy::prelude::*;
+use std::{
+    any, mem,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{
+    ecs::{event::Events, system::SystemState},
+    prelude::*,
+    utils::HashMap,
+};
 use bevy_replicon::prelude::*;
 
 use bevy::ecs::{
@@ -8,6 +19,7 @@ use bevy::ecs::{
     reflect::ReflectMapEntities,
 };
 use bevy_renet::renet::transport::NetcodeClientTransport;
+use serde::{Deserialize, Serialize};
 
 #[test]
 fn acked_ticks_cleanup() {
@@ -58,41 +70,69 @@ fn tick_acks_receiving() {
 }
 
 #[test]
-fn spawn_replication() {
+fn empty_diff_keepalive() {
     let mut server_app = App::new();
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
         app.add_plugins((
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
-        ))
-        .replicate::<TableComponent>();
+        ));
     }
 
     common::connect(&mut server_app, &mut client_app);
 
-    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+    client_app.init_resource::<ReceivedWorldDiffs>();
 
     server_app.update();
     client_app.update();
 
-    let client_entity = client_app
-        .world
-        .query_filtered::<Entity, (With<TableComponent>, With<Replication>)>()
-        .single(&client_app.world);
-    let entity_map = client_app.world.resource::<NetworkEntityMap>();
-    let mapped_entity = entity_map
-        .to_client()
-        .get(server_entity)
-        .expect("server entity should be mapped on client");
+    let messages = client_app.world.resource::<ReceivedWorldDiffs>();
+    let message = messages.last().expect("client should receive a keepalive");
     assert_eq!(
-        mapped_entity, client_entity,
-        "mapped entity should correspond to the replicated entity on client"
+        message.len(),
+        1 + 2 * mem::size_of::<u32>(),
+        "keepalive should contain only version and ticks"
+    );
+    assert_eq!(
+        *client_app.world.resource::<ServerTick>(),
+        *server_app.world.resource::<ServerTick>()
     );
 }
 
 #[test]
-fn insert_replication() {
+fn server_tick_applied() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app
+        .world
+        .resource_mut::<Events<ServerTickApplied>>()
+        .clear();
+
+    server_app.update();
+    client_app.update();
+
+    let last_tick = *client_app.world.resource::<LastReceivedServerTick>();
+    let ticks: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<ServerTickApplied>>()
+        .drain()
+        .map(|ServerTickApplied(tick)| tick)
+        .collect();
+    assert_eq!(ticks, [last_tick.get()]);
+}
+
+#[test]
+fn initial_sync() {
     let mut server_app = App::new();
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
@@ -100,54 +140,150 @@ fn insert_replication() {
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
-        .register_type::<NonReflectedComponent>()
-        .register_type::<ExclusionComponent>()
-        .replicate::<TableComponent>()
-        .replicate::<SparseSetComponent>()
-        .replicate::<IgnoredComponent>()
-        .replicate::<MappedComponent>()
-        .not_replicate_if_present::<IgnoredComponent, ExclusionComponent>();
+        .replicate::<TableComponent>();
     }
+    server_app.init_resource::<SyncedClientIds>().add_systems(
+        Update,
+        |mut sync_events: EventReader<ClientSynced>, mut synced: ResMut<SyncedClientIds>| {
+            synced
+                .0
+                .extend(sync_events.iter().map(|event| event.client_id))
+        },
+    );
+    client_app.init_resource::<SyncsCompleted>().add_systems(
+        Update,
+        |mut sync_events: EventReader<InitialSyncComplete>,
+         mut completed: ResMut<SyncsCompleted>| completed.0 += sync_events.iter().count(),
+    );
+
+    server_app.world.spawn((Replication, TableComponent));
 
     common::connect(&mut server_app, &mut client_app);
 
-    let server_map_entity = server_app.world.spawn_empty().id();
-    let client_map_entity = client_app.world.spawn_empty().id();
+    for _ in 0..3 {
+        server_app.update();
+        client_app.update();
+    }
 
-    let client_entity = client_app.world.spawn_empty().id();
-    let server_entity = server_app
+    assert_eq!(client_app.world.resource::<SyncsCompleted>().0, 1);
+    assert_eq!(
+        client_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .to_client()
+            .len(),
+        1
+    );
+
+    let client_id = client_app
         .world
-        .spawn((
-            Replication,
-            TableComponent,
-            SparseSetComponent,
-            NonReflectedComponent,
-            MappedComponent(server_map_entity),
-            IgnoredComponent,
-            ExclusionComponent,
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    assert_eq!(
+        server_app.world.resource::<SyncedClientIds>().0,
+        [client_id]
+    );
+
+    server_app.world.spawn((Replication, TableComponent));
+    for _ in 0..3 {
+        server_app.update();
+        client_app.update();
+    }
+
+    assert_eq!(
+        client_app.world.resource::<SyncsCompleted>().0,
+        1,
+        "initial sync should complete only once"
+    );
+    assert_eq!(server_app.world.resource::<SyncedClientIds>().0.len(), 1);
+}
+
+#[test]
+fn initial_sync_with_entity_limit() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
-        .id();
+        .replicate::<TableComponent>();
+    }
+    server_app
+        .insert_resource(MessageEntityLimit(1))
+        .init_resource::<SyncedClientIds>()
+        .add_systems(
+            Update,
+            |mut sync_events: EventReader<ClientSynced>, mut synced: ResMut<SyncedClientIds>| {
+                synced
+                    .0
+                    .extend(sync_events.iter().map(|event| event.client_id))
+            },
+        );
+    client_app.init_resource::<SyncsCompleted>().add_systems(
+        Update,
+        |mut sync_events: EventReader<InitialSyncComplete>,
+         mut completed: ResMut<SyncsCompleted>| completed.0 += sync_events.iter().count(),
+    );
 
-    let mut entity_map = client_app.world.resource_mut::<NetworkEntityMap>();
-    entity_map.insert(server_map_entity, client_map_entity);
-    entity_map.insert(server_entity, client_entity);
+    const ENTITIES_COUNT: usize = 4;
+    for _ in 0..ENTITIES_COUNT {
+        server_app.world.spawn((Replication, TableComponent));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    for _ in 0..ENTITIES_COUNT + 3 {
+        server_app.update();
+        client_app.update();
+
+        let entities_count = client_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .to_client()
+            .len();
+        if client_app.world.resource::<SyncsCompleted>().0 != 0 {
+            assert_eq!(
+                entities_count, ENTITIES_COUNT,
+                "initial sync should complete only after all entities are received"
+            );
+        }
+        if !server_app.world.resource::<SyncedClientIds>().0.is_empty() {
+            assert_eq!(entities_count, ENTITIES_COUNT);
+        }
+    }
+
+    assert_eq!(client_app.world.resource::<SyncsCompleted>().0, 1);
+    assert_eq!(server_app.world.resource::<SyncedClientIds>().0.len(), 1);
+}
+
+#[test]
+fn server_tick_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
 
     server_app.update();
     client_app.update();
 
-    let client_entity = client_app.world.entity(client_entity);
-    assert!(client_entity.contains::<SparseSetComponent>());
-    assert!(client_entity.contains::<TableComponent>());
-    assert!(!client_entity.contains::<NonReflectedComponent>());
-    assert!(!client_entity.contains::<ExclusionComponent>());
+    let server_tick = *server_app.world.resource::<ServerTick>();
+    assert_ne!(server_tick.get(), 0, "tick should be incremented on send");
+    assert_eq!(*client_app.world.resource::<ServerTick>(), server_tick);
     assert_eq!(
-        client_entity.get::<MappedComponent>().unwrap().0,
-        client_map_entity
+        client_app.world.resource::<ServerTickEstimate>().tick(),
+        server_tick.get()
     );
 }
 
 #[test]
-fn removal_replication() {
+fn ordered_replication() {
     let mut server_app = App::new();
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
@@ -155,90 +291,2111 @@ fn removal_replication() {
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
-        .register_type::<NonReflectedComponent>()
         .replicate::<TableComponent>();
+        app.world
+            .resource_mut::<NetworkChannels>()
+            .set_replication_policy(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
 
-    let server_entity = server_app
-        .world
-        .spawn((Replication, TableComponent, NonReflectedComponent))
-        .id();
+    server_app.world.spawn((Replication, TableComponent));
 
     server_app.update();
+    client_app.update();
 
-    server_app
-        .world
-        .entity_mut(server_entity)
-        .remove::<TableComponent>();
+    let mut components = client_app.world.query::<&TableComponent>();
+    assert_eq!(components.iter(&client_app.world).count(), 1);
+}
 
-    let client_entity = client_app
-        .world
-        .spawn((Replication, TableComponent, NonReflectedComponent))
-        .id();
+#[test]
+fn stale_diffs_discarding() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
 
-    client_app
-        .world
-        .resource_mut::<NetworkEntityMap>()
-        .insert(server_entity, client_entity);
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
 
     server_app.update();
     client_app.update();
 
-    let client_entity = client_app.world.entity(client_entity);
-    assert!(!client_entity.contains::<TableComponent>());
-    assert!(client_entity.contains::<NonReflectedComponent>());
+    // Pretend that the client already received a newer diff.
+    let server_tick = server_app.world.resource::<ServerTick>().get();
+    let newer_tick: ServerTick = bincode::deserialize(&(server_tick + 1).to_le_bytes()).unwrap();
+    client_app.world.insert_resource(newer_tick);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let mut components = client_app.world.query::<&TableComponent>();
+    assert_eq!(
+        components.iter(&client_app.world).count(),
+        1,
+        "diffs with older server ticks should be discarded"
+    );
 }
 
 #[test]
-fn despawn_replication() {
+fn spawn_replication() {
     let mut server_app = App::new();
     let mut client_app = App::new();
     for app in [&mut server_app, &mut client_app] {
         app.add_plugins((
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
-        ));
+        ))
+        .replicate::<TableComponent>();
     }
 
     common::connect(&mut server_app, &mut client_app);
 
-    let server_entity = server_app.world.spawn(Replication).id();
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
 
     server_app.update();
+    client_app.update();
 
-    server_app.world.despawn(server_entity);
+    let client_entity = client_app
+        .world
+        .query_filtered::<Entity, (With<TableComponent>, With<Replication>)>()
+        .single(&client_app.world);
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let mapped_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be mapped on client");
+    assert_eq!(
+        mapped_entity, client_entity,
+        "mapped entity should correspond to the replicated entity on client"
+    );
+}
+
+#[test]
+fn pooled_spawn_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.insert_resource(ClientEntityPool::new(2));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let pool = client_app.world.resource::<ClientEntityPool>();
+    assert_eq!(pool.entities().len(), pool.size());
+    let pooled_entities = pool.entities().to_vec();
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
 
-    let child_entity = client_app.world.spawn_empty().id();
     let client_entity = client_app
         .world
-        .spawn_empty()
-        .push_children(&[child_entity])
-        .id();
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+    assert!(
+        pooled_entities.contains(&client_entity),
+        "replicated entity should be taken from the pool"
+    );
+    assert!(client_app
+        .world
+        .get::<TableComponent>(client_entity)
+        .is_some());
 
-    client_app
+    let pool = client_app.world.resource::<ClientEntityPool>();
+    assert_eq!(
+        pool.entities().len(),
+        pool.size(),
+        "pool should be refilled"
+    );
+}
+
+#[test]
+fn tick_atomicity() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .replicate::<SparseSetComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let removed_entity = server_app
         .world
-        .resource_mut::<NetworkEntityMap>()
-        .insert(server_entity, client_entity);
+        .spawn((Replication, TableComponent, SparseSetComponent))
+        .id();
+    let despawned_entity = server_app.world.spawn((Replication, TableComponent)).id();
 
     server_app.update();
     client_app.update();
 
-    assert!(client_app.world.get_entity(client_entity).is_none());
-    assert!(client_app.world.get_entity(child_entity).is_none());
+    // Change everything within a single tick.
+    server_app.world.spawn((Replication, TableComponent));
+    server_app
+        .world
+        .entity_mut(removed_entity)
+        .remove::<SparseSetComponent>();
+    server_app.world.despawn(despawned_entity);
+
+    server_app.update();
+    client_app.update();
 
     let entity_map = client_app.world.resource::<NetworkEntityMap>();
-    assert!(entity_map.to_client().is_empty());
+    assert_eq!(entity_map.to_client().len(), 2);
+    let client_removed_entity = entity_map.to_client().get(removed_entity).unwrap();
+    assert!(!client_app
+        .world
+        .entity(client_removed_entity)
+        .contains::<SparseSetComponent>());
+    assert!(
+        entity_map.to_client().get(despawned_entity).is_none(),
+        "the whole tick should be applied at once"
+    );
 }
 
-#[derive(Component, Reflect)]
-#[reflect(Component, MapEntities)]
-struct MappedComponent(Entity);
+#[test]
+fn diff_coalescing() {
+    for coalescing in [DiffCoalescing::Coalesce, DiffCoalescing::Sequential] {
+        let mut server_app = App::new();
+        let mut client_app = App::new();
+        for app in [&mut server_app, &mut client_app] {
+            app.add_plugins((
+                MinimalPlugins,
+                ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+            ))
+            .replicate::<TableComponent>();
+        }
+        client_app.insert_resource(coalescing);
 
-impl MapEntities for MappedComponent {
-    fn map_entities(&mut self, entity_map: &mut EntityMapper) {
-        self.0 = entity_map.get_or_reserve(self.0);
+        common::connect(&mut server_app, &mut client_app);
+
+        // Send two diffs without receiving them.
+        server_app.world.spawn((Replication, TableComponent));
+        server_app.update();
+        server_app.world.spawn((Replication, TableComponent));
+        server_app.update();
+
+        client_app
+            .world
+            .resource_mut::<Events<ServerTickApplied>>()
+            .clear();
+        client_app.update();
+
+        let applied_events = client_app.world.resource::<Events<ServerTickApplied>>();
+        match coalescing {
+            DiffCoalescing::Coalesce => assert_eq!(applied_events.len(), 1),
+            DiffCoalescing::Sequential => assert!(applied_events.len() > 1),
+        }
+        let entity_map = client_app.world.resource::<NetworkEntityMap>();
+        assert_eq!(entity_map.to_client().len(), 2);
+    }
+}
+
+#[test]
+fn diff_playback_catch_up() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app.insert_resource(DiffPlayback::new(1, 2));
+
+    // Send multiple diffs without receiving them.
+    for _ in 0..4 {
+        server_app.world.spawn((Replication, TableComponent));
+        server_app.update();
+    }
+
+    client_app.update();
+
+    let playback = client_app.world.resource::<DiffPlayback>();
+    assert!(playback.is_catching_up());
+    let buffered_count = playback.buffered_count();
+    assert!(buffered_count > playback.target_delay);
+
+    client_app.update();
+
+    let playback = client_app.world.resource::<DiffPlayback>();
+    assert_eq!(
+        playback.buffered_count(),
+        buffered_count - playback.catch_up_rate,
+        "multiple diffs should be applied per frame while behind"
+    );
+
+    while client_app.world.resource::<DiffPlayback>().buffered_count() > 0 {
+        client_app.update();
+    }
+
+    assert!(!client_app.world.resource::<DiffPlayback>().is_catching_up());
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 4);
+}
+
+#[test]
+fn jitter_buffer() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    client_app.insert_resource(JitterBuffer::new(1000));
+
+    server_app.world.spawn((Replication, TableComponent));
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.resource::<JitterBuffer>().buffered_count() > 0);
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "diffs should be held for the delay"
+    );
+
+    client_app.world.resource_mut::<JitterBuffer>().delay = 0;
+    client_app.update();
+
+    assert_eq!(
+        client_app.world.resource::<JitterBuffer>().buffered_count(),
+        0
+    );
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 1);
+}
+
+#[test]
+fn replication_tick() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let server_tick = *client_app.world.resource::<ServerTick>();
+    let replication_tick = client_app
+        .world
+        .query::<&ReplicationTick>()
+        .single(&client_app.world);
+    assert_eq!(replication_tick.get(), server_tick);
+}
+
+#[test]
+fn diff_build_budget() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app.insert_resource(DiffBuildBudget::new(Duration::ZERO));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let budget = server_app.world.resource::<DiffBuildBudget>();
+    assert_eq!(
+        budget.pending_clients().count(),
+        0,
+        "at least one client should be processed per frame"
+    );
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 1);
+}
+
+#[test]
+fn spawn_budget() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.insert_resource(SpawnBudget(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app
+        .world
+        .spawn_batch([(TableComponent, Replication), (TableComponent, Replication)]);
+
+    for expected_count in 1..=2 {
+        server_app.update();
+        client_app.update();
+
+        let count = client_app
+            .world
+            .query_filtered::<(), (With<TableComponent>, With<Replication>)>()
+            .iter(&client_app.world)
+            .count();
+        assert_eq!(
+            count, expected_count,
+            "only one entity should be spawned per frame"
+        );
+    }
+}
+
+#[test]
+fn spawn_budget_unconfirmed() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.insert_resource(SpawnBudget(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app
+        .world
+        .spawn_batch([(TableComponent, Replication), (TableComponent, Replication)]);
+
+    server_app.update();
+    client_app.update();
+    server_app.update();
+
+    client_app.init_resource::<ReceivedWorldDiffs>();
+    client_app.update();
+
+    let message = client_app
+        .world
+        .resource::<ReceivedWorldDiffs>()
+        .last()
+        .cloned()
+        .expect("client should receive a diff");
+    let mut shadow_world = World::new();
+    shadow_world.insert_resource(client_app.world.resource::<AppTypeRegistry>().clone());
+    let mut entity_map = NetworkEntityMap::default();
+    apply_world_diff_message(&mut shadow_world, &mut entity_map, &message)
+        .expect("received message should be a world diff");
+    assert_eq!(
+        entity_map.to_client().len(),
+        1,
+        "only the unconfirmed entity should be resent"
+    );
+}
+
+#[test]
+fn uuid_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let uuid = NetworkUuid::new();
+    let server_entity = server_app.world.spawn((Replication, uuid)).id();
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        server_app.world.resource::<NetworkUuids>().get(uuid.0),
+        Some(server_entity)
+    );
+    let client_entity = client_app
+        .world
+        .resource::<NetworkUuids>()
+        .get(uuid.0)
+        .expect("replicated UUID should be registered on client");
+    assert_eq!(
+        client_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .to_client()
+            .get(server_entity),
+        Some(client_entity)
+    );
+
+    server_app.world.despawn(server_entity);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app
+        .world
+        .resource::<NetworkUuids>()
+        .get(uuid.0)
+        .is_none());
+}
+
+#[test]
+fn synced_timer_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_tick = server_app.world.resource::<ServerTick>().get();
+    let timer = SyncedTimer::new(server_tick, 10);
+    server_app.world.spawn((Replication, timer));
+
+    server_app.update();
+    client_app.update();
+
+    let client_timer = *client_app
+        .world
+        .query::<&SyncedTimer>()
+        .single(&client_app.world);
+    assert_eq!(client_timer, timer);
+
+    let mut clock = SystemState::<SyncedClock>::new(&mut client_app.world);
+    let tick = clock.get(&client_app.world).tick();
+    assert!(tick >= server_tick as f64);
+    assert!(client_timer.remaining_ticks(tick) <= 10.0);
+}
+
+#[test]
+fn spawn_budget_groups() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.insert_resource(SpawnBudget(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn_batch([
+        (TableComponent, Replication, SpawnGroup(0)),
+        (TableComponent, Replication, SpawnGroup(0)),
+    ]);
+    server_app.world.spawn((TableComponent, Replication));
+
+    for expected_count in [2, 3] {
+        server_app.update();
+        client_app.update();
+
+        let count = client_app
+            .world
+            .query_filtered::<(), (With<TableComponent>, With<Replication>)>()
+            .iter(&client_app.world)
+            .count();
+        assert_eq!(
+            count, expected_count,
+            "entities from the same group should be spawned together"
+        );
+    }
+}
+
+#[test]
+fn spawn_budget_hierarchy() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+    client_app.insert_resource(SpawnBudget(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    // Spawn the child first to place it before the parent in the diff.
+    let server_child_entity = server_app
+        .world
+        .spawn((Replication, ParentSync::default()))
+        .id();
+    let server_parent_entity = server_app
+        .world
+        .spawn((Replication, ParentSync::default()))
+        .id();
+
+    // Parent after the first update to avoid sync of the inserted `ParentSync`.
+    // The client doesn't receive anything yet, so both entities will be sent in the next diff.
+    server_app.update();
+    server_app
+        .world
+        .entity_mut(server_child_entity)
+        .set_parent(server_parent_entity);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().get(server_child_entity).is_none(),
+        "child shouldn't be spawned before its parent"
+    );
+    assert!(entity_map.to_client().get(server_parent_entity).is_some());
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_parent_entity = entity_map.to_client().get(server_parent_entity).unwrap();
+    let client_child_entity = entity_map
+        .to_client()
+        .get(server_child_entity)
+        .expect("child should be spawned on the next frame");
+    let parent = client_app.world.get::<Parent>(client_child_entity).unwrap();
+    assert_eq!(**parent, client_parent_entity);
+}
+
+#[test]
+fn acked_changes_skipping() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
+    server_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be mapped on client");
+    client_app
+        .world
+        .entity_mut(client_entity)
+        .remove::<TableComponent>();
+
+    client_app.update();
+
+    assert!(
+        !client_app
+            .world
+            .entity(client_entity)
+            .contains::<TableComponent>(),
+        "acknowledged component shouldn't be sent again"
+    );
+}
+
+#[test]
+fn replication_memory() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let server_memory = server_app.world.replication_memory();
+    assert_eq!(server_memory.clients.len(), 1);
+    assert!(server_memory.tick_history > 0);
+
+    let client_memory = client_app.world.replication_memory();
+    assert!(client_memory.clients.is_empty());
+    assert!(client_memory.entity_maps > 0);
+    assert!(client_memory.total() >= client_memory.entity_maps);
+}
+
+#[test]
+fn shadow_world_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.init_resource::<ReceivedWorldDiffs>();
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let mut shadow_world = World::new();
+    shadow_world.insert_resource(client_app.world.resource::<AppTypeRegistry>().clone());
+    let mut entity_map = NetworkEntityMap::default();
+    let mut received_diffs = client_app.world.resource_mut::<ReceivedWorldDiffs>();
+    assert!(!received_diffs.is_empty());
+    for message in received_diffs.drain(..) {
+        apply_world_diff_message(&mut shadow_world, &mut entity_map, &message)
+            .expect("received message should be a world diff");
+    }
+
+    let shadow_count = shadow_world
+        .query_filtered::<(), (With<Replication>, With<TableComponent>)>()
+        .iter(&shadow_world)
+        .count();
+    assert_eq!(shadow_count, 1);
+    assert_eq!(entity_map.to_client().len(), 1);
+}
+
+#[test]
+fn insert_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .register_type::<NonReflectedComponent>()
+        .register_type::<ExclusionComponent>()
+        .replicate::<TableComponent>()
+        .replicate::<SparseSetComponent>()
+        .replicate::<IgnoredComponent>()
+        .replicate::<MappedComponent>()
+        .not_replicate_if_present::<IgnoredComponent, ExclusionComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_map_entity = server_app.world.spawn_empty().id();
+    let client_map_entity = client_app.world.spawn_empty().id();
+
+    let client_entity = client_app.world.spawn_empty().id();
+    let server_entity = server_app
+        .world
+        .spawn((
+            Replication,
+            TableComponent,
+            SparseSetComponent,
+            NonReflectedComponent,
+            MappedComponent(server_map_entity),
+            IgnoredComponent,
+            ExclusionComponent,
+        ))
+        .id();
+
+    let mut entity_map = client_app.world.resource_mut::<NetworkEntityMap>();
+    entity_map.insert(server_map_entity, client_map_entity);
+    entity_map.insert(server_entity, client_entity);
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app.world.entity(client_entity);
+    assert!(client_entity.contains::<SparseSetComponent>());
+    assert!(client_entity.contains::<TableComponent>());
+    assert!(!client_entity.contains::<NonReflectedComponent>());
+    assert!(!client_entity.contains::<ExclusionComponent>());
+    assert_eq!(
+        client_entity.get::<MappedComponent>().unwrap().0,
+        client_map_entity
+    );
+}
+
+#[test]
+fn from_world_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<FromWorldComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, FromWorldComponent(1)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+    let component = client_app
+        .world
+        .get::<FromWorldComponent>(client_entity)
+        .expect("component without `Default` should be constructed with `FromWorld`");
+    assert_eq!(component.0, 1);
+}
+
+#[test]
+fn once_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .once();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be mapped on client");
+    assert_eq!(
+        *client_app.world.get::<Transform>(client_entity).unwrap(),
+        Transform::default()
+    );
+
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation = Vec3::ONE;
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        *client_app.world.get::<Transform>(client_entity).unwrap(),
+        Transform::default(),
+        "changes of components replicated once shouldn't be sent"
+    );
+}
+
+#[test]
+fn derived_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate_derived::<DerivedComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, DerivedComponent(0)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be mapped on client");
+    assert_eq!(
+        client_app
+            .world
+            .get::<DerivedComponent>(client_entity)
+            .unwrap()
+            .0,
+        0
+    );
+
+    server_app
+        .world
+        .get_mut::<DerivedComponent>(server_entity)
+        .unwrap()
+        .0 = 1;
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        client_app
+            .world
+            .get::<DerivedComponent>(client_entity)
+            .unwrap()
+            .0,
+        0,
+        "derived options should be applied"
+    );
+}
+
+#[test]
+fn reliable_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .replicate::<Transform>()
+        .reliable();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, TableComponent, Transform::default()))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("server entity should be mapped on client");
+    let client_entity_ref = client_app.world.entity(client_entity);
+    assert!(client_entity_ref.contains::<TableComponent>());
+    assert_eq!(
+        *client_entity_ref.get::<Transform>().unwrap(),
+        Transform::default()
+    );
+
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation = Vec3::ONE;
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        client_app
+            .world
+            .get::<Transform>(client_entity)
+            .unwrap()
+            .translation,
+        Vec3::ONE
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<Transform>();
+
+    server_app.update();
+    client_app.update();
+
+    assert!(!client_app
+        .world
+        .entity(client_entity)
+        .contains::<Transform>());
+}
+
+#[test]
+fn deferred_mapping() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .replicate::<MappedComponent>();
+    }
+    client_app.insert_resource(EntityMappingPolicy::Defer);
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_map_entity = server_app.world.spawn_empty().id();
+    server_app
+        .world
+        .spawn((Replication, MappedComponent(server_map_entity)));
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .query_filtered::<Entity, With<Replication>>()
+        .single(&client_app.world);
+    assert!(!client_app
+        .world
+        .entity(client_entity)
+        .contains::<MappedComponent>());
+    assert_eq!(
+        client_app
+            .world
+            .resource::<NetworkEntityMap>()
+            .deferred_count(),
+        1
+    );
+
+    server_app
+        .world
+        .entity_mut(server_map_entity)
+        .insert((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_map_entity = entity_map
+        .to_client()
+        .get(server_map_entity)
+        .expect("referenced entity should be replicated");
+    assert_eq!(entity_map.deferred_count(), 0);
+    assert_eq!(
+        client_app
+            .world
+            .get::<MappedComponent>(client_entity)
+            .unwrap()
+            .0,
+        client_map_entity
+    );
+}
+
+#[test]
+fn collection_mapping() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .register_type::<Vec<Entity>>()
+        .register_type::<HashMap<Entity, u32>>()
+        .replicate::<CollectionComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entities: Vec<_> = (0..3)
+        .map(|_| server_app.world.spawn_empty().id())
+        .collect();
+    let client_entities: Vec<_> = (0..3)
+        .map(|_| client_app.world.spawn_empty().id())
+        .collect();
+    let mut entity_map = client_app.world.resource_mut::<NetworkEntityMap>();
+    for (&server_entity, &client_entity) in server_entities.iter().zip(&client_entities) {
+        entity_map.insert(server_entity, client_entity);
+    }
+
+    server_app.world.spawn((
+        Replication,
+        CollectionComponent {
+            option: Some(server_entities[0]),
+            vec: server_entities.clone(),
+            map: HashMap::from([(server_entities[1], 1), (server_entities[2], 2)]),
+        },
+    ));
+
+    server_app.update();
+    client_app.update();
+
+    let component = client_app
+        .world
+        .query::<&CollectionComponent>()
+        .single(&client_app.world);
+    assert_eq!(component.option, Some(client_entities[0]));
+    assert_eq!(component.vec, client_entities);
+    assert_eq!(
+        component.map,
+        HashMap::from([(client_entities[1], 1), (client_entities[2], 2)])
+    );
+}
+
+#[test]
+fn recursive_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_child = server_app.world.spawn(TableComponent).id();
+    server_app
+        .world
+        .spawn((ReplicationRecursive, TableComponent))
+        .add_child(server_child);
+
+    server_app.update();
+    client_app.update();
+
+    let server_grandchild = server_app.world.spawn(TableComponent).id();
+    server_app
+        .world
+        .entity_mut(server_child)
+        .add_child(server_grandchild);
+
+    server_app.update();
+    client_app.update();
+
+    for entity in [server_child, server_grandchild] {
+        assert!(server_app.world.entity(entity).contains::<Replication>());
+    }
+    let replicated_count = client_app
+        .world
+        .query_filtered::<(), (With<Replication>, With<TableComponent>)>()
+        .iter(&client_app.world)
+        .count();
+    assert_eq!(replicated_count, 3);
+}
+
+#[test]
+fn removal_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .register_type::<NonReflectedComponent>()
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, TableComponent, NonReflectedComponent))
+        .id();
+
+    server_app.update();
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<TableComponent>();
+
+    let client_entity = client_app
+        .world
+        .spawn((Replication, TableComponent, NonReflectedComponent))
+        .id();
+
+    client_app
+        .world
+        .resource_mut::<NetworkEntityMap>()
+        .insert(server_entity, client_entity);
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app.world.entity(client_entity);
+    assert!(!client_entity.contains::<TableComponent>());
+    assert!(client_entity.contains::<NonReflectedComponent>());
+}
+
+#[test]
+fn sparse_set_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<SparseSetComponent>()
+        .replicate::<SparseSetValueComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    // Entities without replicated table components.
+    let marker_entity = server_app
+        .world
+        .spawn((Replication, SparseSetComponent))
+        .id();
+    let value_entity = server_app
+        .world
+        .spawn((Replication, SparseSetValueComponent(0)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_marker_entity = entity_map
+        .to_client()
+        .get(marker_entity)
+        .expect("entity with a sparse set marker should be replicated");
+    let client_value_entity = entity_map
+        .to_client()
+        .get(value_entity)
+        .expect("entity with a sparse set component should be replicated");
+    assert!(client_app
+        .world
+        .entity(client_marker_entity)
+        .contains::<SparseSetComponent>());
+
+    server_app
+        .world
+        .get_mut::<SparseSetValueComponent>(value_entity)
+        .unwrap()
+        .0 = 1;
+
+    server_app.update();
+    client_app.update();
+
+    let component = client_app
+        .world
+        .get::<SparseSetValueComponent>(client_value_entity)
+        .unwrap();
+    assert_eq!(component.0, 1, "changes should be replicated");
+
+    server_app
+        .world
+        .entity_mut(marker_entity)
+        .remove::<SparseSetComponent>();
+    server_app
+        .world
+        .entity_mut(value_entity)
+        .remove::<SparseSetValueComponent>();
+
+    server_app.update();
+    client_app.update();
+
+    assert!(!client_app
+        .world
+        .entity(client_marker_entity)
+        .contains::<SparseSetComponent>());
+    assert!(!client_app
+        .world
+        .entity(client_value_entity)
+        .contains::<SparseSetValueComponent>());
+}
+
+#[test]
+fn despawn_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn(Replication).id();
+
+    server_app.update();
+
+    server_app.world.despawn(server_entity);
+
+    let child_entity = client_app.world.spawn_empty().id();
+    let client_entity = client_app
+        .world
+        .spawn_empty()
+        .push_children(&[child_entity])
+        .id();
+
+    client_app
+        .world
+        .resource_mut::<NetworkEntityMap>()
+        .insert(server_entity, client_entity);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+    assert!(client_app.world.get_entity(child_entity).is_none());
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(entity_map.to_client().is_empty());
+    assert_eq!(
+        entity_map.get_by_server(server_entity),
+        MappedEntity::Despawned
+    );
+
+    let despawned_events = client_app.world.resource::<Events<ReplicationDespawned>>();
+    let mut reader = despawned_events.get_reader();
+    let event = reader
+        .iter(despawned_events)
+        .next()
+        .expect("client should receive despawn event");
+    assert_eq!(event.entity, client_entity);
+    assert_eq!(event.reason, DespawnReason::Despawned);
+}
+
+#[test]
+fn despawn_reason_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    const KILLED: u16 = 1;
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .despawn_with_reason(KILLED);
+
+    server_app.update();
+    client_app.update();
+
+    let despawned_events = client_app.world.resource::<Events<ReplicationDespawned>>();
+    let mut reader = despawned_events.get_reader();
+    let event = reader
+        .iter(despawned_events)
+        .next()
+        .expect("client should receive despawn event");
+    assert_eq!(event.reason, DespawnReason::Custom(KILLED));
+    assert!(event.reason.is_despawned());
+}
+
+#[test]
+fn budgeted_despawn_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    client_app.insert_resource(DespawnBudget::new(0));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((Replication, TableComponent)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+
+    server_app.world.despawn(server_entity);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app.world.get_entity(client_entity).is_some(),
+        "despawn should be queued"
+    );
+    assert_eq!(
+        client_app.world.resource::<DespawnBudget>().pending_count(),
+        1
+    );
+    assert!(client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .is_empty());
+
+    client_app.world.resource_mut::<DespawnBudget>().per_frame = 1;
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+    assert_eq!(
+        client_app.world.resource::<DespawnBudget>().pending_count(),
+        0
+    );
+}
+
+#[test]
+fn local_despawn_cleanup() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let MappedEntity::Mapped(client_entity) = entity_map.get_by_server(server_entity) else {
+        panic!("server entity should be mapped on client");
+    };
+
+    client_app.world.despawn(client_entity);
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(entity_map.to_client().is_empty());
+    assert!(entity_map.to_server().is_empty());
+    assert_eq!(
+        entity_map.get_by_client(client_entity),
+        MappedEntity::Despawned
+    );
+    assert_eq!(
+        entity_map.get_by_server(Entity::PLACEHOLDER),
+        MappedEntity::Unknown
+    );
+}
+
+#[test]
+fn single_despawn_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn(Replication).id();
+
+    server_app.update();
+
+    server_app.world.despawn(server_entity);
+
+    let child_entity = client_app.world.spawn_empty().id();
+    let client_entity = client_app
+        .world
+        .spawn(DespawnPolicy::Single)
+        .push_children(&[child_entity])
+        .id();
+
+    client_app
+        .world
+        .resource_mut::<NetworkEntityMap>()
+        .insert(server_entity, client_entity);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+    let child_entity = client_app.world.entity(child_entity);
+    assert!(!child_entity.contains::<Parent>());
+}
+
+#[test]
+fn parent_removal_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_parent = server_app.world.spawn((Replication, TableComponent)).id();
+    let server_child = server_app
+        .world
+        .spawn((Replication, ParentSync::default()))
+        .id();
+
+    server_app.update();
+
+    // Parent after the first update to avoid sync of the inserted `ParentSync`.
+    server_app
+        .world
+        .entity_mut(server_child)
+        .set_parent(server_parent);
+
+    server_app.update();
+    client_app.update();
+
+    let (client_child, _) = client_app
+        .world
+        .query::<(Entity, &Parent)>()
+        .single(&client_app.world);
+
+    // Let `ParentSync` changes from server hierarchy be observed to avoid syncing them back.
+    server_app.update();
+
+    server_app.world.entity_mut(server_child).remove_parent();
+
+    server_app.update();
+    client_app.update();
+
+    assert!(!client_app.world.entity(client_child).contains::<Parent>());
+}
+
+#[test]
+fn parent_sync_removal_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins
+                .set(ServerPlugin::new(TickPolicy::Manual))
+                .set(ParentSyncPlugin::new(true)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_parent = server_app.world.spawn((Replication, TableComponent)).id();
+    let server_child = server_app
+        .world
+        .spawn((Replication, TableComponent))
+        .set_parent(server_parent)
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let (client_child, _) = client_app
+        .world
+        .query::<(Entity, &Parent)>()
+        .single(&client_app.world);
+
+    // Let `ParentSync` changes from server hierarchy be observed to avoid syncing them back.
+    server_app.update();
+
+    server_app.world.entity_mut(server_child).remove_parent();
+
+    server_app.update();
+    client_app.update();
+
+    let client_child = client_app.world.entity(client_child);
+    assert!(!client_child.contains::<ParentSync>());
+    assert!(!client_child.contains::<Parent>());
+}
+
+#[test]
+fn message_entity_limit() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app.insert_resource(MessageEntityLimit(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    for _ in 0..2 {
+        server_app.world.spawn((Replication, TableComponent));
+    }
+    let high_entity = server_app
+        .world
+        .spawn((Replication, TableComponent, ReplicationPriority::new(10.0)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 1);
+    assert!(
+        entity_map.to_client().get(high_entity).is_some(),
+        "entities with the highest priority should be sent first"
+    );
+
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(
+        entity_map.to_client().len(),
+        3,
+        "entities over the limit should be sent on the next ticks"
+    );
+}
+
+#[test]
+fn congested_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+    // Negative packet loss threshold makes all clients congested.
+    server_app.insert_resource(CongestionPolicy {
+        max_packet_loss: -1.0,
+        ..Default::default()
+    });
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let low_entity = server_app
+        .world
+        .spawn((
+            Transform::default(),
+            ReplicationPriority::new(0.0),
+            Replication,
+        ))
+        .id();
+    let high_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_low = entity_map
+        .to_client()
+        .get(low_entity)
+        .expect("spawns should be sent to congested clients");
+    let client_high = entity_map
+        .to_client()
+        .get(high_entity)
+        .expect("spawns should be sent to congested clients");
+
+    for entity in [low_entity, high_entity] {
+        server_app
+            .world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x = 1.0;
+    }
+
+    // Changes are sent only on every second tick for congested clients.
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    let translation = |entity| {
+        client_app
+            .world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x
+    };
+    assert_eq!(translation(client_high), 1.0);
+    assert_eq!(
+        translation(client_low),
+        0.0,
+        "low-priority changes should be deferred"
+    );
+
+    server_app.insert_resource(CongestionPolicy {
+        max_packet_loss: f64::MAX,
+        max_rtt: Duration::MAX,
+        ..Default::default()
+    });
+
+    server_app.update();
+    client_app.update();
+
+    let translation = |entity| {
+        client_app
+            .world
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .x
+    };
+    assert_eq!(
+        translation(client_low),
+        1.0,
+        "deferred changes should be sent after congestion"
+    );
+}
+
+#[test]
+fn send_rate_override() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    server_app
+        .world
+        .resource_mut::<ClientSendRates>()
+        .set_interval(client_id, u32::MAX);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("spawns should be sent on any tick");
+
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 1.0;
+
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    let translation = |client_app: &App| {
+        client_app
+            .world
+            .get::<Transform>(client_entity)
+            .unwrap()
+            .translation
+            .x
+    };
+    assert_eq!(
+        translation(&client_app),
+        0.0,
+        "changes should be deferred until the send tick"
+    );
+
+    server_app
+        .world
+        .resource_mut::<ClientSendRates>()
+        .remove_override(client_id);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        translation(&client_app),
+        1.0,
+        "deferred changes should be sent with the default rate"
+    );
+}
+
+#[test]
+fn replication_notifications() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .notify();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((Replication, TableComponent)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let changes = |client_app: &App| {
+        client_app
+            .world
+            .resource::<Events<ComponentReplicated<TableComponent>>>()
+            .iter_current_update_events()
+            .map(|event| event.change)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(changes(&client_app), [ReplicatedChange::Inserted]);
+
+    server_app
+        .world
+        .get_mut::<TableComponent>(server_entity)
+        .unwrap()
+        .set_changed();
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(changes(&client_app), [ReplicatedChange::Updated]);
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<TableComponent>();
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(changes(&client_app), [ReplicatedChange::Removed]);
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+    client_app
+        .world
+        .entity_mut(client_entity)
+        .insert(TableComponent);
+    client_app.update();
+
+    assert!(
+        changes(&client_app).is_empty(),
+        "local changes shouldn't be reported"
+    );
+}
+
+#[test]
+fn owner_only_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .replicate_to_owner::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let server_entity = server_app
+        .world
+        .spawn((
+            Replication,
+            Transform::default(),
+            TableComponent,
+            ClientOwned(client_id + 1),
+        ))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+    assert!(
+        !client_app
+            .world
+            .entity(client_entity)
+            .contains::<TableComponent>(),
+        "component shouldn't be sent to other clients"
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(ClientOwned(client_id));
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app
+            .world
+            .entity(client_entity)
+            .contains::<TableComponent>(),
+        "component should be sent to the new owner"
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(ClientOwned(client_id + 1));
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        !client_app
+            .world
+            .entity(client_entity)
+            .contains::<TableComponent>(),
+        "component should be removed from the previous owner"
+    );
+}
+
+#[test]
+#[should_panic(expected = "has no `ReflectComponent`")]
+fn registration_validation() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .replicate::<UnreflectedComponent>();
+
+    app.finish();
+}
+
+#[test]
+fn unreplicated_diagnostics() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .replicate::<TableComponent>()
+        .register_type::<NonReplicatingComponent>();
+
+    let server_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0);
+    app.world
+        .create_netcode_server(server_addr, 1, 0, None)
+        .unwrap();
+
+    app.world
+        .resource_mut::<UnreplicatedDiagnostics>()
+        .ignore::<Name>();
+    app.world.spawn((
+        Replication,
+        TableComponent,
+        NonReplicatingComponent,
+        UnreflectedComponent,
+        Name::default(),
+    ));
+    app.world.spawn((Replication, NonReplicatingComponent));
+
+    app.update();
+
+    let diagnostics = app.world.resource::<UnreplicatedDiagnostics>();
+    assert_eq!(
+        diagnostics.reported(),
+        [any::type_name::<NonReplicatingComponent>()],
+        "only unmarked reflected components should be reported once"
+    );
+}
+
+#[test]
+fn lagging_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual).with_catch_up_policy(
+                CatchUpPolicy {
+                    // Acknowledgments always arrive on the next tick, which makes all clients lagging.
+                    max_lag: 0,
+                    send_interval: u32::MAX,
+                    ..Default::default()
+                },
+            )),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .expect("spawns should be sent to lagging clients");
+
+    server_app.update();
+    client_app.update();
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    assert!(server_app
+        .world
+        .resource::<ClientCongestion>()
+        .is_lagging(client_id));
+
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 1.0;
+
+    for _ in 0..2 {
+        server_app.update();
+        client_app.update();
+    }
+
+    let translation = |client_app: &App| {
+        client_app
+            .world
+            .get::<Transform>(client_entity)
+            .unwrap()
+            .translation
+            .x
+    };
+    assert_eq!(
+        translation(&client_app),
+        0.0,
+        "changes should be throttled for lagging clients"
+    );
+
+    server_app.insert_resource(CatchUpPolicy {
+        max_lag: u32::MAX,
+        ..Default::default()
+    });
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        translation(&client_app),
+        1.0,
+        "deferred changes should be sent after catching up"
+    );
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component, MapEntities)]
+struct MappedComponent(Entity);
+
+impl MapEntities for MappedComponent {
+    fn map_entities(&mut self, entity_map: &mut EntityMapper) {
+        self.0 = entity_map.get_or_reserve(self.0);
     }
 }
 
@@ -248,21 +2405,1305 @@ impl FromWorld for MappedComponent {
     }
 }
 
-#[derive(Component, Default, Reflect)]
-#[reflect(Component)]
-struct TableComponent;
+#[derive(Component, Default, Reflect)]
+#[reflect(Component, MapEntities)]
+struct CollectionComponent {
+    option: Option<Entity>,
+    vec: Vec<Entity>,
+    map: HashMap<Entity, u32>,
+}
+
+impl MapEntities for CollectionComponent {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.option.map_nested_entities(entity_mapper);
+        self.vec.map_nested_entities(entity_mapper);
+        self.map.map_nested_entities(entity_mapper);
+    }
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct TableComponent;
+
+#[derive(Component, Reflect)]
+struct UnreflectedComponent;
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct NonReplicatingComponent;
+
+#[derive(Component, Default, Reflect)]
+#[component(storage = "SparseSet")]
+#[reflect(Component)]
+struct SparseSetComponent;
+
+#[derive(Component, Default, Reflect)]
+#[component(storage = "SparseSet")]
+#[reflect(Component)]
+struct SparseSetValueComponent(u8);
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+struct FromWorldComponent(u8);
+
+#[derive(Component, Default, Reflect, Replicate)]
+#[reflect(Component)]
+#[replicate(once)]
+struct DerivedComponent(u8);
+
+impl FromWorld for FromWorldComponent {
+    fn from_world(_world: &mut World) -> Self {
+        Self(u8::MAX)
+    }
+}
+
+#[derive(Component, Reflect)]
+struct NonReflectedComponent;
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct IgnoredComponent;
+
+#[derive(Component, Reflect)]
+struct ExclusionComponent;
+
+#[test]
+fn distance_falloff() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let mut falloff = DistanceFalloff::new([(10.0, 1000)]);
+    falloff.set_viewpoint(client_id, Vec3::ZERO);
+    server_app.world.insert_resource(falloff);
+
+    let near_entity = server_app
+        .world
+        .spawn((
+            Replication,
+            Transform::default(),
+            GlobalTransform::default(),
+        ))
+        .id();
+    let far_entity = server_app
+        .world
+        .spawn((
+            Replication,
+            Transform::default(),
+            GlobalTransform::from_translation(Vec3::X * 100.0),
+        ))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    for entity in [near_entity, far_entity] {
+        server_app
+            .world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation = Vec3::ONE;
+    }
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_near_entity = entity_map.to_client().get(near_entity).unwrap();
+    let client_far_entity = entity_map.to_client().get(far_entity).unwrap();
+    assert_eq!(
+        client_app
+            .world
+            .get::<Transform>(client_near_entity)
+            .unwrap()
+            .translation,
+        Vec3::ONE
+    );
+    assert_eq!(
+        client_app
+            .world
+            .get::<Transform>(client_far_entity)
+            .unwrap()
+            .translation,
+        Vec3::ZERO,
+        "changes of distant entities should be deferred"
+    );
+}
+
+#[test]
+fn lod_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .replicate::<TableComponent>()
+        .max_lod(0);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_id = client_app
+        .world
+        .resource::<NetcodeClientTransport>()
+        .client_id();
+    let server_entity = server_app
+        .world
+        .spawn((Replication, Transform::default(), TableComponent))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+    assert!(client_app
+        .world
+        .get::<TableComponent>(client_entity)
+        .is_some());
+
+    server_app
+        .world
+        .resource_mut::<ClientLods>()
+        .set_lod(client_id, server_entity, 1);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get::<Transform>(client_entity).is_some());
+    assert!(
+        client_app
+            .world
+            .get::<TableComponent>(client_entity)
+            .is_none(),
+        "component should be removed when it's not included in the new LOD"
+    );
+
+    server_app
+        .world
+        .resource_mut::<ClientLods>()
+        .set_lod(client_id, server_entity, 0);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app
+            .world
+            .get::<TableComponent>(client_entity)
+            .is_some(),
+        "component should be restored when the LOD includes it again"
+    );
+}
+
+#[test]
+fn state_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate_state::<GameState>();
+    }
+    client_app.init_resource::<PlayingEntered>().add_systems(
+        OnEnter(GameState::Playing),
+        |mut entered: ResMut<PlayingEntered>| entered.0 += 1,
+    );
+
+    server_app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    server_app.update();
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        *client_app.world.resource::<State<GameState>>(),
+        GameState::Playing,
+        "newly connected client should receive the current state"
+    );
+    assert_eq!(client_app.world.resource::<PlayingEntered>().0, 1);
+
+    server_app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Lobby);
+
+    server_app.update();
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        *client_app.world.resource::<State<GameState>>(),
+        GameState::Lobby,
+        "client should follow server transitions"
+    );
+}
+
+#[test]
+fn state_gated_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .add_state::<GameState>()
+        .replicate_in_state(GameState::Playing);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((TableComponent, Replication));
+
+    server_app.update();
+    client_app.update();
+
+    let mut replicated = client_app.world.query::<&TableComponent>();
+    assert_eq!(
+        replicated.iter(&client_app.world).count(),
+        0,
+        "replication should be suspended outside of the state"
+    );
+
+    server_app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        replicated.iter(&client_app.world).count(),
+        0,
+        "client should discard diffs outside of the state"
+    );
+
+    client_app
+        .world
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+
+    client_app.update();
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        replicated.iter(&client_app.world).count(),
+        1,
+        "changes should be sent after resumption"
+    );
+}
+
+#[test]
+fn rules_introspection() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .replicate::<TableComponent>()
+        .replicate::<IgnoredComponent>()
+        .not_replicate_if_present::<IgnoredComponent, ExclusionComponent>()
+        .replicate_in_rule_set::<SparseSetComponent>("game");
+
+    let table_id = app.world.component_id::<TableComponent>().unwrap();
+    let ignored_id = app.world.component_id::<IgnoredComponent>().unwrap();
+    let exclusion_id = app.world.component_id::<ExclusionComponent>().unwrap();
+    let sparse_set_id = app.world.component_id::<SparseSetComponent>().unwrap();
+
+    let replication_rules = app.world.resource::<ReplicationRules>();
+    let replicated: Vec<_> = replication_rules.replicated_components().collect();
+    assert!(replicated.contains(&table_id));
+    assert!(replicated.contains(&ignored_id));
+    assert!(!replicated.contains(&sparse_set_id));
+    assert_eq!(
+        replication_rules.ignored_if_present(ignored_id),
+        [exclusion_id]
+    );
+    assert!(replication_rules.ignored_if_present(table_id).is_empty());
+
+    let (_, components) = replication_rules
+        .rule_sets()
+        .find(|&(rule_set, _)| rule_set == "game")
+        .unwrap();
+    assert!(components.contains(&sparse_set_id));
+}
+
+#[test]
+fn rule_set_switching() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .replicate_in_rule_set::<SparseSetComponent>("game");
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app
+        .world
+        .spawn((TableComponent, SparseSetComponent, Replication));
+
+    server_app.update();
+    client_app.update();
+
+    let mut replicated = client_app
+        .world
+        .query_filtered::<Option<&SparseSetComponent>, With<TableComponent>>();
+    let component = replicated.single(&client_app.world);
+    assert!(
+        component.is_none(),
+        "component shouldn't be replicated without active rule set"
+    );
+
+    server_app
+        .world
+        .resource_mut::<ReplicationRules>()
+        .set_active_rule_set(Some("game"));
+
+    server_app.update();
+    client_app.update();
+
+    let component = replicated.single(&client_app.world);
+    assert!(
+        component.is_some(),
+        "unchanged component should be sent after switching"
+    );
+    assert_eq!(
+        client_app
+            .world
+            .resource::<ReplicationRules>()
+            .active_rule_set(),
+        Some("game"),
+        "client should be informed about the switch"
+    );
+
+    let events = client_app.world.resource::<Events<RuleSetChanged>>();
+    let mut reader = events.get_reader();
+    let event = reader
+        .iter(events)
+        .last()
+        .expect("client should receive rule set change");
+    assert_eq!(event.rule_set.as_deref(), Some("game"));
+}
+
+#[test]
+fn component_toggling() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+
+    let transform_id = server_app.world.component_id::<Transform>().unwrap();
+    server_app
+        .world
+        .resource_mut::<ReplicationRules>()
+        .set_component_enabled(transform_id, false);
+    server_app
+        .world
+        .get_mut::<Transform>(server_entity)
+        .unwrap()
+        .translation
+        .x = 1.0;
+    client_app
+        .world
+        .get_mut::<Transform>(client_entity)
+        .unwrap()
+        .translation
+        .y = 1.0;
+
+    server_app.update();
+    client_app.update();
+
+    let transform = *client_app.world.get::<Transform>(client_entity).unwrap();
+    assert_eq!(
+        transform.translation,
+        Vec3::Y,
+        "local changes should be kept while replication is disabled"
+    );
+    let client_transform_id = client_app.world.component_id::<Transform>().unwrap();
+    assert!(
+        !client_app
+            .world
+            .resource::<ReplicationRules>()
+            .is_component_enabled(client_transform_id),
+        "client should be informed about the toggle"
+    );
+
+    server_app
+        .world
+        .resource_mut::<ReplicationRules>()
+        .set_component_enabled(transform_id, true);
+
+    server_app.update();
+    client_app.update();
+
+    let transform = *client_app.world.get::<Transform>(client_entity).unwrap();
+    assert_eq!(
+        transform.translation,
+        Vec3::X,
+        "current value should be sent after enabling"
+    );
+
+    let events = client_app.world.resource::<Events<ReplicationToggled>>();
+    let mut reader = events.get_reader();
+    let toggles: Vec<_> = reader.iter(events).map(|event| event.enabled).collect();
+    assert_eq!(toggles, [false, true]);
+}
+
+#[test]
+fn component_toggling_removal() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((Transform::default(), Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_entity = client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .get(server_entity)
+        .unwrap();
+
+    let transform_id = server_app.world.component_id::<Transform>().unwrap();
+    server_app
+        .world
+        .resource_mut::<ReplicationRules>()
+        .set_component_enabled(transform_id, false);
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<Transform>();
+
+    // Let the client acknowledge ticks after the removal.
+    for _ in 0..3 {
+        server_app.update();
+        client_app.update();
+    }
+
+    assert!(
+        client_app.world.get::<Transform>(client_entity).is_some(),
+        "removals should not be sent while replication is disabled"
+    );
+
+    server_app
+        .world
+        .resource_mut::<ReplicationRules>()
+        .set_component_enabled(transform_id, true);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app.world.get::<Transform>(client_entity).is_none(),
+        "removal should be sent after enabling"
+    );
+}
+
+#[test]
+fn visibility_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app.insert_resource(ClientVisibility::new(
+        VisibilityPolicy::Whitelist,
+        LeavePolicy::Despawn,
+    ));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "entity shouldn't be replicated while hidden"
+    );
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ClientVisibility>()
+        .set_visibility(client_id, server_entity, true);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated after becoming visible");
+    assert!(client_app
+        .world
+        .entity(client_entity)
+        .contains::<TableComponent>());
+
+    server_app
+        .world
+        .resource_mut::<ClientVisibility>()
+        .set_visibility(client_id, server_entity, false);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+
+    let despawned_events = client_app.world.resource::<Events<ReplicationDespawned>>();
+    let mut reader = despawned_events.get_reader();
+    let event = reader
+        .iter(despawned_events)
+        .next()
+        .expect("client should receive despawn event");
+    assert_eq!(event.entity, client_entity);
+    assert_eq!(event.reason, DespawnReason::LeftVisibility);
+}
+
+#[test]
+fn context_membership() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    const META: ReplicationContext = ReplicationContext(1);
+    let server_entity = server_app
+        .world
+        .spawn((TableComponent, META, Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "entity shouldn't be replicated before joining its context"
+    );
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ReplicationContexts>()
+        .join(client_id, META);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated after joining its context");
+    assert!(client_app
+        .world
+        .entity(client_entity)
+        .contains::<TableComponent>());
+
+    server_app
+        .world
+        .resource_mut::<ReplicationContexts>()
+        .leave(client_id, META);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+
+    let despawned_events = client_app.world.resource::<Events<ReplicationDespawned>>();
+    let mut reader = despawned_events.get_reader();
+    let event = reader
+        .iter(despawned_events)
+        .next()
+        .expect("client should receive despawn event");
+    assert_eq!(event.entity, client_entity);
+    assert_eq!(event.reason, DespawnReason::LeftContext);
+}
+
+#[test]
+fn context_moving() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated in the default context");
+
+    const META: ReplicationContext = ReplicationContext(1);
+    server_app.world.entity_mut(server_entity).insert(META);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(
+        client_app.world.get_entity(client_entity).is_none(),
+        "entity should be despawned after moving to a context the client isn't a member of"
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<ReplicationContext>();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated again after moving back");
+    assert!(client_app
+        .world
+        .entity(client_entity)
+        .contains::<TableComponent>());
+}
+
+#[test]
+fn context_interval() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    const META: ReplicationContext = ReplicationContext(1);
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    let mut contexts = server_app.world.resource_mut::<ReplicationContexts>();
+    contexts.join(client_id, META);
+    contexts.set_interval(META, 1000);
+
+    let default_entity = server_app
+        .world
+        .spawn((Replication, Transform::default()))
+        .id();
+    let meta_entity = server_app
+        .world
+        .spawn((Replication, Transform::default(), META))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    for entity in [default_entity, meta_entity] {
+        server_app
+            .world
+            .get_mut::<Transform>(entity)
+            .unwrap()
+            .translation = Vec3::ONE;
+    }
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_default_entity = entity_map.to_client().get(default_entity).unwrap();
+    let client_meta_entity = entity_map
+        .to_client()
+        .get(meta_entity)
+        .expect("new entities should be sent regardless of the interval");
+    assert_eq!(
+        client_app
+            .world
+            .get::<Transform>(client_default_entity)
+            .unwrap()
+            .translation,
+        Vec3::ONE
+    );
+    assert_eq!(
+        client_app
+            .world
+            .get::<Transform>(client_meta_entity)
+            .unwrap()
+            .translation,
+        Vec3::ZERO,
+        "changes should be deferred until the context interval"
+    );
+}
+
+#[test]
+fn context_rule_set() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>()
+        .replicate_in_rule_set::<SparseSetComponent>("meta");
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    const META: ReplicationContext = ReplicationContext(1);
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    let mut contexts = server_app.world.resource_mut::<ReplicationContexts>();
+    contexts.join(client_id, META);
+    contexts.set_rule_set(META, Some("meta"));
+
+    let default_entity = server_app
+        .world
+        .spawn((TableComponent, SparseSetComponent, Replication))
+        .id();
+    let meta_entity = server_app
+        .world
+        .spawn((TableComponent, SparseSetComponent, META, Replication))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_default_entity = entity_map.to_client().get(default_entity).unwrap();
+    let client_meta_entity = entity_map.to_client().get(meta_entity).unwrap();
+    assert!(
+        !client_app
+            .world
+            .entity(client_default_entity)
+            .contains::<SparseSetComponent>(),
+        "component shouldn't be replicated outside of its rule set"
+    );
+    let client_meta_entity = client_app.world.entity(client_meta_entity);
+    assert!(client_meta_entity.contains::<TableComponent>());
+    assert!(
+        client_meta_entity.contains::<SparseSetComponent>(),
+        "component should be replicated for entities of a context with its rule set"
+    );
+}
+
+#[test]
+fn tag_visibility() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app.insert_resource(ClientVisibility::new(
+        VisibilityPolicy::Whitelist,
+        LeavePolicy::Despawn,
+    ));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((
+            TableComponent,
+            Replication,
+            TagVisibility(TagFilter::equals("team", "red")),
+        ))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "entity shouldn't be replicated for untagged client"
+    );
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ClientTags>()
+        .insert(client_id, "team", "red");
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("entity should be replicated after tagging client");
+
+    server_app
+        .world
+        .resource_mut::<ClientTags>()
+        .insert(client_id, "team", "blue");
+
+    server_app.update();
+    client_app.update();
+
+    assert!(client_app.world.get_entity(client_entity).is_none());
+}
+
+#[test]
+fn interest_limit() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app
+        .insert_resource(ClientVisibility::new(
+            VisibilityPolicy::Blacklist,
+            LeavePolicy::Despawn,
+        ))
+        .insert_resource(InterestLimit(1));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let high_entity = server_app
+        .world
+        .spawn((TableComponent, Replication, ReplicationPriority::new(2.0)))
+        .id();
+    let low_entity = server_app
+        .world
+        .spawn((TableComponent, Replication, ReplicationPriority::new(0.5)))
+        .id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    let visibility = server_app.world.resource::<ClientVisibility>();
+    assert!(visibility.is_visible(client_id, high_entity));
+    assert!(!visibility.is_visible(client_id, low_entity));
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(entity_map.to_client().get(high_entity).is_some());
+    assert!(entity_map.to_client().get(low_entity).is_none());
+}
+
+#[test]
+fn chunk_streaming() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app
+        .insert_resource(ClientVisibility::new(
+            VisibilityPolicy::Whitelist,
+            LeavePolicy::Despawn,
+        ))
+        .insert_resource(ChunkStreaming::new(1));
 
-#[derive(Component, Default, Reflect)]
-#[component(storage = "SparseSet")]
-#[reflect(Component)]
-struct SparseSetComponent;
+    common::connect(&mut server_app, &mut client_app);
 
-#[derive(Component, Reflect)]
-struct NonReflectedComponent;
+    server_app.world.spawn_batch([
+        (TableComponent, Replication, ReplicationChunk(IVec3::ZERO)),
+        (TableComponent, Replication, ReplicationChunk(IVec3::ZERO)),
+        (TableComponent, Replication, ReplicationChunk(IVec3::X)),
+    ]);
 
-#[derive(Component, Default, Reflect)]
-#[reflect(Component)]
-struct IgnoredComponent;
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ChunkStreaming>()
+        .subscribe(client_id, IVec3::ZERO);
 
-#[derive(Component, Reflect)]
-struct ExclusionComponent;
+    for expected_count in 1..=2 {
+        server_app.update();
+        client_app.update();
+
+        let entity_map = client_app.world.resource::<NetworkEntityMap>();
+        assert_eq!(
+            entity_map.to_client().len(),
+            expected_count,
+            "entities of the subscribed chunk should be streamed in within the budget"
+        );
+    }
+
+    server_app
+        .world
+        .resource_mut::<ChunkStreaming>()
+        .unsubscribe(client_id, IVec3::ZERO);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "entities should be streamed out after unsubscribing"
+    );
+}
+
+#[test]
+fn chunk_streaming_exit_delay() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    const EXIT_DELAY: u32 = 2;
+    server_app
+        .insert_resource(ClientVisibility::new(
+            VisibilityPolicy::Whitelist,
+            LeavePolicy::Despawn,
+        ))
+        .insert_resource(ChunkStreaming::default().with_exit_delay(EXIT_DELAY));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app
+        .world
+        .spawn((TableComponent, Replication, ReplicationChunk(IVec3::ZERO)))
+        .id();
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ChunkStreaming>()
+        .subscribe(client_id, IVec3::ZERO);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(entity_map.to_client().len(), 1);
+
+    // Cross the border and return back within the delay.
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(ReplicationChunk(IVec3::X));
+
+    server_app.update();
+    client_app.update();
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(ReplicationChunk(IVec3::ZERO));
+
+    for _ in 0..=EXIT_DELAY {
+        server_app.update();
+        client_app.update();
+    }
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert_eq!(
+        entity_map.to_client().len(),
+        1,
+        "entity returned within the delay should stay visible"
+    );
+
+    server_app
+        .world
+        .entity_mut(server_entity)
+        .insert(ReplicationChunk(IVec3::X));
+
+    for _ in 0..EXIT_DELAY {
+        server_app.update();
+        client_app.update();
+
+        let entity_map = client_app.world.resource::<NetworkEntityMap>();
+        assert_eq!(
+            entity_map.to_client().len(),
+            1,
+            "entity should stay visible until the delay passes"
+        );
+    }
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    assert!(
+        entity_map.to_client().is_empty(),
+        "entity should be streamed out after the delay"
+    );
+}
+
+#[test]
+fn traffic_log() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let records = Arc::new(Mutex::new(Vec::new()));
+    let sink_records = records.clone();
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    let mut traffic_log = server_app.world.resource_mut::<TrafficLog>();
+    traffic_log.watch(client_id);
+    traffic_log.set_sink(move |record| sink_records.lock().unwrap().push(record.clone()));
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+    server_app.update();
+
+    let records = records.lock().unwrap();
+    let diff_record = records
+        .iter()
+        .find(|record| record.direction == TrafficDirection::Sent)
+        .expect("sent diff should be logged");
+    assert_eq!(diff_record.client_id, client_id);
+    assert!(
+        diff_record.summary.contains("TableComponent"),
+        "summary should contain component names, but it's `{}`",
+        diff_record.summary
+    );
+    assert!(
+        records
+            .iter()
+            .any(|record| record.direction == TrafficDirection::Received),
+        "received ack should be logged"
+    );
+}
+
+#[test]
+fn traffic_capture_decoding() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<SparseSetValueComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let path = std::env::temp_dir().join(format!(
+        "bevy_replicon_traffic_capture_decoding_{}",
+        std::process::id()
+    ));
+    server_app
+        .world
+        .resource_mut::<TrafficLog>()
+        .start_capture(&path)
+        .unwrap();
+
+    server_app
+        .world
+        .spawn((Replication, SparseSetValueComponent(42)));
+
+    server_app.update();
+    client_app.update();
+    server_app.update();
+
+    let mut traffic_log = server_app.world.resource_mut::<TrafficLog>();
+    traffic_log.stop_capture();
+
+    let messages = CapturedMessage::read_all(std::fs::File::open(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    assert!(messages
+        .iter()
+        .all(|message| message.client_id == client_id));
+
+    let decoder = TrafficDecoder::new(&server_app.world);
+    let summaries: Vec<_> = messages
+        .iter()
+        .map(|message| decoder.decode(message).summary)
+        .collect();
+    assert!(
+        summaries
+            .iter()
+            .any(|summary| summary.contains("SparseSetValueComponent") && summary.contains("42")),
+        "diff should be decoded with component values, but summaries are {summaries:?}"
+    );
+    assert!(
+        summaries.iter().any(|summary| summary.starts_with("ack")),
+        "ack should be decoded, but summaries are {summaries:?}"
+    );
+}
+
+#[test]
+fn hidden_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+    server_app.insert_resource(ClientVisibility::new(
+        VisibilityPolicy::Blacklist,
+        LeavePolicy::Hide,
+    ));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = server_app.world.spawn((TableComponent, Replication)).id();
+
+    server_app.update();
+    client_app.update();
+
+    let client_id = server_app.world.resource::<RenetServer>().clients_id()[0];
+    server_app
+        .world
+        .resource_mut::<ClientVisibility>()
+        .set_visibility(client_id, server_entity, false);
+
+    server_app.update();
+    client_app.update();
+
+    let entity_map = client_app.world.resource::<NetworkEntityMap>();
+    let client_entity = entity_map
+        .to_client()
+        .get(server_entity)
+        .expect("hidden entity should be kept on client");
+    assert!(client_app
+        .world
+        .entity(client_entity)
+        .contains::<ReplicationHidden>());
+
+    server_app
+        .world
+        .resource_mut::<ClientVisibility>()
+        .set_visibility(client_id, server_entity, true);
+
+    server_app.update();
+    client_app.update();
+
+    assert!(!client_app
+        .world
+        .entity(client_entity)
+        .contains::<ReplicationHidden>());
+}
+
+#[derive(Default, Resource)]
+struct SyncedClientIds(Vec<u64>);
+
+#[derive(Default, Resource)]
+struct SyncsCompleted(usize);
+
+#[derive(Default, Resource)]
+struct PlayingEntered(usize);
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+enum GameState {
+    #[default]
+    Lobby,
+    Playing,
+}
@@ -0,0 +1,69 @@
+use std::any;
+
+use bevy::prelude::*;
+use bevy_replicon::prelude::*;
+use bevy_save::{Loader, RMPLoader, RMPSaver, SaveableRegistry, Saver, WorldSaveableExt};
+
+#[test]
+fn replicated_saveable() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, ReplicationPlugins))
+        .replicate::<Transform>();
+
+    let registry = app.world.resource::<SaveableRegistry>();
+    assert!(registry.contains(any::type_name::<Transform>()));
+    assert!(registry.contains(any::type_name::<Replication>()));
+}
+
+#[test]
+fn save_load() {
+    let mut saved_app = App::new();
+    let mut loaded_app = App::new();
+    for app in [&mut saved_app, &mut loaded_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ParentSyncPlugin::new(true)),
+        ))
+        .replicate::<Transform>();
+    }
+
+    let saved_parent = saved_app
+        .world
+        .spawn((Replication, Transform::from_xyz(1.0, 0.0, 0.0)))
+        .id();
+    saved_app
+        .world
+        .spawn((Replication, Transform::from_xyz(2.0, 0.0, 0.0)))
+        .set_parent(saved_parent);
+
+    saved_app.update();
+
+    // Use the same format as `bevy_save` uses for files, but in memory.
+    let mut save = Vec::new();
+    saved_app
+        .world
+        .serialize(&mut RMPSaver.serializer((&mut save).into()))
+        .expect("world should be serialized");
+
+    loaded_app
+        .world
+        .deserialize(&mut RMPLoader.deserializer((&mut save.as_slice()).into()))
+        .expect("world should be loaded");
+
+    loaded_app.update();
+
+    let mut children = loaded_app
+        .world
+        .query_filtered::<(&Parent, &Transform), (With<Replication>, With<ParentSync>)>();
+    let (loaded_parent, transform) = children.single(&loaded_app.world);
+    assert_eq!(transform.translation.x, 2.0);
+
+    assert_eq!(
+        **loaded_parent, saved_parent,
+        "entity references should stay valid after loading"
+    );
+
+    let parent_entity = loaded_app.world.entity(**loaded_parent);
+    assert!(parent_entity.contains::<Replication>());
+    assert_eq!(parent_entity.get::<Transform>().unwrap().translation.x, 1.0);
+}
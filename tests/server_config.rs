@@ -0,0 +1,47 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_renet::renet::transport::NetcodeClientTransport;
+use bevy_replicon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn config_sync() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>()
+        .sync_server_config::<DummyConfig>();
+    }
+
+    server_app.insert_resource(DummyConfig { seed: 42 });
+    server_app.world.spawn((Transform::default(), Replication));
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(client_app.world.resource::<DummyConfig>().seed, 42);
+    let replicated = client_app
+        .world
+        .query_filtered::<(), With<Transform>>()
+        .iter(&client_app.world)
+        .count();
+    assert_eq!(replicated, 1);
+
+    client_app.world.remove_resource::<NetcodeClientTransport>();
+    client_app.world.remove_resource::<RenetClient>();
+    client_app.update();
+
+    assert!(!client_app.world.contains_resource::<DummyConfig>());
+}
+
+#[derive(Debug, Deserialize, Resource, Serialize)]
+struct DummyConfig {
+    seed: u64,
+}
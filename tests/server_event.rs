@@ -3,7 +3,8 @@ mod common;
 use bevy::prelude::*;
 use bevy::{ecs::event::Events, time::TimePlugin};
 use bevy_renet::renet::transport::NetcodeClientTransport;
-use bevy_replicon::prelude::*;
+use bevy_replicon::{network_event::EventChannel, prelude::*, wire_format};
+use serde::{Deserialize, Serialize};
 
 use common::{DummyEvent, ReflectEvent, ReflectEventDeserializer, ReflectEventSerializer};
 
@@ -14,7 +15,7 @@ fn without_server_plugin() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins)
         .add_plugins(ReplicationPlugins.build().disable::<ServerPlugin>())
-        .add_server_event_with::<DummyEvent, _, _>(SendPolicy::Ordered, || {}, || {})
+        .add_server_event_with::<DummyEvent, _, _>(SendPolicy::reliable().ordered(), || {}, || {})
         .update();
 }
 
@@ -23,7 +24,7 @@ fn without_client_plugin() {
     let mut app = App::new();
     app.add_plugins(MinimalPlugins)
         .add_plugins(ReplicationPlugins.build().disable::<ClientPlugin>())
-        .add_server_event_with::<DummyEvent, _, _>(SendPolicy::Ordered, || {}, || {})
+        .add_server_event_with::<DummyEvent, _, _>(SendPolicy::reliable().ordered(), || {}, || {})
         .update();
 }
 
@@ -36,7 +37,7 @@ fn sending_receiving() {
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
-        .add_server_event::<DummyEvent>(SendPolicy::Ordered);
+        .add_server_event::<DummyEvent>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -45,12 +46,23 @@ fn sending_receiving() {
         .world
         .resource::<NetcodeClientTransport>()
         .client_id();
+    let mut client_groups = server_app.world.resource_mut::<ClientGroups>();
+    client_groups.insert(CLIENT_GROUP, client_id);
+    client_groups.insert(SERVER_GROUP, SERVER_ID);
+    let mut client_tags = server_app.world.resource_mut::<ClientTags>();
+    client_tags.insert(client_id, TEAM_TAG, RED_TEAM);
+    client_tags.set_filter(RED_FILTER, red_filter());
+    client_tags.set_filter(BLUE_FILTER, TagFilter::equals(TEAM_TAG, BLUE_TEAM));
     for (mode, events_count) in [
         (SendMode::Broadcast, 1),
         (SendMode::Direct(SERVER_ID), 0),
         (SendMode::Direct(client_id), 1),
         (SendMode::BroadcastExcept(SERVER_ID), 1),
         (SendMode::BroadcastExcept(client_id), 0),
+        (SendMode::BroadcastGroup(CLIENT_GROUP), 1),
+        (SendMode::BroadcastGroup(SERVER_GROUP), 0),
+        (SendMode::BroadcastTagged(RED_FILTER), 1),
+        (SendMode::BroadcastTagged(BLUE_FILTER), 0),
     ] {
         server_app
             .world
@@ -72,6 +84,50 @@ fn sending_receiving() {
     }
 }
 
+#[test]
+fn scheduled_sending_receiving() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_scheduled_server_event::<DummyEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let tick = server_app.world.resource::<ServerTick>().get() + 3;
+    server_app.world.send_event(ToClients::scheduled(
+        SendMode::Broadcast,
+        tick,
+        DummyEvent(Entity::PLACEHOLDER),
+    ));
+
+    loop {
+        server_app.update();
+        client_app.update();
+
+        let client_tick = client_app.world.resource::<ServerTick>().get();
+        let events_count = client_app
+            .world
+            .resource_mut::<Events<DummyEvent>>()
+            .drain()
+            .count();
+        if client_tick < tick {
+            assert_eq!(
+                events_count, 0,
+                "event shouldn't be emitted before its tick"
+            );
+        } else {
+            assert_eq!(client_tick, tick);
+            assert_eq!(events_count, 1, "event should be emitted at its tick");
+            break;
+        }
+    }
+}
+
 #[test]
 fn sending_receiving_and_mapping() {
     let mut server_app = App::new();
@@ -81,7 +137,7 @@ fn sending_receiving_and_mapping() {
             MinimalPlugins,
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
-        .add_mapped_server_event::<DummyEvent>(SendPolicy::Ordered);
+        .add_mapped_server_event::<DummyEvent>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -113,6 +169,111 @@ fn sending_receiving_and_mapping() {
     assert_eq!(mapped_entities, [client_entity]);
 }
 
+#[test]
+fn sending_receiving_and_mapping_derived() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_mapped_server_event::<DerivedMappedEvent>(SendPolicy::reliable().ordered());
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let client_entity = Entity::from_raw(0);
+    let server_entity = Entity::from_raw(client_entity.index() + 1);
+    client_app
+        .world
+        .resource_mut::<NetworkEntityMap>()
+        .insert(server_entity, client_entity);
+
+    let mut server_events = server_app
+        .world
+        .resource_mut::<Events<ToClients<DerivedMappedEvent>>>();
+    for event in [
+        DerivedMappedEvent::Named {
+            single: server_entity,
+            optional: Some(server_entity),
+            unmapped: server_entity,
+        },
+        DerivedMappedEvent::Unnamed(server_entity, vec![server_entity, server_entity]),
+        DerivedMappedEvent::Unit,
+    ] {
+        server_events.send(ToClients {
+            mode: SendMode::Broadcast,
+            event,
+        });
+    }
+
+    server_app.update();
+    client_app.update();
+
+    let events: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<DerivedMappedEvent>>()
+        .drain()
+        .collect();
+    assert_eq!(
+        events,
+        [
+            DerivedMappedEvent::Named {
+                single: client_entity,
+                optional: Some(client_entity),
+                unmapped: server_entity,
+            },
+            DerivedMappedEvent::Unnamed(server_entity, vec![client_entity, client_entity]),
+            DerivedMappedEvent::Unit,
+        ]
+    );
+}
+
+#[test]
+fn receiving_unmapped_with_placeholder() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .add_mapped_server_event::<DummyEvent>(SendPolicy::reliable().ordered())
+        .set_server_event_map_policy::<DummyEvent>(MapFailurePolicy::Placeholder);
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    let server_entity = Entity::from_raw(0);
+    server_app
+        .world
+        .resource_mut::<Events<ToClients<DummyEvent>>>()
+        .send(ToClients {
+            mode: SendMode::Broadcast,
+            event: DummyEvent(server_entity),
+        });
+
+    server_app.update();
+    client_app.update();
+
+    let mapped_entities: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<DummyEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect();
+    assert_eq!(mapped_entities, [Entity::PLACEHOLDER]);
+
+    let failures: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<EventMappingFailed>>()
+        .drain()
+        .map(|failure| (failure.entity, failure.delivered))
+        .collect();
+    assert_eq!(failures, [(server_entity, true)]);
+}
+
 #[test]
 fn sending_receiving_reflect() {
     let mut server_app = App::new();
@@ -124,7 +285,7 @@ fn sending_receiving_reflect() {
         ))
         .register_type::<ReflectedValue>()
         .add_server_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(
-            SendPolicy::Ordered,
+            SendPolicy::reliable().ordered(),
         );
     }
 
@@ -134,12 +295,17 @@ fn sending_receiving_reflect() {
         .world
         .resource::<NetcodeClientTransport>()
         .client_id();
+    let mut client_groups = server_app.world.resource_mut::<ClientGroups>();
+    client_groups.insert(CLIENT_GROUP, client_id);
+    client_groups.insert(SERVER_GROUP, SERVER_ID);
     for (mode, events_count) in [
         (SendMode::Broadcast, 1),
         (SendMode::Direct(SERVER_ID), 0),
         (SendMode::Direct(client_id), 1),
         (SendMode::BroadcastExcept(SERVER_ID), 1),
         (SendMode::BroadcastExcept(client_id), 0),
+        (SendMode::BroadcastGroup(CLIENT_GROUP), 1),
+        (SendMode::BroadcastGroup(SERVER_GROUP), 0),
     ] {
         server_app
             .world
@@ -174,7 +340,7 @@ fn sending_receiving_and_mapping_reflect() {
             ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
         ))
         .register_type::<ReflectedValue>()
-        .add_mapped_server_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(SendPolicy::Ordered);
+        .add_mapped_server_reflect_event::<ReflectEvent, ReflectEventSerializer, ReflectEventDeserializer>(SendPolicy::reliable().ordered());
     }
 
     common::connect(&mut server_app, &mut client_app);
@@ -216,15 +382,27 @@ fn local_resending() {
         TimePlugin,
         ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
     ))
-    .add_server_event::<DummyEvent>(SendPolicy::Ordered);
+    .add_server_event::<DummyEvent>(SendPolicy::reliable().ordered());
 
     const DUMMY_CLIENT_ID: u64 = 1;
+    let mut client_groups = app.world.resource_mut::<ClientGroups>();
+    client_groups.insert(CLIENT_GROUP, DUMMY_CLIENT_ID);
+    client_groups.insert(SERVER_GROUP, SERVER_ID);
+    let mut client_tags = app.world.resource_mut::<ClientTags>();
+    client_tags.insert(SERVER_ID, TEAM_TAG, RED_TEAM);
+    client_tags.insert(DUMMY_CLIENT_ID, TEAM_TAG, BLUE_TEAM);
+    client_tags.set_filter(RED_FILTER, red_filter());
+    client_tags.set_filter(BLUE_FILTER, TagFilter::equals(TEAM_TAG, BLUE_TEAM));
     for (mode, events_count) in [
         (SendMode::Broadcast, 1),
         (SendMode::Direct(SERVER_ID), 1),
         (SendMode::Direct(DUMMY_CLIENT_ID), 0),
         (SendMode::BroadcastExcept(SERVER_ID), 0),
         (SendMode::BroadcastExcept(DUMMY_CLIENT_ID), 1),
+        (SendMode::BroadcastGroup(CLIENT_GROUP), 0),
+        (SendMode::BroadcastGroup(SERVER_GROUP), 1),
+        (SendMode::BroadcastTagged(RED_FILTER), 1),
+        (SendMode::BroadcastTagged(BLUE_FILTER), 0),
     ] {
         app.world
             .resource_mut::<Events<ToClients<DummyEvent>>>()
@@ -246,3 +424,75 @@ fn local_resending() {
         );
     }
 }
+
+#[test]
+fn unmapped_entity() {
+    let mut world = World::new();
+    let mut entity_map = NetworkEntityMap::default();
+    let server_entity = Entity::from_raw(0);
+    let client_entity = world.spawn_empty().id();
+    entity_map.insert(server_entity, client_entity);
+
+    let mut event = DummyEvent(server_entity);
+    assert!(entity_map.map_to_client(&mut world, &mut event).is_ok());
+    assert_eq!(event.0, client_entity);
+
+    let unmapped_entity = Entity::from_raw(1);
+    let mut event = DummyEvent(unmapped_entity);
+    assert_eq!(
+        entity_map.map_to_client(&mut world, &mut event),
+        Err(unmapped_entity)
+    );
+    assert_eq!(
+        entity_map.to_client().len(),
+        1,
+        "placeholder for unmapped entity shouldn't be kept"
+    );
+}
+
+#[test]
+fn captured_event_decoding() {
+    let mut app = App::new();
+    app.add_plugins(ReplicationPlugins)
+        .add_server_event::<DummyEvent>(SendPolicy::reliable().ordered());
+
+    let channel_id = app.world.resource::<EventChannel<DummyEvent>>().id;
+    let message = wire_format::serialize_message(&DummyEvent(Entity::from_raw(1))).unwrap();
+    let decoder = TrafficDecoder::new(&app.world);
+    let record = decoder.decode(&CapturedMessage {
+        direction: TrafficDirection::Sent,
+        client_id: 1,
+        channel_id,
+        tick: 0,
+        message,
+    });
+    assert_eq!(record.channel_id, channel_id);
+    assert_eq!(record.summary, "DummyEvent DummyEvent(1v0)");
+}
+
+#[derive(Debug, Deserialize, Event, MapEventEntities, PartialEq, Serialize)]
+enum DerivedMappedEvent {
+    Named {
+        #[entities]
+        single: Entity,
+        #[entities]
+        optional: Option<Entity>,
+        unmapped: Entity,
+    },
+    Unnamed(Entity, #[entities] Vec<Entity>),
+    Unit,
+}
+
+const CLIENT_GROUP: &str = "client";
+const SERVER_GROUP: &str = "server";
+
+const TEAM_TAG: &str = "team";
+const RED_TEAM: &str = "red";
+const BLUE_TEAM: &str = "blue";
+const SPECTATOR_TAG: &str = "spectator";
+const RED_FILTER: &str = "red";
+const BLUE_FILTER: &str = "blue";
+
+fn red_filter() -> TagFilter {
+    TagFilter::equals(TEAM_TAG, RED_TEAM).and(!TagFilter::has(SPECTATOR_TAG))
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::{prelude::*, replication_core::NetworkChannels};
+use serde::{Deserialize, Serialize};
+
+#[test]
+fn sending_receiving() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    let network_channels = server_app.world.resource::<NetworkChannels>();
+    let server_channels = network_channels.server_channels().len();
+    let client_channels = network_channels.client_channels().len();
+
+    for app in [&mut server_app, &mut client_app] {
+        app.add_shared_server_event::<FirstEvent>()
+            .add_shared_server_event::<SecondEvent>()
+            .add_shared_client_event::<ThirdEvent>()
+            .add_shared_client_event::<FourthEvent>();
+    }
+
+    let network_channels = server_app.world.resource::<NetworkChannels>();
+    assert_eq!(
+        network_channels.server_channels().len(),
+        server_channels + 1,
+        "server events should share a single channel"
+    );
+    assert_eq!(
+        network_channels.client_channels().len(),
+        client_channels + 1,
+        "client events should share a single channel"
+    );
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.send_event(ToClients {
+        mode: SendMode::Broadcast,
+        event: SecondEvent(2),
+    });
+    server_app.world.send_event(ToClients {
+        mode: SendMode::Broadcast,
+        event: FirstEvent(1),
+    });
+    client_app.world.send_event(FourthEvent(4));
+    client_app.world.send_event(ThirdEvent(3));
+
+    server_app.update();
+    client_app.update();
+    server_app.update();
+
+    let first_events: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<FirstEvent>>()
+        .drain()
+        .collect();
+    assert_eq!(first_events, [FirstEvent(1)]);
+    let second_events: Vec<_> = client_app
+        .world
+        .resource_mut::<Events<SecondEvent>>()
+        .drain()
+        .collect();
+    assert_eq!(second_events, [SecondEvent(2)]);
+
+    let third_events: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<FromClient<ThirdEvent>>>()
+        .drain()
+        .map(|FromClient { event, .. }| event)
+        .collect();
+    assert_eq!(third_events, [ThirdEvent(3)]);
+    let fourth_events: Vec<_> = server_app
+        .world
+        .resource_mut::<Events<FromClient<FourthEvent>>>()
+        .drain()
+        .map(|FromClient { event, .. }| event)
+        .collect();
+    assert_eq!(fourth_events, [FourthEvent(4)]);
+}
+
+#[test]
+fn local_resending() {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+    ))
+    .add_shared_server_event::<FirstEvent>()
+    .add_shared_client_event::<ThirdEvent>();
+
+    app.world.send_event(ToClients {
+        mode: SendMode::Broadcast,
+        event: FirstEvent(1),
+    });
+    app.world.send_event(ThirdEvent(2));
+
+    app.update();
+
+    assert_eq!(app.world.resource::<Events<FirstEvent>>().len(), 1);
+    assert_eq!(
+        app.world.resource::<Events<FromClient<ThirdEvent>>>().len(),
+        1
+    );
+}
+
+#[derive(Debug, Deserialize, Event, PartialEq, Serialize)]
+struct FirstEvent(u32);
+
+#[derive(Debug, Deserialize, Event, PartialEq, Serialize)]
+struct SecondEvent(u32);
+
+#[derive(Debug, Deserialize, Event, PartialEq, Serialize)]
+struct ThirdEvent(u32);
+
+#[derive(Debug, Deserialize, Event, PartialEq, Serialize)]
+struct FourthEvent(u32);
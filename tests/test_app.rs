@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use bevy_replicon::{prelude::*, test_app::LockstepApps};
+
+#[test]
+fn lockstep() {
+    let mut apps = LockstepApps::new(2, |app| {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<Transform>();
+    });
+
+    apps.server.world.spawn((Replication, Transform::default()));
+
+    let server_tick = *apps.server.world.resource::<ServerTick>();
+    const TICKS: u32 = 3;
+    apps.advance_ticks(TICKS);
+
+    assert_eq!(
+        apps.server.world.resource::<ServerTick>().get(),
+        server_tick.get() + TICKS
+    );
+    for client_app in &mut apps.clients {
+        assert_eq!(
+            *client_app.world.resource::<ServerTick>(),
+            *apps.server.world.resource::<ServerTick>()
+        );
+        let replicated = client_app
+            .world
+            .query_filtered::<(), With<Transform>>()
+            .iter(&client_app.world)
+            .count();
+        assert_eq!(replicated, 1);
+    }
+}
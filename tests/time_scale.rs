@@ -0,0 +1,51 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_renet::renet::transport::NetcodeClientTransport;
+use bevy_replicon::prelude::*;
+
+#[test]
+fn time_scale_replication() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    let mut paused = ServerTimeScale::default();
+    paused.paused = true;
+    paused.set_speed(0.5);
+    *server_app.world.resource_mut::<ServerTimeScale>() = paused;
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        *client_app.world.resource::<ServerTimeScale>(),
+        paused,
+        "client should receive the current scale on connection"
+    );
+
+    server_app.world.resource_mut::<ServerTimeScale>().paused = false;
+
+    server_app.update();
+    client_app.update();
+
+    let time_scale = *client_app.world.resource::<ServerTimeScale>();
+    assert!(!time_scale.paused);
+    assert_eq!(time_scale.relative_speed(), 0.5);
+
+    client_app.world.remove_resource::<NetcodeClientTransport>();
+    client_app.world.remove_resource::<RenetClient>();
+    client_app.update();
+
+    assert_eq!(
+        *client_app.world.resource::<ServerTimeScale>(),
+        ServerTimeScale::default()
+    );
+}
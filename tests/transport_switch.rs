@@ -0,0 +1,128 @@
+mod common;
+
+use std::net::{Ipv4Addr, SocketAddr};
+
+use bevy::{ecs::event::Events, prelude::*};
+use bevy_replicon::{
+    prelude::*,
+    renet::transport::{NetcodeClientTransport, NetcodeServerTransport},
+};
+
+#[test]
+fn client_switch() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ))
+        .replicate::<TableComponent>();
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.world.spawn((Replication, TableComponent));
+
+    server_app.update();
+    client_app.update();
+
+    let mut components = client_app.world.query::<&TableComponent>();
+    assert_eq!(components.iter(&client_app.world).count(), 1);
+
+    client_app.world.start_transport_switch();
+    assert!(client_app.world.contains_resource::<TransportSwitch>());
+
+    client_app.update();
+    server_app.update();
+
+    let started_events = client_app
+        .world
+        .resource::<Events<TransportSwitchStarted>>();
+    assert_eq!(started_events.len(), 1);
+    assert!(!client_app
+        .world
+        .resource::<NetworkEntityMap>()
+        .to_client()
+        .is_empty());
+
+    let server_port = server_app
+        .world
+        .resource::<NetcodeServerTransport>()
+        .addr()
+        .port();
+    client_app
+        .world
+        .create_netcode_client(
+            SocketAddr::new(Ipv4Addr::LOCALHOST.into(), server_port),
+            1,
+            0,
+        )
+        .unwrap();
+
+    client_app.update();
+    assert!(!client_app.world.contains_resource::<TransportSwitch>());
+    let switched_events = client_app.world.resource::<Events<TransportSwitched>>();
+    assert_eq!(switched_events.len(), 1);
+
+    loop {
+        server_app.update();
+        client_app.update();
+        if client_app
+            .world
+            .resource::<NetcodeClientTransport>()
+            .is_connected()
+        {
+            break;
+        }
+    }
+
+    server_app.update();
+    client_app.update();
+
+    assert_eq!(
+        components.iter(&client_app.world).count(),
+        1,
+        "entity should be mapped to the already replicated one"
+    );
+}
+
+#[test]
+fn server_switch() {
+    let mut server_app = App::new();
+    let mut client_app = App::new();
+    for app in [&mut server_app, &mut client_app] {
+        app.add_plugins((
+            MinimalPlugins,
+            ReplicationPlugins.set(ServerPlugin::new(TickPolicy::Manual)),
+        ));
+    }
+
+    common::connect(&mut server_app, &mut client_app);
+
+    server_app.update();
+    let tick = server_app.world.resource::<ServerTick>().get();
+
+    server_app.world.start_transport_switch();
+    server_app.update();
+
+    assert!(server_app.world.contains_resource::<TransportSwitch>());
+    assert!(
+        server_app.world.resource::<ServerTick>().get() >= tick,
+        "tick shouldn't be reset"
+    );
+
+    server_app
+        .world
+        .create_netcode_server(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), 1, 0, None)
+        .unwrap();
+    server_app.update();
+
+    assert!(!server_app.world.contains_resource::<TransportSwitch>());
+    let switched_events = server_app.world.resource::<Events<TransportSwitched>>();
+    assert_eq!(switched_events.len(), 1);
+}
+
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+struct TableComponent;